@@ -0,0 +1,87 @@
+//! Benchmarks the binary against generated procfs trees of growing size,
+//! then the scan, tree building and rendering stages of the library one by
+//! one on the same trees.
+//!
+//! `--depth 0` keeps rendering down to one line per root, so the `scan` cases
+//! of the binary mostly measure reading /proc and building the tree.
+
+#[path = "../tests/support/fake_procfs.rs"]
+mod fake_procfs;
+
+use std::io;
+use std::process::{Command, Stdio};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rusty_pstree::{scan_source, LinuxProcfs, ProcessInfo, ProcessTree, PsTreePrintConfig, ScanOptions};
+
+use fake_procfs::FakeProcfs;
+
+
+const PROCESS_COUNTS: [u32; 3] = [1_000, 10_000, 50_000];
+const FAN_OUT: u32 = 8;
+
+fn run_binary(procfs: &FakeProcfs, extra_args: &[&str])
+{
+    let status = Command::new(env!("CARGO_BIN_EXE_rusty-pstree"))
+        .arg("--proc-root")
+        .arg(procfs.path())
+        .args(extra_args)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn bench_procfs(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("procfs");
+    group.sample_size(10);
+
+    for process_count in PROCESS_COUNTS {
+        let procfs = FakeProcfs::generate(process_count, FAN_OUT);
+
+        group.bench_with_input(BenchmarkId::new("scan", process_count), &procfs, |b, procfs| {
+            b.iter(|| run_binary(procfs, &["--depth", "0"]))
+        });
+        group.bench_with_input(BenchmarkId::new("print", process_count), &procfs, |b, procfs| {
+            b.iter(|| run_binary(procfs, &[]))
+        });
+        group.bench_with_input(BenchmarkId::new("print_pids", process_count), &procfs, |b, procfs| {
+            b.iter(|| run_binary(procfs, &["--show-pid"]))
+        });
+    }
+
+    group.finish();
+}
+
+fn scan_processes(procfs: &FakeProcfs) -> Vec<ProcessInfo>
+{
+    scan_source(&LinuxProcfs::new(procfs.path()), &ScanOptions::default()).unwrap().processes
+}
+
+fn bench_stages(c: &mut Criterion)
+{
+    let config = PsTreePrintConfig::new();
+
+    for process_count in PROCESS_COUNTS {
+        let procfs = FakeProcfs::generate(process_count, FAN_OUT);
+        let processes = scan_processes(&procfs);
+        let tree = ProcessTree::from_process_list(processes.clone());
+
+        let mut group = c.benchmark_group("stages");
+        group.sample_size(10);
+        group.bench_with_input(BenchmarkId::new("scan_source", process_count), &procfs, |b, procfs| {
+            b.iter(|| scan_processes(procfs))
+        });
+        group.bench_with_input(BenchmarkId::new("from_process_list", process_count), &processes, |b, processes| {
+            b.iter_batched(|| processes.clone(), ProcessTree::from_process_list, BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("write", process_count), &tree, |b, tree| {
+            b.iter(|| tree.write(io::sink(), &config).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_procfs, bench_stages);
+criterion_main!(benches);
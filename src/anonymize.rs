@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io;
+use std::sync::Arc;
+
+use crate::process::ProcessInfo;
+use crate::tree::ProcessTree;
+use crate::user::UserTable;
+
+
+/// Replaces what tells a machine and its users apart in the processes
+/// handed to it with placeholders, so a tree can be shared.
+///
+/// Names become `proc-01`, `proc-02` and so on, users `user-1`, `user-2`,
+/// numbered in the order they are met. Equal names get the same
+/// placeholder for as long as the anonymizer lives, so the shape of the
+/// tree can still be read. Command lines, executable paths and cgroups
+/// are dropped; pids, states and sizes are kept.
+#[derive(Clone, Debug, Default)]
+pub struct Anonymizer
+{
+    /// The placeholder of every name met, by name.
+    placeholders: HashMap<Arc<[u8]>, Arc<[u8]>>,
+    /// The names met, in the order of their placeholders.
+    names: Vec<Arc<[u8]>>,
+    /// The uids met, in the order of their placeholders.
+    uids: Vec<u32>,
+}
+
+impl Anonymizer
+{
+    /// Creates an anonymizer that has not met any name or user yet.
+    pub fn new() -> Anonymizer
+    {
+        Anonymizer::default()
+    }
+
+    /// Replaces the name of `proc_info` with its placeholder and drops its
+    /// command line, executable and cgroup. Its uid is kept, the user
+    /// behind it getting a placeholder in [`user_names`](Self::user_names).
+    pub fn anonymize(&mut self, proc_info: &mut ProcessInfo)
+    {
+        proc_info.name = self.placeholder(&proc_info.name);
+        proc_info.cmdline = None;
+        proc_info.exe = None;
+        proc_info.cgroup = None;
+        if let Some(uid) = proc_info.uid.filter(|uid| !self.uids.contains(uid)) {
+            self.uids.push(uid);
+        }
+    }
+
+    /// The placeholder of `name`, a new one if the name was not met yet.
+    pub fn placeholder(&mut self, name: &Arc<[u8]>) -> Arc<[u8]>
+    {
+        if let Some(placeholder) = self.placeholders.get(name) {
+            return placeholder.clone();
+        }
+        let placeholder: Arc<[u8]> = Arc::from(format!("proc-{:02}", self.names.len() + 1).into_bytes());
+        self.placeholders.insert(name.clone(), placeholder.clone());
+        self.names.push(name.clone());
+        placeholder
+    }
+
+    /// A user table naming every uid met after its placeholder, to draw
+    /// user names with instead of the one of the system.
+    pub fn user_names(&self) -> UserTable
+    {
+        let mut table = UserTable::default();
+        for (number, &uid) in self.uids.iter().enumerate() {
+            table.insert(format!("user-{}", number + 1), uid);
+        }
+        table
+    }
+
+    /// Writes the placeholders met so far as a JSON object, with the name
+    /// each one stands for under `names` and the user under `users`. Users
+    /// missing from `users` are written as their uid. Names that are not
+    /// UTF-8 have their invalid sequences replaced by U+FFFD.
+    pub fn write_map<W: io::Write>(&self, mut out: W, users: &UserTable) -> io::Result<()>
+    {
+        let names: Vec<(String, String)> = self.names.iter()
+            .map(|name| {
+                let placeholder = String::from_utf8_lossy(&self.placeholders[name]).into_owned();
+                (placeholder, String::from_utf8_lossy(name).into_owned())
+            })
+            .collect();
+        let user_names: Vec<(String, String)> = self.uids.iter().enumerate()
+            .map(|(number, &uid)| {
+                let name = users.name(uid).map_or_else(|| uid.to_string(), str::to_string);
+                (format!("user-{}", number + 1), name)
+            })
+            .collect();
+
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"names\": {},", json_object(&names))?;
+        writeln!(out, "  \"users\": {}", json_object(&user_names))?;
+        writeln!(out, "}}")
+    }
+}
+
+/// `entries` as a JSON object, one entry per line.
+fn json_object(entries: &[(String, String)]) -> String
+{
+    if entries.is_empty() {
+        return "{}".to_string();
+    }
+    let entries: Vec<String> = entries.iter()
+        .map(|(key, value)| format!("    {}: {}", json_string(key), json_string(value)))
+        .collect();
+    format!("{{\n{}\n  }}", entries.join(",\n"))
+}
+
+/// `text` as a quoted JSON string.
+fn json_string(text: &str) -> String
+{
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            ch if ch.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", u32::from(ch));
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl ProcessTree
+{
+    /// Anonymizes every process of the tree with `anonymizer`, the ones
+    /// outside the drawn subtrees included since the ancestor header
+    /// names them.
+    pub fn anonymize(&mut self, anonymizer: &mut Anonymizer)
+    {
+        for node in &mut self.nodes {
+            anonymizer.anonymize(&mut node.proc_info);
+        }
+    }
+}
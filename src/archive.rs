@@ -0,0 +1,372 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::gzip::{gunzip, is_gzip};
+use crate::source::{parse_children, parse_pid_dir_name, ProcSource};
+
+
+const BLOCK_SIZE: usize = 512;
+const USTAR_MAGIC: &[u8] = b"ustar";
+/// Where the magic sits in a tar header.
+const USTAR_MAGIC_OFFSET: usize = 257;
+/// File name extensions of the archives read without looking at their magic.
+const ARCHIVE_EXTENSIONS: [&str; 3] = [".tar", ".tar.gz", ".tgz"];
+
+/// What the archive holds at a path.
+#[derive(Clone, Debug)]
+enum Entry
+{
+    File(Vec<u8>),
+    Symlink(PathBuf),
+    Directory,
+}
+
+/// A tar archive of /proc, optionally gzip compressed, read into memory.
+/// The directories above the pid directories are left out of the paths, like
+/// the "proc/" of `tar -c /proc/*/status`. Files left out of the archive
+/// read as missing, like those of a process that exited.
+#[derive(Clone, Debug)]
+pub struct ArchiveSource
+{
+    root: PathBuf,
+    files: BTreeMap<u32, HashMap<String, Entry>>,
+    other_files: HashMap<String, Vec<u8>>,
+}
+
+impl ArchiveSource
+{
+    /// Whether `path` is a tar archive rather than a procfs directory, by its
+    /// extension or else by the gzip or tar magic it starts with.
+    pub fn is_archive(path: &Path) -> bool
+    {
+        let name = path.file_name().map_or(&[][..], OsStr::as_bytes);
+        if ARCHIVE_EXTENSIONS.iter().any(|extension| name.ends_with(extension.as_bytes())) {
+            return true;
+        }
+        if !path.is_file() {
+            return false;
+        }
+        let mut start: Vec<u8> = Vec::new();
+        let read = File::open(path)
+            .and_then(|file| file.take((USTAR_MAGIC_OFFSET + USTAR_MAGIC.len()) as u64).read_to_end(&mut start));
+        read.is_ok() && (is_gzip(&start) || start.get(USTAR_MAGIC_OFFSET..) == Some(USTAR_MAGIC))
+    }
+
+    /// Reads the archive at `path`.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<ArchiveSource>
+    {
+        let root = path.into();
+        let data = fs::read(&root)?;
+        ArchiveSource::from_bytes(root, &data)
+    }
+
+    /// Reads the archive `data`, shown as `root` in error messages.
+    pub fn from_bytes(root: impl Into<PathBuf>, data: &[u8]) -> io::Result<ArchiveSource>
+    {
+        let tar = if is_gzip(data) { Cow::Owned(gunzip(data)?) } else { Cow::Borrowed(data) };
+        let entries = read_tar(&tar)?;
+        let mut source = ArchiveSource { root: root.into(), files: BTreeMap::new(), other_files: HashMap::new() };
+
+        // The directories above the first pid directory stand for /proc.
+        let proc_dir: Vec<&[u8]> = entries.iter()
+            .find_map(|(path, _)| {
+                let components = path_components(path);
+                let pid_dir = components.iter()
+                    .position(|&component| parse_pid_dir_name(OsStr::from_bytes(component)).is_some())?;
+                Some(components[..pid_dir].to_vec())
+            })
+            .unwrap_or_default();
+        for (path, entry) in &entries {
+            if let Some(components) = path_components(path).strip_prefix(&proc_dir[..]) {
+                source.insert(components, entry.clone());
+            }
+        }
+        Ok(source)
+    }
+
+    /// Stores `entry` at the path `components` below /proc.
+    fn insert(&mut self, components: &[&[u8]], entry: Entry)
+    {
+        let Some((&first, rest)) = components.split_first() else { return };
+        let path = String::from_utf8_lossy(&rest.join(&b'/')).into_owned();
+        match parse_pid_dir_name(OsStr::from_bytes(first)) {
+            Some(pid) => {
+                let files = self.files.entry(pid).or_default();
+                if !rest.is_empty() {
+                    files.insert(path, entry);
+                }
+            }
+            None => if let Entry::File(contents) = entry {
+                self.other_files.insert(String::from_utf8_lossy(&components.join(&b'/')).into_owned(), contents);
+            },
+        }
+    }
+
+    fn read(&self, pid: u32, file: &str) -> io::Result<&[u8]>
+    {
+        match self.files.get(&pid).and_then(|files| files.get(file)) {
+            Some(Entry::File(contents)) => Ok(contents),
+            _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+
+    /// The target of the symlink `file` of `pid`.
+    fn read_link(&self, pid: u32, file: &str) -> io::Result<PathBuf>
+    {
+        match self.files.get(&pid).and_then(|files| files.get(file)) {
+            Some(Entry::Symlink(target)) => Ok(target.clone()),
+            _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+
+    fn read_other(&self, file: &str) -> io::Result<Vec<u8>>
+    {
+        self.other_files.get(file).cloned().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+}
+
+impl ProcSource for ArchiveSource
+{
+    type Status = io::Cursor<Vec<u8>>;
+
+    fn root(&self) -> &Path
+    {
+        &self.root
+    }
+
+    fn list_pids(&self) -> io::Result<Vec<u32>>
+    {
+        Ok(self.files.keys().copied().collect())
+    }
+
+    fn read_status(&self, pid: u32) -> io::Result<io::Cursor<Vec<u8>>>
+    {
+        self.read(pid, "status").map(|status| io::Cursor::new(status.to_vec()))
+    }
+
+    fn read_stat(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "stat").map(<[u8]>::to_vec)
+    }
+
+    fn read_cmdline(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "cmdline").map(<[u8]>::to_vec)
+    }
+
+    fn read_cgroup(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "cgroup").map(<[u8]>::to_vec)
+    }
+
+    fn read_fd_targets(&self, pid: u32) -> io::Result<Vec<PathBuf>>
+    {
+        let files = self.files.get(&pid).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        if !files.contains_key("fd") && !files.keys().any(|file| file.starts_with("fd/")) {
+            return Err(io::Error::from(io::ErrorKind::NotFound));
+        }
+        Ok(files.iter()
+            .filter(|(file, _)| file.starts_with("fd/"))
+            .filter_map(|(_, entry)| match entry {
+                Entry::Symlink(target) => Some(target.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn read_exe(&self, pid: u32) -> io::Result<PathBuf>
+    {
+        self.read_link(pid, "exe")
+    }
+
+    fn read_ns(&self, pid: u32, ns: &str) -> io::Result<PathBuf>
+    {
+        self.read_link(pid, &format!("ns/{}", ns))
+    }
+
+    fn read_limits(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "limits").map(<[u8]>::to_vec)
+    }
+
+    fn read_wchan(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "wchan").map(<[u8]>::to_vec)
+    }
+
+    fn read_maps(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "maps").map(<[u8]>::to_vec)
+    }
+
+    fn read_smaps_rollup(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "smaps_rollup").map(<[u8]>::to_vec)
+    }
+
+    fn read_cpuinfo(&self) -> io::Result<Vec<u8>>
+    {
+        self.read_other("cpuinfo")
+    }
+
+    fn read_net(&self, table: &str) -> io::Result<Vec<u8>>
+    {
+        self.read_other(&format!("net/{}", table))
+    }
+
+    fn read_children(&self, pid: u32) -> Option<Vec<u32>>
+    {
+        let mut children: Option<Vec<u32>> = None;
+        for (file, entry) in self.files.get(&pid)? {
+            let is_children = file.strip_prefix("task/")
+                .and_then(|task| task.strip_suffix("/children"))
+                .is_some_and(|tid| !tid.contains('/'));
+            if let (true, Entry::File(task_children)) = (is_children, entry) {
+                children.get_or_insert_with(Vec::new).extend(parse_children(task_children));
+            }
+        }
+        children
+    }
+}
+
+/// The files, symlinks and directories of the tar archive `data`, with
+/// their paths. GNU long names and pax paths are followed, other entry
+/// types are skipped.
+fn read_tar(data: &[u8]) -> io::Result<Vec<(Vec<u8>, Entry)>>
+{
+    let mut entries: Vec<(Vec<u8>, Entry)> = Vec::new();
+    let mut long_path: Option<Vec<u8>> = None;
+    let mut long_link: Option<Vec<u8>> = None;
+    let mut offset = 0;
+
+    while let Some(header) = data.get(offset..offset + BLOCK_SIZE) {
+        // The archive ends with zeroed blocks.
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+        if !checksum_matches(header) {
+            return Err(invalid("not a tar archive"));
+        }
+        let size = parse_size(&header[124..136]).ok_or_else(|| invalid("invalid tar entry size"))?;
+        let contents = data.get(offset + BLOCK_SIZE..offset + BLOCK_SIZE + size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated tar archive"))?;
+        offset += BLOCK_SIZE + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+
+        match header[156] {
+            b'L' => long_path = Some(until_nul(contents).to_vec()),
+            b'K' => long_link = Some(until_nul(contents).to_vec()),
+            b'x' => for (key, value) in pax_records(contents) {
+                match key {
+                    b"path" => long_path = Some(value.to_vec()),
+                    b"linkpath" => long_link = Some(value.to_vec()),
+                    _ => {}
+                }
+            },
+            b'0' | b'7' | 0 => entries.push((entry_path(header, &mut long_path), Entry::File(contents.to_vec()))),
+            b'2' => {
+                let path = entry_path(header, &mut long_path);
+                let target = long_link.take().unwrap_or_else(|| until_nul(&header[157..257]).to_vec());
+                entries.push((path, Entry::Symlink(PathBuf::from(OsStr::from_bytes(&target)))));
+            }
+            b'5' => entries.push((entry_path(header, &mut long_path), Entry::Directory)),
+            _ => {
+                long_path = None;
+                long_link = None;
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The names of the directories and file of `path`, without "." ones.
+fn path_components(path: &[u8]) -> Vec<&[u8]>
+{
+    path.split(|&byte| byte == b'/')
+        .filter(|component| !component.is_empty() && *component != b".")
+        .collect()
+}
+
+/// The path of the entry of `header`: the long path given before it, or
+/// its name joined to its ustar prefix if it has one.
+fn entry_path(header: &[u8], long_path: &mut Option<Vec<u8>>) -> Vec<u8>
+{
+    if let Some(path) = long_path.take() {
+        return path;
+    }
+    let name = until_nul(&header[..100]);
+    let prefix = until_nul(&header[345..500]);
+    if header[USTAR_MAGIC_OFFSET..].starts_with(USTAR_MAGIC) && !prefix.is_empty() {
+        [prefix, b"/", name].concat()
+    } else {
+        name.to_vec()
+    }
+}
+
+/// Whether the checksum field matches the header, summed with the field
+/// itself as spaces. Some old archivers summed signed bytes.
+fn checksum_matches(header: &[u8]) -> bool
+{
+    let Some(expected) = parse_octal(&header[148..156]) else { return false };
+    let (mut unsigned, mut signed) = (0u64, 0i64);
+    for (index, &byte) in header.iter().enumerate() {
+        let byte = if (148..156).contains(&index) { b' ' } else { byte };
+        unsigned += u64::from(byte);
+        signed += i64::from(byte as i8);
+    }
+    expected == unsigned || i64::try_from(expected) == Ok(signed)
+}
+
+/// A size field, in octal or, for sizes beyond its digits, in big endian
+/// base 256 marked by the high bit of its first byte.
+fn parse_size(field: &[u8]) -> Option<usize>
+{
+    let size = if field[0] & 0x80 != 0 {
+        field[1..].iter()
+            .try_fold(u64::from(field[0] & 0x7f), |size, &byte| size.checked_mul(256)?.checked_add(byte.into()))?
+    } else {
+        parse_octal(field)?
+    };
+    usize::try_from(size).ok()
+}
+
+/// An octal number padded with spaces and ended by a NUL or a space.
+fn parse_octal(field: &[u8]) -> Option<u64>
+{
+    let mut digits = field.iter()
+        .skip_while(|&&byte| byte == b' ')
+        .take_while(|&&byte| byte != 0 && byte != b' ');
+    digits.try_fold(0u64, |value, &digit| match digit {
+        b'0'..=b'7' => value.checked_mul(8)?.checked_add(u64::from(digit - b'0')),
+        _ => None,
+    })
+}
+
+/// The records of a pax extended header, each "<length> <key>=<value>\n".
+fn pax_records(mut contents: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])>
+{
+    std::iter::from_fn(move || {
+        let space = contents.iter().position(|&byte| byte == b' ')?;
+        let length: usize = std::str::from_utf8(&contents[..space]).ok()?.parse().ok()?;
+        let record = contents.get(space + 1..length)?;
+        contents = &contents[length..];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        let equals = record.iter().position(|&byte| byte == b'=')?;
+        Some((&record[..equals], &record[equals + 1..]))
+    })
+}
+
+fn until_nul(field: &[u8]) -> &[u8]
+{
+    field.split(|&byte| byte == 0).next().unwrap_or(field)
+}
+
+fn invalid(message: &str) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
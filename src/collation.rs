@@ -0,0 +1,73 @@
+use std::cmp::Ordering;
+
+
+/// How names are compared when sorting processes and groups.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Collation
+{
+    /// Runs of digits compare by value, so `worker-2` comes before
+    /// `worker-10`, see [`natural_cmp`].
+    #[default]
+    Natural,
+    /// Byte by byte, so `worker-10` comes before `worker-2`.
+    Bytewise,
+}
+
+impl Collation
+{
+    /// Compares two names in this collation.
+    pub fn compare(self, a: &[u8], b: &[u8]) -> Ordering
+    {
+        match self {
+            Collation::Natural => natural_cmp(a, b),
+            Collation::Bytewise => a.cmp(b),
+        }
+    }
+}
+
+/// Compares `a` and `b` split into runs of ASCII digits and runs of other
+/// bytes. Digit runs compare by value, of any length, and the other bytes
+/// one by one. Names that differ only in leading zeros, like `a01` and
+/// `a1`, compare byte by byte, so the order stays total.
+pub fn natural_cmp(a: &[u8], b: &[u8]) -> Ordering
+{
+    let (mut a_rest, mut b_rest) = (a, b);
+    loop {
+        match (a_rest.first(), b_rest.first()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (a_digits, a_after) = split_digits(a_rest);
+                let (b_digits, b_after) = split_digits(b_rest);
+                let ordering = compare_numbers(a_digits, b_digits);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                (a_rest, b_rest) = (a_after, b_after);
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                (a_rest, b_rest) = (&a_rest[1 ..], &b_rest[1 ..]);
+            }
+        }
+    }
+}
+
+/// Splits the leading run of digits off `name`.
+fn split_digits(name: &[u8]) -> (&[u8], &[u8])
+{
+    let end = name.iter().position(|byte| !byte.is_ascii_digit()).unwrap_or(name.len());
+    name.split_at(end)
+}
+
+/// Compares two runs of digits by value without parsing them, so they
+/// may be longer than any integer type.
+fn compare_numbers(a: &[u8], b: &[u8]) -> Ordering
+{
+    let trim = |digits: &[u8]| -> usize { digits.iter().position(|&digit| digit != b'0').unwrap_or(digits.len()) };
+    let (a, b) = (&a[trim(a) ..], &b[trim(b) ..]);
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
@@ -0,0 +1,126 @@
+use ptree::Color;
+use regex::Regex;
+
+
+fn invalid_color(color_str: &str) -> String
+{
+    format!(
+        "invalid color '{}' (expected a name like red/bright-red/..., a palette index from 0 to 255, \
+         R,G,B with 0-255 components, #RRGGBB or #RGB)",
+        color_str
+    )
+}
+
+fn parse_rgb(rgb_str: &str) -> Result<Color, String>
+{
+    let regex = Regex::new(r"^\s*(?P<r>\d+)\s*,\s*(?P<g>\d+)\s*,\s*(?P<b>\d+)\s*$").unwrap();
+    let capture = regex.captures(rgb_str).ok_or_else(|| invalid_color(rgb_str))?;
+    let mut rgb: [u8; 3] = [0, 0, 0];
+
+    for (i, color) in "rgb".chars().enumerate() {
+        rgb[i] = match str::parse::<u8>(&capture[String::from(color).as_str()]) {
+            Ok(color) => color,
+            Err(_) => return Err(invalid_color(rgb_str)),
+        };
+    }
+
+    Ok(Color::RGB(rgb[0], rgb[1], rgb[2]))
+}
+
+/// Parses `#RRGGBB`, `RRGGBB` or `#RGB` in any case. The short form needs
+/// the `#`, so that three digits without it are never taken for hex.
+fn parse_hex(color_str: &str) -> Option<Color>
+{
+    let (digits, short_allowed) = match color_str.strip_prefix('#') {
+        Some(digits) => (digits, true),
+        None => (color_str, false),
+    };
+    if !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |hex: &str| u8::from_str_radix(hex, 16).ok();
+    match digits.len() {
+        6 => Some(Color::RGB(channel(&digits[0 .. 2])?, channel(&digits[2 .. 4])?, channel(&digits[4 .. 6])?)),
+        3 if short_allowed => {
+            // #RGB stands for #RRGGBB.
+            let short = |index: usize| channel(&digits[index .. index + 1]).map(|value| value * 17);
+            Some(Color::RGB(short(0)?, short(1)?, short(2)?))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a 256-color palette index like "123" or "color123". Bare six digit
+/// numbers are left to [`parse_hex`].
+fn parse_palette_index(color_str: &str) -> Option<Result<Color, String>>
+{
+    let index = match color_str.strip_prefix("color") {
+        Some(index) => index,
+        None if color_str.len() != 6 => color_str,
+        None => return None,
+    };
+    if index.is_empty() || !index.bytes().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(index.parse::<u8>().map(Color::Fixed).map_err(|_| {
+        format!("invalid color '{}' (palette indices go from 0 to 255)", color_str)
+    }))
+}
+
+/// Parses a color name like "red" or "bright-red", a 256-color palette index
+/// like "123" or "color123", an RGB triple like "255,255,0" or hex like
+/// "#ffff00", "ffff00" or "#ff0". Anything with a comma is a triple, and a
+/// bare number is a palette index unless it has six digits, which makes it
+/// hex.
+pub fn parse_color(color_str: &str) -> Result<Color, String>
+{
+    let lower = color_str.trim().to_lowercase();
+    match lower.as_str() {
+        "black"   => Ok(Color::Black),
+        "red"     => Ok(Color::Red),
+        "green"   => Ok(Color::Green),
+        "yellow"  => Ok(Color::Yellow),
+        "blue"    => Ok(Color::Blue),
+        "purple"  => Ok(Color::Purple),
+        "cyan"    => Ok(Color::Cyan),
+        "white"   => Ok(Color::White),
+        "bright-black"  => Ok(Color::Fixed(8)),
+        "bright-red"    => Ok(Color::Fixed(9)),
+        "bright-green"  => Ok(Color::Fixed(10)),
+        "bright-yellow" => Ok(Color::Fixed(11)),
+        "bright-blue"   => Ok(Color::Fixed(12)),
+        "bright-purple" => Ok(Color::Fixed(13)),
+        "bright-cyan"   => Ok(Color::Fixed(14)),
+        "bright-white"  => Ok(Color::Fixed(15)),
+        _ if lower.contains(',') => parse_rgb(color_str),
+        _ => match parse_palette_index(&lower) {
+            Some(color) => color,
+            None => parse_hex(&lower).ok_or_else(|| invalid_color(color_str)),
+        },
+    }
+}
+
+/// The state letters of /proc/[pid]/stat, see proc(5).
+const PROCESS_STATES: &str = "RSDZTtXxKWPI";
+
+/// Parses `STATE=COLOR` like "Z=red", where STATE is a process state
+/// letter and COLOR anything [`parse_color`] accepts, or "none" to leave
+/// the state uncolored.
+pub fn parse_state_color(mapping: &str) -> Result<(char, Option<Color>), String>
+{
+    let (state, color) = mapping.split_once('=')
+        .ok_or_else(|| format!("invalid state color '{}' (expected STATE=COLOR, e.g. Z=red)", mapping))?;
+
+    let mut letters = state.trim().chars();
+    let state = match (letters.next(), letters.next()) {
+        (Some(letter), None) if PROCESS_STATES.contains(letter) => letter,
+        _ => return Err(format!("unknown process state '{}' (expected one of {})", state, PROCESS_STATES)),
+    };
+
+    match color.trim() {
+        none if none.eq_ignore_ascii_case("none") => Ok((state, None)),
+        color => parse_color(color).map(|color| (state, Some(color))),
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::Write as _;
+use std::io;
+
+use crate::config::PsTreePrintConfig;
+use crate::render::NodeItem;
+use crate::tree::ProcessTree;
+
+
+/// The line pieces of the classic pstree layout.
+struct Symbols
+{
+    /// Below a parent whose last child is already drawn.
+    empty: &'static str,
+    /// Before a child that has siblings after it.
+    branch: &'static str,
+    /// Below a parent that still has children to draw.
+    vertical: &'static str,
+    /// Before the last child.
+    last: &'static str,
+    /// Between a parent and its only child.
+    single: &'static str,
+    /// Between a parent and the first of its children.
+    first: &'static str,
+}
+
+const SYMBOLS: Symbols = Symbols { empty: "  ", branch: "├─", vertical: "│ ", last: "└─", single: "───", first: "─┬─" };
+const ASCII_SYMBOLS: Symbols = Symbols { empty: "  ", branch: "|-", vertical: "| ", last: "`-", single: "---", first: "-+-" };
+
+/// Ends a line cut at the line width.
+const CUT_MARK: char = '+';
+
+/// The lines of a tree drawn like the classic pstree, built one process at
+/// a time.
+struct CompatLayout
+{
+    symbols: &'static Symbols,
+    line: String,
+    lines: Vec<String>,
+    /// The width of the label drawn at every level of the current branch.
+    widths: Vec<usize>,
+    /// Whether the process at every level of the current branch has
+    /// siblings left to draw below it.
+    more: Vec<bool>,
+}
+
+impl CompatLayout
+{
+    /// Adds `item`, standing for `repeats` equal subtrees, at `level`.
+    /// `first` is set for the first child of its parent, which goes on the
+    /// line of the parent, `last` for its last child. Every leaf ends with
+    /// `closing` brackets, those of the repeated subtrees it ends.
+    fn add(&mut self, item: &NodeItem, level: usize, repeats: usize, first: bool, last: bool, closing: usize)
+    {
+        if !first {
+            for lvl in 0..level {
+                self.line.push_str(&" ".repeat(self.widths[lvl] + 1));
+                self.line.push_str(match lvl + 1 == level {
+                    true if last => self.symbols.last,
+                    true => self.symbols.branch,
+                    false if self.more[lvl + 1] => self.symbols.vertical,
+                    false => self.symbols.empty,
+                });
+            }
+        }
+        let start = self.line.chars().count();
+        if repeats > 1 {
+            let _ = write!(self.line, "{}*[", repeats);
+        }
+        self.line.push_str(&item.text.replace(['\n', '\r'], " "));
+        let width = self.line.chars().count() - start;
+
+        if item.children.is_empty() {
+            self.line.push_str(&"]".repeat(closing));
+            self.lines.push(std::mem::take(&mut self.line));
+            return;
+        }
+        self.widths.truncate(level);
+        self.widths.push(width);
+        self.more.truncate(level);
+        self.more.push(!last);
+
+        let children = compact(&item.children);
+        for (index, &(child, count)) in children.iter().enumerate() {
+            let has_next = index + 1 < children.len();
+            if index == 0 {
+                self.line.push_str(if has_next { self.symbols.first } else { self.symbols.single });
+            }
+            self.add(child, level + 1, count, index == 0, !has_next, closing + usize::from(count > 1));
+        }
+    }
+}
+
+/// The distinct subtrees of `children` in order, each with the number of
+/// subtrees equal to it, wherever they are among the siblings.
+fn compact<'i, 'a>(children: &'i [NodeItem<'a>]) -> Vec<(&'i NodeItem<'a>, usize)>
+{
+    let mut taken = vec![false; children.len()];
+    let mut compacted = Vec::new();
+    for (index, child) in children.iter().enumerate() {
+        if taken[index] {
+            continue;
+        }
+        let mut count = 1;
+        for (other_index, other) in children.iter().enumerate().skip(index + 1) {
+            if !taken[other_index] && subtrees_equal(child, other) {
+                taken[other_index] = true;
+                count += 1;
+            }
+        }
+        compacted.push((child, count));
+    }
+    compacted
+}
+
+/// True if both subtrees have the same labels in the same shape.
+fn subtrees_equal(a: &NodeItem, b: &NodeItem) -> bool
+{
+    a.text == b.text
+        && a.children.len() == b.children.len()
+        && a.children.iter().zip(&b.children).all(|(a, b)| subtrees_equal(a, b))
+}
+
+/// `line` cut to `width` columns, ending with "+" when it was longer.
+fn cut_line(line: &str, width: Option<usize>) -> String
+{
+    match width {
+        Some(width) if line.chars().count() > width => {
+            let mut cut: String = line.chars().take(width).collect();
+            cut.push(CUT_MARK);
+            cut
+        }
+        _ => line.to_string(),
+    }
+}
+
+impl ProcessTree
+{
+    /// Draws every root like the classic pstree: a process and its first
+    /// child on one line joined by "───" or "─┬─", the other children below
+    /// it, and equal sibling subtrees drawn once like "3*[sleep]". Lines
+    /// longer than `config.line_width` are cut and end with "+".
+    pub(crate) fn write_compat<W: io::Write>(&self, mut out: W, config: &PsTreePrintConfig) -> io::Result<()>
+    {
+        let symbols = match config.print_config.characters.down.is_ascii() {
+            true => &ASCII_SYMBOLS,
+            false => &SYMBOLS,
+        };
+        for item in self.root_items(config, false) {
+            let mut layout = CompatLayout {
+                symbols,
+                line: String::new(),
+                lines: Vec::new(),
+                widths: Vec::new(),
+                more: Vec::new(),
+            };
+            layout.add(&item, 0, 1, true, true, 0);
+            for line in &layout.lines {
+                writeln!(out, "{}", cut_line(line, config.line_width))?;
+            }
+        }
+        Ok(())
+    }
+}
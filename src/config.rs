@@ -0,0 +1,627 @@
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use ptree::print_config::StyleWhen;
+use ptree::{Color, PrintConfig};
+
+use crate::collation::Collation;
+use crate::highlight::Highlight;
+use crate::size::SizeUnit;
+use crate::style::TextStyle;
+use crate::user::GroupTable;
+
+
+/// Colors of the process states that need attention: uninterruptible
+/// sleep, stopped and zombie.
+const DEFAULT_STATE_COLORS: [(char, Color); 3] = [('D', Color::Yellow), ('T', Color::Purple), ('Z', Color::Red)];
+
+/// The colors [`ColorBy`] cycles through unless given others.
+pub(crate) const DEFAULT_PALETTE: [Color; 5] = [Color::Cyan, Color::Green, Color::Yellow, Color::Blue, Color::Purple];
+
+/// The order siblings are drawn in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey
+{
+    /// Ascending pid, the order processes are scanned in.
+    Pid,
+    /// Process name in the [`Collation`](crate::Collation) of the config,
+    /// ties broken by pid.
+    Name,
+}
+
+impl FromStr for SortKey
+{
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<SortKey, String>
+    {
+        match key {
+            "pid"  => Ok(SortKey::Pid),
+            "name" => Ok(SortKey::Name),
+            _ => Err(format!("invalid sort key '{}' (expected pid or name)", key)),
+        }
+    }
+}
+
+/// How pids are written next to the process names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PidFormat
+{
+    /// Before the name: `[812] sshd`.
+    Brackets,
+    /// After the name like the classic pstree: `sshd(812)`.
+    Parens,
+}
+
+impl FromStr for PidFormat
+{
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<PidFormat, String>
+    {
+        match format {
+            "brackets" => Ok(PidFormat::Brackets),
+            "parens"   => Ok(PidFormat::Parens),
+            _ => Err(format!("invalid pid format '{}' (expected brackets or parens)", format)),
+        }
+    }
+}
+
+/// What picks the color of every node from the palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorBy
+{
+    /// The depth below the root, so every level has its own color.
+    Depth,
+}
+
+impl FromStr for ColorBy
+{
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<ColorBy, String>
+    {
+        match key {
+            "depth" => Ok(ColorBy::Depth),
+            _ => Err(format!("invalid color key '{}' (expected depth)", key)),
+        }
+    }
+}
+
+/// A combination of options that cannot be drawn.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigError
+{
+    /// The indentation leaves no room for the branch line after the padding.
+    IndentTooSmall
+    {
+        /// The requested indentation.
+        indent: usize,
+        /// The requested padding.
+        padding: usize,
+    },
+}
+
+impl fmt::Display for ConfigError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            ConfigError::IndentTooSmall { indent, padding } => write!(
+                f, "indent ({}) must be larger than padding ({})", indent, padding
+            ),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// How a [`ProcessTree`](crate::ProcessTree) is shaped and drawn.
+pub struct PsTreePrintConfig
+{
+    /// Prefix every process name with its pid.
+    pub show_pid: bool,
+    /// Print the pids right-aligned in a column left of the tree instead.
+    pub align_pids: bool,
+    /// How `show_pid` writes the pids.
+    pub pid_format: PidFormat,
+    /// Write the parent pid after every pid, like `[812<1]`.
+    pub show_ppid: bool,
+    /// Draw each root as its own tree instead of grouping them under "(all)".
+    pub forest: bool,
+    /// Hide processes more than this many levels below the roots.
+    pub depth: Option<u32>,
+    /// Keep only this many of the largest subtrees below the root, see
+    /// [`ProcessTree::keep_top_subtrees`](crate::ProcessTree::keep_top_subtrees).
+    pub top: Option<usize>,
+    /// Remove leaves that repeat the name of the sibling before them.
+    pub unique: bool,
+    /// Draw at most this many lines, cutting the deepest subtrees first, see
+    /// [`ProcessTree::truncate`](crate::ProcessTree::truncate). Every process
+    /// counts as one line, even when `wrap` splits its label, and the tree
+    /// of the gone processes drawn after it is not counted.
+    pub max_lines: Option<usize>,
+    /// Draw each run of processes with a single child on one line, like
+    /// "sh ▸ sudo ▸ myapp".
+    pub collapse_chains: bool,
+    /// Start every line with "level N: ", N counting from 1 at the top.
+    pub level_prefix: bool,
+    /// Draw the children above their parent, so each tree ends with its
+    /// root.
+    pub upside_down: bool,
+    /// Draw the ancestors of a single root that is not top-level on a
+    /// dimmed line above the tree.
+    pub ancestor_header: bool,
+    /// Draw the tree like the classic pstree, with the first child of every
+    /// process on its line and equal subtrees drawn once like "3*[sleep]".
+    pub compat: bool,
+    /// With `compat`, cut lines longer than this many columns, ending them
+    /// with "+".
+    pub line_width: Option<usize>,
+    /// Wrap the labels that would reach past this many columns onto lines
+    /// of their own, aligned under the start of the label.
+    pub wrap: Option<usize>,
+    /// The order siblings are drawn in.
+    pub sort: SortKey,
+    /// How names compare when sorting by name.
+    pub collation: Collation,
+    /// Show the umask of processes that use the usual 0022 too.
+    pub show_default_umask: bool,
+    /// Tag the processes whose no_new_privs flag has this value.
+    pub mark_no_new_privs: Option<bool>,
+    /// Tag the processes confined by seccomp with their mode.
+    pub show_seccomp: bool,
+    /// Show the highest resident memory of each process after its current
+    /// one.
+    pub show_peak_memory: bool,
+    /// Show the highest virtual memory size of each process as well.
+    pub show_peak_virtual: bool,
+    /// How memory sizes are written.
+    pub size_unit: SizeUnit,
+    /// Show the proportional set size of each process instead of its
+    /// resident memory, the latter marked with "*" where it was not read.
+    pub pss: bool,
+    /// Follow the memory of each process with its private and shared
+    /// parts, where they were read.
+    pub memory_detail: bool,
+    /// Show the supplementary groups of each process after its name.
+    pub show_groups: bool,
+    /// Mark the processes whose namespace of one of these types, like
+    /// `mnt`, differs from the one of their parent.
+    pub show_ns: Vec<String>,
+    /// Names shown for supplementary group ids. Ids missing from it are
+    /// shown as numbers.
+    pub group_names: GroupTable,
+    /// Foreground colors of processes by state letter, e.g. red for `Z`.
+    /// Processes in other states use the node color.
+    pub state_colors: BTreeMap<char, Color>,
+    /// Draw the processes whose name matches one of these patterns in its
+    /// color, the first match winning. A legend follows the tree.
+    pub highlights: Vec<Highlight>,
+    /// Color every node from `palette`, unless its state has a color.
+    pub color_by: Option<ColorBy>,
+    /// The colors `color_by` picks from, cycling when there are fewer than
+    /// needed.
+    pub palette: Vec<Color>,
+    /// Colors, indentation and line characters passed on to ptree.
+    pub print_config: PrintConfig,
+}
+
+impl PsTreePrintConfig 
+{
+    /// The default configuration: names only, roots grouped under "(all)".
+    pub fn new() -> PsTreePrintConfig
+    {
+        PsTreePrintConfig 
+        { 
+            show_pid: false,
+            align_pids: false,
+            pid_format: PidFormat::Brackets,
+            show_ppid: false,
+            forest: false,
+            depth: None,
+            top: None,
+            unique: false,
+            max_lines: None,
+            collapse_chains: false,
+            level_prefix: false,
+            upside_down: false,
+            ancestor_header: false,
+            compat: false,
+            line_width: None,
+            wrap: None,
+            sort: SortKey::Pid,
+            collation: Collation::Natural,
+            show_default_umask: false,
+            mark_no_new_privs: None,
+            show_seccomp: false,
+            show_peak_memory: false,
+            show_peak_virtual: false,
+            size_unit: SizeUnit::Human,
+            pss: false,
+            memory_detail: false,
+            show_groups: false,
+            show_ns: Vec::new(),
+            group_names: GroupTable::default(),
+            state_colors: DEFAULT_STATE_COLORS.into_iter().collect(),
+            highlights: Vec::new(),
+            color_by: None,
+            palette: DEFAULT_PALETTE.to_vec(),
+            print_config: PrintConfig::default(),
+        }
+    }
+
+    /// Starts a builder from the default configuration.
+    pub fn builder() -> PsTreePrintConfigBuilder
+    {
+        PsTreePrintConfigBuilder { config: PsTreePrintConfig::new() }
+    }
+
+    /// The palette color of a node `depth` levels below its root with
+    /// [`ColorBy::Depth`], None without `color_by` or with an empty palette.
+    pub fn depth_color(&self, depth: usize) -> Option<&Color>
+    {
+        match self.color_by? {
+            ColorBy::Depth if !self.palette.is_empty() => self.palette.get(depth % self.palette.len()),
+            ColorBy::Depth => None,
+        }
+    }
+}
+
+impl Default for PsTreePrintConfig
+{
+    fn default() -> PsTreePrintConfig
+    {
+        PsTreePrintConfig::new()
+    }
+}
+
+/// Builds a [`PsTreePrintConfig`], checking the options in [`build`](Self::build).
+pub struct PsTreePrintConfigBuilder
+{
+    config: PsTreePrintConfig,
+}
+
+impl PsTreePrintConfigBuilder
+{
+    /// Prefix every process name with its pid.
+    pub fn show_pid(mut self, show_pid: bool) -> Self
+    {
+        self.config.show_pid = show_pid;
+        self
+    }
+
+    /// Print the pids in a column left of the tree.
+    pub fn align_pids(mut self, align_pids: bool) -> Self
+    {
+        self.config.align_pids = align_pids;
+        self
+    }
+
+    /// Write the pids shown by `show_pid` as `pid_format`.
+    pub fn pid_format(mut self, pid_format: PidFormat) -> Self
+    {
+        self.config.pid_format = pid_format;
+        self
+    }
+
+    /// Follow every pid with the pid of its parent, like `[812<1] sshd`.
+    pub fn show_ppid(mut self, show_ppid: bool) -> Self
+    {
+        self.config.show_ppid = show_ppid;
+        self
+    }
+
+    /// Draw each root as its own tree.
+    pub fn forest(mut self, forest: bool) -> Self
+    {
+        self.config.forest = forest;
+        self
+    }
+
+    /// Hide processes more than `depth` levels below the roots.
+    pub fn depth(mut self, depth: u32) -> Self
+    {
+        self.config.depth = Some(depth);
+        self
+    }
+
+    /// Keep the `count` largest subtrees below the root and draw the others
+    /// as a single line.
+    pub fn top(mut self, count: usize) -> Self
+    {
+        self.config.top = Some(count);
+        self
+    }
+
+    /// Remove duplicated leaves.
+    pub fn unique(mut self, unique: bool) -> Self
+    {
+        self.config.unique = unique;
+        self
+    }
+
+    /// Draw at most `max_lines` lines, counting one per process.
+    pub fn max_lines(mut self, max_lines: usize) -> Self
+    {
+        self.config.max_lines = Some(max_lines);
+        self
+    }
+
+    /// Merge runs of processes that have a single child into one line.
+    pub fn collapse_chains(mut self, collapse_chains: bool) -> Self
+    {
+        self.config.collapse_chains = collapse_chains;
+        self
+    }
+
+    /// Draw the children above their parent.
+    pub fn upside_down(mut self, upside_down: bool) -> Self
+    {
+        self.config.upside_down = upside_down;
+        self
+    }
+
+    /// Draw the ancestors of a single root above the tree.
+    pub fn ancestor_header(mut self, ancestor_header: bool) -> Self
+    {
+        self.config.ancestor_header = ancestor_header;
+        self
+    }
+
+    /// Draw the tree like the classic pstree.
+    pub fn compat(mut self, compat: bool) -> Self
+    {
+        self.config.compat = compat;
+        self
+    }
+
+    /// With `compat`, cut lines longer than `line_width` columns.
+    pub fn line_width(mut self, line_width: usize) -> Self
+    {
+        self.config.line_width = Some(line_width);
+        self
+    }
+
+    /// Wrap the labels reaching past `width` columns.
+    pub fn wrap(mut self, width: usize) -> Self
+    {
+        self.config.wrap = Some(width);
+        self
+    }
+
+    /// Order siblings by `sort`.
+    pub fn sort(mut self, sort: SortKey) -> Self
+    {
+        self.config.sort = sort;
+        self
+    }
+
+    /// Compare names in `collation` when sorting by name.
+    pub fn collation(mut self, collation: Collation) -> Self
+    {
+        self.config.collation = collation;
+        self
+    }
+
+    /// Show umasks equal to 0022 too.
+    pub fn show_default_umask(mut self, show_default_umask: bool) -> Self
+    {
+        self.config.show_default_umask = show_default_umask;
+        self
+    }
+
+    /// Tag the processes whose no_new_privs flag is `no_new_privs`, as
+    /// `[nnp]` or `[no nnp]`.
+    pub fn mark_no_new_privs(mut self, no_new_privs: bool) -> Self
+    {
+        self.config.mark_no_new_privs = Some(no_new_privs);
+        self
+    }
+
+    /// Tag the processes confined by seccomp, as `[seccomp:strict]` or
+    /// `[seccomp:filter]`.
+    pub fn show_seccomp(mut self, show_seccomp: bool) -> Self
+    {
+        self.config.show_seccomp = show_seccomp;
+        self
+    }
+
+    /// Show the highest resident memory of each process, as `peak=6.3G`,
+    /// and with `show_peak_virtual` its highest virtual memory size, as
+    /// `vpeak=12G`.
+    pub fn show_peak_memory(mut self, show_peak_memory: bool, show_peak_virtual: bool) -> Self
+    {
+        self.config.show_peak_memory = show_peak_memory;
+        self.config.show_peak_virtual = show_peak_virtual;
+        self
+    }
+
+    /// Write memory sizes in `size_unit`.
+    pub fn size_unit(mut self, size_unit: SizeUnit) -> Self
+    {
+        self.config.size_unit = size_unit;
+        self
+    }
+
+    /// Show proportional set sizes instead of resident memory when `pss`
+    /// is set.
+    pub fn pss(mut self, pss: bool) -> Self
+    {
+        self.config.pss = pss;
+        self
+    }
+
+    /// Split the memory of each process into its private and shared parts
+    /// when `memory_detail` is set.
+    pub fn memory_detail(mut self, memory_detail: bool) -> Self
+    {
+        self.config.memory_detail = memory_detail;
+        self
+    }
+
+    /// Show the supplementary groups of each process, like "(wheel,docker)".
+    pub fn show_groups(mut self, show_groups: bool) -> Self
+    {
+        self.config.show_groups = show_groups;
+        self
+    }
+
+    /// Mark the processes that left the namespaces of their parent of the
+    /// types `show_ns`, like `⊘mnt`.
+    pub fn show_ns(mut self, show_ns: Vec<String>) -> Self
+    {
+        self.config.show_ns = show_ns;
+        self
+    }
+
+    /// Show supplementary groups by their names in `group_names`.
+    pub fn group_names(mut self, group_names: GroupTable) -> Self
+    {
+        self.config.group_names = group_names;
+        self
+    }
+
+    /// Draws processes in `state` with `color`, or with the node color when
+    /// `color` is None, replacing the default color of the state.
+    pub fn state_color(mut self, state: char, color: Option<Color>) -> Self
+    {
+        match color {
+            Some(color) => self.config.state_colors.insert(state, color),
+            None => self.config.state_colors.remove(&state),
+        };
+        self
+    }
+
+    /// Color every node by `color_by`.
+    pub fn color_by(mut self, color_by: ColorBy) -> Self
+    {
+        self.config.color_by = Some(color_by);
+        self
+    }
+
+    /// Draw the processes matching the patterns of `highlights` in their
+    /// colors.
+    pub fn highlights(mut self, highlights: Vec<Highlight>) -> Self
+    {
+        self.config.highlights = highlights;
+        self
+    }
+
+    /// The colors `color_by` picks from.
+    pub fn palette(mut self, palette: Vec<Color>) -> Self
+    {
+        self.config.palette = palette;
+        self
+    }
+
+    /// Foreground color of the process names.
+    pub fn node_color(mut self, color: Color) -> Self
+    {
+        self.config.print_config.leaf.foreground = Some(color);
+        self
+    }
+
+    /// Background color of the process names.
+    pub fn background(mut self, color: Color) -> Self
+    {
+        self.config.print_config.leaf.background = Some(color);
+        self
+    }
+
+    /// Text attributes of the process names, combined with their colors.
+    pub fn node_style(mut self, style: TextStyle) -> Self
+    {
+        style.apply(&mut self.config.print_config.leaf);
+        self
+    }
+
+    /// Text attributes of the branch lines, combined with their color.
+    pub fn branch_style(mut self, style: TextStyle) -> Self
+    {
+        style.apply(&mut self.config.print_config.branch);
+        self
+    }
+
+    /// Color of the branch lines.
+    pub fn branch_color(mut self, color: Color) -> Self
+    {
+        self.config.print_config.branch.foreground = Some(color);
+        self
+    }
+
+    /// Background color of the branch lines.
+    pub fn branch_background(mut self, color: Color) -> Self
+    {
+        self.config.print_config.branch.background = Some(color);
+        self
+    }
+
+    /// Draw the branches with ASCII characters.
+    pub fn ascii(mut self, ascii: bool) -> Self
+    {
+        if ascii {
+            let characters = &mut self.config.print_config.characters;
+            characters.down = String::from("|");
+            characters.right = String::from("-");
+            characters.down_and_right = String::from("|");
+            characters.turn_right = String::from("`");
+        }
+        self
+    }
+
+    /// Indent with spaces only, drawing no branches.
+    pub fn plain(mut self, plain: bool) -> Self
+    {
+        if plain {
+            let characters = &mut self.config.print_config.characters;
+            characters.down = String::from(" ");
+            characters.right = String::from(" ");
+            characters.down_and_right = String::from(" ");
+            characters.turn_right = String::from(" ");
+        }
+        self
+    }
+
+    /// Start every line with its level.
+    pub fn level_prefix(mut self, level_prefix: bool) -> Self
+    {
+        self.config.level_prefix = level_prefix;
+        self
+    }
+
+    /// Leave the output uncolored, even on a terminal.
+    pub fn no_colors(mut self, no_colors: bool) -> Self
+    {
+        if no_colors {
+            self.config.print_config.styled = StyleWhen::Never;
+        }
+        self
+    }
+
+    /// Width of one indentation level, 3 by default.
+    pub fn indent(mut self, indent: usize) -> Self
+    {
+        self.config.print_config.indent = indent;
+        self
+    }
+
+    /// Spaces between the branch line and the name, 1 by default.
+    pub fn padding(mut self, padding: usize) -> Self
+    {
+        self.config.print_config.padding = padding;
+        self
+    }
+
+    /// Checks the options and returns the configuration.
+    pub fn build(self) -> Result<PsTreePrintConfig, ConfigError>
+    {
+        let PrintConfig { indent, padding, .. } = self.config.print_config;
+        if indent <= padding {
+            return Err(ConfigError::IndentTooSmall { indent, padding });
+        }
+        Ok(self.config)
+    }
+}
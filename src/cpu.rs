@@ -0,0 +1,35 @@
+use std::collections::BTreeSet;
+
+
+/// Parses a CPU list like `0-3,8,10-11`, the format of `Cpus_allowed_list`
+/// in /proc/[pid]/status and of /sys/devices/system/cpu/online. Returns
+/// None for malformed lists and for ranges running backwards.
+pub fn parse_cpu_list(list: &str) -> Option<BTreeSet<u32>>
+{
+    let mut cpus: BTreeSet<u32> = BTreeSet::new();
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        let (first, last): (u32, u32) = match range.split_once('-') {
+            Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+            None => {
+                let cpu = range.parse().ok()?;
+                (cpu, cpu)
+            }
+        };
+        if first > last {
+            return None;
+        }
+        cpus.extend(first ..= last);
+    }
+    Some(cpus)
+}
+
+/// The CPUs listed in /proc/cpuinfo, which only shows the online ones.
+pub(crate) fn parse_online_cpus(cpuinfo: &[u8]) -> BTreeSet<u32>
+{
+    String::from_utf8_lossy(cpuinfo).lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if key.trim() == "processor" { value.trim().parse().ok() } else { None }
+        })
+        .collect()
+}
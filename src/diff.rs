@@ -0,0 +1,182 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::group::ProcessGroup;
+use crate::process::ProcessInfo;
+use crate::tree::ProcessTree;
+
+
+const GONE_GROUP_LABEL: &str = "(gone)";
+
+/// Identifies a process across scans. Pids are reused, so the start time
+/// tells a new process from an exited one that had the same pid.
+pub type ProcessKey = (u32, Option<u64>);
+
+/// How a process differs from an earlier scan of the same system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change
+{
+    /// Started since the earlier scan.
+    New,
+    /// Exited since the earlier scan.
+    Gone,
+    /// Running in both scans, with the state or resident memory it had in
+    /// the earlier one when that changed, see [`ChangeThresholds`].
+    Changed
+    {
+        /// The earlier state, if it changed.
+        state: Option<char>,
+        /// The earlier resident memory in bytes, if it changed enough.
+        rss: Option<u64>,
+    },
+    /// Unchanged, kept only as the ancestor of a change by
+    /// [`ProcessTree::retain_changes`].
+    Context,
+}
+
+/// Which changes of a running process [`ProcessTree::diff_with`] reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChangeThresholds
+{
+    /// Report changes of the state letter, if both scans read it.
+    pub state: bool,
+    /// Report changes of the resident memory by more than this percentage
+    /// of the earlier value, if both scans read it.
+    pub rss_percent: Option<u32>,
+}
+
+/// The processes that differ between an earlier tree and a later one, see
+/// [`ProcessTree::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TreeDiff
+{
+    /// The keys of the processes of the later tree missing from the earlier one.
+    pub new: HashSet<ProcessKey>,
+    /// The processes of the earlier tree missing from the later one, in the
+    /// order of the earlier tree.
+    pub gone: Vec<ProcessInfo>,
+    /// The processes of both trees that changed, keyed like `new`, each with
+    /// a [`Change::Changed`].
+    pub changed: HashMap<ProcessKey, Change>,
+}
+
+impl ProcessInfo
+{
+    /// The pid and start time of the process. Both trees of a diff need
+    /// their start times read for reused pids to be told apart.
+    pub fn key(&self) -> ProcessKey
+    {
+        (self.pid, self.start_time)
+    }
+}
+
+impl TreeDiff
+{
+    /// True if `proc_info` was started since the earlier scan.
+    pub fn is_new(&self, proc_info: &ProcessInfo) -> bool
+    {
+        self.new.contains(&proc_info.key())
+    }
+
+    /// True if `proc_info` was started or changed since the earlier scan.
+    pub fn is_changed(&self, proc_info: &ProcessInfo) -> bool
+    {
+        let key = proc_info.key();
+        self.new.contains(&key) || self.changed.contains_key(&key)
+    }
+
+    /// True if no process started, exited or changed.
+    pub fn is_empty(&self) -> bool
+    {
+        self.new.is_empty() && self.gone.is_empty() && self.changed.is_empty()
+    }
+
+    /// The exited processes linked by their parent pids in a group labeled
+    /// "(gone)", every node marked [`Change::Gone`]. Processes whose parent
+    /// is still running are roots of the group.
+    pub fn gone_tree(&self) -> ProcessTree
+    {
+        let mut tree = ProcessTree::from_process_list(self.gone.clone());
+        for node in &mut tree.nodes {
+            node.change = Some(Change::Gone);
+        }
+        if !tree.roots.is_empty() {
+            tree.groups.push(ProcessGroup {
+                label: GONE_GROUP_LABEL.to_string(),
+                roots: tree.roots.clone(),
+                subgroups: Vec::new(),
+            });
+        }
+        tree
+    }
+}
+
+impl ProcessTree
+{
+    /// Compares every process of the tree, drawn or not, with the ones of
+    /// `earlier`.
+    pub fn diff(&self, earlier: &ProcessTree) -> TreeDiff
+    {
+        let current: HashSet<ProcessKey> = self.nodes.iter().map(|node| node.proc_info.key()).collect();
+        let earlier_keys: HashSet<ProcessKey> = earlier.nodes.iter().map(|node| node.proc_info.key()).collect();
+
+        TreeDiff {
+            new: current.difference(&earlier_keys).copied().collect(),
+            gone: earlier.nodes.iter()
+                .filter(|node| !current.contains(&node.proc_info.key()))
+                .map(|node| node.proc_info.clone())
+                .collect(),
+            changed: HashMap::new(),
+        }
+    }
+
+    /// Like [`diff`](Self::diff), and also compares the processes running
+    /// in both trees as `thresholds` asks.
+    pub fn diff_with(&self, earlier: &ProcessTree, thresholds: &ChangeThresholds) -> TreeDiff
+    {
+        let mut diff = self.diff(earlier);
+        for node in &self.nodes {
+            let later = &node.proc_info;
+            let Some(&index) = earlier.pid_index.get(&later.pid) else { continue };
+            let before = &earlier.nodes[index].proc_info;
+            if before.key() != later.key() {
+                continue;
+            }
+            let state = before.state.filter(|&state| thresholds.state && later.state.is_some_and(|now| now != state));
+            let rss = match (thresholds.rss_percent, before.rss, later.rss) {
+                (Some(percent), Some(rss), Some(now)) if rss.abs_diff(now) * 100 > rss * u64::from(percent) => Some(rss),
+                _ => None,
+            };
+            if state.is_some() || rss.is_some() {
+                diff.changed.insert(later.key(), Change::Changed { state, rss });
+            }
+        }
+        diff
+    }
+
+    /// Marks the processes of `diff.new` as [`Change::New`] and the ones of
+    /// `diff.changed` with their change.
+    pub fn mark_changes(&mut self, diff: &TreeDiff)
+    {
+        for node in &mut self.nodes {
+            if diff.is_new(&node.proc_info) {
+                node.change = Some(Change::New);
+            } else if let Some(&change) = diff.changed.get(&node.proc_info.key()) {
+                node.change = Some(change);
+            }
+        }
+    }
+
+    /// Keeps only the started and changed processes of `diff` and their
+    /// ancestors, which are marked [`Change::Context`]. Returns the number
+    /// of changes kept.
+    pub fn retain_changes(&mut self, diff: &TreeDiff) -> usize
+    {
+        let changes = self.retain_matches(|proc_info| diff.is_changed(proc_info));
+        let kept: Vec<usize> = self.iter_dfs().map(|(_, index)| index).collect();
+        for index in kept {
+            let node = &mut self.nodes[index];
+            node.change = node.change.or(Some(Change::Context));
+        }
+        changes
+    }
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+
+/// Formats a duration in its two largest units, with tenths of a second
+/// below a minute: `0.4s`, `38.2s`, `12m5s`, `4h12m`, `3d7h`.
+pub fn format_duration(duration: Duration) -> String
+{
+    let tenths = (duration.as_millis() + 50) / 100;
+    if tenths < 600 {
+        return format!("{}.{}s", tenths / 10, tenths % 10);
+    }
+
+    let seconds = (duration.as_millis() + 500) / 1000;
+    let (minutes, hours, days) = (seconds / 60, seconds / 3600, seconds / 86400);
+    if hours == 0 {
+        format!("{}m{}s", minutes, seconds % 60)
+    } else if days == 0 {
+        format!("{}h{}m", hours, minutes % 60)
+    } else {
+        format!("{}d{}h", days, hours % 24)
+    }
+}
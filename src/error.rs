@@ -0,0 +1,174 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::keyword::PidKeyword;
+
+
+const EXIT_NO_PROCESS: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_SYSTEM_ERROR: i32 = 3;
+const EXIT_SIGNAL_FAILED: i32 = 4;
+const EXIT_DECLINED: i32 = 5;
+
+/// Errors that stop the process tree from being built.
+#[derive(Debug)]
+pub enum PsTreeError
+{
+    /// The requested root processes are not in the scanned set.
+    ProcessNotFound(Vec<u32>),
+    /// A pid keyword like "parent" stands for a process that is not in the
+    /// scanned set, usually because it exited in between.
+    KeywordNotFound(PidKeyword, u32),
+    /// No process matches the given name pattern.
+    NoMatch(String),
+    /// Several processes match the given name equally well, as their pids
+    /// and matched names.
+    AmbiguousMatch(String, Vec<(u32, String)>),
+    /// The requested user owns none of the processes.
+    NoUserProcess(u32),
+    /// No process has the requested supplementary group.
+    NoGroupProcess(u32),
+    /// No process passes a filter, described by an adjective like "traced".
+    NoFilterMatch(String),
+    /// No process has the file open.
+    FileNotOpen(PathBuf),
+    /// The start time of a process compared with could not be read.
+    StartTimeUnknown(u32),
+    /// The proc root was readable but no process could be parsed from it,
+    /// usually because it is not a procfs mount.
+    NoProcesses(PathBuf),
+    /// The proc root itself could not be read.
+    ProcUnreadable(PathBuf, io::Error),
+    /// A snapshot file could not be read or parsed.
+    LoadFailed(PathBuf, io::Error),
+    /// The output of ps given to read could not be read or parsed.
+    PsOutputUnreadable(PathBuf, io::Error),
+    /// A snapshot file could not be written.
+    SaveFailed(PathBuf, io::Error),
+    /// The placeholders of --anonymize could not be written to their file.
+    MapWriteFailed(PathBuf, io::Error),
+    /// A signal could not be delivered to some processes, as the number of
+    /// failures out of the number of processes signaled.
+    SignalFailed(usize, usize),
+    /// The nice value of some processes could not be changed, as the
+    /// number of failures out of the number of processes reniced.
+    ReniceFailed(usize, usize),
+    /// The selection holds a process that is only acted on with --force,
+    /// init or an ancestor of the running process, with the action refused.
+    ProtectedProcess(u32, String),
+    /// The confirmation asked before acting on the processes was declined,
+    /// with what was not done like "no signal sent".
+    Declined(String),
+}
+
+/// Result type of the scanning and tree building functions.
+pub type Result<T> = std::result::Result<T, PsTreeError>;
+
+impl PsTreeError
+{
+    /// The exit status the command line tool uses for this error.
+    pub fn exit_code(&self) -> i32
+    {
+        match self {
+            PsTreeError::ProcessNotFound(_) => EXIT_NO_PROCESS,
+            PsTreeError::KeywordNotFound(..) => EXIT_NO_PROCESS,
+            PsTreeError::NoMatch(_) => EXIT_NO_PROCESS,
+            PsTreeError::AmbiguousMatch(..) => EXIT_USAGE,
+            PsTreeError::NoUserProcess(_) => EXIT_NO_PROCESS,
+            PsTreeError::NoGroupProcess(_) => EXIT_NO_PROCESS,
+            PsTreeError::NoFilterMatch(_) => EXIT_NO_PROCESS,
+            PsTreeError::FileNotOpen(_) => EXIT_NO_PROCESS,
+            PsTreeError::StartTimeUnknown(_) => EXIT_SYSTEM_ERROR,
+            PsTreeError::NoProcesses(_) => EXIT_SYSTEM_ERROR,
+            PsTreeError::ProcUnreadable(..) => EXIT_SYSTEM_ERROR,
+            PsTreeError::LoadFailed(..) => EXIT_SYSTEM_ERROR,
+            PsTreeError::PsOutputUnreadable(..) => EXIT_SYSTEM_ERROR,
+            PsTreeError::SaveFailed(..) => EXIT_SYSTEM_ERROR,
+            PsTreeError::MapWriteFailed(..) => EXIT_SYSTEM_ERROR,
+            PsTreeError::SignalFailed(..) => EXIT_SIGNAL_FAILED,
+            PsTreeError::ReniceFailed(..) => EXIT_SIGNAL_FAILED,
+            PsTreeError::ProtectedProcess(..) => EXIT_USAGE,
+            PsTreeError::Declined(_) => EXIT_DECLINED,
+        }
+    }
+
+    /// True if the processes were read but the requested roots, name or
+    /// user selected none of them.
+    pub fn is_empty_selection(&self) -> bool
+    {
+        matches!(
+            self,
+            PsTreeError::ProcessNotFound(_) | PsTreeError::KeywordNotFound(..) | PsTreeError::NoMatch(_)
+                | PsTreeError::NoUserProcess(_) | PsTreeError::NoGroupProcess(_) | PsTreeError::NoFilterMatch(_)
+                | PsTreeError::FileNotOpen(_)
+        )
+    }
+}
+
+impl fmt::Display for PsTreeError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            PsTreeError::ProcessNotFound(pids) if pids.len() == 1 => {
+                write!(f, "process {} does not exist", pids[0])
+            }
+            PsTreeError::ProcessNotFound(pids) => {
+                let pids: Vec<String> = pids.iter().map(u32::to_string).collect();
+                write!(f, "processes {} do not exist", pids.join(", "))
+            }
+            PsTreeError::KeywordNotFound(keyword, pid) => {
+                write!(f, "'{}' is process {}, which does not exist anymore", keyword, pid)
+            }
+            PsTreeError::NoMatch(pattern) => write!(f, "no process matches '{}'", pattern),
+            PsTreeError::AmbiguousMatch(query, candidates) => {
+                let candidates: Vec<String> = candidates.iter()
+                    .map(|(pid, name)| format!("'{}' (pid {})", name, pid))
+                    .collect();
+                write!(
+                    f, "'{}' matches {} processes equally well: {} (use --first to pick the oldest)",
+                    query, candidates.len(), candidates.join(", ")
+                )
+            }
+            PsTreeError::NoUserProcess(uid) => write!(f, "no process of user {} found", uid),
+            PsTreeError::NoGroupProcess(gid) => write!(f, "no process in group {} found", gid),
+            PsTreeError::NoFilterMatch(filter) => write!(f, "no {} process found", filter),
+            PsTreeError::FileNotOpen(path) => write!(f, "no process has \"{}\" open", path.display()),
+            PsTreeError::StartTimeUnknown(pid) => write!(f, "unable to read the start time of process {}", pid),
+            PsTreeError::NoProcesses(proc_root) => {
+                write!(f, "no processes found in \"{}\"", proc_root.display())
+            }
+            PsTreeError::ProcUnreadable(proc_root, why) => write!(
+                f, "unable to read \"{}\": {} (use --proc-root to point at a procfs mount)",
+                proc_root.display(), why
+            ),
+            PsTreeError::LoadFailed(path, why) => {
+                write!(f, "unable to read snapshot \"{}\": {}", path.display(), why)
+            }
+            PsTreeError::PsOutputUnreadable(path, why) => {
+                write!(f, "unable to read ps output \"{}\": {}", path.display(), why)
+            }
+            PsTreeError::SaveFailed(path, why) => {
+                write!(f, "unable to write snapshot \"{}\": {}", path.display(), why)
+            }
+            PsTreeError::MapWriteFailed(path, why) => {
+                write!(f, "unable to write anonymize map \"{}\": {}", path.display(), why)
+            }
+            PsTreeError::SignalFailed(failed, total) => {
+                write!(f, "could not signal {} of {} processes", failed, total)
+            }
+            PsTreeError::ReniceFailed(failed, total) => {
+                write!(f, "could not renice {} of {} processes", failed, total)
+            }
+            PsTreeError::ProtectedProcess(pid, action) => write!(
+                f, "refusing to {} process {}, which is init or runs rusty-pstree (use --force to {} it anyway)",
+                action, pid, action
+            ),
+            PsTreeError::Declined(what) => write!(f, "{}", what),
+        }
+    }
+}
+
+impl error::Error for PsTreeError {}
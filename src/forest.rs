@@ -0,0 +1,183 @@
+use std::fmt::Write as _;
+use std::io;
+use std::str::FromStr;
+
+use crate::config::PsTreePrintConfig;
+use crate::render::{format_memory, NodeItem};
+use crate::tree::ProcessTree;
+use crate::user::UserTable;
+
+
+/// A column of [`ProcessTree::write_forest`], written before COMMAND.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForestColumn
+{
+    /// The process id.
+    Pid,
+    /// The parent process id.
+    Ppid,
+    /// The user name, or the uid when it has none.
+    User,
+    /// The state letter, like "S".
+    Stat,
+    /// The resident memory, written like the sizes of the drawing, or the
+    /// proportional set size with [`PsTreePrintConfig::pss`].
+    Rss,
+}
+
+impl ForestColumn
+{
+    /// The title of the column, PSS for the memory when `pss` is set.
+    fn header(self, pss: bool) -> &'static str
+    {
+        match self {
+            ForestColumn::Rss if pss => "PSS",
+            ForestColumn::Pid  => "PID",
+            ForestColumn::Ppid => "PPID",
+            ForestColumn::User => "USER",
+            ForestColumn::Stat => "STAT",
+            ForestColumn::Rss  => "RSS",
+        }
+    }
+
+    /// True for the columns of numbers, which are aligned right like ps does.
+    fn is_numeric(self) -> bool
+    {
+        matches!(self, ForestColumn::Pid | ForestColumn::Ppid | ForestColumn::Rss)
+    }
+}
+
+impl FromStr for ForestColumn
+{
+    type Err = String;
+
+    fn from_str(column: &str) -> Result<ForestColumn, String>
+    {
+        match column {
+            "pid"  => Ok(ForestColumn::Pid),
+            "ppid" => Ok(ForestColumn::Ppid),
+            "user" => Ok(ForestColumn::User),
+            "stat" => Ok(ForestColumn::Stat),
+            "rss"  => Ok(ForestColumn::Rss),
+            _ => Err(format!("invalid column '{}' (expected pid, ppid, user, stat or rss)", column)),
+        }
+    }
+}
+
+/// How [`ProcessTree::write_forest`] lays out the table.
+#[derive(Clone, Debug)]
+pub struct ForestOptions
+{
+    /// The columns written before COMMAND, in order.
+    pub columns: Vec<ForestColumn>,
+    /// The names written in the USER column. Uids missing from it are
+    /// written as numbers.
+    pub user_names: UserTable,
+}
+
+impl Default for ForestOptions
+{
+    fn default() -> ForestOptions
+    {
+        ForestOptions {
+            columns: vec![ForestColumn::Pid, ForestColumn::User, ForestColumn::Stat],
+            user_names: UserTable::default(),
+        }
+    }
+}
+
+impl ProcessTree
+{
+    /// Writes the drawn tree as a table like `ps f`: a header line, then one
+    /// line per process with the `options.columns` padded to their widest
+    /// value and a COMMAND column labeled like the drawing, where every
+    /// level below the roots is indented two more spaces behind a `\_`.
+    pub fn write_forest<W: io::Write>(
+        &self,
+        mut out: W,
+        config: &PsTreePrintConfig,
+        options: &ForestOptions,
+    ) -> io::Result<()>
+    {
+        let mut rows: Vec<(Vec<String>, String)> = Vec::new();
+        for item in self.root_items(config, false) {
+            self.collect_forest_rows(&item, 0, config, options, &mut rows);
+        }
+
+        let widths: Vec<usize> = options.columns.iter().enumerate()
+            .map(|(column_index, column)| {
+                rows.iter()
+                    .map(|(cells, _)| cells[column_index].chars().count())
+                    .fold(column.header(config.pss).len(), usize::max)
+            })
+            .collect();
+
+        let headers: Vec<String> = options.columns.iter().map(|column| column.header(config.pss).to_string()).collect();
+        writeln!(out, "{}", forest_line(&options.columns, &widths, &headers, "COMMAND"))?;
+        for (cells, command) in &rows {
+            writeln!(out, "{}", forest_line(&options.columns, &widths, cells, command))?;
+        }
+        Ok(())
+    }
+
+    fn collect_forest_rows(
+        &self,
+        item: &NodeItem,
+        depth: usize,
+        config: &PsTreePrintConfig,
+        options: &ForestOptions,
+        rows: &mut Vec<(Vec<String>, String)>,
+    )
+    {
+        let cells = options.columns.iter().map(|&column| self.forest_cell(item, column, config, options)).collect();
+        let mut command = String::new();
+        if depth > 0 {
+            let _ = write!(command, "{:indent$} \\_ ", "", indent = 2 * (depth - 1));
+        }
+        command.push_str(&item.text.replace(['\n', '\r'], " "));
+        rows.push((cells, command));
+        for child in &item.children {
+            self.collect_forest_rows(child, depth + 1, config, options, rows);
+        }
+    }
+
+    /// The value of `column` for the process of `item`, empty for labels
+    /// and for fields that were not read.
+    fn forest_cell(
+        &self,
+        item: &NodeItem,
+        column: ForestColumn,
+        config: &PsTreePrintConfig,
+        options: &ForestOptions,
+    ) -> String
+    {
+        let Some(node) = item.pid.and_then(|pid| self.find(pid)) else { return String::new() };
+        let proc_info = &node.proc_info;
+        match column {
+            ForestColumn::Pid => proc_info.pid.to_string(),
+            ForestColumn::Ppid => proc_info.ppid.to_string(),
+            ForestColumn::User => match proc_info.uid {
+                Some(uid) => options.user_names.name(uid).map_or_else(|| uid.to_string(), str::to_string),
+                None => String::new(),
+            },
+            ForestColumn::Stat => proc_info.state.map(String::from).unwrap_or_default(),
+            ForestColumn::Rss => format_memory(proc_info, config).unwrap_or_default(),
+        }
+    }
+}
+
+/// One line of the table, every cell padded to the width of its column and
+/// followed by a space, then the unpadded `command`.
+fn forest_line(columns: &[ForestColumn], widths: &[usize], cells: &[String], command: &str) -> String
+{
+    let mut line = String::new();
+    for ((column, width), cell) in columns.iter().zip(widths).zip(cells) {
+        if column.is_numeric() {
+            let _ = write!(line, "{:>width$} ", cell, width = width);
+        } else {
+            let _ = write!(line, "{:<width$} ", cell, width = width);
+        }
+    }
+    line.push_str(command);
+    line
+}
@@ -0,0 +1,109 @@
+/// The score of a name equal to the query, case aside, above every other
+/// score.
+pub const EXACT_MATCH_SCORE: u32 = 3000;
+
+/// The middle of the scores of names holding the query as a subsequence,
+/// which stay between 1000 and 3000.
+const SUBSEQUENCE_SCORE: i64 = 2000;
+/// The score of a name holding the query with one typo too few, the
+/// scores of names with typos staying between 0 and 1000.
+const TYPO_SCORE: i64 = 1000;
+
+/// Bonus for every query character matched right after the previous one.
+const CONSECUTIVE_BONUS: i64 = 15;
+/// Bonus for a match starting the name.
+const PREFIX_BONUS: i64 = 30;
+/// Bonus for every query character matched at the start of a word.
+const WORD_START_BONUS: i64 = 10;
+/// Penalty for every name character skipped between two matched ones.
+const GAP_PENALTY: i64 = 3;
+/// Penalty for every typo.
+const TYPO_PENALTY: i64 = 100;
+
+/// How well `name` matches `query`, a name half remembered, higher being
+/// better, or None if it does not match at all. Case is ignored.
+///
+/// A name equal to the query scores [`EXACT_MATCH_SCORE`]. A name holding
+/// the characters of the query in order scores above a name that does not,
+/// more so when they are close together and at the start of the name or of
+/// its words. Failing that, a name holding the query with a typo for every
+/// four characters of the query still matches, scoring lower per typo.
+/// Every name character left unmatched costs a point, so shorter names win
+/// ties.
+pub fn fuzzy_score(query: &str, name: &str) -> Option<u32>
+{
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    if query.is_empty() {
+        return None;
+    }
+    if query == name {
+        return Some(EXACT_MATCH_SCORE);
+    }
+
+    let score = match subsequence_bonus(&query, &name) {
+        Some(bonus) => (SUBSEQUENCE_SCORE + bonus).clamp(TYPO_SCORE + 1, EXACT_MATCH_SCORE as i64 - 1),
+        None => {
+            let typos = substring_distance(&query, &name);
+            if typos > query.len() / 4 {
+                return None;
+            }
+            let unmatched = name.len().saturating_sub(query.len()) as i64;
+            (TYPO_SCORE - TYPO_PENALTY * typos as i64 - unmatched).clamp(1, TYPO_SCORE)
+        }
+    };
+    Some(score as u32)
+}
+
+/// The bonus of the best placement of `query` as a subsequence of `name`,
+/// or None if `name` does not hold it. Every start of the match is tried,
+/// the rest of the query being matched as early as possible.
+fn subsequence_bonus(query: &[char], name: &[char]) -> Option<i64>
+{
+    (0 .. name.len())
+        .filter(|&start| name[start] == query[0])
+        .filter_map(|start| {
+            let mut positions = vec![start];
+            for &ch in &query[1..] {
+                let from = positions[positions.len() - 1] + 1;
+                positions.push(from + name[from..].iter().position(|&candidate| candidate == ch)?);
+            }
+            Some(placement_bonus(&positions, name))
+        })
+        .max()
+}
+
+/// The bonus of the query matched at `positions` of `name`.
+fn placement_bonus(positions: &[usize], name: &[char]) -> i64
+{
+    let consecutive = positions.windows(2).filter(|pair| pair[1] == pair[0] + 1).count() as i64;
+    let word_starts = positions.iter()
+        .filter(|&&position| position == 0 || !name[position - 1].is_alphanumeric())
+        .count() as i64;
+    let span = positions[positions.len() - 1] - positions[0] + 1;
+    let gaps = (span - positions.len()) as i64;
+    let unmatched = (name.len() - positions.len()) as i64;
+
+    let mut bonus = CONSECUTIVE_BONUS * consecutive + WORD_START_BONUS * word_starts - GAP_PENALTY * gaps - unmatched;
+    if positions[0] == 0 {
+        bonus += PREFIX_BONUS;
+    }
+    bonus
+}
+
+/// The fewest insertions, deletions and substitutions turning `query` into
+/// some part of `name`.
+fn substring_distance(query: &[char], name: &[char]) -> usize
+{
+    // The part may start anywhere, so the row of the empty query is free.
+    let mut previous = vec![0; name.len() + 1];
+    for (query_index, &query_ch) in query.iter().enumerate() {
+        let mut current = vec![query_index + 1; name.len() + 1];
+        for (name_index, &name_ch) in name.iter().enumerate() {
+            let substitution = previous[name_index] + usize::from(query_ch != name_ch);
+            current[name_index + 1] = substitution.min(previous[name_index + 1] + 1).min(current[name_index] + 1);
+        }
+        previous = current;
+    }
+    previous.into_iter().min().unwrap_or(query.len())
+}
@@ -0,0 +1,191 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::collation::Collation;
+use crate::process::ProcessInfo;
+use crate::tree::{DfsIter, ProcessTree};
+
+
+/// A synthetic node gathering process subtrees that share a property, like
+/// their user. Groups are drawn above the processes but are not processes
+/// themselves: they have no pid and never show up in [`ProcessTree::nodes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessGroup
+{
+    /// The text drawn for the group.
+    pub label: String,
+    /// Indices of the process subtrees directly in this group.
+    pub roots: Vec<usize>,
+    /// Nested groups, drawn before the processes.
+    pub subgroups: Vec<ProcessGroup>,
+}
+
+/// The order groups are drawn in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupOrder
+{
+    /// By label.
+    Name,
+    /// By number of processes, largest first, then by label.
+    Count,
+    /// By the key the groups were made from.
+    Key,
+}
+
+impl FromStr for GroupOrder
+{
+    type Err = String;
+
+    fn from_str(order: &str) -> Result<GroupOrder, String>
+    {
+        match order {
+            "name"  => Ok(GroupOrder::Name),
+            "count" => Ok(GroupOrder::Count),
+            "key"   => Ok(GroupOrder::Key),
+            _ => Err(format!("invalid group order '{}' (expected name, count or key)", order)),
+        }
+    }
+}
+
+impl fmt::Display for GroupOrder
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str(match self {
+            GroupOrder::Name => "name",
+            GroupOrder::Count => "count",
+            GroupOrder::Key => "key",
+        })
+    }
+}
+
+impl ProcessTree
+{
+    /// Groups the processes below the roots by `key`. Child links between
+    /// processes with different keys are cut, so every group holds whole
+    /// subtrees of processes sharing its key. `label` names each group, and
+    /// the roots become the roots of all groups in drawing order. Groups in
+    /// key order stay in the order of their keys, labels compare in
+    /// `collation` for the other orders.
+    pub fn group_by<K, F, L>(&mut self, key: F, label: L, order: GroupOrder, collation: Collation)
+    where
+        K: Ord,
+        F: FnMut(&ProcessInfo) -> K,
+        L: Fn(&K) -> String,
+    {
+        let mut groups: Vec<ProcessGroup> = self.split_by_key(key).into_iter()
+            .map(|(key, roots)| ProcessGroup { label: label(&key), roots, subgroups: Vec::new() })
+            .collect();
+
+        self.sort_groups(&mut groups, order, collation);
+        self.groups = groups;
+        self.roots = group_roots_in_order(&self.groups);
+    }
+
+    /// Like [`group_by`](Self::group_by), but `key` returns a path and the
+    /// groups are nested along it: the processes with key `["a", "b"]` end
+    /// up in group `b` inside group `a`. Processes with an empty path are
+    /// put in a group labeled `empty_label`.
+    pub fn group_by_path<F>(&mut self, key: F, empty_label: &str, order: GroupOrder, collation: Collation)
+    where
+        F: FnMut(&ProcessInfo) -> Vec<String>,
+    {
+        let mut groups: Vec<ProcessGroup> = Vec::new();
+
+        for (mut path, roots) in self.split_by_key(key) {
+            if path.is_empty() {
+                path.push(empty_label.to_string());
+            }
+            insert_at_path(&mut groups, path, roots);
+        }
+
+        self.sort_groups(&mut groups, order, collation);
+        self.groups = groups;
+        self.roots = group_roots_in_order(&self.groups);
+    }
+
+    /// Cuts the child links between processes with different keys and
+    /// buckets the resulting subtrees by key, in drawing order.
+    fn split_by_key<K: Ord, F: FnMut(&ProcessInfo) -> K>(&mut self, mut key: F) -> BTreeMap<K, Vec<usize>>
+    {
+        let reachable: Vec<usize> = self.iter_dfs().map(|(_, index)| index).collect();
+        let mut keys: HashMap<usize, K> = reachable.iter()
+            .map(|&index| (index, key(&self.nodes[index].proc_info)))
+            .collect();
+
+        let mut group_roots: Vec<usize> = self.roots.clone();
+        for &index in &reachable {
+            let (kept, cut): (Vec<usize>, Vec<usize>) = self.nodes[index].children.iter()
+                .partition(|&child| keys[child] == keys[&index]);
+            self.nodes[index].children = kept;
+            group_roots.extend(cut);
+        }
+
+        let position: HashMap<usize, usize> = reachable.iter().enumerate()
+            .map(|(position, &index)| (index, position))
+            .collect();
+        group_roots.sort_by_key(|root| position[root]);
+
+        let mut buckets: BTreeMap<K, Vec<usize>> = BTreeMap::new();
+        for root in group_roots {
+            buckets.entry(keys.remove(&root).unwrap()).or_default().push(root);
+        }
+        buckets
+    }
+
+    /// Orders `groups` and every level of subgroups.
+    fn sort_groups(&self, groups: &mut [ProcessGroup], order: GroupOrder, collation: Collation)
+    {
+        for group in groups.iter_mut() {
+            self.sort_groups(&mut group.subgroups, order, collation);
+        }
+        if order == GroupOrder::Key {
+            return;
+        }
+        groups.sort_by(|a, b| collation.compare(a.label.as_bytes(), b.label.as_bytes()));
+        if order == GroupOrder::Count {
+            // Stable, so groups of the same size stay in label order.
+            groups.sort_by_cached_key(|group| Reverse(self.group_size(group)));
+        }
+    }
+
+    /// Number of processes in `group` and its subgroups.
+    fn group_size(&self, group: &ProcessGroup) -> usize
+    {
+        DfsIter::new(self, &group.roots).count()
+            + group.subgroups.iter().map(|subgroup| self.group_size(subgroup)).sum::<usize>()
+    }
+}
+
+/// Adds `roots` to the group at `path` below `groups`, creating the
+/// groups along the way.
+fn insert_at_path(groups: &mut Vec<ProcessGroup>, mut path: Vec<String>, roots: Vec<usize>)
+{
+    let label = path.remove(0);
+    let position = match groups.iter().position(|group| group.label == label) {
+        Some(position) => position,
+        None => {
+            groups.push(ProcessGroup { label, roots: Vec::new(), subgroups: Vec::new() });
+            groups.len() - 1
+        }
+    };
+
+    if path.is_empty() {
+        groups[position].roots.extend(roots);
+    } else {
+        insert_at_path(&mut groups[position].subgroups, path, roots);
+    }
+}
+
+/// The roots of `groups` and their subgroups in drawing order.
+pub(crate) fn group_roots_in_order(groups: &[ProcessGroup]) -> Vec<usize>
+{
+    let mut roots: Vec<usize> = Vec::new();
+    for group in groups {
+        roots.extend(group_roots_in_order(&group.subgroups));
+        roots.extend(&group.roots);
+    }
+    roots
+}
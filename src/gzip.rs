@@ -0,0 +1,284 @@
+use std::io;
+
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// The compression method of every gzip member in practice.
+const METHOD_DEFLATE: u8 = 8;
+const FLAG_HEADER_CRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+/// Magic, method, flags, mtime, extra flags and OS.
+const HEADER_SIZE: usize = 10;
+/// CRC-32 and size of the uncompressed data.
+const TRAILER_SIZE: usize = 8;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+/// The order the code lengths of the code length alphabet are stored in.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+const MAX_CODE_LENGTH: usize = 15;
+const END_OF_BLOCK: u16 = 256;
+
+/// Whether `data` starts like a gzip file.
+pub(crate) fn is_gzip(data: &[u8]) -> bool
+{
+    data.starts_with(&GZIP_MAGIC)
+}
+
+/// Decompresses every member of the gzip file `data`, checking their sizes
+/// and CRC-32s. Anything after the last member is ignored.
+pub(crate) fn gunzip(data: &[u8]) -> io::Result<Vec<u8>>
+{
+    let mut output: Vec<u8> = Vec::new();
+    let mut rest = data;
+    while is_gzip(rest) {
+        let start = output.len();
+        let header_size = parse_header(rest)?;
+        let deflate_size = inflate(&rest[header_size..], &mut output)?;
+        let trailer = rest.get(header_size + deflate_size..header_size + deflate_size + TRAILER_SIZE)
+            .ok_or_else(truncated)?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        // The size is stored modulo 2^32.
+        if crc != crc32(&output[start..]) || size != (output.len() - start) as u32 {
+            return Err(invalid("gzip checksum mismatch"));
+        }
+        rest = &rest[header_size + deflate_size + TRAILER_SIZE..];
+    }
+    if output.is_empty() && !data.is_empty() && !is_gzip(data) {
+        return Err(invalid("not a gzip file"));
+    }
+    Ok(output)
+}
+
+/// The size of the member header at the start of `data`.
+fn parse_header(data: &[u8]) -> io::Result<usize>
+{
+    let header = data.get(..HEADER_SIZE).ok_or_else(truncated)?;
+    if header[2] != METHOD_DEFLATE {
+        return Err(invalid("unknown gzip compression method"));
+    }
+    let flags = header[3];
+    let mut size = HEADER_SIZE;
+    if flags & FLAG_EXTRA != 0 {
+        let length = data.get(size..size + 2).ok_or_else(truncated)?;
+        size += 2 + usize::from(u16::from_le_bytes([length[0], length[1]]));
+    }
+    for flag in [FLAG_NAME, FLAG_COMMENT] {
+        if flag & flags != 0 {
+            let end = data.get(size..)
+                .and_then(|field| field.iter().position(|&byte| byte == 0))
+                .ok_or_else(truncated)?;
+            size += end + 1;
+        }
+    }
+    if flags & FLAG_HEADER_CRC != 0 {
+        size += 2;
+    }
+    if size > data.len() {
+        return Err(truncated());
+    }
+    Ok(size)
+}
+
+/// Decompresses the raw deflate stream at the start of `data` into
+/// `output`, returning the number of bytes it took.
+fn inflate(data: &[u8], output: &mut Vec<u8>) -> io::Result<usize>
+{
+    let mut bits = BitReader { data, position: 0 };
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => copy_stored(&mut bits, output)?,
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut bits, output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, output, &literals, &distances)?;
+            }
+            _ => return Err(invalid("invalid deflate block type")),
+        }
+        if last {
+            return Ok(bits.position.div_ceil(8));
+        }
+    }
+}
+
+fn copy_stored(bits: &mut BitReader, output: &mut Vec<u8>) -> io::Result<()>
+{
+    let start = bits.position.div_ceil(8);
+    let lengths = bits.data.get(start..start + 4).ok_or_else(truncated)?;
+    let length = u16::from_le_bytes([lengths[0], lengths[1]]);
+    if length != !u16::from_le_bytes([lengths[2], lengths[3]]) {
+        return Err(invalid("corrupt stored deflate block"));
+    }
+    let stored = bits.data.get(start + 4..start + 4 + usize::from(length)).ok_or_else(truncated)?;
+    output.extend_from_slice(stored);
+    bits.position = (start + 4 + usize::from(length)) * 8;
+    Ok(())
+}
+
+fn inflate_block(bits: &mut BitReader, output: &mut Vec<u8>, literals: &Huffman, distances: &Huffman) -> io::Result<()>
+{
+    loop {
+        let symbol = literals.decode(bits)?;
+        if symbol < END_OF_BLOCK {
+            output.push(symbol as u8);
+            continue;
+        }
+        if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+        let index = usize::from(symbol - END_OF_BLOCK - 1);
+        let length = *LENGTH_BASE.get(index).ok_or_else(|| invalid("invalid deflate length"))? as usize
+            + bits.read(LENGTH_EXTRA[index].into())? as usize;
+        let index = usize::from(distances.decode(bits)?);
+        let distance = *DISTANCE_BASE.get(index).ok_or_else(|| invalid("invalid deflate distance"))? as usize
+            + bits.read(DISTANCE_EXTRA[index].into())? as usize;
+        if distance > output.len() {
+            return Err(invalid("deflate distance too far back"));
+        }
+        // The copy may overlap what it appends, so go byte by byte.
+        let start = output.len() - distance;
+        for offset in 0..length {
+            output.push(output[start + offset]);
+        }
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman)
+{
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(bits: &mut BitReader) -> io::Result<(Huffman, Huffman)>
+{
+    let literal_count = bits.read(5)? as usize + 257;
+    let distance_count = bits.read(5)? as usize + 1;
+    let code_length_count = bits.read(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = bits.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths: Vec<u8> = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_lengths.decode(bits)? {
+            length @ 0..=15 => (length as u8, 1),
+            16 => (*lengths.last().ok_or_else(|| invalid("deflate length repeated first"))?, 3 + bits.read(2)?),
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(invalid("too many deflate code lengths"));
+    }
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+/// Reads a deflate stream bit by bit, least significant bit first.
+struct BitReader<'a>
+{
+    data: &'a [u8],
+    /// In bits from the start of `data`.
+    position: usize,
+}
+
+impl BitReader<'_>
+{
+    fn read(&mut self, count: u32) -> io::Result<u32>
+    {
+        let mut value = 0;
+        for shift in 0..count {
+            let byte = *self.data.get(self.position / 8).ok_or_else(truncated)?;
+            value |= u32::from((byte >> (self.position % 8)) & 1) << shift;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of every length and
+/// the symbols ordered by code.
+struct Huffman
+{
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman
+{
+    fn new(lengths: &[u8]) -> Huffman
+    {
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols: Vec<(u8, u16)> = lengths.iter().enumerate()
+            .filter(|&(_, &length)| length > 0)
+            .map(|(symbol, &length)| (length, symbol as u16))
+            .collect();
+        symbols.sort_unstable();
+        Huffman { counts, symbols: symbols.into_iter().map(|(_, symbol)| symbol).collect() }
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> io::Result<u16>
+    {
+        // Codes of one length are consecutive, starting at `first`.
+        let (mut code, mut first, mut index) = (0usize, 0usize, 0usize);
+        for &count in &self.counts[1..] {
+            code |= bits.read(1)? as usize;
+            let count = usize::from(count);
+            if code < first + count {
+                return Ok(self.symbols[index + code - first]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid deflate code"))
+    }
+}
+
+fn crc32(data: &[u8]) -> u32
+{
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn invalid(message: &str) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn truncated() -> io::Error
+{
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated gzip file")
+}
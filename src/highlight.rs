@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+use ptree::Color;
+use regex::Regex;
+
+use crate::color::parse_color;
+use crate::config::DEFAULT_PALETTE;
+
+
+/// A pattern whose matching processes are drawn in their own color, see
+/// [`PsTreePrintConfig::highlights`](crate::PsTreePrintConfig::highlights).
+#[derive(Clone, Debug)]
+pub struct Highlight
+{
+    /// Matched anywhere in the process name.
+    pub pattern: Regex,
+    /// The color of the matching processes.
+    pub color: Color,
+}
+
+/// A highlight as written on the command line, "pattern" or
+/// "pattern=color", before [`assign_highlight_colors`] gives the ones
+/// without a color theirs.
+#[derive(Clone, Debug)]
+pub struct HighlightSpec
+{
+    /// Matched anywhere in the process name.
+    pub pattern: Regex,
+    /// The color written after the pattern, if any.
+    pub color: Option<Color>,
+}
+
+impl FromStr for HighlightSpec
+{
+    type Err = String;
+
+    /// Parses "pattern" or "pattern=color", the color being anything
+    /// [`parse_color`] accepts. The color follows the last "=", so a
+    /// pattern holding one ends with "=" to leave the color out, like
+    /// "a=b=".
+    fn from_str(spec: &str) -> Result<HighlightSpec, String>
+    {
+        let (pattern, color) = match spec.rsplit_once('=') {
+            Some((pattern, "")) => (pattern, None),
+            Some((pattern, color)) => (pattern, Some(parse_color(color)?)),
+            None => (spec, None),
+        };
+        if pattern.is_empty() {
+            return Err(format!("invalid highlight '{}' (expected PATTERN or PATTERN=COLOR)", spec));
+        }
+        let pattern = Regex::new(pattern).map_err(|why| format!("invalid highlight pattern '{}': {}", pattern, why))?;
+        Ok(HighlightSpec { pattern, color })
+    }
+}
+
+/// Gives every spec of `specs` a color: its own, else the next color of
+/// `palette` no spec asks for, cycling through the whole palette once
+/// every color is taken. An empty palette stands for the default one.
+pub fn assign_highlight_colors(specs: &[HighlightSpec], palette: &[Color]) -> Vec<Highlight>
+{
+    let palette = if palette.is_empty() { &DEFAULT_PALETTE[..] } else { palette };
+    let asked: Vec<&Color> = specs.iter().filter_map(|spec| spec.color.as_ref()).collect();
+    let free: Vec<Color> = palette.iter().filter(|color| !asked.contains(color)).cloned().collect();
+    let auto = if free.is_empty() { palette } else { &free[..] };
+
+    let mut next = 0;
+    specs.iter()
+        .map(|spec| {
+            let color = spec.color.clone().unwrap_or_else(|| {
+                next += 1;
+                auto[(next - 1) % auto.len()].clone()
+            });
+            Highlight { pattern: spec.pattern.clone(), color }
+        })
+        .collect()
+}
@@ -0,0 +1,87 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::process::{parse_proc_stat, parse_session, StatusFields};
+use crate::source::ProcSource;
+
+/// A word accepted in place of a pid that stands for a process related to
+/// the running one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PidKeyword
+{
+    /// "self", the running process.
+    Current,
+    /// "parent", the parent of the running process.
+    Parent,
+    /// "session", the leader of the session of the running process.
+    Session,
+}
+
+impl PidKeyword
+{
+    /// Every keyword, in the order they are documented.
+    pub const ALL: [PidKeyword; 3] = [PidKeyword::Current, PidKeyword::Parent, PidKeyword::Session];
+
+    /// The word that stands for the keyword.
+    pub fn name(self) -> &'static str
+    {
+        match self {
+            PidKeyword::Current => "self",
+            PidKeyword::Parent  => "parent",
+            PidKeyword::Session => "session",
+        }
+    }
+
+    /// The pid the keyword stands for when the running process is `pid`,
+    /// reading its status or stat from `source`. A parent or session leader
+    /// outside the pid namespace, reported as 0, is an error.
+    pub fn resolve<S: ProcSource>(self, source: &S, pid: u32) -> io::Result<u32>
+    {
+        let resolved = match self {
+            PidKeyword::Current => return Ok(pid),
+            PidKeyword::Parent => {
+                let status = source.read_status(pid)?;
+                parse_proc_stat(pid, status, StatusFields::default())
+                    .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why.reason))?
+                    .ppid
+            }
+            PidKeyword::Session => parse_session(&source.read_stat(pid)?)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed stat"))?,
+        };
+        match resolved {
+            0 => Err(io::Error::new(io::ErrorKind::NotFound, format!("the {} is outside this pid namespace", self.describe()))),
+            resolved => Ok(resolved),
+        }
+    }
+
+    /// What the keyword stands for, like "parent process".
+    fn describe(self) -> &'static str
+    {
+        match self {
+            PidKeyword::Current => "running process",
+            PidKeyword::Parent  => "parent process",
+            PidKeyword::Session => "session leader",
+        }
+    }
+}
+
+impl fmt::Display for PidKeyword
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for PidKeyword
+{
+    type Err = String;
+
+    fn from_str(keyword: &str) -> Result<PidKeyword, String>
+    {
+        PidKeyword::ALL.into_iter()
+            .find(|candidate| candidate.name() == keyword)
+            .ok_or_else(|| format!("invalid pid '{}' (expected a number, self, parent or session)", keyword))
+    }
+}
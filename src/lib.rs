@@ -0,0 +1,90 @@
+//! A simple pstree implemented in rust.
+//!
+//! The crate scans procfs into a flat list of [`ProcessInfo`], links that
+//! list into a [`ProcessTree`] and renders the tree with [`ptree`].
+//!
+//! ```no_run
+//! use rusty_pstree::{ProcessTree, PsTreePrintConfig};
+//!
+//! let processes = rusty_pstree::scan()?;
+//! let tree = ProcessTree::from_process_list(processes);
+//! tree.write(std::io::stdout(), &PsTreePrintConfig::new())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+#![warn(missing_docs)]
+
+extern crate libc;
+extern crate ptree;
+extern crate regex;
+extern crate unicode_width;
+
+
+mod anonymize;
+mod archive;
+mod collation;
+mod color;
+mod compat;
+mod config;
+mod cpu;
+mod diff;
+mod duration;
+mod error;
+mod forest;
+mod fuzzy;
+mod group;
+mod gzip;
+mod highlight;
+mod keyword;
+mod limits;
+mod metrics;
+mod net;
+mod nice;
+mod org;
+mod process;
+mod ps;
+mod render;
+mod scan;
+mod serializable;
+mod signal;
+mod size;
+mod source;
+mod style;
+mod summary;
+mod tree;
+mod user;
+mod wrap;
+mod xml;
+
+pub use anonymize::Anonymizer;
+pub use archive::ArchiveSource;
+pub use collation::{natural_cmp, Collation};
+pub use color::{parse_color, parse_state_color};
+pub use config::{ColorBy, ConfigError, PidFormat, PsTreePrintConfig, PsTreePrintConfigBuilder, SortKey};
+pub use cpu::parse_cpu_list;
+pub use diff::{Change, ChangeThresholds, ProcessKey, TreeDiff};
+pub use duration::format_duration;
+pub use error::{PsTreeError, Result};
+pub use forest::{ForestColumn, ForestOptions};
+pub use fuzzy::{fuzzy_score, EXACT_MATCH_SCORE};
+pub use group::{GroupOrder, ProcessGroup};
+pub use highlight::{assign_highlight_colors, Highlight, HighlightSpec};
+pub use keyword::PidKeyword;
+pub use limits::{parse_soft_limit, ResourceLimit};
+pub use nice::{set_nice, NiceChange, MAX_NICE, MIN_NICE};
+pub use org::{OrgOptions, DEFAULT_ORG_MAX_LEVEL};
+pub use process::{ParseError, ProcessInfo, StatusFields};
+pub use ps::parse_ps_output;
+pub use scan::{
+    scan, scan_source, scan_source_cached, scan_with, ScanCache, ScanOptions, ScanOutput, SkipReason, SkippedProcess,
+    DEFAULT_SCAN_CACHE_CAPACITY,
+};
+pub use serializable::{SerializableGroup, SerializableNode};
+pub use signal::{send_all, Freeze, Kill, Signal, SignalSender};
+pub use size::{format_size, SizeUnit};
+pub use source::{LinuxProcfs, MockSource, ProcSource};
+pub use style::TextStyle;
+pub use summary::{TreeStats, TreeSummary};
+pub use tree::{BfsIter, DfsIter, OmittedSubtrees, PostOrderIter, ProcessNode, ProcessTree};
+pub use user::{GroupTable, UserTable};
+pub use xml::XmlMetadata;
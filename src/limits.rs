@@ -0,0 +1,45 @@
+use std::fmt;
+
+
+/// A resource limit of a process, as listed in /proc/[pid]/limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ResourceLimit
+{
+    /// At most this many units.
+    Limited(u64),
+    /// No limit.
+    Unlimited,
+}
+
+impl fmt::Display for ResourceLimit
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            ResourceLimit::Limited(limit) => write!(f, "{}", limit),
+            ResourceLimit::Unlimited => f.write_str("unlimited"),
+        }
+    }
+}
+
+/// The soft limit named `name`, like "Max open files", in the contents of
+/// /proc/[pid]/limits. The file is laid out in fixed width columns and
+/// limit names contain spaces, so the soft limit is taken from the column
+/// the header puts it in.
+pub fn parse_soft_limit(limits: &str, name: &str) -> Option<ResourceLimit>
+{
+    let mut lines = limits.lines();
+    let column = lines.next()?.find("Soft Limit")?;
+    let line = lines.find(|line| {
+        line.strip_prefix(name)
+            .and_then(|rest| rest.get(.. column.checked_sub(name.len())?))
+            .is_some_and(|padding| padding.trim().is_empty())
+    })?;
+
+    match line.get(column ..)?.split_whitespace().next()? {
+        "unlimited" => Some(ResourceLimit::Unlimited),
+        limit => limit.parse().ok().map(ResourceLimit::Limited),
+    }
+}
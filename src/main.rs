@@ -1,336 +1,2311 @@
 extern crate clap;
+extern crate libc;
 extern crate ptree;
 extern crate regex;
+extern crate roff;
+extern crate rusty_pstree;
 
 
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
-use std::rc::Rc;
-
-use clap::Parser;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{Arg, ArgGroup, CommandFactory, ErrorKind, Parser};
+use roff::{bold, italic, line_break, roman, Inline, Roff};
 use ptree::Color;
 use regex::Regex;
-use ptree::TreeBuilder;
-use ptree::PrintConfig;
+use rusty_pstree::{
+    assign_highlight_colors, fuzzy_score, parse_color, parse_ps_output, parse_state_color, send_all, set_nice,
+    Anonymizer, ArchiveSource, Collation, ColorBy, ConfigError, ForestColumn, ForestOptions, Freeze, GroupOrder,
+    GroupTable, HighlightSpec, Kill, LinuxProcfs, NiceChange, OrgOptions, PidFormat, PidKeyword, ProcSource,
+    ProcessInfo, ProcessTree, PsTreeError, PsTreePrintConfig, ScanCache, ScanOptions, ScanOutput, Signal, SizeUnit,
+    SkipReason, SkippedProcess, SortKey, StatusFields, TextStyle, UserTable, XmlMetadata, DEFAULT_ORG_MAX_LEVEL,
+};
+use rusty_pstree::{ChangeThresholds, ProcessKey, TreeDiff};
 
 
-#[derive(Clone)]
-struct ProcessInfo
+#[derive(Parser)]
+#[clap(version, about = "Display the running processes as a tree")]
+#[clap(after_help = "ENVIRONMENT:\n    Options marked with [env: ...] default from that variable. Command line flags take\n    precedence. Boolean variables accept 1/true/yes and 0/false/no.\n\nEXIT STATUS:\n    0  success\n    1  no matching process\n    2  usage error\n    3  system error\n    4  some processes could not be signaled or reniced\n    5  the confirmation was declined")]
+#[clap(group(ArgGroup::new("pattern").args(&["root-name", "root-name-fuzzy", "find"])))]
+#[clap(group(ArgGroup::new("name-root").args(&["root-name", "root-name-fuzzy"])))]
+#[clap(group(ArgGroup::new("pid-list").args(&["pids", "kill-order"])))]
+#[clap(group(ArgGroup::new("action").args(&["signal", "renice", "pause", "resume"])))]
+#[clap(group(ArgGroup::new("freeze").args(&["pause", "resume"])))]
+struct Args
 {
-    pid: u32,
-    ppid: u32,
-    name: String,
-}
+    /// node color, a string in ["white", "red", "green", ...] or an RGB triple like "255,255,0"
+    ///
+    /// The names black, red, green, yellow, blue, purple, cyan and white are accepted in any
+    /// case, also with a "bright-" prefix. A number from 0 to 255, or "color" followed by
+    /// one, picks from the 256-color palette. An RGB triple gives three components from 0 to 255 separated by commas, e.g.
+    /// "--node-color 255,128,0" for orange. Hex colors are written "#ff8000", "ff8000" or
+    /// "#f80"; the three digit form needs the "#". Colors are only used when writing to a
+    /// terminal
+    #[clap(short = 'c', long, value_name = "COLOR", env = "PSTREE_NODE_COLOR", parse(try_from_str = parse_color))]
+    node_color: Option<Color>,
+
+    /// Branch color, a string in ["white", "red", "green", ...] or an RGB triple like "255,255,0"
+    ///
+    /// Accepts the same values as --node-color, e.g. "--branch-color blue"
+    #[clap(short, long, value_name = "COLOR", env = "PSTREE_BRANCH_COLOR", parse(try_from_str = parse_color))]
+    branch_color: Option<Color>,
+
+    /// Node background color, a string in ["white", "red", "green", ...] or an RGB triple like "255,255,0"
+    ///
+    /// Accepts the same values as --node-color, e.g. "--background 40,40,40"
+    #[clap(short = 'g', long, value_name = "COLOR", env = "PSTREE_BACKGROUND", parse(try_from_str = parse_color))]
+    background: Option<Color>,
+
+    /// Branch background color, accepts the same values as --node-color, e.g. "--branch-background 0,43,54"
+    #[clap(long, value_name = "COLOR", env = "PSTREE_BRANCH_BACKGROUND", parse(try_from_str = parse_color))]
+    branch_background: Option<Color>,
+
+    /// Color every node by KEY, picking from --palette. Processes colored by state keep their color
+    ///
+    /// "depth" gives every level below the roots the next color of the palette, starting over after
+    /// the last one
+    #[clap(long, value_name = "KEY", possible_values = ["depth"])]
+    color_by: Option<ColorBy>,
+
+    /// The colors of --color-by and of the --highlight patterns given without one, a comma
+    /// separated list like "cyan,green,208,#ff8000"
+    ///
+    /// Every color accepts the same values as --node-color except RGB triples, which have to be
+    /// written in hex here. The default is cyan,green,yellow,blue,purple
+    #[clap(
+        long, value_name = "COLORS", use_value_delimiter = true, require_value_delimiter = true,
+        multiple_occurrences = false, parse(try_from_str = parse_color)
+    )]
+    palette: Vec<Color>,
+
+    /// Draw the processes whose name matches PATTERN, a regex, in COLOR, e.g.
+    /// "--highlight postgres=green --highlight pgbouncer=cyan". Repeat it to highlight several
+    /// patterns; a process matching more than one takes the color of the first
+    ///
+    /// COLOR accepts the same values as --node-color. Without it the pattern gets the next color
+    /// of --palette that no other pattern asks for. The color follows the last "=", so a pattern
+    /// holding one ends with "=" when it has no color, like "a=b=". A legend of the patterns in
+    /// their colors follows the tree
+    #[clap(long, value_name = "PATTERN[=COLOR]", multiple_occurrences(true), multiple_values(false))]
+    highlight: Vec<HighlightSpec>,
+
+    /// Node text attributes, a comma separated list of bold, dimmed, italic, underline, blink and reverse
+    ///
+    /// The attributes are drawn together with --node-color and --background, e.g.
+    /// "--node-style bold,underline". Highlighted processes keep them on top of their own style
+    #[clap(long, value_name = "STYLE", env = "PSTREE_NODE_STYLE")]
+    node_style: Option<TextStyle>,
+
+    /// Branch text attributes, accepts the same values as --node-style, e.g. "--branch-style dimmed"
+    #[clap(long, value_name = "STYLE", env = "PSTREE_BRANCH_STYLE")]
+    branch_style: Option<TextStyle>,
+
+    /// Draw the processes in STATE with COLOR, e.g. "--state-color D=cyan". Repeat for more
+    /// states. COLOR takes the same values as --node-color, or "none" for the node color
+    ///
+    /// By default D (uninterruptible sleep) is yellow, T (stopped) purple and Z (zombie) red.
+    /// STATE is one of the letters of the State column of ps, which is case sensitive
+    #[clap(
+        long, value_name = "STATE=COLOR", multiple_occurrences(true), multiple_values(false),
+        parse(try_from_str = parse_state_color)
+    )]
+    state_color: Vec<(char, Option<Color>)>,
+
+    /// Indentation size. The default value is 3.
+    #[clap(short = 'I', long, value_name = "WIDTH", env = "PSTREE_INDENT")]
+    pub indent: Option<usize>,
+
+    /// Padding size. The default value is 1.
+    #[clap(short = 'P', long, value_name = "WIDTH", env = "PSTREE_PADDING")]
+    pub padding: Option<usize>,
+
+    /// Use ASCII characters to draw the tree.
+    #[clap(short = 'A', long, env = "PSTREE_ASCII")]
+    ascii: bool,
+
+    /// Indent with spaces only, drawing no branches, for screen readers and diffs.
+    /// "--plain=levels" also starts every line with its level, like "level 2: sshd"
+    ///
+    /// The output is uncolored unless a color option like --node-color is given. The indentation
+    /// is --indent wide. Overrides --ascii
+    #[clap(
+        long, value_name = "MODE", min_values = 0, multiple_values = false, require_equals = true,
+        default_missing_value = "indent", possible_values = ["indent", "levels"]
+    )]
+    plain: Option<Plain>,
+
+    /// Show PIDs
+    #[clap(short = 'p', long, env = "PSTREE_SHOW_PID")]
+    show_pid: bool,
+
+    /// How --show-pid writes the pids: "brackets" for "[812] sshd", "parens" for "sshd(812)" like
+    /// pstree -p
+    #[clap(
+        long, value_name = "FORMAT", env = "PSTREE_PID_FORMAT", default_value = "brackets",
+        possible_values = ["brackets", "parens"]
+    )]
+    pid_format: PidFormat,
+
+    /// Show the parent pid after every pid, like "[30790<30212] vim". Implies --show-pid
+    ///
+    /// With --pid-format parens it reads "vim(30790<30212)", with --align-pids the column
+    /// holds both. Every process of a chain drawn by --collapse-chains shows its own parent
+    #[clap(long)]
+    show_ppid: bool,
+
+    /// Print the pids right-aligned in a column left of the tree, like ps --forest, instead of
+    /// "[pid]" before every name
+    #[clap(long, env = "PSTREE_ALIGN_PIDS")]
+    align_pids: bool,
+
+    /// Remove the duplicated leaf node 
+    ///
+    /// A process without children is hidden when the sibling drawn just before it has the
+    /// same name, so "sleep" started three times by one shell shows up once
+    #[clap(short, long, env = "PSTREE_UNIQUE")]
+    unique: bool,
+
+    /// Print each top-level process as a separate tree instead of grouping them under "(all)"
+    #[clap(long)]
+    forest: bool,
+
+    /// Leave out the roots, like the process of --root-pid, and print each of their
+    /// children as a separate tree. The other output formats leave them out as well
+    #[clap(long)]
+    no_root: bool,
+
+    /// Draw each run of processes that have a single child on one line, like
+    /// "systemd ▸ sh ▸ sudo ▸ myapp", with the children of the last one below it
+    ///
+    /// Only the drawing changes: filters, --count and signals still see every process of the
+    /// chain. With --align-pids the column shows the pid of the first process of the chain
+    #[clap(long)]
+    collapse_chains: bool,
+
+    /// Draw each tree upside down, with the children above their parent, so the deepest
+    /// processes come first and the root last
+    #[clap(long)]
+    upside_down: bool,
+
+    /// Draw the tree like the classic pstree of psmisc, for the scripts reading its output:
+    /// the first child of every process on its line, like "init───sshd─┬─bash", equal
+    /// sibling subtrees drawn once, like "3*[sleep]", and --show-pid written "sshd(812)"
+    ///
+    /// --ascii draws "-+-", "|-" and "`-". Lines wider than the terminal, $COLUMNS or 132
+    /// columns when neither is known are cut and end with "+"
+    #[clap(long, conflicts_with_all = &["align-pids", "upside-down", "plain"])]
+    compat: bool,
+
+    /// Wrap the labels that would reach past WIDTH columns onto more lines aligned under the
+    /// start of the label, instead of letting the terminal break them across the tree. WIDTH
+    /// defaults to the width of the terminal, $COLUMNS or 132 when neither is known
+    ///
+    /// Lines break at a space when there is one, and wide characters count two columns. The
+    /// continuation lines carry only the branches running past them, so they cannot be taken
+    /// for children
+    #[clap(
+        long, value_name = "WIDTH", min_values = 0, require_equals = true,
+        conflicts_with_all = &["compat", "align-pids", "upside-down", "plain"]
+    )]
+    wrap: Option<Option<usize>>,
+
+    /// Draw the process tree rooted on a specific PID, e.g. 2 for the kernel threads.
+    /// 0, the default, draws every top-level process. Repeat the option or give a
+    /// comma-separated list to draw several subtrees side by side. Only the subtrees
+    /// are scanned when the kernel provides /proc/[pid]/task/*/children
+    ///
+    /// PID may also be "self" for rusty-pstree itself, "parent" for the process that started
+    /// it, like the shell or build step running it, or "session" for the leader of its
+    /// session. The keywords are resolved from /proc/self, whatever --proc-root reads
+    #[clap(
+        short, long, value_name = "PID", parse(try_from_str = parse_root_pid),
+        multiple_occurrences(true), multiple_values(false), use_value_delimiter(true)
+    )]
+    root_pid: Vec<u32>,
+
+    /// With --root-pid, leave out the dimmed line above the tree listing the ancestors of
+    /// the root, like "(systemd(1) › sshd(812) › bash(30212))"
+    ///
+    /// The line is only drawn for a single root that is not top-level, and never in the
+    /// machine-readable outputs. A "?" at its start stands for ancestors that could not be read
+    #[clap(long)]
+    no_ancestor_header: bool,
+
+    /// Draw the process tree rooted on the processes whose name matches this regex.
+    /// Matches inside the subtree of another match are drawn in that subtree
+    ///
+    /// The regex may match anywhere in the name, so "ssh" matches both sshd and ssh-agent
+    /// while "^sshd$" matches sshd only. Every match is drawn unless --first is given
+    #[clap(long, value_name = "REGEX", conflicts_with = "root-pid")]
+    root_name: Option<Regex>,
+
+    /// Draw the process tree rooted on the process whose name best matches NAME, a name
+    /// half remembered like "postgre" or "postgress"
+    ///
+    /// Names holding the letters of NAME in order score best, more so when they are close
+    /// together and at the start of a word, then names holding NAME with a typo for every
+    /// four letters. The chosen process is reported on stderr. When several processes
+    /// score best they are listed and nothing is drawn, unless --first picks the oldest
+    #[clap(long, value_name = "NAME", conflicts_with = "root-pid")]
+    root_name_fuzzy: Option<String>,
+
+    /// With --root-name or --root-name-fuzzy, root the tree on the oldest matching
+    /// process only
+    #[clap(long, requires = "name-root")]
+    first: bool,
+
+    /// With --root-name, --root-name-fuzzy or --find, match against the full command line instead of the name
+    #[clap(long, requires = "pattern")]
+    args: bool,
+
+    /// Show only the processes of USER, a user name or numeric uid. The tree is rooted on
+    /// the processes whose parent belongs to another user
+    #[clap(long, value_name = "USER")]
+    user: Option<String>,
+
+    /// Show only the processes traced by a debugger or strace, and their ancestors
+    #[clap(long)]
+    traced_only: bool,
+
+    /// Show only the processes started after PID, and their ancestors, like everything a
+    /// supervisor brought up since it restarted. PID may also be one of the keywords of
+    /// --root-pid
+    ///
+    /// Start times are compared in clock ticks since boot, as read from /proc/PID/stat, so
+    /// processes started within the same tick as PID count as started with it and are left
+    /// out. Exits with status 1 if PID does not exist
+    #[clap(long, value_name = "PID", parse(try_from_str = parse_root_pid))]
+    newer_than: Option<u32>,
+
+    /// Show only the processes started before PID, and their ancestors, leaving out the
+    /// ones started within the same clock tick like --newer-than does
+    #[clap(long, value_name = "PID", parse(try_from_str = parse_root_pid))]
+    older_than: Option<u32>,
+
+    /// Show only the processes that have PATH open, or a file below it when PATH is a
+    /// directory, and their ancestors. Memory mapped files count as open
+    #[clap(long, value_name = "PATH", parse(from_os_str))]
+    open_file: Option<PathBuf>,
+
+    /// Show only the processes whose executable lies below DIR, however they renamed
+    /// themselves, and their ancestors. Given several times, a process below any DIR is
+    /// shown. Processes whose executable cannot be read do not match
+    #[clap(long, value_name = "DIR", parse(from_os_str), multiple_occurrences(true), multiple_values(false))]
+    exe_prefix: Vec<PathBuf>,
+
+    /// Show the umask of each process that does not use the usual 0022, like
+    /// "sshd (umask 0077)". Given twice, show it for every process
+    #[clap(long, parse(from_occurrences))]
+    umask: u8,
+
+    /// Show the resident memory of each process next to the most it ever used, like
+    /// "java rss=1.1G peak=6.3G". "--peak-memory=virtual" adds the peak virtual size
+    #[clap(
+        long, value_name = "WHICH", min_values = 0, multiple_values = false, require_equals = true, default_missing_value = "rss",
+        possible_values = ["rss", "virtual"]
+    )]
+    peak_memory: Option<PeakMemory>,
+
+    /// Show the proportional set size of each process, its resident memory with every shared
+    /// page split among the processes sharing it, like "postgres pss=12.3M", read from
+    /// /proc/[pid]/smaps_rollup
+    ///
+    /// Also used for the RSS column of --output forest, renamed PSS, and the RSS property of
+    /// --output org. Processes whose file cannot be read, those of other users without root
+    /// or all of them before Linux 4.14, show their resident memory marked "*" instead. The
+    /// kernel walks every mapping to write the file, so the scan is slower
+    #[clap(long)]
+    pss: bool,
+
+    /// Follow the memory of each process with the part it has to itself and the part it may
+    /// share with other processes, like "php-fpm rss=41M (priv 9.2M, shr 32M)"
+    ///
+    /// The private part is the anonymous memory of /proc/[pid]/status and the shared part its
+    /// file and shared memory pages. Under --pss both come from the private and shared pages
+    /// of smaps_rollup instead. Processes whose kernel does not show them keep the plain size.
+    /// The metrics output adds pstree_subtree_private_bytes, summing only the private parts:
+    /// shared pages, like those of an interpreter preloaded before forking workers, would
+    /// otherwise be counted once per process sharing them. pstree_subtree_rss_bytes still sums
+    /// everything
+    #[clap(long)]
+    memory_detail: bool,
+
+    /// How memory sizes are written: "human" picks the largest unit like "rss=1.1G", "raw" writes
+    /// whole kilobytes without a unit for scripts, "kib" and "mib" always use that unit, like
+    /// "rss=1153433K" or "rss=1126.4M", so sizes line up for comparison
+    ///
+    /// Applies to the sizes of the tree, of --output org and of the --changes-only marks. JSON
+    /// and XML always hold plain numbers
+    #[clap(long, value_name = "UNIT", default_value = "human", possible_values = ["human", "raw", "kib", "mib"])]
+    units: SizeUnit,
+
+    /// Show the size of the file descriptor table against the open files limit of the
+    /// processes close to running out, like "haproxy [fds 31k/32k]". "--fd-pressure=all"
+    /// shows it for every process
+    ///
+    /// A process is close when its table takes 80% of its soft limit or more. The table
+    /// grows in steps, so it can be larger than the number of open files
+    #[clap(
+        long, value_name = "WHICH", min_values = 0, multiple_values = false, require_equals = true, default_missing_value = "high",
+        possible_values = ["high", "all"]
+    )]
+    fd_pressure: Option<FdPressure>,
+
+    /// Show the CPUs each pinned process may run on, like "worker [cpus:0-3]". Processes
+    /// allowed on every online CPU are left as they are
+    #[clap(long)]
+    affinity: bool,
+
+    /// Show the CPU each process last ran on, like "nginx cpu=17"
+    #[clap(long)]
+    show_cpu_id: bool,
+
+    /// Show the user and system CPU time each process used, like "postgres time=4h12m".
+    /// "--cpu-time=children" adds the time of the children it waited for, marked with a
+    /// plus, like "make time=38.2s+"
+    #[clap(
+        long, value_name = "WHICH", min_values = 0, multiple_values = false, require_equals = true, default_missing_value = "own",
+        possible_values = ["own", "children"]
+    )]
+    cpu_time: Option<CpuTime>,
+
+    /// Show the kernel function each sleeping process waits in, like "nfsd [svc_recv]"
+    #[clap(long)]
+    wchan: bool,
+
+    /// Show the supplementary groups of each process, like "dockerd (adm,docker,sudo)"
+    #[clap(long)]
+    groups: bool,
+
+    /// Show only the processes with GROUP among their supplementary groups, and their
+    /// ancestors. GROUP is a group name or numeric gid
+    #[clap(long, value_name = "GROUP")]
+    in_group: Option<String>,
+
+    /// Tag the processes that set no_new_privs, which keeps execve from granting them
+    /// privileges, with "[nnp]". "--nnp=missing" tags the ones without it as "[no nnp]"
+    #[clap(
+        long, value_name = "WHICH", min_values = 0, multiple_values = false, require_equals = true, default_missing_value = "set",
+        possible_values = ["set", "missing"]
+    )]
+    nnp: Option<NoNewPrivs>,
+
+    /// With --nnp, show only the tagged processes and their ancestors
+    #[clap(long, requires = "nnp")]
+    nnp_only: bool,
+
+    /// Mark the processes confined by seccomp, as "[seccomp:strict]" or "[seccomp:filter]".
+    /// With more than one filter attached the count is added, like "[seccomp:filter x3]"
+    #[clap(long)]
+    seccomp: bool,
+
+    /// Show only the processes confined by seccomp, and their ancestors
+    #[clap(long)]
+    seccomp_only: bool,
+
+    /// Show only the processes not confined by seccomp, and their ancestors
+    #[clap(long, conflicts_with = "seccomp-only")]
+    no_seccomp_only: bool,
+
+    /// Mark the processes that do not share the namespaces of TYPES with their parent, like
+    /// "snapd ⊘mnt". TYPES is a comma separated list of cgroup, ipc, mnt, net, pid, time, user
+    /// and uts
+    ///
+    /// Namespaces are compared by the inodes of the /proc/[pid]/ns links, so a process is not
+    /// marked when its link or the one of its parent cannot be read, usually for lack of
+    /// permission. With --ascii the mark is written "!mnt"
+    #[clap(
+        long, value_name = "TYPES", use_value_delimiter = true, require_value_delimiter = true,
+        multiple_occurrences = false, possible_values = ["cgroup", "ipc", "mnt", "net", "pid", "time", "user", "uts"]
+    )]
+    show_ns: Vec<String>,
+
+    /// Show the TCP ports and bound UDP ports each process listens on, like "nginx (:80,:443)"
+    #[clap(long)]
+    listen: bool,
+
+    /// Root the tree on PID, or show only the processes of USER, like pstree. A value made
+    /// only of digits, or one of the keywords self, parent and session of --root-pid, is
+    /// taken as a PID
+    #[clap(value_name = "PID|USER", conflicts_with_all = &["root-pid", "root-name", "root-name-fuzzy", "user"])]
+    target: Option<String>,
+
+    /// Print the chain of processes from the top of the tree down to PID instead of the
+    /// tree. "self" stands for rusty-pstree itself
+    #[clap(
+        long, value_name = "PID", parse(try_from_str = parse_path_pid),
+        conflicts_with_all = &[
+            "root-pid", "root-name", "root-name-fuzzy", "user", "target", "count", "signal", "renice", "pause", "resume",
+            "summary", "pids", "kill-order",
+        ]
+    )]
+    path: Option<u32>,
+
+    /// List the processes whose name matches this regex instead of drawing the tree, one
+    /// per line by pid with the chain of processes leading to it
+    ///
+    /// Each line holds the pid, the name and the chain as printed by --path, separated by
+    /// tabs. Exits with status 1 if nothing matches
+    #[clap(
+        long, value_name = "REGEX",
+        conflicts_with_all = &[
+            "root-pid", "root-name", "root-name-fuzzy", "user", "target", "count", "signal", "renice", "pause", "resume",
+            "summary", "path", "pids", "kill-order",
+        ]
+    )]
+    find: Option<Regex>,
+
+    /// Limit the tree to DEPTH levels below the root process(es).
+    /// Applied before --unique
+    ///
+    /// The root processes are at depth 0, so "--depth 1" shows the roots and their
+    /// children only
+    #[clap(short, long)]
+    depth: Option<u32>,
 
-type ProcessNodeRef = Rc<RefCell<ProcessNode>>;
+    /// Keep only the N largest subtrees below the root, or of the top-level processes when
+    /// there are several, and draw the others as one line like
+    /// "… 41 smaller subtrees (388 processes)"
+    ///
+    /// Subtrees are ranked by their number of processes, the lower pid first on a tie. The
+    /// selection and filter options apply first, so only what they keep is ranked. The kept
+    /// subtrees are drawn in the --sort order
+    #[clap(long, value_name = "N", conflicts_with = "group-by")]
+    top: Option<usize>,
+
+    /// Draw at most N lines of tree, then a line counting the processes left out, like
+    /// "… output truncated, 612 more processes". 0 draws every line
+    ///
+    /// The limit counts a line per process, plus the ancestor header and the "(all)" line.
+    /// The extra lines of labels split by --wrap, the "(gone)" tree of --diff and --watch and
+    /// the --highlight legend are drawn beyond it. The deepest processes are left out first,
+    /// as whole subtrees, so every drawn process hangs below its parent. Only the tree output
+    /// is limited
+    #[clap(long, value_name = "N", conflicts_with = "group-by")]
+    max_lines: Option<usize>,
+
+    /// Order sibling processes by "pid" or "name". Applied before --unique
+    #[clap(long, value_name = "KEY", env = "PSTREE_SORT", default_value = "pid", possible_values = ["pid", "name"])]
+    sort: SortKey,
+
+    /// Compare names byte by byte in --sort name and --group-sort, so "worker-10" comes
+    /// before "worker-2". By default runs of digits compare by value
+    #[clap(long)]
+    bytewise_sort: bool,
+
+    /// Draw the processes under one synthetic node per KEY: "user" groups them by owner,
+    /// "unit" by systemd slice and unit, "session" by login session and "exe" by executable.
+    /// A process whose parent is in another group starts a new subtree, drawn below its
+    /// parent in parentheses
+    ///
+    /// "unit" reads /proc/[pid]/cgroup and nests the groups like systemd-cgls, e.g.
+    /// system.slice, then nginx.service, then the processes of the service. Processes
+    /// outside the hierarchy, like kernel threads, are drawn under "(unmanaged)"
+    ///
+    /// "session" draws one group per session leader in session id order, followed by
+    /// "(leader gone)" for the sessions whose leader has exited and "(no session)" for
+    /// kernel threads
+    ///
+    /// "exe" draws one group per executable, like "/usr/sbin/nginx (4 processes)", the
+    /// largest first. Processes whose executable cannot be read, like kernel threads, are
+    /// grouped by name in brackets, like "[kworker/0:1] (3 processes)"
+    #[clap(long, value_name = "KEY", possible_values = ["user", "unit", "session", "exe"])]
+    group_by: Option<GroupBy>,
+
+    /// Order the groups of --group-by by "name", by process "count", largest first, or by
+    /// "key": uid, cgroup path, session id or executable. Sessions default to key,
+    /// executables to count, the others to name
+    #[clap(long, value_name = "ORDER", possible_values = ["name", "count", "key"], requires = "group-by")]
+    group_sort: Option<GroupOrder>,
+
+    /// Print a line after the tree counting the processes, threads and zombies shown and
+    /// the depth of the deepest one
+    #[clap(long)]
+    summary: bool,
+
+    /// Print facts about the drawn tree instead of the tree: the number of processes and
+    /// threads, the deepest level, the process with the most children, the number of leaves
+    /// and users, and the process below the roots with the largest subtree
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "summary", "count", "pids", "kill-order", "group-by", "signal", "renice", "pause", "resume", "watch",
+        ]
+    )]
+    stats: bool,
+
+    /// Print only the number of processes in the selected tree, roots included, instead of
+    /// the tree. A selection that matches nothing counts 0 and still exits with status 0
+    ///
+    /// Display options like --depth and --unique do not change the count, every process
+    /// below the roots is counted once. The ancestors a filter like --traced-only draws above
+    /// its matches are not counted
+    #[clap(long, conflicts_with = "summary")]
+    count: bool,
+
+    /// Print only the pids of the selected tree, one per line, instead of the tree. By
+    /// default parents come before their children, "--pids=leaves-first" reverses that
+    ///
+    /// parents-first lists the pids in drawing order. leaves-first lists every process
+    /// after all of its descendants, the order --signal uses. Like --count, every process
+    /// below the roots is listed whatever --depth and --unique would draw, except the
+    /// ancestors a filter like --traced-only draws above its matches
+    #[clap(
+        long, value_name = "ORDER", min_values = 0, multiple_values = false, require_equals = true, default_missing_value = "parents-first",
+        possible_values = ["parents-first", "leaves-first"],
+        conflicts_with_all = &["count", "signal", "renice", "pause", "resume", "summary"]
+    )]
+    pids: Option<PidOrder>,
+
+    /// Print only the pids of the selected tree, every process after all of its descendants,
+    /// the safe order to stop them by hand: "rusty-pstree --root-name myapp --kill-order |
+    /// xargs -r kill". Same as --pids=leaves-first, nothing is signaled
+    #[clap(long, conflicts_with_all = &["count", "signal", "renice", "pause", "resume", "summary"])]
+    kill_order: bool,
+
+    /// With --pids or --kill-order, end every pid with a NUL byte instead of a newline, for
+    /// xargs -0
+    #[clap(short = '0', long, requires = "pid-list")]
+    null: bool,
+
+    /// With --count, exit with status 1 when the count is 0
+    #[clap(long, requires = "count")]
+    fail_if_none: bool,
+
+    /// Replace every process name with a placeholder like "proc-07" and every user name with
+    /// one like "user-2", to share the output without revealing the machine it comes from
+    ///
+    /// Equal names share their placeholder, so the shape of the tree can still be read.
+    /// Command lines, executable paths, cgroups, group names and the hostname of --output
+    /// xml are left out; pids, states and sizes are kept. --root-name, --find and --user
+    /// still select by the real names
+    #[clap(long)]
+    anonymize: bool,
+
+    /// With --anonymize, write to FILE as JSON which name and user every placeholder stands
+    /// for, to read the answers to a shared tree
+    #[clap(long, value_name = "FILE", parse(from_os_str), requires = "anonymize")]
+    anonymize_map: Option<PathBuf>,
+
+    /// Write every process to FILE as JSON for a later --diff, then draw the tree as usual.
+    /// "-" writes the snapshot to stdout instead of drawing the tree
+    #[cfg(feature = "serde")]
+    #[clap(long, value_name = "FILE", conflicts_with_all = &["watch", "anonymize"])]
+    save: Option<PathBuf>,
+
+    /// Draw the processes of a snapshot written by --save instead of scanning --proc-root,
+    /// "-" reads it from stdin
+    #[cfg(feature = "serde")]
+    #[clap(
+        long, value_name = "FILE",
+        conflicts_with_all = &["watch", "signal", "renice", "pause", "resume", "from-ps"]
+    )]
+    load: Option<PathBuf>,
+
+    /// Draw the processes listed by ps instead of scanning --proc-root, "-" reads them from
+    /// stdin, like "ps -eo pid,ppid,user,stat,rss,args | rusty-pstree --from-ps -"
+    ///
+    /// The first line has to name the columns, with PID, PPID and the command last: COMMAND,
+    /// CMD or ARGS for the command line, or COMM for the name alone. UID or USER, S or STAT and
+    /// RSS are read as well, other columns are skipped
+    #[clap(
+        long, value_name = "FILE", parse(from_os_str),
+        conflicts_with_all = &["watch", "signal", "renice", "pause", "resume"]
+    )]
+    from_ps: Option<PathBuf>,
+
+    /// Compare with a snapshot written by --save: processes started since are marked "[new]"
+    /// in green, and the ones that exited are drawn after the tree under "(gone)", marked "[gone]" in red
+    ///
+    /// Processes are matched by pid and start time, so a pid reused by another process counts
+    /// as one new and one gone process. With --root-pid only the exited processes that were
+    /// below the roots are drawn. "-" reads the snapshot from stdin
+    #[cfg(feature = "serde")]
+    #[clap(
+        long, value_name = "FILE",
+        conflicts_with_all = &[
+            "count", "pids", "kill-order", "group-by", "signal", "renice", "pause", "resume", "watch", "changes-only",
+            "from-ps",
+        ]
+    )]
+    diff: Option<PathBuf>,
+
+    /// With --diff, draw only the new processes and their ancestors, then the exited ones.
+    /// Prints "no changes" when there are neither
+    #[cfg(feature = "serde")]
+    #[clap(long, requires = "diff")]
+    diff_only: bool,
+
+    /// "tree" draws the tree, "json" prints the drawn processes on one line as a JSON array of
+    /// nested objects like {"pid":1,"ppid":0,"name":"init","children":[...]}, "org" writes an
+    /// org-mode outline with one heading per process, as many stars as its depth, "xml" writes
+    /// nested <process pid="1" ppid="0" name="init"> elements inside a <processes> element
+    /// carrying the hostname and the Unix time of the scan
+    ///
+    /// XML processes carry the optional fields that were read as attributes named like the JSON
+    /// fields, and their command line, sockets and groups as <arg>, <listen> and <group gid="...">
+    /// elements before their children
+    ///
+    /// "tree-json" follows the JSON format of tree -J: an array with one object per root,
+    /// {"type":"process","name":LABEL,"pid":1,"contents":[...]} where LABEL is the line of the
+    /// drawing and contents, the children, is left out for leaves, then a last object
+    /// {"type":"report","directories":N,"files":M} counting the processes with children as
+    /// directories and the others as files
+    ///
+    /// "metrics" writes gauges in the Prometheus text format, for a node_exporter textfile
+    /// collector: pstree_processes_total, pstree_processes{state="Z"} for the states R, S, D,
+    /// I, T, t, Z, X and any other seen, pstree_subtree_processes{root="nginx",pid="812"} for
+    /// every child of the root, or every root when there are several, and
+    /// pstree_subtree_rss_bytes with the same labels, with pstree_subtree_pss_bytes next to it
+    /// under --pss
+    ///
+    /// "forest" writes a table like ps f: the --columns padded to their widest value, then a
+    /// COMMAND column holding the line of the drawing, indented two spaces per level behind
+    /// a "\_" connector
+    #[clap(
+        long, value_name = "FORMAT", default_value = "tree",
+        possible_values = ["tree", "json", "org", "xml", "tree-json", "metrics", "forest"],
+        conflicts_with_all = &["group-by", "summary", "align-pids"]
+    )]
+    output: OutputFormat,
+
+    /// With --output org, the deepest heading level. Deeper processes are written as nested
+    /// list items under the last heading
+    #[clap(long, value_name = "LEVEL", default_value_t = DEFAULT_ORG_MAX_LEVEL)]
+    org_max_level: usize,
+
+    /// With --output org, add a property drawer with PID, PPID, USER and RSS under each
+    /// process heading
+    #[clap(long)]
+    org_properties: bool,
+
+    /// With --output forest, the columns written before COMMAND, a comma-separated list of
+    /// pid, ppid, user, stat and rss
+    #[clap(
+        long, value_name = "COLUMNS", default_value = "pid,user,stat", use_value_delimiter = true,
+        require_value_delimiter = true, multiple_occurrences = false
+    )]
+    columns: Vec<ForestColumn>,
+
+    /// Scan and draw again every SECONDS seconds until interrupted, e.g. "--watch 2" or
+    /// "--watch 0.5"
+    ///
+    /// The tree is redrawn on a cleared terminal. With --output json every refresh is printed
+    /// as its own line, {"timestamp":...,"tree":[...]} with the Unix time in seconds and the
+    /// array a single --output json prints, and flushed at once for the reader of the stream
+    #[clap(
+        long, value_name = "SECONDS", parse(try_from_str = parse_interval),
+        conflicts_with_all = &["count", "pids", "kill-order", "path", "find", "signal", "renice", "pause", "resume"]
+    )]
+    watch: Option<Duration>,
+
+    /// With --watch, draw only what changed since the previous refresh: started processes
+    /// marked "[new]", exited ones under "(gone)", and processes whose state changed marked
+    /// like "[S→R]" in yellow, with their unchanged ancestors dimmed. A refresh without
+    /// changes prints "no changes"
+    #[clap(long, requires = "watch")]
+    changes_only: bool,
+
+    /// With --changes-only, also report processes whose resident memory grew or shrank by
+    /// more than PERCENT percent, marked like "[rss 1.2M→3.4M]"
+    #[clap(long, value_name = "PERCENT", requires = "changes-only")]
+    rss_change: Option<u32>,
+
+    /// Send SIG to every process of the selected tree after drawing it, children before
+    /// their parents. SIG is a name like TERM or SIGTERM, or a number
+    ///
+    /// Asks for confirmation on the terminal unless --yes is given. Every process that
+    /// cannot be signaled is reported, and the exit status is 4 if there was any. The
+    /// depth limit and --unique only shorten the drawing, hidden processes are signaled
+    /// too. The ancestors a filter like --traced-only draws above its matches are not.
+    /// rusty-pstree never signals itself, and refuses like --pause when the selection
+    /// holds init or a process it runs under
+    #[clap(long, value_name = "SIG", conflicts_with = "count")]
+    signal: Option<Signal>,
+
+    /// Change the nice value of every process of the selected tree after drawing it: "+5"
+    /// lowers their priority by 5, "-3" raises it by 3 and "=10" sets it to 10
+    ///
+    /// Values are kept between -20 and 19. Asks for confirmation and refuses like --signal,
+    /// and every process that cannot be reniced is reported, usually because raising the
+    /// priority needs privileges, with exit status 4 if there was any
+    #[clap(long, value_name = "CHANGE", allow_hyphen_values = true, conflicts_with = "count")]
+    renice: Option<NiceChange>,
+
+    /// Stop every process of the selected tree with SIGSTOP after drawing it, children
+    /// before their parents so no parent starts a new child in place of a stopped one
+    ///
+    /// Asks for confirmation like --signal. Refuses when the selection holds init or a
+    /// process rusty-pstree runs under, like the shell, unless --force is given
+    #[clap(long, conflicts_with = "count")]
+    pause: bool,
+
+    /// Continue every process of the selected tree with SIGCONT after drawing it, parents
+    /// before their children. Asks for confirmation and refuses like --pause
+    #[clap(long, conflicts_with = "count")]
+    resume: bool,
+
+    /// With --signal, --renice, --pause or --resume, also act on init and the processes
+    /// rusty-pstree runs under
+    #[clap(long, requires = "action")]
+    force: bool,
+
+    /// With --signal, --renice, --pause or --resume, list the processes that would be changed
+    /// instead of changing them. --renice shows their current and new nice values
+    #[clap(long, requires = "action")]
+    dry_run: bool,
+
+    /// With --signal, --renice, --pause or --resume, do not ask for confirmation
+    #[clap(short, long, requires = "action")]
+    yes: bool,
+
+    /// Read process information from this directory instead of /proc, or from a tar archive
+    /// of one, like "tar -czf proc.tar.gz /proc/[0-9]*/status /proc/[0-9]*/stat"
+    ///
+    /// Archives are recognized by a .tar, .tar.gz or .tgz extension or by their content, and
+    /// read without extracting them. Files left out of the archive are treated as unreadable.
+    /// --signal, --renice, --pause and --resume are refused for an archive, whose processes
+    /// are not running here
+    #[clap(long, value_name = "PATH", parse(from_os_str), default_value = "/proc")]
+    proc_root: PathBuf,
+
+    /// Number of threads used to scan the processes. Defaults to the number of CPUs
+    #[clap(short, long)]
+    jobs: Option<usize>,
+
+    /// Do not report skipped process entries on stderr
+    #[clap(short, long)]
+    quiet: bool,
 
-struct ProcessTree
+    /// Report on stderr how many processes were read, how many were skipped because they
+    /// exited during the scan, their status could not be opened or was malformed, and how
+    /// many are drawn as roots because their parent is missing. Given twice, also list them
+    #[clap(short, long, parse(from_occurrences), conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Write the manual page in roff format to stdout and exit
+    #[clap(long)]
+    generate_man: bool,
+
+}
+
+/// What is printed for the selected tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat
 {
-    pub root: ProcessNodeRef,
+    Tree,
+    #[cfg(feature = "serde")]
+    Json,
+    #[cfg(feature = "serde")]
+    TreeJson,
+    Org,
+    Xml,
+    Metrics,
+    Forest,
 }
 
-impl ProcessTree 
+impl FromStr for OutputFormat
 {
-    pub fn new(root: &ProcessNodeRef) -> ProcessTree
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<OutputFormat, String>
     {
-        ProcessTree { root: ProcessNodeRef::clone(root) }
+        match format {
+            "tree" => Ok(OutputFormat::Tree),
+            #[cfg(feature = "serde")]
+            "json" => Ok(OutputFormat::Json),
+            #[cfg(not(feature = "serde"))]
+            "json" => Err("json output needs the serde feature".to_string()),
+            #[cfg(feature = "serde")]
+            "tree-json" => Ok(OutputFormat::TreeJson),
+            #[cfg(not(feature = "serde"))]
+            "tree-json" => Err("tree-json output needs the serde feature".to_string()),
+            "org" => Ok(OutputFormat::Org),
+            "xml" => Ok(OutputFormat::Xml),
+            "metrics" => Ok(OutputFormat::Metrics),
+            "forest" => Ok(OutputFormat::Forest),
+            _ => Err(format!(
+                "invalid output format '{}' (expected tree, json, tree-json, org, xml, metrics or forest)",
+                format
+            )),
+        }
     }
 }
 
-struct ProcessNode
+/// The order of the --pids listing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PidOrder
 {
-    pub proc_info: ProcessInfo,
-    pub children: Vec<ProcessNodeRef>,
+    ParentsFirst,
+    LeavesFirst,
 }
 
-struct PsTreePrintConfig
+impl FromStr for PidOrder
 {
-    show_pid: bool,
-    root_pid: u32,
-    print_config: PrintConfig,
+    type Err = String;
+
+    fn from_str(order: &str) -> Result<PidOrder, String>
+    {
+        match order {
+            "parents-first" => Ok(PidOrder::ParentsFirst),
+            "leaves-first"  => Ok(PidOrder::LeavesFirst),
+            _ => Err(format!("invalid pid order '{}' (expected parents-first or leaves-first)", order)),
+        }
+    }
 }
 
-impl ProcessTree
+/// The share of its open files limit a descriptor table must take for
+/// --fd-pressure to show it.
+const FD_PRESSURE_PERCENT: u32 = 80;
+/// The path that stands for stdin or stdout.
+const STDIO_PATH: &str = "-";
+/// The line width of --compat and --wrap when neither the terminal nor
+/// $COLUMNS gives one, the one pstree uses.
+const DEFAULT_LINE_WIDTH: usize = 132;
+
+/// What --peak-memory shows besides the current resident memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PeakMemory
 {
-    pub fn print(&self, config: &PsTreePrintConfig)
-    {
-        let mut stack: Vec<(ProcessNodeRef, i32)> = vec![(Rc::clone(&self.root), 0)];
+    Rss,
+    Virtual,
+}
 
-        let mut tree_builder = TreeBuilder::new(String::new());
+impl FromStr for PeakMemory
+{
+    type Err = String;
 
-        while !stack.is_empty() {
+    fn from_str(which: &str) -> Result<PeakMemory, String>
+    {
+        match which {
+            "rss"     => Ok(PeakMemory::Rss),
+            "virtual" => Ok(PeakMemory::Virtual),
+            _ => Err(format!("invalid peak memory '{}' (expected rss or virtual)", which)),
+        }
+    }
+}
 
-            let node_depth_entry = stack.pop().unwrap();
-            let node = node_depth_entry.0.try_borrow().unwrap();
-            let depth = node_depth_entry.1;
+/// What --plain draws besides the indentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Plain
+{
+    Indent,
+    Levels,
+}
 
-            let proc_info = node.proc_info.clone();
+impl FromStr for Plain
+{
+    type Err = String;
 
-            let node_str = if config.show_pid {
-                format!("[{}] {}", proc_info.pid, proc_info.name)
-            } else {
-                proc_info.name
-            };
+    fn from_str(mode: &str) -> Result<Plain, String>
+    {
+        match mode {
+            "indent" => Ok(Plain::Indent),
+            "levels" => Ok(Plain::Levels),
+            _ => Err(format!("invalid plain mode '{}' (expected indent or levels)", mode)),
+        }
+    }
+}
 
-            if depth == 0 {
-                tree_builder = TreeBuilder::new(node_str);
-            } else if node.children_count() > 0 {
-                tree_builder.begin_child(node_str);
-            } else {
-                tree_builder.add_empty_child(node_str);
-            }
+/// Whose time --cpu-time shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CpuTime
+{
+    Own,
+    Children,
+}
 
-            for child in node.children.iter().rev() {
-                stack.push((Rc::clone(&child), depth + 1));
-            }
+impl FromStr for CpuTime
+{
+    type Err = String;
 
-            if !stack.is_empty() {
-                let next_depth = stack.last().unwrap().1;
-                for _ in 0 .. depth - next_depth {
-                    tree_builder.end_child();
-                }
-            }
+    fn from_str(which: &str) -> Result<CpuTime, String>
+    {
+        match which {
+            "own"      => Ok(CpuTime::Own),
+            "children" => Ok(CpuTime::Children),
+            _ => Err(format!("invalid cpu time '{}' (expected own or children)", which)),
         }
+    }
+}
+
+/// Which processes --nnp tags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NoNewPrivs
+{
+    Set,
+    Missing,
+}
 
-        let tree = tree_builder.build();
-        let _ = ptree::print_tree_with(&tree, &config.print_config);
+impl NoNewPrivs
+{
+    /// The flag value of the tagged processes.
+    fn flag(self) -> bool
+    {
+        self == NoNewPrivs::Set
     }
+}
+
+impl FromStr for NoNewPrivs
+{
+    type Err = String;
 
-    pub fn filter_unique(&mut self)
+    fn from_str(which: &str) -> Result<NoNewPrivs, String>
     {
-        let mut stack: Vec<ProcessNodeRef> = vec![Rc::clone(&self.root)];
-
-        while !stack.is_empty() {
-            let node_ref = stack.pop().unwrap();
-            node_ref.borrow_mut().children.dedup_by(|p1, p2| {
-                p1.borrow().children.len() == 0 && 
-                p1.borrow().children.len() == 0 && 
-                p1.borrow().proc_info.name == p2.borrow().proc_info.name
-            });
-            node_ref.borrow().children.iter().for_each(|child| stack.push(Rc::clone(child)));
+        match which {
+            "set"     => Ok(NoNewPrivs::Set),
+            "missing" => Ok(NoNewPrivs::Missing),
+            _ => Err(format!("invalid nnp value '{}' (expected set or missing)", which)),
         }
     }
 }
 
-impl ProcessNode 
+/// Which processes --fd-pressure annotates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FdPressure
+{
+    High,
+    All,
+}
+
+impl FromStr for FdPressure
 {
-    pub fn new(proc_info: ProcessInfo) -> ProcessNode
+    type Err = String;
+
+    fn from_str(which: &str) -> Result<FdPressure, String>
     {
-        ProcessNode 
-        { 
-            proc_info, 
-            children: Vec::new(),
+        match which {
+            "high" => Ok(FdPressure::High),
+            "all"  => Ok(FdPressure::All),
+            _ => Err(format!("invalid fd pressure '{}' (expected high or all)", which)),
         }
     }
+}
 
-    pub fn to_heap(self) -> ProcessNodeRef
+/// What --group-by groups the processes by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GroupBy
+{
+    User,
+    Unit,
+    Session,
+    Exe,
+}
+
+/// The groups of --group-by session, in drawing order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SessionGroup
+{
+    Led(u32),
+    LeaderGone,
+    NoSession,
+}
+
+impl FromStr for GroupBy
+{
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<GroupBy, String>
     {
-        Rc::new(RefCell::new(self))
+        match key {
+            "user" => Ok(GroupBy::User),
+            "unit" => Ok(GroupBy::Unit),
+            "session" => Ok(GroupBy::Session),
+            "exe" => Ok(GroupBy::Exe),
+            _ => Err(format!("invalid group key '{}' (expected user, unit, session or exe)", key)),
+        }
     }
+}
 
-    pub fn add_child(&mut self, child: ProcessNodeRef)
-    {
-        self.children.push(child);
+/// Where pid keywords are resolved: they stand for processes related to the
+/// running one, not to the processes of --proc-root.
+const OWN_PROC_ROOT: &str = "/proc";
+
+/// Parses a --root-pid value, a pid or a [`PidKeyword`] resolved for the
+/// running process.
+fn parse_root_pid(pid: &str) -> Result<u32, String>
+{
+    if let Ok(pid) = pid.parse() {
+        return Ok(pid);
     }
+    let keyword: PidKeyword = pid.parse()?;
+    keyword.resolve(&LinuxProcfs::new(OWN_PROC_ROOT), process::id())
+        .map_err(|why| format!("unable to resolve '{}': {}", keyword, why))
+}
 
-    pub fn children_count(&self) -> usize
-    {
-        self.children.len()
+/// Names the missing root of a [`PsTreeError::ProcessNotFound`] after the
+/// pid keyword it resolves to, so a parent that exited before the scan is
+/// not reported as a bare pid.
+fn name_keyword_root(error: PsTreeError) -> PsTreeError
+{
+    if let PsTreeError::ProcessNotFound(missing) = &error {
+        let source = LinuxProcfs::new(OWN_PROC_ROOT);
+        let named = PidKeyword::ALL.into_iter().find_map(|keyword| {
+            let pid = keyword.resolve(&source, process::id()).ok()?;
+            missing.contains(&pid).then_some(PsTreeError::KeywordNotFound(keyword, pid))
+        });
+        if let Some(named) = named {
+            return named;
+        }
+    }
+    error
+}
+
+/// Parses the --path argument, a pid or "self".
+fn parse_path_pid(pid: &str) -> Result<u32, String>
+{
+    match pid {
+        "self" => Ok(process::id()),
+        _ => pid.parse().map_err(|_| format!("invalid pid '{}' (expected a number or self)", pid)),
     }
+}
 
+fn parse_interval(seconds: &str) -> Result<Duration, String>
+{
+    seconds.parse::<f64>().ok()
+        .filter(|&seconds| seconds > 0.0)
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("invalid interval '{}' (expected a positive number of seconds)", seconds))
 }
 
-impl PsTreePrintConfig 
+/// `path` with symlinks and `..` resolved, as the kernel reports open files.
+/// A file that no longer exists is resolved through its directory.
+fn canonical_path(path: &Path) -> PathBuf
 {
-    pub fn new() -> PsTreePrintConfig
-    {
-        PsTreePrintConfig 
-        { 
-            show_pid: false,
-            root_pid: 0,
-            print_config: PrintConfig::default(),
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent().map(fs::canonicalize), path.file_name()) {
+        (Some(Ok(parent)), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Environment variables backing the boolean flags.
+const BOOL_ENV_VARS: [&str; 4] = ["PSTREE_ALIGN_PIDS", "PSTREE_ASCII", "PSTREE_SHOW_PID", "PSTREE_UNIQUE"];
+
+/// clap treats any value other than a false literal as true, including an
+/// empty one, so the boolean variables are checked before parsing.
+fn check_bool_env() -> Result<(), String>
+{
+    for name in BOOL_ENV_VARS {
+        if let Some(value) = env::var_os(name) {
+            let value = value.to_string_lossy().to_lowercase();
+            if !["1", "true", "yes", "0", "false", "no"].contains(&value.as_str()) {
+                return Err(format!(
+                    "invalid value '{}' for {} (expected 1/true/yes or 0/false/no)", value, name
+                ));
+            }
         }
     }
+    Ok(())
+}
+
+/// Whether a color option was given, which colors --plain output too.
+fn colors_given(args: &Args) -> bool
+{
+    args.node_color.is_some()
+        || args.branch_color.is_some()
+        || args.background.is_some()
+        || args.branch_background.is_some()
+        || args.color_by.is_some()
+        || !args.highlight.is_empty()
+}
+
+fn parse_config(args: &Args, groups: GroupTable) -> Result<PsTreePrintConfig, ConfigError>
+{
+    let mut builder = PsTreePrintConfig::builder()
+        .show_pid(args.show_pid)
+        .align_pids(args.align_pids)
+        .pid_format(if args.compat { PidFormat::Parens } else { args.pid_format })
+        .show_ppid(args.show_ppid)
+        .forest(args.forest || args.no_root)
+        .unique(args.unique)
+        .collapse_chains(args.collapse_chains)
+        .upside_down(args.upside_down)
+        .compat(args.compat)
+        .ancestor_header(!args.root_pid.is_empty() && !args.no_ancestor_header)
+        .sort(args.sort)
+        .collation(collation(args))
+        .show_default_umask(args.umask > 1)
+        .show_seccomp(args.seccomp)
+        .show_peak_memory(args.peak_memory.is_some(), args.peak_memory == Some(PeakMemory::Virtual))
+        .size_unit(args.units)
+        .pss(args.pss)
+        .memory_detail(args.memory_detail)
+        .show_groups(args.groups)
+        .show_ns(args.show_ns.clone())
+        .group_names(groups)
+        .ascii(args.ascii)
+        .plain(args.plain.is_some())
+        .level_prefix(args.plain == Some(Plain::Levels))
+        .no_colors(args.plain.is_some() && !colors_given(args));
+
+    if let Some(nnp) = args.nnp {
+        builder = builder.mark_no_new_privs(nnp.flag());
+    }
+
+    if let Some(color_by) = args.color_by {
+        builder = builder.color_by(color_by);
+    }
+
+    if !args.palette.is_empty() {
+        builder = builder.palette(args.palette.clone());
+    }
+
+    if !args.highlight.is_empty() {
+        builder = builder.highlights(assign_highlight_colors(&args.highlight, &args.palette));
+    }
+
+    for (state, color) in &args.state_color {
+        builder = builder.state_color(*state, color.clone());
+    }
+
+    if let Some(color) = args.node_color.clone() {
+        builder = builder.node_color(color);
+    }
+
+    if let Some(color) = args.background.clone() {
+        builder = builder.background(color);
+    }
+
+    if let Some(color) = args.branch_color.clone() {
+        builder = builder.branch_color(color);
+    }
+
+    if let Some(color) = args.branch_background.clone() {
+        builder = builder.branch_background(color);
+    }
+
+    if let Some(style) = args.node_style {
+        builder = builder.node_style(style);
+    }
+
+    if let Some(style) = args.branch_style {
+        builder = builder.branch_style(style);
+    }
+
+    if let Some(val) = args.indent {
+        builder = builder.indent(val);
+    }
+
+    if let Some(val) = args.padding {
+        builder = builder.padding(val);
+    }
+
+    if let Some(val) = args.depth {
+        builder = builder.depth(val);
+    }
+
+    if let Some(val) = args.top {
+        builder = builder.top(val);
+    }
 
+    if args.compat {
+        builder = builder.line_width(terminal_width());
+    }
+
+    if let Some(width) = args.wrap {
+        builder = builder.wrap(width.unwrap_or_else(terminal_width));
+    }
+
+    if let Some(val) = args.max_lines.filter(|&val| val > 0 && args.output == OutputFormat::Tree) {
+        builder = builder.max_lines(val);
+    }
+
+    builder.build()
 }
 
-fn parse_proc_stat(stat: &str) -> ProcessInfo
+/// The command line with `match_args`, else the name, of a process, the
+/// name standing in for processes without a command line.
+fn matched_text(proc_info: &ProcessInfo, match_args: bool) -> String
 {
-    let regex = Regex::new(r"(Name:\s*(?P<name>.+)\n)([\s\S]*)(Pid:\s*(?P<pid>\d+))([\s\S]*)(PPid:\s*(?P<ppid>\d+))").unwrap();
-    let capture = regex.captures_iter(stat).next().unwrap();
+    match proc_info.display_cmdline() {
+        Some(cmdline) if match_args => cmdline,
+        _ => proc_info.display_name().into_owned(),
+    }
+}
 
-    let pid = str::parse::<u32>(&capture["pid"]).unwrap();
-    let ppid = str::parse::<u32>(&capture["ppid"]).unwrap();
-    let name = capture["name"].to_string();
+/// Matches `pattern` against the command line with `match_args`, falling
+/// back to the name for processes without one.
+fn matches_pattern(proc_info: &ProcessInfo, pattern: &Regex, match_args: bool) -> bool
+{
+    pattern.is_match(&matched_text(proc_info, match_args))
+}
 
-    ProcessInfo { pid, ppid, name }
+/// The oldest of the processes at `indices`. Processes without a start time
+/// count as the newest.
+fn oldest(pstree: &ProcessTree, indices: Vec<usize>) -> usize
+{
+    indices.into_iter()
+        .min_by_key(|&index| {
+            let proc_info = &pstree.nodes[index].proc_info;
+            (proc_info.start_time.unwrap_or(u64::MAX), proc_info.pid)
+        })
+        .unwrap()
 }
 
-fn get_process_info() -> Vec<ProcessInfo>
+/// Roots the tree on the processes matching `pattern`, or on the oldest of
+/// them with `first`. Processes without a start time count as the newest.
+fn select_root_name(pstree: &mut ProcessTree, pattern: &Regex, match_args: bool, first: bool) -> Result<(), PsTreeError>
 {
-    let proc_path_iter = match fs::read_dir("/proc") {
-        Ok(proc_dir) => {
-            proc_dir.map(Result::unwrap).filter(|path| {
-                path.file_name().into_string().unwrap().chars().all(char::is_numeric)
-            })
+    let matches = pstree.find_all(|proc_info| matches_pattern(proc_info, pattern, match_args));
+
+    if matches.is_empty() {
+        return Err(PsTreeError::NoMatch(pattern.to_string()));
+    }
+
+    if first {
+        let oldest = oldest(pstree, matches);
+        pstree.set_root_nodes(vec![oldest]);
+    } else {
+        pstree.set_root_nodes(matches);
+    }
+
+    Ok(())
+}
+
+/// Roots the tree on the process whose name, or command line with
+/// `match_args`, scores best against `query`, reporting it unless `quiet`.
+/// Several processes sharing the best score are an error, unless `first`
+/// picks the oldest of them.
+fn select_root_fuzzy(
+    pstree: &mut ProcessTree,
+    query: &str,
+    match_args: bool,
+    first: bool,
+    quiet: bool,
+) -> Result<(), PsTreeError>
+{
+    let mut best = Vec::new();
+    let mut best_score = 0;
+    for index in 0 .. pstree.nodes.len() {
+        let Some(score) = fuzzy_score(query, &matched_text(&pstree.nodes[index].proc_info, match_args)) else {
+            continue;
+        };
+        if score > best_score {
+            best_score = score;
+            best.clear();
+        }
+        if score == best_score {
+            best.push(index);
+        }
+    }
+
+    let chosen = match best.len() {
+        0 => return Err(PsTreeError::NoMatch(query.to_string())),
+        1 => best[0],
+        _ if first => oldest(pstree, best),
+        _ => {
+            let candidates = best.iter()
+                .map(|&index| {
+                    let proc_info = &pstree.nodes[index].proc_info;
+                    (proc_info.pid, matched_text(proc_info, match_args))
+                })
+                .collect();
+            return Err(PsTreeError::AmbiguousMatch(query.to_string(), candidates));
         }
-        Err(why) => panic!("Unable to open \"/proc\": {}", why)
     };
+    if !quiet {
+        let proc_info = &pstree.nodes[chosen].proc_info;
+        eprintln!("rusty-pstree: rooting at '{}' (pid {})", matched_text(proc_info, match_args), proc_info.pid);
+    }
+    pstree.set_root_nodes(vec![chosen]);
+    Ok(())
+}
 
-    let mut proc_vec: Vec<ProcessInfo> = Vec::new();
+/// The start time of `pid`, the reference of --newer-than and --older-than,
+/// which must be in the tree.
+fn reference_start_time(pstree: &ProcessTree, pid: u32) -> Result<u64, PsTreeError>
+{
+    let node = pstree.find(pid).ok_or_else(|| name_keyword_root(PsTreeError::ProcessNotFound(vec![pid])))?;
+    node.proc_info.start_time.ok_or(PsTreeError::StartTimeUnknown(pid))
+}
 
-    for mut proc_path in proc_path_iter.map(|dir| dir.path()) {
-        proc_path.push("status");
-        if let Ok(proc_stat) = fs::read_to_string(proc_path) {
-            proc_vec.push(parse_proc_stat(&proc_stat));
+/// Roots the tree on the processes picked by --root-pid, --root-name and --user,
+/// then applies the filters.
+fn select_roots(pstree: &mut ProcessTree, args: &Args, uid: Option<u32>, gid: Option<u32>) -> Result<(), PsTreeError>
+{
+    if !args.root_pid.is_empty() {
+        for pid in pstree.set_roots(&args.root_pid).map_err(name_keyword_root)? {
+            if !args.quiet {
+                eprintln!("rusty-pstree: process {} is drawn inside another requested subtree, skipping it as a root", pid);
+            }
         }
     }
+    if let Some(pattern) = &args.root_name {
+        select_root_name(pstree, pattern, args.args, args.first)?;
+    }
+    if let Some(query) = &args.root_name_fuzzy {
+        select_root_fuzzy(pstree, query, args.args, args.first, args.quiet)?;
+    }
+    if let Some(uid) = uid {
+        pstree.set_user_roots(uid)?;
+    }
+    if args.traced_only && pstree.retain_matches(|proc_info| proc_info.tracer().is_some()) == 0 {
+        return Err(PsTreeError::NoFilterMatch("traced".to_string()));
+    }
+    if let Some(pid) = args.newer_than {
+        let reference = reference_start_time(pstree, pid)?;
+        if pstree.retain_matches(|proc_info| proc_info.start_time.is_some_and(|start| start > reference)) == 0 {
+            return Err(PsTreeError::NoFilterMatch("newer".to_string()));
+        }
+    }
+    if let Some(pid) = args.older_than {
+        let reference = reference_start_time(pstree, pid)?;
+        if pstree.retain_matches(|proc_info| proc_info.start_time.is_some_and(|start| start < reference)) == 0 {
+            return Err(PsTreeError::NoFilterMatch("older".to_string()));
+        }
+    }
+    if let Some(gid) = gid {
+        let in_group = |proc_info: &ProcessInfo| proc_info.groups.as_ref().is_some_and(|groups| groups.contains(&gid));
+        if pstree.retain_matches(in_group) == 0 {
+            return Err(PsTreeError::NoGroupProcess(gid));
+        }
+    }
+    if let Some(nnp) = args.nnp.filter(|_| args.nnp_only) {
+        if pstree.retain_matches(|proc_info| proc_info.no_new_privs == Some(nnp.flag())) == 0 {
+            return Err(PsTreeError::NoFilterMatch(if nnp.flag() { "nnp" } else { "non-nnp" }.to_string()));
+        }
+    }
+    if args.seccomp_only && pstree.retain_matches(ProcessInfo::is_seccomp_confined) == 0 {
+        return Err(PsTreeError::NoFilterMatch("seccomp confined".to_string()));
+    }
+    if args.no_seccomp_only && pstree.retain_matches(|proc_info| proc_info.seccomp == Some(0)) == 0 {
+        return Err(PsTreeError::NoFilterMatch("unconfined".to_string()));
+    }
+    if let Some(path) = &args.open_file {
+        if pstree.retain_matches(|proc_info| proc_info.holds_file == Some(true)) == 0 {
+            return Err(PsTreeError::FileNotOpen(path.clone()));
+        }
+    }
+    if !args.exe_prefix.is_empty() {
+        let dirs: Vec<PathBuf> = args.exe_prefix.iter().map(|dir| canonical_path(dir)).collect();
+        if pstree.retain_matches(|proc_info| dirs.iter().any(|dir| proc_info.exe_is_under(dir))) == 0 {
+            let dirs: Vec<String> = dirs.iter().map(|dir| dir.display().to_string()).collect();
+            return Err(PsTreeError::NoFilterMatch(dirs.join(" or ")));
+        }
+    }
+    Ok(())
+}
 
-    proc_vec.sort_by(|p0, p1| p0.pid.cmp(&p1.pid));
-    return proc_vec;
+/// True if a snapshot is written or compared with.
+#[cfg(feature = "serde")]
+fn uses_snapshots(args: &Args) -> bool
+{
+    args.save.is_some() || args.diff.is_some()
 }
 
-fn treefy_proc(proc_info_vec: Vec<ProcessInfo>, root_pid: u32) -> ProcessTree
+#[cfg(not(feature = "serde"))]
+fn uses_snapshots(_args: &Args) -> bool
 {
-    let mut node_map: HashMap<u32, ProcessNodeRef> = HashMap::new();
+    false
+}
 
-    let first_proc = if root_pid == 0 {
-        proc_info_vec.first().unwrap().clone()
+/// The snapshot of --load, drawn instead of a scan.
+#[cfg(feature = "serde")]
+fn load_path(args: &Args) -> Option<&Path>
+{
+    args.load.as_deref()
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_path(_args: &Args) -> Option<&Path>
+{
+    None
+}
+
+/// Reads the snapshot at `path`, from stdin if it is "-".
+#[cfg(feature = "serde")]
+fn read_snapshot(path: &Path) -> Result<ProcessTree, PsTreeError>
+{
+    let input: io::Result<Box<dyn io::Read>> = if path == Path::new(STDIO_PATH) {
+        Ok(Box::new(io::stdin().lock()))
     } else {
-        proc_info_vec.iter()
-            .find(|&proc| proc.pid == root_pid)
-            .expect(&format!("process {} does not exist", root_pid))
-            .clone()
+        fs::File::open(path).map(|file| Box::new(file) as Box<dyn io::Read>)
     };
+    input
+        .and_then(|input| ProcessTree::read_snapshot(io::BufReader::new(input)))
+        .map_err(|why| PsTreeError::LoadFailed(path.to_path_buf(), why))
+}
 
-    for proc_info in proc_info_vec {
-        let node_ptr = ProcessNode::new(proc_info.clone()).to_heap();
-        if proc_info.ppid != 0 {
-            node_map.entry(proc_info.ppid).and_modify(|node| {
-                node.borrow_mut().add_child(Rc::clone(&node_ptr));
-            });
+/// Reads the output of ps given to --from-ps from `path`, from stdin if it
+/// is "-".
+fn read_ps_output(path: &Path) -> Result<ProcessTree, PsTreeError>
+{
+    let input: io::Result<Box<dyn io::Read>> = if path == Path::new(STDIO_PATH) {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        fs::File::open(path).map(|file| Box::new(file) as Box<dyn io::Read>)
+    };
+    input
+        .and_then(io::read_to_string)
+        .and_then(|output| {
+            parse_ps_output(&output, &UserTable::load()).map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))
+        })
+        .map(ProcessTree::from_process_list)
+        .map_err(|why| PsTreeError::PsOutputUnreadable(path.to_path_buf(), why))
+}
+
+/// Writes `pstree` as a snapshot to `path`, to stdout if it is "-".
+#[cfg(feature = "serde")]
+fn write_snapshot(pstree: &ProcessTree, path: &Path) -> Result<(), PsTreeError>
+{
+    let output: io::Result<Box<dyn Write>> = if path == Path::new(STDIO_PATH) {
+        Ok(Box::new(io::stdout().lock()))
+    } else {
+        fs::File::create(path).map(|file| Box::new(file) as Box<dyn Write>)
+    };
+    output
+        .and_then(|output| pstree.write_snapshot(io::BufWriter::new(output)))
+        .map_err(|why| PsTreeError::SaveFailed(path.to_path_buf(), why))
+}
+
+#[cfg(not(feature = "serde"))]
+fn read_snapshot(_path: &Path) -> Result<ProcessTree, PsTreeError>
+{
+    unreachable!("snapshots need the serde feature")
+}
+
+/// The scan `pstree` is compared with: the snapshot of --diff or, with
+/// --changes-only, the previous refresh, which `pstree` then replaces.
+fn earlier_tree(
+    pstree: &ProcessTree,
+    args: &Args,
+    previous: &mut Option<Vec<ProcessInfo>>,
+) -> Result<Option<ProcessTree>, PsTreeError>
+{
+    #[cfg(feature = "serde")]
+    if let Some(path) = &args.diff {
+        return read_snapshot(path).map(Some);
+    }
+    if !args.changes_only {
+        return Ok(None);
+    }
+    let current = pstree.nodes.iter().map(|node| node.proc_info.clone()).collect();
+    Ok(previous.replace(current).map(ProcessTree::from_process_list))
+}
+
+/// Marks the drawn processes of `pstree` that started or changed since
+/// `earlier` and returns the difference, limited to the drawn processes
+/// and, with --root-pid, to the exited processes that were below the
+/// roots. With `only_changes` the processes that neither changed nor are
+/// an ancestor of a change are removed.
+fn compare_trees(pstree: &mut ProcessTree, snapshot: &ProcessTree, args: &Args, only_changes: bool) -> TreeDiff
+{
+    let thresholds = ChangeThresholds { state: args.changes_only, rss_percent: args.rss_change };
+    let mut diff = pstree.diff_with(snapshot, &thresholds);
+    let drawn: HashSet<ProcessKey> = pstree.iter_dfs().map(|(_, index)| pstree.nodes[index].proc_info.key()).collect();
+    diff.new.retain(|key| drawn.contains(key));
+    diff.changed.retain(|key, _| drawn.contains(key));
+    if !args.root_pid.is_empty() && !args.root_pid.contains(&0) {
+        let roots: HashSet<ProcessKey> = pstree.roots.iter()
+            .map(|&root| pstree.nodes[root].proc_info.key())
+            .collect();
+        diff.gone.retain(|proc_info| snapshot.ancestry(proc_info.pid).is_some_and(|chain| {
+            chain.iter().any(|&index| roots.contains(&snapshot.nodes[index].proc_info.key()))
+        }));
+    }
+    pstree.mark_changes(&diff);
+    if only_changes {
+        pstree.retain_changes(&diff);
+    }
+    diff
+}
+
+/// Runs `run` every `interval` until it fails, clearing the terminal
+/// before every drawing of the tree. Processes whose stat line did not
+/// change are not read again, see [`ScanCache`].
+fn watch(
+    args: &Args,
+    config: &PsTreePrintConfig,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    interval: Duration,
+) -> Result<(), PsTreeError>
+{
+    let clear = args.output == OutputFormat::Tree && io::stdout().is_terminal();
+    let mut previous = None;
+    let mut cache = ScanCache::default();
+    // Shared by every refresh, so a name keeps its placeholder.
+    let mut anonymizer = args.anonymize.then(Anonymizer::new);
+    loop {
+        if clear {
+            print!("\x1b[H\x1b[2J");
         }
-        node_map.insert(proc_info.pid, node_ptr);
+        run(args, config, uid, gid, &mut previous, Some(&mut cache), anonymizer.as_mut())?;
+        thread::sleep(interval);
     }
+}
 
-    return ProcessTree::new(node_map.get(&first_proc.pid).unwrap());
+/// Scans `source`, reusing the processes of `cache` when given.
+fn scan_proc_root<S: ProcSource + Sync>(
+    source: &S,
+    options: &ScanOptions,
+    cache: Option<&mut ScanCache>,
+) -> Result<ScanOutput, PsTreeError>
+{
+    match cache {
+        Some(cache) => rusty_pstree::scan_source_cached(source, options, cache),
+        None => rusty_pstree::scan_source(source, options),
+    }
 }
 
-#[derive(Parser)]
-#[clap(version)]
-struct Args
+/// The width --compat cuts lines and --wrap wraps labels at: the one of the
+/// terminal, else $COLUMNS, else the default of pstree.
+fn terminal_width() -> usize
 {
-    /// node color, a string in ["white", "red", "green", ...] or an RGB triple like "255,255,0"
-    #[clap(short = 'c', long)]
-    node_color: Option<String>,
+    // SAFETY: an all-zero winsize is valid, and TIOCGWINSZ only writes into it.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+        return usize::from(size.ws_col);
+    }
+    env::var("COLUMNS").ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(DEFAULT_LINE_WIDTH)
+}
 
-    /// Branch color, a string in ["white", "red", "green", ...] or an RGB triple like "255,255,0"
-    #[clap(short, long)]
-    branch_color: Option<String>,
+/// True if --output forest writes `column`.
+fn forest_shows(args: &Args, column: ForestColumn) -> bool
+{
+    args.output == OutputFormat::Forest && args.columns.contains(&column)
+}
 
-    /// Node background color, a string in ["white", "red", "green", ...] or an RGB triple like "255,255,0"
-    #[clap(short = 'g', long)]
-    background: Option<String>,
+/// "process" or "processes", for `count` of them.
+fn processes(count: usize) -> &'static str
+{
+    if count == 1 { "process" } else { "processes" }
+}
 
-    /// Indentation size. The default value is 3.
-    #[clap(short = 'I', long)]
-    pub indent: Option<usize>,
+/// Reports the processes of `scan_output` and the ones skipped by reason on
+/// stderr, with `list` every skipped one on its own line.
+fn report_scan(scan_output: &ScanOutput, list: bool)
+{
+    let read = scan_output.processes.len();
+    eprintln!("rusty-pstree: read {} {}", read, processes(read));
+
+    let vanished: Vec<&SkippedProcess> = scan_output.skipped.iter()
+        .filter(|skip| matches!(skip.reason, SkipReason::Vanished))
+        .collect();
+    eprintln!("rusty-pstree: skipped {} {} that exited during the scan", vanished.len(), processes(vanished.len()));
+    if list {
+        for skip in vanished {
+            eprintln!("rusty-pstree:   {}", skip.pid);
+        }
+    }
 
-    /// Padding size. The default value is 1.
-    #[clap(short = 'P', long)]
-    pub padding: Option<usize>,
+    let unreadable: Vec<(u32, &io::Error)> = scan_output.skipped.iter()
+        .filter_map(|skip| match &skip.reason {
+            SkipReason::Unreadable(why) => Some((skip.pid, why)),
+            SkipReason::Vanished => None,
+        })
+        .collect();
+    eprintln!("rusty-pstree: skipped {} {} whose status could not be opened", unreadable.len(), processes(unreadable.len()));
+    if list {
+        for (pid, why) in unreadable {
+            eprintln!("rusty-pstree:   {}: {}", pid, why);
+        }
+    }
 
-    /// Use ASCII characters to draw the tree.
-    #[clap(short = 'A', long)]
-    ascii: bool,
+    let malformed = scan_output.errors.len();
+    eprintln!("rusty-pstree: skipped {} {} with a malformed status", malformed, processes(malformed));
+    if list {
+        for error in &scan_output.errors {
+            eprintln!("rusty-pstree:   {}: {}", error.pid, error.reason);
+        }
+    }
+}
 
-    /// Show PIDs
-    #[clap(short = 'p', long)]
-    show_pid: bool,
+/// Reports the processes drawn as roots because their parent is missing on
+/// stderr, with `list` their pids and missing parents.
+fn report_orphans(pstree: &ProcessTree, list: bool)
+{
+    let orphans = pstree.orphans();
+    eprintln!("rusty-pstree: drew {} {} without a parent as roots", orphans.len(), processes(orphans.len()));
+    if list {
+        for pid in orphans {
+            eprintln!("rusty-pstree:   {}: parent {} not found", pid, pstree.nodes[pstree.pid_index[&pid]].proc_info.ppid);
+        }
+    }
+}
 
-    /// Remove the duplicated leaf node 
-    #[clap(short, long)]
-    unique: bool,
+fn run(
+    args: &Args,
+    config: &PsTreePrintConfig,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    previous: &mut Option<Vec<ProcessInfo>>,
+    cache: Option<&mut ScanCache>,
+    mut anonymizer: Option<&mut Anonymizer>,
+) -> Result<(), PsTreeError>
+{
+    let compares_age = args.newer_than.is_some() || args.older_than.is_some();
+    let mut scan_options = ScanOptions {
+        // Snapshots hold every process, which the subtree scan would leave out, and so
+        // would the process --newer-than and --older-than compare with.
+        root_pids: if uses_snapshots(args) || compares_age { Vec::new() } else { args.root_pid.clone() },
+        read_ancestors: config.ancestor_header && args.output == OutputFormat::Tree,
+        read_cmdline: args.args,
+        read_start_time: args.first || args.changes_only || uses_snapshots(args) || compares_age,
+        read_session: args.group_by == Some(GroupBy::Session),
+        read_nice: args.renice.is_some(),
+        read_cgroup: args.group_by == Some(GroupBy::Unit),
+        fd_pressure: args.fd_pressure.map(|which| match which {
+            FdPressure::High => FD_PRESSURE_PERCENT,
+            FdPressure::All => 0,
+        }),
+        read_processor: args.show_cpu_id,
+        read_cpu_time: args.cpu_time.is_some(),
+        read_children_cpu_time: args.cpu_time == Some(CpuTime::Children),
+        read_wchan: args.wchan,
+        read_pss: args.pss,
+        read_listen: args.listen,
+        open_file: args.open_file.as_deref().map(canonical_path),
+        read_exe: !args.exe_prefix.is_empty() || args.group_by == Some(GroupBy::Exe),
+        namespaces: args.show_ns.clone(),
+        status_fields: StatusFields {
+            state: args.summary || args.changes_only || args.output == OutputFormat::Metrics
+                || !config.state_colors.is_empty() || forest_shows(args, ForestColumn::Stat),
+            threads: args.summary || args.stats,
+            umask: args.umask > 0,
+            groups: args.groups || args.in_group.is_some(),
+            no_new_privs: args.nnp.is_some(),
+            seccomp: args.seccomp || args.seccomp_only || args.no_seccomp_only,
+            rss: args.peak_memory.is_some() || args.rss_change.is_some() || args.org_properties || args.pss
+                || args.memory_detail
+                || args.output == OutputFormat::Metrics || forest_shows(args, ForestColumn::Rss),
+            peak_virtual: args.peak_memory == Some(PeakMemory::Virtual),
+            memory_split: args.memory_detail,
+            cpus_allowed: args.affinity,
+            ..StatusFields::default()
+        },
+        ..ScanOptions::default()
+    };
+    if let Some(jobs) = args.jobs {
+        scan_options.jobs = jobs;
+    }
+    let source = LinuxProcfs::new(&args.proc_root);
+    let mut pstree = if let Some(path) = load_path(args) {
+        read_snapshot(path)?
+    } else if let Some(path) = &args.from_ps {
+        read_ps_output(path)?
+    } else {
+        let scan_output = if ArchiveSource::is_archive(&args.proc_root) {
+            let archive = ArchiveSource::open(&args.proc_root)
+                .map_err(|why| PsTreeError::ProcUnreadable(args.proc_root.clone(), why))?;
+            scan_proc_root(&archive, &scan_options, cache)?
+        } else {
+            scan_proc_root(&source, &scan_options, cache)?
+        };
+        if args.verbose > 0 {
+            report_scan(&scan_output, args.verbose > 1);
+        } else if !args.quiet && !scan_output.errors.is_empty() {
+            eprintln!("rusty-pstree: skipped {} malformed process entries", scan_output.errors.len());
+        }
+        let pstree = ProcessTree::from_process_list(scan_output.processes);
+        if args.verbose > 0 {
+            report_orphans(&pstree, args.verbose > 1);
+        }
+        pstree
+    };
+    let earlier = earlier_tree(&pstree, args, previous)?;
+    #[cfg(feature = "serde")]
+    if let Some(path) = &args.save {
+        write_snapshot(&pstree, path)?;
+        if path == Path::new(STDIO_PATH) {
+            return Ok(());
+        }
+    }
+    if let Some(pid) = args.path {
+        let chain = pstree.ancestry(pid).ok_or_else(|| PsTreeError::ProcessNotFound(vec![pid]))?;
+        if let Some(anonymizer) = anonymizer {
+            anonymize(&mut pstree, None, args, anonymizer)?;
+        }
+        println!("{}", format_chain(&pstree, &chain, args.ascii));
+        return Ok(());
+    }
+    if let Some(pattern) = &args.find {
+        return find_processes(&mut pstree, pattern, args, anonymizer);
+    }
+    let selection = select_roots(&mut pstree, args, uid, gid);
+    if args.count {
+        let count = match selection {
+            Ok(()) => {
+                let selected = pstree.selected();
+                pstree.iter_dfs().filter(|&(_, index)| selected[index]).count()
+            }
+            Err(why) if why.is_empty_selection() => 0,
+            Err(why) => return Err(why),
+        };
+        println!("{}", count);
+        if count == 0 && args.fail_if_none {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+    selection?;
+    if args.no_root {
+        pstree.hide_roots();
+    }
+    #[cfg(feature = "serde")]
+    let only_changes = args.changes_only || args.diff_only;
+    #[cfg(not(feature = "serde"))]
+    let only_changes = args.changes_only;
+    let mut diff = earlier.map(|earlier| compare_trees(&mut pstree, &earlier, args, only_changes));
+    if let Some(anonymizer) = anonymizer.as_deref_mut() {
+        anonymize(&mut pstree, diff.as_mut(), args, anonymizer)?;
+    }
+    let user_names = || anonymizer.as_deref().map_or_else(UserTable::load, Anonymizer::user_names);
+    if only_changes && args.output == OutputFormat::Tree && diff.as_ref().is_some_and(TreeDiff::is_empty) {
+        if writeln!(io::stdout(), "no changes").is_err() && args.watch.is_some() {
+            process::exit(0);
+        }
+        return Ok(());
+    }
+    let pid_order = if args.kill_order { Some(PidOrder::LeavesFirst) } else { args.pids };
+    if let Some(order) = pid_order {
+        let mut indices: Vec<usize> = match order {
+            PidOrder::ParentsFirst => pstree.iter_dfs().map(|(_, index)| index).collect(),
+            PidOrder::LeavesFirst => pstree.iter_post_order().map(|(_, index)| index).collect(),
+        };
+        let selected = pstree.selected();
+        indices.retain(|&index| selected[index]);
+        let terminator = if args.null { '\0' } else { '\n' };
+        let mut out = io::stdout().lock();
+        for index in indices {
+            if write!(out, "{}{}", pstree.nodes[index].proc_info.pid, terminator).is_err() {
+                break;
+            }
+        }
+        return Ok(());
+    }
+    if !args.quiet {
+        for cycle in &pstree.broken_cycles {
+            let pids: Vec<String> = cycle.iter().map(u32::to_string).collect();
+            eprintln!(
+                "rusty-pstree: parent cycle between pids {}, treating {} as a top-level process",
+                pids.join(" -> "), cycle.last().unwrap()
+            );
+        }
+    }
+    let freeze = match (args.pause, args.resume) {
+        (true, _) => Some(Freeze::Pause),
+        (_, true) => Some(Freeze::Resume),
+        _ => None,
+    };
+    let mut targets: Vec<usize> = match freeze {
+        Some(freeze) => freeze.order(&pstree),
+        None if args.signal.is_some() || args.renice.is_some() => Freeze::Pause.order(&pstree),
+        None => Vec::new(),
+    };
+    // The ancestors a filter keeps to draw its matches are not acted on.
+    let selected = pstree.selected();
+    targets.retain(|&index| selected[index] && pstree.nodes[index].proc_info.pid != process::id());
+    let action = freeze
+        .map(|freeze| freeze.to_string())
+        .or_else(|| args.signal.map(|_| "signal".to_string()))
+        .or_else(|| args.renice.map(|_| "renice".to_string()));
+    if let Some(action) = action.filter(|_| !args.force) {
+        check_unprotected(&pstree, &targets, &action)?;
+    }
+    if let Some(group_by) = args.group_by {
+        group_processes(&mut pstree, group_by, args.group_sort, collation(args), user_names);
+    }
+    pstree.apply_config(config);
+    if args.stats {
+        print!("{}", pstree.stats());
+        return Ok(());
+    }
+    let written = match args.output {
+        OutputFormat::Tree => pstree.print(config),
+        #[cfg(feature = "serde")]
+        OutputFormat::Json if args.watch.is_some() => pstree.write_json_frame(io::stdout().lock(), SystemTime::now()),
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => pstree.write_json(io::stdout().lock()),
+        #[cfg(feature = "serde")]
+        OutputFormat::TreeJson => pstree.write_tree_json(io::stdout().lock(), config),
+        OutputFormat::Org => pstree.write_org(io::stdout().lock(), config, &OrgOptions {
+            max_level: args.org_max_level,
+            properties: args.org_properties,
+            user_names: if args.org_properties { user_names() } else { UserTable::default() },
+        }),
+        OutputFormat::Xml => pstree.write_xml(io::stdout().lock(), &XmlMetadata {
+            // A loaded snapshot or ps output may come from another host.
+            hostname: fs::read_to_string(source.root().join("sys/kernel/hostname"))
+                .ok()
+                .map(|hostname| hostname.trim_end().to_string())
+                .filter(|hostname| {
+                    !hostname.is_empty() && load_path(args).is_none() && args.from_ps.is_none() && !args.anonymize
+                }),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|since| since.as_secs()),
+        }),
+        OutputFormat::Metrics => pstree.write_metrics(io::stdout().lock()),
+        OutputFormat::Forest => pstree.write_forest(io::stdout().lock(), config, &ForestOptions {
+            columns: args.columns.clone(),
+            user_names: if args.columns.contains(&ForestColumn::User) { user_names() } else { UserTable::default() },
+        }),
+    };
+    // Nobody is left to read the next refresh.
+    if written.is_err() && args.watch.is_some() {
+        process::exit(0);
+    }
+    if let Some(diff) = &diff {
+        let _ = diff.gone_tree().print(config);
+    }
+    if args.summary {
+        let mut summary = pstree.summary();
+        // A --root-pid scan only reads the selected subtrees, so count the
+        // whole system from the pid directories instead.
+        if !args.root_pid.is_empty() && !args.root_pid.contains(&0) {
+            if let Ok(pids) = source.list_pids() {
+                summary.total_processes = pids.len();
+            }
+        }
+        println!("{}", summary);
+    }
+    if let Some(signal) = args.signal {
+        signal_processes(&pstree, &targets, signal, args)?;
+    }
+    if let Some(change) = args.renice {
+        renice_processes(&pstree, &targets, change, args)?;
+    }
+    if let Some(freeze) = freeze {
+        signal_processes(&pstree, &targets, freeze.signal(), args)?;
+    }
+    Ok(())
+}
 
-    /// Draw the process tree rooted on a specific PID
-    #[clap(short, long, default_value = "0")]
-    root_pid: u32,
+/// How --sort name and --group-sort compare names.
+fn collation(args: &Args) -> Collation
+{
+    if args.bytewise_sort { Collation::Bytewise } else { Collation::Natural }
+}
 
-    #[clap(short, long)]
-    depth: Option<u32>,
+/// Groups the selected processes for drawing, by name unless `order` says otherwise.
+/// Users are named after `user_names`.
+fn group_processes(
+    pstree: &mut ProcessTree,
+    group_by: GroupBy,
+    order: Option<GroupOrder>,
+    collation: Collation,
+    user_names: impl FnOnce() -> UserTable,
+)
+{
+    match group_by {
+        GroupBy::User => {
+            let users = user_names();
+            pstree.group_by(
+                |proc_info| proc_info.uid,
+                |uid| match uid {
+                    Some(uid) => users.name(*uid).map_or_else(|| uid.to_string(), str::to_string),
+                    None => "(unknown user)".to_string(),
+                },
+                order.unwrap_or(GroupOrder::Name),
+                collation,
+            );
+        }
+        GroupBy::Unit => pstree.group_by_path(
+            |proc_info| {
+                let cgroup = proc_info.cgroup.as_deref().unwrap_or("");
+                cgroup.split('/').filter(|unit| !unit.is_empty()).map(str::to_string).collect()
+            },
+            "(unmanaged)",
+            order.unwrap_or(GroupOrder::Name),
+            collation,
+        ),
+        GroupBy::Session => {
+            let leaders: HashSet<u32> = pstree.nodes.iter()
+                .filter(|node| node.proc_info.is_session_leader())
+                .map(|node| node.proc_info.pid)
+                .collect();
+            pstree.group_by(
+                |proc_info| match proc_info.session {
+                    None | Some(0) => SessionGroup::NoSession,
+                    Some(session) if leaders.contains(&session) => SessionGroup::Led(session),
+                    Some(_) => SessionGroup::LeaderGone,
+                },
+                |group| match group {
+                    SessionGroup::Led(session) => format!("session {}", session),
+                    SessionGroup::LeaderGone => "(leader gone)".to_string(),
+                    SessionGroup::NoSession => "(no session)".to_string(),
+                },
+                order.unwrap_or(GroupOrder::Key),
+                collation,
+            );
+        }
+        GroupBy::Exe => {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for (_, index) in pstree.iter_dfs() {
+                *counts.entry(exe_group(&pstree.nodes[index].proc_info)).or_default() += 1;
+            }
+            pstree.group_by(
+                exe_group,
+                |exe| match counts.get(exe).copied().unwrap_or(0) {
+                    1 => format!("{} (1 process)", exe),
+                    count => format!("{} ({} processes)", exe, count),
+                },
+                order.unwrap_or(GroupOrder::Count),
+                collation,
+            );
+        }
+    }
+}
 
+/// The --group-by exe key of a process: its executable, or its name in
+/// brackets when the exe link was not read.
+fn exe_group(proc_info: &ProcessInfo) -> String
+{
+    match &proc_info.exe {
+        Some(exe) => exe.to_string_lossy().into_owned(),
+        None => format!("[{}]", proc_info.display_name()),
+    }
 }
 
-fn parse_rgb(rgb_str: &str) -> Option<Color>
+/// Lists the processes matching `pattern` by pid, each with its ancestry.
+fn find_processes(
+    pstree: &mut ProcessTree,
+    pattern: &Regex,
+    args: &Args,
+    anonymizer: Option<&mut Anonymizer>,
+) -> Result<(), PsTreeError>
 {
-    let regex = Regex::new(r"(?P<r>\d+),(?P<g>\d+),(?P<b>\d+)").unwrap();
-    let capture = regex.captures_iter(&rgb_str).next().unwrap();
-    let mut rgb: [u8; 3] = [0, 0, 0];
+    let mut matches = pstree.find_all(|proc_info| matches_pattern(proc_info, pattern, args.args));
+    if matches.is_empty() {
+        return Err(PsTreeError::NoMatch(pattern.to_string()));
+    }
+    if let Some(anonymizer) = anonymizer {
+        anonymize(pstree, None, args, anonymizer)?;
+    }
+
+    matches.sort_by_key(|&index| pstree.nodes[index].proc_info.pid);
+    for index in matches {
+        let proc_info = &pstree.nodes[index].proc_info;
+        let chain = pstree.ancestry(proc_info.pid).unwrap();
+        println!("{}\t{}\t{}", proc_info.pid, proc_info.display_name(), format_chain(pstree, &chain, args.ascii));
+    }
+    Ok(())
+}
+
+/// Anonymizes the processes of `pstree` and the gone ones of `diff`, then
+/// writes what the placeholders stand for to the --anonymize-map file.
+fn anonymize(
+    pstree: &mut ProcessTree,
+    diff: Option<&mut TreeDiff>,
+    args: &Args,
+    anonymizer: &mut Anonymizer,
+) -> Result<(), PsTreeError>
+{
+    pstree.anonymize(anonymizer);
+    for proc_info in diff.into_iter().flat_map(|diff| &mut diff.gone) {
+        anonymizer.anonymize(proc_info);
+    }
+    if let Some(path) = &args.anonymize_map {
+        fs::File::create(path)
+            .and_then(|file| anonymizer.write_map(io::BufWriter::new(file), &UserTable::load()))
+            .map_err(|why| PsTreeError::MapWriteFailed(path.clone(), why))?;
+    }
+    Ok(())
+}
 
-    for (i, color) in "rgb".chars().enumerate() {
-        rgb[i] = match str::parse::<u8>(&capture[String::from(color).as_str()]) {
-            Ok(color) => color,
-            Err(_) => return None,
+/// Formats the nodes at `chain` as `name(pid)` hops joined by arrows.
+fn format_chain(pstree: &ProcessTree, chain: &[usize], ascii: bool) -> String
+{
+    let hops: Vec<String> = chain.iter()
+        .map(|&index| {
+            let proc_info = &pstree.nodes[index].proc_info;
+            format!("{}({})", proc_info.display_name(), proc_info.pid)
+        })
+        .collect();
+    hops.join(if ascii { " -> " } else { " → " })
+}
+
+/// Sends `signal` to the nodes at `targets` in order, or lists them with --dry-run.
+fn signal_processes(pstree: &ProcessTree, targets: &[usize], signal: Signal, args: &Args) -> Result<(), PsTreeError>
+{
+    let describe = |index: usize| {
+        let proc_info = &pstree.nodes[index].proc_info;
+        format!("{} ({})", proc_info.pid, proc_info.display_name())
+    };
+
+    if args.dry_run {
+        for &index in targets {
+            println!("would send {} to {}", signal, describe(index));
+        }
+        return Ok(());
+    }
+
+    if !args.yes && !confirm(&format!("send {} to {} processes?", signal, targets.len())) {
+        return Err(PsTreeError::Declined("no signal sent".to_string()));
+    }
+
+    let pids: Vec<u32> = targets.iter().map(|&index| pstree.nodes[index].proc_info.pid).collect();
+    let failures = send_all(&mut Kill, signal, &pids);
+    for (pid, why) in &failures {
+        let reason = match why.raw_os_error() {
+            Some(libc::ESRCH) => "process already exited".to_string(),
+            Some(libc::EPERM) => "permission denied".to_string(),
+            _ => why.to_string(),
         };
+        eprintln!("rusty-pstree: cannot send {} to {}: {}", signal, describe(pstree.pid_index[pid]), reason);
     }
 
-    Some(Color::RGB(rgb[0], rgb[1], rgb[2]))
+    if !failures.is_empty() {
+        return Err(PsTreeError::SignalFailed(failures.len(), targets.len()));
+    }
+    Ok(())
 }
 
-fn parse_color(color_str: &str) -> Option<Color>
+/// Fails if `targets` holds init or an ancestor of rusty-pstree, which
+/// `action`, like "pause", would take down along with the terminal it runs
+/// in.
+fn check_unprotected(pstree: &ProcessTree, targets: &[usize], action: &str) -> Result<(), PsTreeError>
 {
-    match color_str.to_lowercase().as_str() {
-        "black"   => Some(Color::Black),
-        "red"     => Some(Color::Red),
-        "green"   => Some(Color::Green),
-        "yellow"  => Some(Color::Yellow),
-        "blue"    => Some(Color::Blue),
-        "purple"  => Some(Color::Purple),
-        "cyan"    => Some(Color::Cyan),
-        "white"   => Some(Color::White),
-        _ => parse_rgb(color_str),
+    let ancestors = pstree.ancestry(process::id()).unwrap_or_default();
+    match targets.iter().find(|&&index| pstree.nodes[index].proc_info.pid == 1 || ancestors.contains(&index)) {
+        Some(&index) => Err(PsTreeError::ProtectedProcess(pstree.nodes[index].proc_info.pid, action.to_string())),
+        None => Ok(()),
     }
 }
 
-fn parse_config(args: Args) -> PsTreePrintConfig
+/// Applies `change` to the nice value of the processes at `targets`, as
+/// read by the scan.
+fn renice_processes(pstree: &ProcessTree, targets: &[usize], change: NiceChange, args: &Args) -> Result<(), PsTreeError>
 {
-    let mut config = PsTreePrintConfig::new();
+    let describe = |index: usize| {
+        let proc_info = &pstree.nodes[index].proc_info;
+        format!("{} ({})", proc_info.pid, proc_info.display_name())
+    };
 
-    config.print_config.leaf.foreground = args.node_color.and_then(|color_str| parse_color(&color_str));
-    config.print_config.leaf.background = args.background.and_then(|color_str| parse_color(&color_str));
-    config.print_config.branch.foreground = args.branch_color.and_then(|color_str| parse_color(&color_str));
+    if args.dry_run {
+        for &index in targets {
+            match pstree.nodes[index].proc_info.nice {
+                Some(nice) => println!("would renice {} from {} to {}", describe(index), nice, change.apply(nice)),
+                None => println!("would renice {} to {}", describe(index), change),
+            }
+        }
+        return Ok(());
+    }
 
-    config.show_pid = args.show_pid;
-    config.root_pid = args.root_pid;
+    if !args.yes && !confirm(&format!("renice {} processes by {}?", targets.len(), change)) {
+        return Err(PsTreeError::Declined("no process reniced".to_string()));
+    }
 
-    if args.ascii {
-        config.print_config.characters.down = String::from("|");
-        config.print_config.characters.right = String::from("-");
-        config.print_config.characters.down_and_right = String::from("|");
-        config.print_config.characters.turn_right = String::from("`");
+    let mut failed = 0;
+    for &index in targets {
+        let result = match pstree.nodes[index].proc_info.nice {
+            Some(nice) => set_nice(pstree.nodes[index].proc_info.pid, change.apply(nice)),
+            None => Err(io::Error::from_raw_os_error(libc::ESRCH)),
+        };
+        if let Err(why) = result {
+            let reason = match why.raw_os_error() {
+                Some(libc::ESRCH) => "process already exited".to_string(),
+                Some(libc::EPERM | libc::EACCES) => "permission denied".to_string(),
+                _ => why.to_string(),
+            };
+            eprintln!("rusty-pstree: cannot renice {}: {}", describe(index), reason);
+            failed += 1;
+        }
     }
 
-    if let Some(val) = args.depth {
-        config.print_config.depth = val;
+    if failed > 0 {
+        return Err(PsTreeError::ReniceFailed(failed, targets.len()));
     }
+    Ok(())
+}
 
-    if let Some(val) = args.indent {
-        config.print_config.indent = val;
+/// Asks `question` on stderr and reads the answer from stdin. Only y or yes
+/// agree, end of input declines.
+fn confirm(question: &str) -> bool
+{
+    eprint!("{} [y/N] ", question);
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
     }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
 
-    if let Some(val) = args.padding {
-        config.print_config.padding = val;
+/// Writes the manual page in roff format: an entry per option holding its
+/// long help and the notes --help adds to it, then the sections of the
+/// text --help ends with.
+fn write_man_page<W: io::Write>(mut out: W) -> io::Result<()>
+{
+    let mut command = Args::command();
+    // Adds --help and --version, which clap only creates when parsing.
+    command._build_all();
+    let name = command.get_name().to_string();
+    let footer = format!("{} {}", name, command.get_version().unwrap_or_default());
+
+    let mut page = Roff::new();
+    page.control("TH", [name.to_uppercase().as_str(), "1", footer.as_str()]);
+    page.control("SH", ["NAME"]);
+    page.text([roman(format!("{} - {}", name, command.get_about().unwrap_or_default()))]);
+    page.control("SH", ["SYNOPSIS"]);
+    page.text([bold(name.as_str()), roman(" ["), italic("OPTIONS"), roman("]")]);
+
+    page.control("SH", ["OPTIONS"]);
+    for arg in command.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        page.control("TP", []);
+        page.text(man_option_names(arg));
+        let help = arg.get_long_help().or_else(|| arg.get_help()).unwrap_or_default();
+        for (index, paragraph) in help.split("\n\n").enumerate() {
+            if index > 0 {
+                page.control("IP", []);
+            }
+            page.text([roman(paragraph)]);
+        }
+        let notes = man_option_notes(arg);
+        if !notes.is_empty() {
+            page.control("IP", []);
+            page.text([roman(notes.join(" "))]);
+        }
     }
 
-    return config;
+    // The text after the options holds sections like "EXIT STATUS:" whose
+    // lines are kept as they are.
+    let after_help = command.get_after_long_help().or_else(|| command.get_after_help()).unwrap_or_default();
+    for section in after_help.split("\n\n").filter(|section| !section.is_empty()) {
+        let (title, body) = section.split_once(":\n").unwrap_or(("NOTES", section));
+        page.control("SH", [title]);
+        for line in body.lines() {
+            page.text([roman(line.trim()), line_break()]);
+        }
+    }
+    out.write_all(page.render().as_bytes())
+}
+
+/// The names of an option for its man page entry, like `-p, --show-pid`,
+/// followed by its value like `=PID`.
+fn man_option_names(arg: &Arg) -> Vec<Inline>
+{
+    let mut names = Vec::new();
+    if let Some(short) = arg.get_short() {
+        names.push(bold(format!("-{}", short)));
+    }
+    if let Some(long) = arg.get_long() {
+        if !names.is_empty() {
+            names.push(roman(", "));
+        }
+        names.push(bold(format!("--{}", long)));
+    }
+    if arg.is_positional() {
+        names.push(italic(arg.get_id().to_uppercase()));
+    } else if arg.is_takes_value_set() {
+        let value = arg.get_value_names().map_or_else(|| arg.get_id().to_uppercase(), |values| values.join(" "));
+        names.push(roman("="));
+        names.push(italic(value));
+    }
+    names
+}
+
+/// The notes --help writes after the help of an option: its possible
+/// values, default and environment variable.
+fn man_option_notes(arg: &Arg) -> Vec<String>
+{
+    let mut notes = Vec::new();
+    let possible: Vec<&str> = arg.get_possible_values().unwrap_or_default().iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name())
+        .collect();
+    if !possible.is_empty() && !arg.is_hide_possible_values_set() {
+        notes.push(format!("[possible values: {}]", possible.join(", ")));
+    }
+    let defaults: Vec<String> = arg.get_default_values().iter().map(|value| value.to_string_lossy().into_owned()).collect();
+    if arg.is_takes_value_set() && !defaults.is_empty() && !arg.is_hide_default_value_set() {
+        notes.push(format!("[default: {}]", defaults.join(",")));
+    }
+    if let Some(env) = arg.get_env().filter(|_| !arg.is_hide_env_set()) {
+        notes.push(format!("[env: {}]", env.to_string_lossy()));
+    }
+    notes
 }
 
 fn main()
 {
-    let ps_info = get_process_info();
-    let args = Args::parse();
-    let mut pstree = treefy_proc(ps_info, args.root_pid);
-    args.unique.then(|| pstree.filter_unique());
-    pstree.print(&parse_config(args));
+    if let Err(why) = check_bool_env() {
+        Args::command().error(ErrorKind::InvalidValue, why).exit();
+    }
+    let mut args = Args::parse();
+    if args.generate_man {
+        if let Err(why) = write_man_page(io::stdout()) {
+            eprintln!("rusty-pstree: {}", why);
+            process::exit(3);
+        }
+        return;
+    }
+    #[cfg(feature = "serde")]
+    if args.output != OutputFormat::Tree && args.diff.is_some() {
+        Args::command().error(ErrorKind::ArgumentConflict, "--diff can only be used with --output tree").exit();
+    }
+    #[cfg(feature = "serde")]
+    {
+        let stdin_readers = [&args.load, &args.diff].into_iter()
+            .filter(|path| path.as_deref() == Some(Path::new(STDIO_PATH)))
+            .count();
+        if stdin_readers > 1 {
+            Args::command().error(ErrorKind::ArgumentConflict, "only one of --load and --diff can read stdin").exit();
+        }
+        if stdin_readers == 1 && io::stdin().is_terminal() {
+            Args::command()
+                .error(ErrorKind::InvalidValue, "stdin is a terminal, pipe a snapshot in, e.g. from --save -")
+                .exit();
+        }
+    }
+    if args.from_ps.as_deref() == Some(Path::new(STDIO_PATH)) && io::stdin().is_terminal() {
+        Args::command().error(ErrorKind::InvalidValue, "stdin is a terminal, pipe the output of ps in").exit();
+    }
+    let acts = args.signal.is_some() || args.renice.is_some() || args.pause || args.resume;
+    if acts && ArchiveSource::is_archive(&args.proc_root) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "--signal, --renice, --pause and --resume cannot act on an archive")
+            .exit();
+    }
+    if !args.palette.is_empty() && args.color_by.is_none() && args.highlight.is_empty() {
+        Args::command()
+            .error(ErrorKind::MissingRequiredArgument, "--palette needs --color-by or --highlight")
+            .exit();
+    }
+    if let Some(target) = args.target.take() {
+        if target.bytes().all(|byte| byte.is_ascii_digit()) || target.parse::<PidKeyword>().is_ok() {
+            let pid = parse_root_pid(&target).unwrap_or_else(|why| {
+                Args::command().error(ErrorKind::InvalidValue, why).exit()
+            });
+            args.root_pid.push(pid);
+        } else {
+            args.user = Some(target);
+        }
+    }
+    let uid = args.user.as_ref().map(|user| {
+        UserTable::load().resolve(user).unwrap_or_else(|| {
+            Args::command().error(ErrorKind::InvalidValue, format!("unknown user '{}'", user)).exit()
+        })
+    });
+    let groups = if args.groups || args.in_group.is_some() { GroupTable::load() } else { GroupTable::default() };
+    let gid = args.in_group.as_ref().map(|group| {
+        groups.resolve(group).unwrap_or_else(|| {
+            Args::command().error(ErrorKind::InvalidValue, format!("unknown group '{}'", group)).exit()
+        })
+    });
+    // Anonymized trees show gids, as a group is often named after its user.
+    let group_names = if args.anonymize { GroupTable::default() } else { groups };
+    let config = parse_config(&args, group_names).unwrap_or_else(|why| {
+        Args::command().error(ErrorKind::ArgumentConflict, why).exit()
+    });
+    let result = match args.watch {
+        Some(interval) => watch(&args, &config, uid, gid, interval),
+        None => run(&args, &config, uid, gid, &mut None, None, args.anonymize.then(Anonymizer::new).as_mut()),
+    };
+    if let Err(why) = result {
+        eprintln!("rusty-pstree: {}", why);
+        process::exit(why.exit_code());
+    }
 }
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::process::ProcessInfo;
+use crate::tree::{DfsIter, ProcessTree};
+
+
+/// The states that always have a `pstree_processes` series, 0 when no
+/// process is in them, so alerts on them never see a missing series.
+const KNOWN_STATES: [char; 8] = ['R', 'S', 'D', 'I', 'T', 't', 'Z', 'X'];
+
+impl ProcessTree
+{
+    /// Writes counts over the drawn tree in the Prometheus text exposition
+    /// format, each metric a gauge with its HELP and TYPE lines:
+    ///
+    /// - `pstree_processes_total`, the number of drawn processes;
+    /// - `pstree_processes{state="Z"}`, the drawn processes by state letter,
+    ///   if the states were read, for R, S, D, I, T, t, Z and X and any other
+    ///   state seen;
+    /// - `pstree_subtree_processes{root="nginx",pid="812"}` for every child of
+    ///   the root, or every root when there are several, counting the
+    ///   subtree with its top process and the descendants cut by the depth
+    ///   limit;
+    /// - `pstree_subtree_rss_bytes` with the same labels, the resident memory
+    ///   of the drawn processes of each subtree, if it was read;
+    /// - `pstree_subtree_pss_bytes` with the same labels, their proportional
+    ///   set size, if it was read, counting the resident memory of the
+    ///   processes whose proportional set size could not be read;
+    /// - `pstree_subtree_private_bytes` with the same labels, the private
+    ///   memory of the drawn processes of each subtree, if it was read. Only
+    ///   the private part is summed since pages shared between processes of
+    ///   a subtree would be counted once for each of them.
+    pub fn write_metrics<W: io::Write>(&self, mut out: W) -> io::Result<()>
+    {
+        let summary = self.summary();
+        write_header(&mut out, "pstree_processes_total", "Processes in the selected tree.")?;
+        writeln!(out, "pstree_processes_total {}", summary.processes)?;
+
+        let mut states: BTreeMap<char, usize> = BTreeMap::new();
+        for (_, index) in self.iter_dfs() {
+            if let Some(state) = self.nodes[index].proc_info.state {
+                *states.entry(state).or_insert(0) += 1;
+            }
+        }
+        if !states.is_empty() {
+            for state in KNOWN_STATES {
+                states.entry(state).or_insert(0);
+            }
+            write_header(&mut out, "pstree_processes", "Processes in the selected tree by state.")?;
+            for (state, count) in &states {
+                writeln!(out, "pstree_processes{{state=\"{}\"}} {}", escape_label(&state.to_string()), count)?;
+            }
+        }
+
+        let subtrees: &[usize] = match self.roots[..] {
+            [root] => &self.nodes[root].children,
+            _ => &self.roots,
+        };
+        let labels: Vec<String> = subtrees.iter()
+            .map(|&index| {
+                let proc_info = &self.nodes[index].proc_info;
+                format!("{{root=\"{}\",pid=\"{}\"}}", escape_label(&String::from_utf8_lossy(&proc_info.name)), proc_info.pid)
+            })
+            .collect();
+        write_header(&mut out, "pstree_subtree_processes", "Processes in each subtree below the root.")?;
+        for (&index, labels) in subtrees.iter().zip(&labels) {
+            writeln!(out, "pstree_subtree_processes{} {}", labels, self.descendant_count(index) + 1)?;
+        }
+
+        let subtree_totals = |memory: fn(&ProcessInfo) -> Option<u64>| -> Vec<Option<u64>> {
+            subtrees.iter()
+                .map(|&index| {
+                    DfsIter::new(self, &[index])
+                        .filter_map(|(_, node)| memory(&self.nodes[node].proc_info))
+                        .fold(None, |total, bytes| Some(total.unwrap_or(0) + bytes))
+                })
+                .collect()
+        };
+        let rss = subtree_totals(|proc_info| proc_info.rss);
+        if rss.iter().any(Option::is_some) {
+            write_header(&mut out, "pstree_subtree_rss_bytes", "Resident memory of each subtree below the root.")?;
+            for (rss, labels) in rss.iter().zip(&labels) {
+                writeln!(out, "pstree_subtree_rss_bytes{} {}", labels, rss.unwrap_or(0))?;
+            }
+        }
+        if self.iter_dfs().any(|(_, index)| self.nodes[index].proc_info.pss.is_some()) {
+            let pss = subtree_totals(|proc_info| proc_info.memory(true).map(|(bytes, _)| bytes));
+            write_header(&mut out, "pstree_subtree_pss_bytes", "Proportional set size of each subtree below the root.")?;
+            for (pss, labels) in pss.iter().zip(&labels) {
+                writeln!(out, "pstree_subtree_pss_bytes{} {}", labels, pss.unwrap_or(0))?;
+            }
+        }
+        let private = subtree_totals(|proc_info| proc_info.private_memory);
+        if private.iter().any(Option::is_some) {
+            write_header(&mut out, "pstree_subtree_private_bytes", "Private memory of each subtree below the root.")?;
+            for (private, labels) in private.iter().zip(&labels) {
+                writeln!(out, "pstree_subtree_private_bytes{} {}", labels, private.unwrap_or(0))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_header<W: io::Write>(out: &mut W, name: &str, help: &str) -> io::Result<()>
+{
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} gauge", name)
+}
+
+/// Escapes a label value as the exposition format asks: backslashes,
+/// double quotes and line feeds.
+fn escape_label(value: &str) -> String
+{
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
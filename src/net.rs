@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use crate::source::ProcSource;
+
+
+/// The /proc/net tables read for listening sockets.
+const NET_TABLES: [&str; 4] = ["tcp", "tcp6", "udp", "udp6"];
+
+/// `st` column of a listening TCP socket.
+const TCP_LISTEN: &str = "0A";
+/// `st` column of an unconnected UDP socket, which is how a bound UDP
+/// socket waiting for packets shows up.
+const UDP_UNCONNECTED: &str = "07";
+
+/// A listening socket, ordered TCP before UDP, then by port.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Listener
+{
+    udp: bool,
+    port: u16,
+    address: String,
+}
+
+impl Listener
+{
+    /// Formats as `:80` for a wildcard address, `127.0.0.1:631` or
+    /// `[::1]:631` otherwise, with `/udp` appended for UDP sockets.
+    pub(crate) fn label(&self) -> String
+    {
+        format!("{}:{}{}", self.address, self.port, if self.udp { "/udp" } else { "" })
+    }
+}
+
+/// Reads the listening sockets of every table `source` has, keyed by inode.
+/// Missing or unreadable tables are skipped.
+pub(crate) fn read_listeners<S: ProcSource>(source: &S) -> HashMap<u64, Listener>
+{
+    let mut listeners: HashMap<u64, Listener> = HashMap::new();
+    for table in NET_TABLES {
+        if let Ok(contents) = source.read_net(table) {
+            parse_net_table(&String::from_utf8_lossy(&contents), table, &mut listeners);
+        }
+    }
+    listeners
+}
+
+/// Adds the listening sockets of one /proc/net table to `listeners`.
+fn parse_net_table(contents: &str, table: &str, listeners: &mut HashMap<u64, Listener>)
+{
+    let udp = table.starts_with("udp");
+    let listening_state = if udp { UDP_UNCONNECTED } else { TCP_LISTEN };
+
+    for line in contents.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 || fields[3] != listening_state {
+            continue;
+        }
+        let (address, port) = match parse_socket_address(fields[1]) {
+            Some(local) => local,
+            None => continue,
+        };
+        match fields[9].parse::<u64>() {
+            Ok(inode) if inode != 0 && port != 0 => {
+                listeners.insert(inode, Listener { udp, port, address });
+            }
+            _ => continue,
+        }
+    }
+}
+
+/// Parses `0100007F:0277`, an address printed as native endian 32 bit words
+/// followed by a port in hex. Wildcard addresses come back empty.
+fn parse_socket_address(socket: &str) -> Option<(String, u16)>
+{
+    let (address, port) = socket.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    let words: Vec<u32> = (0 .. address.len() / 8)
+        .map(|word| u32::from_str_radix(&address[word * 8 .. word * 8 + 8], 16).ok())
+        .collect::<Option<_>>()?;
+
+    let address = match words[..] {
+        [word] => match Ipv4Addr::from(word.to_ne_bytes()) {
+            ip if ip.is_unspecified() => String::new(),
+            ip => ip.to_string(),
+        },
+        [a, b, c, d] => {
+            let mut octets = [0u8; 16];
+            for (chunk, word) in octets.chunks_mut(4).zip([a, b, c, d]) {
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            match Ipv6Addr::from(octets) {
+                ip if ip.is_unspecified() => String::new(),
+                ip => format!("[{}]", ip),
+            }
+        }
+        _ => return None,
+    };
+
+    Some((address, port))
+}
+
+/// The inode of a `socket:[12345]` file descriptor target.
+pub(crate) fn socket_inode(target: &Path) -> Option<u64>
+{
+    target.to_str()?.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+
+/// The highest priority a nice value can give.
+pub const MIN_NICE: i32 = -20;
+/// The lowest priority a nice value can give.
+pub const MAX_NICE: i32 = 19;
+
+/// A change of nice value, parsed from `+5` or `-3` to add to the current
+/// value, or from `=10` to set it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NiceChange
+{
+    /// Added to the current value.
+    Relative(i32),
+    /// Replaces the current value.
+    Absolute(i32),
+}
+
+impl NiceChange
+{
+    /// The nice value a process at `current` gets, kept within
+    /// [`MIN_NICE`] and [`MAX_NICE`] like renice(1) does.
+    pub fn apply(self, current: i32) -> i32
+    {
+        match self {
+            NiceChange::Relative(delta) => current.saturating_add(delta).clamp(MIN_NICE, MAX_NICE),
+            NiceChange::Absolute(nice) => nice,
+        }
+    }
+}
+
+/// Sets the nice value of `pid`.
+pub fn set_nice(pid: u32, nice: i32) -> io::Result<()>
+{
+    let pid = libc::id_t::try_from(pid).map_err(|_| io::Error::from_raw_os_error(libc::ESRCH))?;
+    // SAFETY: setpriority has no memory safety requirements.
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+impl FromStr for NiceChange
+{
+    type Err = String;
+
+    fn from_str(change: &str) -> Result<NiceChange, String>
+    {
+        let invalid = || format!("invalid nice change '{}' (expected +N, -N or =N)", change);
+        if let Some(value) = change.strip_prefix('=') {
+            let nice: i32 = value.parse().map_err(|_| invalid())?;
+            if !(MIN_NICE ..= MAX_NICE).contains(&nice) {
+                return Err(format!("invalid nice value {} (expected {} to {})", nice, MIN_NICE, MAX_NICE));
+            }
+            return Ok(NiceChange::Absolute(nice));
+        }
+        if !change.starts_with(['+', '-']) {
+            return Err(invalid());
+        }
+        let delta: i32 = change.parse().map_err(|_| invalid())?;
+        let range = MAX_NICE - MIN_NICE;
+        if !(-range ..= range).contains(&delta) {
+            return Err(format!("invalid nice change {} (expected -{} to +{})", change, range, range));
+        }
+        Ok(NiceChange::Relative(delta))
+    }
+}
+
+impl fmt::Display for NiceChange
+{
+    /// Formats as parsed: `+5`, `-3` or `=10`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            NiceChange::Relative(delta) => write!(f, "{:+}", delta),
+            NiceChange::Absolute(nice) => write!(f, "={}", nice),
+        }
+    }
+}
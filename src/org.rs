@@ -0,0 +1,114 @@
+use std::io;
+
+use crate::config::PsTreePrintConfig;
+use crate::render::{format_memory, NodeItem};
+use crate::tree::ProcessTree;
+use crate::user::UserTable;
+
+
+/// Deepest heading level of [`OrgOptions::default`].
+pub const DEFAULT_ORG_MAX_LEVEL: usize = 8;
+/// Put before a label starting with `*`, so it is neither taken for the
+/// stars of a heading nor for bold markup.
+const ZERO_WIDTH_SPACE: char = '\u{200b}';
+
+/// How [`ProcessTree::write_org`] lays out the outline.
+#[derive(Clone, Debug)]
+pub struct OrgOptions
+{
+    /// The deepest heading level. Processes below it are written as nested
+    /// list items under the last heading.
+    pub max_level: usize,
+    /// Write a property drawer with the pid, parent pid, user and resident
+    /// memory under each process heading.
+    pub properties: bool,
+    /// The names written as the user of a process. Uids missing from it
+    /// are written as numbers.
+    pub user_names: UserTable,
+}
+
+impl Default for OrgOptions
+{
+    fn default() -> OrgOptions
+    {
+        OrgOptions { max_level: DEFAULT_ORG_MAX_LEVEL, properties: false, user_names: UserTable::default() }
+    }
+}
+
+impl ProcessTree
+{
+    /// Writes the drawn tree as an org-mode outline, one heading per
+    /// process with as many stars as its depth, labeled like the drawing.
+    /// Every root is a top-level heading.
+    pub fn write_org<W: io::Write>(&self, mut out: W, config: &PsTreePrintConfig, options: &OrgOptions) -> io::Result<()>
+    {
+        for item in self.root_items(config, false) {
+            self.write_org_item(&mut out, &item, 1, config, options)?;
+        }
+        Ok(())
+    }
+
+    fn write_org_item<W: io::Write>(
+        &self,
+        out: &mut W,
+        item: &NodeItem,
+        level: usize,
+        config: &PsTreePrintConfig,
+        options: &OrgOptions,
+    ) -> io::Result<()>
+    {
+        let text = escape_org(&item.text);
+        // There is always a heading for the list items to hang from.
+        let max_level = options.max_level.max(1);
+        if level > max_level {
+            let indent = 2 * (level - max_level - 1);
+            writeln!(out, "{:indent$}- {}", "", text, indent = indent)?;
+        } else {
+            writeln!(out, "{} {}", "*".repeat(level), text)?;
+            if options.properties {
+                self.write_properties(out, item, config, options)?;
+            }
+        }
+        for child in &item.children {
+            self.write_org_item(out, child, level + 1, config, options)?;
+        }
+        Ok(())
+    }
+
+    /// The property drawer of a process heading, none for the other lines.
+    fn write_properties<W: io::Write>(
+        &self,
+        out: &mut W,
+        item: &NodeItem,
+        config: &PsTreePrintConfig,
+        options: &OrgOptions,
+    ) -> io::Result<()>
+    {
+        let Some(node) = item.pid.and_then(|pid| self.find(pid)) else { return Ok(()) };
+        let proc_info = &node.proc_info;
+        writeln!(out, ":PROPERTIES:")?;
+        writeln!(out, ":PID: {}", proc_info.pid)?;
+        writeln!(out, ":PPID: {}", proc_info.ppid)?;
+        if let Some(uid) = proc_info.uid {
+            match options.user_names.name(uid) {
+                Some(name) => writeln!(out, ":USER: {}", name)?,
+                None => writeln!(out, ":USER: {}", uid)?,
+            }
+        }
+        if let Some(memory) = format_memory(proc_info, config) {
+            writeln!(out, ":{}: {}", if config.pss { "PSS" } else { "RSS" }, memory)?;
+        }
+        writeln!(out, ":END:")
+    }
+}
+
+/// Keeps `text` on one line and keeps a leading `*` from being read as
+/// org markup.
+fn escape_org(text: &str) -> String
+{
+    let mut escaped = text.replace(['\n', '\r'], " ");
+    if escaped.starts_with('*') {
+        escaped.insert(0, ZERO_WIDTH_SPACE);
+    }
+    escaped
+}
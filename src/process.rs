@@ -0,0 +1,852 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::BufRead;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::limits::ResourceLimit;
+
+
+/// What rusty-pstree knows about a single process.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessInfo
+{
+    /// Process id.
+    pub pid: u32,
+    /// Parent process id, 0 for processes started by the kernel.
+    pub ppid: u32,
+    /// The raw `Name` field of the status file, which need not be UTF-8.
+    /// Shared between the processes of a scan with the same name.
+    /// Serialized as a string, or as an array of bytes when it is not UTF-8.
+    #[cfg_attr(feature = "serde", serde(with = "name_serde"))]
+    pub name: Arc<[u8]>,
+    /// Real user id, if the status file has a Uid line.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub uid: Option<u32>,
+    /// The raw command line arguments, if they were read. Empty for kernel
+    /// threads. Serialized like the name, every argument on its own.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "cmdline_serde"))]
+    pub cmdline: Option<Vec<Vec<u8>>>,
+    /// Start time in clock ticks after boot, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub start_time: Option<u64>,
+    /// The nice value, from -20 for the highest priority to 19 for the
+    /// lowest, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub nice: Option<i32>,
+    /// Session id, the pid of the session leader, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub session: Option<u32>,
+    /// The sockets the process listens on, like `:80` or `127.0.0.1:53/udp`,
+    /// if its file descriptors were read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub listen: Option<Vec<String>>,
+    /// The supplementary group ids, if they were read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub groups: Option<Vec<u32>>,
+    /// Whether the process and its children can no longer gain privileges
+    /// through execve, if it was read. Kernels before 4.10 do not show it.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub no_new_privs: Option<bool>,
+    /// The seccomp mode, if it was read: 0 when unconfined, 1 for strict
+    /// mode and 2 for filters.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub seccomp: Option<u8>,
+    /// Number of seccomp filters attached, if it was read. Kernels before
+    /// 5.9 do not show it.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub seccomp_filters: Option<u32>,
+    /// Resident memory in bytes, if it was read. Kernel threads have none.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub rss: Option<u64>,
+    /// The highest resident memory in bytes the process ever had, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub peak_rss: Option<u64>,
+    /// The highest virtual memory size in bytes the process ever had, if it
+    /// was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub peak_virtual: Option<u64>,
+    /// The proportional set size in bytes, the resident memory with every
+    /// shared page divided among the processes sharing it, if it was read
+    /// from smaps_rollup. Kernels before 4.14 do not have the file.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub pss: Option<u64>,
+    /// The resident memory in bytes the process has to itself, if it was
+    /// read: its anonymous pages from status, or its private pages when read
+    /// from smaps_rollup. Kernels before 4.5 do not show it in status.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub private_memory: Option<u64>,
+    /// The resident memory in bytes the process may share with others, if
+    /// it was read: its file and shared memory pages from status, or its
+    /// shared pages when read from smaps_rollup.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub shared_memory: Option<u64>,
+    /// The file mode creation mask, if it was read. Kernels before 4.7 do not
+    /// show it.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub umask: Option<u32>,
+    /// Size of the file descriptor table, the FDSize line of the status
+    /// file, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub fd_size: Option<u32>,
+    /// The soft limit on open files, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub fd_limit: Option<ResourceLimit>,
+    /// The CPUs the process may run on, like `0-3,8`, if they were read.
+    /// Scanning leaves this out for processes allowed on every online CPU.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub cpus_allowed: Option<String>,
+    /// The CPU the process last ran on, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub processor: Option<u32>,
+    /// The user and system CPU time in milliseconds, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub cpu_time_ms: Option<u64>,
+    /// The user and system CPU time in milliseconds of the children the
+    /// process waited for, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub children_cpu_time_ms: Option<u64>,
+    /// The kernel function a sleeping process waits in, like `do_select`,
+    /// if it was read. None as well for running processes.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub wchan: Option<String>,
+    /// Whether the process has [`ScanOptions::open_file`](crate::ScanOptions::open_file)
+    /// open through a file descriptor or a memory mapping, if it was checked.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub holds_file: Option<bool>,
+    /// The target of the exe link, like `/usr/sbin/nginx`, ending in
+    /// ` (deleted)` when the file was removed or replaced, if it was read.
+    /// Serialized like the name.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none", with = "exe_serde"))]
+    pub exe: Option<PathBuf>,
+    /// The inodes of the namespaces of the process by type, like `mnt`, if
+    /// they were read. Types whose link could not be read are left out.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub namespaces: Option<BTreeMap<String, u64>>,
+    /// The path of the process in the unified (v2) or systemd (v1) cgroup
+    /// hierarchy, like `/system.slice/nginx.service`, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub cgroup: Option<String>,
+    /// The state letter of the status file, e.g. `S` or `Z`, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub state: Option<char>,
+    /// Number of threads, if it was read.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub threads: Option<u32>,
+    /// Pid of the process tracing this one, 0 if it is not traced. None if
+    /// the status file has no TracerPid line.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub tracer_pid: Option<u32>,
+}
+
+impl ProcessInfo
+{
+    /// Creates a process entry from its pid, parent pid and name.
+    pub fn new(pid: u32, ppid: u32, name: impl Into<Vec<u8>>) -> ProcessInfo
+    {
+        ProcessInfo { pid, ppid, name: Arc::from(name.into()), ..ProcessInfo::default() }
+    }
+
+    /// The process name with invalid UTF-8 sequences replaced by U+FFFD and
+    /// control characters written as escapes like `\n`, so that none of them
+    /// reaches the terminal raw.
+    pub fn display_name(&self) -> Cow<'_, str>
+    {
+        let name = String::from_utf8_lossy(&self.name);
+        if !name.chars().any(char::is_control) {
+            return name;
+        }
+        let mut escaped = String::with_capacity(name.len() + 4);
+        for character in name.chars() {
+            if character.is_control() {
+                escaped.extend(character.escape_default());
+            } else {
+                escaped.push(character);
+            }
+        }
+        Cow::Owned(escaped)
+    }
+
+    /// The command line joined by spaces, if it was read and is not empty,
+    /// with invalid UTF-8 sequences replaced by U+FFFD.
+    pub fn display_cmdline(&self) -> Option<String>
+    {
+        self.cmdline.as_ref()
+            .filter(|cmdline| !cmdline.is_empty())
+            .map(|cmdline| String::from_utf8_lossy(&cmdline.join(&b' ')).into_owned())
+    }
+
+    /// The umask in octal like `0077`, if it was read.
+    pub fn display_umask(&self) -> Option<String>
+    {
+        self.umask.map(|umask| format!("{:04o}", umask))
+    }
+
+    /// True if the executable, deleted or not, lies below `dir`, which should
+    /// be canonical as the kernel reports resolved paths. False when the exe
+    /// link was not read.
+    pub fn exe_is_under(&self, dir: &Path) -> bool
+    {
+        self.exe.as_deref().is_some_and(|exe| strip_deleted(exe).starts_with(dir))
+    }
+
+    /// The memory to show for the process: its proportional set size with
+    /// `pss`, else its resident memory. The flag is set when the resident
+    /// memory stands in for a proportional set size that was not read.
+    pub fn memory(&self, pss: bool) -> Option<(u64, bool)>
+    {
+        match self.pss.filter(|_| pss) {
+            Some(pss) => Some((pss, false)),
+            None => self.rss.map(|rss| (rss, pss)),
+        }
+    }
+
+    /// The private and shared parts of the resident memory, None unless both
+    /// were read.
+    pub fn memory_split(&self) -> Option<(u64, u64)>
+    {
+        self.private_memory.zip(self.shared_memory)
+    }
+
+    /// True if the process runs in seccomp strict or filter mode. False when
+    /// the mode was not read.
+    pub fn is_seccomp_confined(&self) -> bool
+    {
+        self.seccomp.is_some_and(|mode| mode != 0)
+    }
+
+    /// True if the process has exited but was not reaped yet. False when the
+    /// state was not read.
+    pub fn is_zombie(&self) -> bool
+    {
+        self.state == Some('Z')
+    }
+
+    /// True if the process leads its session.
+    pub fn is_session_leader(&self) -> bool
+    {
+        self.session == Some(self.pid)
+    }
+
+    /// The pid of the tracing process, if a debugger or strace is attached.
+    pub fn tracer(&self) -> Option<u32>
+    {
+        self.tracer_pid.filter(|&tracer_pid| tracer_pid != 0)
+    }
+
+    /// Processes started by the kernel itself (init, kthreadd) report a ppid of 0.
+    pub fn is_top_level(&self) -> bool
+    {
+        self.ppid == 0
+    }
+}
+
+/// The optional status fields to read besides Name, Pid, PPid and Uid.
+/// Fields further down the status file cost more reading, so they are only
+/// parsed when asked for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatusFields
+{
+    /// Read the State line into [`ProcessInfo::state`].
+    pub state: bool,
+    /// Read the Threads line into [`ProcessInfo::threads`].
+    pub threads: bool,
+    /// Read the Umask line into [`ProcessInfo::umask`].
+    pub umask: bool,
+    /// Read the Groups line into [`ProcessInfo::groups`].
+    pub groups: bool,
+    /// Read the NoNewPrivs line into [`ProcessInfo::no_new_privs`].
+    pub no_new_privs: bool,
+    /// Read the Seccomp and Seccomp_filters lines into [`ProcessInfo::seccomp`]
+    /// and [`ProcessInfo::seccomp_filters`].
+    pub seccomp: bool,
+    /// Read the VmRSS and VmHWM lines into [`ProcessInfo::rss`] and
+    /// [`ProcessInfo::peak_rss`].
+    pub rss: bool,
+    /// Read the VmPeak line into [`ProcessInfo::peak_virtual`].
+    pub peak_virtual: bool,
+    /// Read the RssAnon, RssFile and RssShmem lines into
+    /// [`ProcessInfo::private_memory`] and [`ProcessInfo::shared_memory`].
+    pub memory_split: bool,
+    /// Read the FDSize line into [`ProcessInfo::fd_size`].
+    pub fd_size: bool,
+    /// Read the Cpus_allowed_list line into [`ProcessInfo::cpus_allowed`].
+    pub cpus_allowed: bool,
+}
+
+/// A status file that could be read but not understood.
+#[derive(Debug)]
+pub struct ParseError
+{
+    /// Pid of the directory the status file was read from.
+    pub pid: u32,
+    /// What was wrong with it.
+    pub reason: String,
+}
+
+impl ParseError
+{
+    /// Creates a parse error for the process `pid`.
+    pub fn new(pid: u32, reason: String) -> ParseError
+    {
+        ParseError { pid, reason }
+    }
+}
+
+impl fmt::Display for ParseError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "malformed status of process {}: {}", self.pid, self.reason)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod name_serde
+{
+    use std::sync::Arc;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Name
+    {
+        Text(String),
+        Bytes(Vec<u8>),
+    }
+
+    pub fn serialize<S: Serializer>(name: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    {
+        match std::str::from_utf8(name) {
+            Ok(text) => serializer.serialize_str(text),
+            Err(_) => serializer.collect_seq(name),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arc<[u8]>, D::Error>
+    {
+        Ok(Arc::from(Name::deserialize(deserializer)?.into_bytes()))
+    }
+
+    impl Name
+    {
+        pub(super) fn into_bytes(self) -> Vec<u8>
+        {
+            match self {
+                Name::Text(text) => text.into_bytes(),
+                Name::Bytes(bytes) => bytes,
+            }
+        }
+    }
+
+    /// Serializes bytes like a name, as a string when they are UTF-8.
+    pub(super) struct AsName<'a>(pub &'a [u8]);
+
+    impl serde::Serialize for AsName<'_>
+    {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        {
+            serialize(self.0, serializer)
+        }
+    }
+
+    pub(super) mod cmdline_serde
+    {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        use super::{AsName, Name};
+
+        pub fn serialize<S: Serializer>(cmdline: &Option<Vec<Vec<u8>>>, serializer: S) -> Result<S::Ok, S::Error>
+        {
+            match cmdline {
+                Some(args) => serializer.collect_seq(args.iter().map(|arg| AsName(arg))),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<Vec<u8>>>, D::Error>
+        {
+            let args = Option::<Vec<Name>>::deserialize(deserializer)?;
+            Ok(args.map(|args| args.into_iter().map(Name::into_bytes).collect()))
+        }
+    }
+
+    pub(super) mod exe_serde
+    {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+        use std::path::PathBuf;
+
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        use super::Name;
+
+        pub fn serialize<S: Serializer>(exe: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error>
+        {
+            match exe {
+                Some(exe) => super::serialize(exe.as_os_str().as_bytes(), serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<PathBuf>, D::Error>
+        {
+            let exe = Option::<Name>::deserialize(deserializer)?;
+            Ok(exe.map(|exe| PathBuf::from(OsString::from_vec(exe.into_bytes()))))
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+use name_serde::{cmdline_serde, exe_serde};
+
+fn parse_status_number(pid: u32, field: &str, value: &[u8]) -> Result<u32, ParseError>
+{
+    String::from_utf8_lossy(value).trim().parse::<u32>()
+        .map_err(|why| invalid_field(pid, field, value, why))
+}
+
+fn parse_status_octal(pid: u32, field: &str, value: &[u8]) -> Result<u32, ParseError>
+{
+    u32::from_str_radix(String::from_utf8_lossy(value).trim(), 8)
+        .map_err(|why| invalid_field(pid, field, value, why))
+}
+
+/// The value of a memory size line like `VmRSS:    4096 kB`, in bytes.
+fn parse_status_size(pid: u32, field: &str, value: &[u8]) -> Result<u64, ParseError>
+{
+    String::from_utf8_lossy(first_field(value)).parse::<u64>()
+        .map(|kib| kib * 1024)
+        .map_err(|why| invalid_field(pid, field, value, why))
+}
+
+/// The error for a status line whose `value` could not be parsed, quoting
+/// the line.
+fn invalid_field(pid: u32, field: &str, value: &[u8], why: impl fmt::Display) -> ParseError
+{
+    let line = format!("{}:{}", field, String::from_utf8_lossy(value));
+    ParseError::new(pid, format!("invalid {} field: {} (line {:?})", field, why, line))
+}
+
+/// The first of the tab separated values of a status line, e.g. the real
+/// id of Uid.
+fn first_field(value: &[u8]) -> &[u8]
+{
+    value.split(u8::is_ascii_whitespace).find(|field| !field.is_empty()).unwrap_or(value)
+}
+
+/// Undoes the escaping of the Name line of status, where the kernel writes
+/// a backslash as `\\` and a newline as `\n`. Older kernels escape other
+/// whitespace and `\e` and `\a` the same way. Anything else is kept as is.
+fn unescape_name(value: &[u8]) -> Vec<u8>
+{
+    let mut name: Vec<u8> = Vec::with_capacity(value.len());
+    let mut bytes = value.iter();
+    while let Some(&byte) = bytes.next() {
+        let decoded = match bytes.as_slice().first().filter(|_| byte == b'\\') {
+            Some(b'\\') => b'\\',
+            Some(b'n') => b'\n',
+            Some(b't') => b'\t',
+            Some(b'r') => b'\r',
+            Some(b'f') => 0x0c,
+            Some(b'v') => 0x0b,
+            Some(b'a') => 0x07,
+            Some(b'e') => 0x1b,
+            Some(b'"') => b'"',
+            _ => {
+                name.push(byte);
+                continue;
+            }
+        };
+        bytes.next();
+        name.push(decoded);
+    }
+    name
+}
+
+/// Reads status lines until every field has been seen. Name, Pid and PPid
+/// are required, Uid, TracerPid and the `fields` asked for are optional. The fields
+/// are matched by name, not position, so a kernel that moves them further
+/// down only costs more reading.
+pub(crate) fn parse_proc_stat<R: BufRead>(pid: u32, mut stat: R, fields: StatusFields) -> Result<ProcessInfo, ParseError>
+{
+    let mut name: Option<Vec<u8>> = None;
+    let mut parsed_pid: Option<u32> = None;
+    let mut ppid: Option<u32> = None;
+    let mut uid: Option<u32> = None;
+    let mut tracer_pid: Option<u32> = None;
+    let mut state: Option<char> = None;
+    let mut threads: Option<u32> = None;
+    let mut umask: Option<u32> = None;
+    let mut groups: Option<Vec<u32>> = None;
+    let mut no_new_privs: Option<bool> = None;
+    let mut seccomp: Option<u8> = None;
+    let mut seccomp_filters: Option<u32> = None;
+    let mut rss: Option<u64> = None;
+    let mut peak_rss: Option<u64> = None;
+    let mut peak_virtual: Option<u64> = None;
+    let mut rss_anon: Option<u64> = None;
+    let mut rss_file: Option<u64> = None;
+    let mut rss_shmem: Option<u64> = None;
+    let mut fd_size: Option<u32> = None;
+    let mut cpus_allowed: Option<String> = None;
+
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while name.is_none() || parsed_pid.is_none() || ppid.is_none() || uid.is_none() || tracer_pid.is_none()
+        || (fields.state && state.is_none()) || (fields.threads && threads.is_none())
+        || (fields.umask && umask.is_none()) || (fields.groups && groups.is_none())
+        || (fields.no_new_privs && no_new_privs.is_none())
+        || (fields.seccomp && (seccomp.is_none() || seccomp_filters.is_none()))
+        || (fields.rss && (rss.is_none() || peak_rss.is_none())) || (fields.peak_virtual && peak_virtual.is_none())
+        || (fields.memory_split && (rss_anon.is_none() || rss_file.is_none() || rss_shmem.is_none()))
+        || (fields.fd_size && fd_size.is_none()) || (fields.cpus_allowed && cpus_allowed.is_none())
+    {
+        buffer.clear();
+        match stat.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(why) => return Err(ParseError::new(pid, format!("read failed: {}", why))),
+        }
+
+        let line = buffer.strip_suffix(b"\n").unwrap_or(&buffer);
+        let (field, value) = match line.iter().position(|&byte| byte == b':') {
+            Some(colon) => (&line[.. colon], &line[colon + 1 ..]),
+            None => continue,
+        };
+
+        match field {
+            // The kernel separates the name with one tab, a second one is part of it.
+            b"Name" => name = Some(unescape_name(value.strip_prefix(b"\t").unwrap_or(value.trim_ascii_start()))),
+            b"Pid"  => parsed_pid = Some(parse_status_number(pid, "Pid", value)?),
+            b"PPid" => ppid = Some(parse_status_number(pid, "PPid", value)?),
+            b"TracerPid" => tracer_pid = Some(parse_status_number(pid, "TracerPid", value)?),
+            b"Uid"  => uid = Some(parse_status_number(pid, "Uid", first_field(value))?),
+            b"State" if fields.state => state = first_field(value).first().map(|&letter| char::from(letter)),
+            b"Threads" if fields.threads => threads = Some(parse_status_number(pid, "Threads", value)?),
+            b"Umask" if fields.umask => umask = Some(parse_status_octal(pid, "Umask", value)?),
+            b"Groups" if fields.groups => {
+                groups = Some(value.split(u8::is_ascii_whitespace)
+                    .filter(|gid| !gid.is_empty())
+                    .map(|gid| parse_status_number(pid, "Groups", gid))
+                    .collect::<Result<_, _>>()?);
+            }
+            b"NoNewPrivs" if fields.no_new_privs => {
+                no_new_privs = Some(parse_status_number(pid, "NoNewPrivs", value)? != 0);
+            }
+            b"Seccomp" if fields.seccomp => {
+                seccomp = Some(parse_status_number(pid, "Seccomp", value)?.try_into()
+                    .map_err(|_| ParseError::new(pid, "invalid Seccomp field".to_string()))?);
+            }
+            b"Seccomp_filters" if fields.seccomp => {
+                seccomp_filters = Some(parse_status_number(pid, "Seccomp_filters", value)?);
+            }
+            b"VmRSS" if fields.rss => rss = Some(parse_status_size(pid, "VmRSS", value)?),
+            b"VmHWM" if fields.rss => peak_rss = Some(parse_status_size(pid, "VmHWM", value)?),
+            b"VmPeak" if fields.peak_virtual => peak_virtual = Some(parse_status_size(pid, "VmPeak", value)?),
+            b"RssAnon" if fields.memory_split => rss_anon = Some(parse_status_size(pid, "RssAnon", value)?),
+            b"RssFile" if fields.memory_split => rss_file = Some(parse_status_size(pid, "RssFile", value)?),
+            b"RssShmem" if fields.memory_split => rss_shmem = Some(parse_status_size(pid, "RssShmem", value)?),
+            b"FDSize" if fields.fd_size => fd_size = Some(parse_status_number(pid, "FDSize", value)?),
+            b"Cpus_allowed_list" if fields.cpus_allowed => {
+                cpus_allowed = Some(String::from_utf8_lossy(value.trim_ascii()).into_owned());
+            }
+            _ => continue,
+        }
+    }
+
+    let missing = |field: &str| ParseError::new(pid, format!("missing {} field", field));
+    // The kernel added the three lines together, a status missing one has no split.
+    let (private_memory, shared_memory) = match (rss_anon, rss_file, rss_shmem) {
+        (Some(anon), Some(file), Some(shmem)) => (Some(anon), Some(file + shmem)),
+        _ => (None, None),
+    };
+
+    Ok(ProcessInfo {
+        pid: parsed_pid.ok_or_else(|| missing("Pid"))?,
+        ppid: ppid.ok_or_else(|| missing("PPid"))?,
+        name: Arc::from(name.ok_or_else(|| missing("Name"))?),
+        uid,
+        tracer_pid,
+        state,
+        threads,
+        umask,
+        groups,
+        no_new_privs,
+        seccomp,
+        seccomp_filters,
+        rss,
+        peak_rss,
+        peak_virtual,
+        private_memory,
+        shared_memory,
+        fd_size,
+        cpus_allowed,
+        ..ProcessInfo::default()
+    })
+}
+
+/// Splits /proc/[pid]/cmdline at its NUL separators. Kernel threads have an
+/// empty file and no arguments.
+pub(crate) fn parse_cmdline(cmdline: &[u8]) -> Vec<Vec<u8>>
+{
+    if cmdline.is_empty() {
+        return Vec::new();
+    }
+    cmdline.strip_suffix(b"\0").unwrap_or(cmdline)
+        .split(|&byte| byte == 0)
+        .map(<[u8]>::to_vec)
+        .collect()
+}
+
+/// Splits /proc/[pid]/stat into the pid and name, up to the closing
+/// parenthesis of the name, which may itself contain spaces and
+/// parentheses, and the fields after it, the first being field 3.
+pub(crate) fn split_stat(stat: &[u8]) -> Option<(&[u8], impl Iterator<Item = &[u8]>)>
+{
+    let name_end = stat.iter().rposition(|&byte| byte == b')')?;
+    let fields = stat[name_end + 1 ..]
+        .split(u8::is_ascii_whitespace)
+        .filter(|value| !value.is_empty());
+    Some((&stat[..= name_end], fields))
+}
+
+/// Field `field` of /proc/[pid]/stat, counting from 1 as proc(5) does.
+fn stat_field<T: FromStr>(stat: &[u8], field: usize) -> Option<T>
+{
+    let value = split_stat(stat)?.1.nth(field.checked_sub(3)?)?;
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+/// The start time, field 22 of /proc/[pid]/stat.
+pub(crate) fn parse_start_time(stat: &[u8]) -> Option<u64>
+{
+    stat_field(stat, 22)
+}
+
+/// The session id, field 6 of /proc/[pid]/stat.
+pub(crate) fn parse_session(stat: &[u8]) -> Option<u32>
+{
+    stat_field(stat, 6)
+}
+
+/// The nice value, field 19 of /proc/[pid]/stat.
+pub(crate) fn parse_nice(stat: &[u8]) -> Option<i32>
+{
+    stat_field(stat, 19)
+}
+
+/// The user and system CPU time in clock ticks, fields 14 and 15 of
+/// /proc/[pid]/stat.
+pub(crate) fn parse_cpu_ticks(stat: &[u8]) -> Option<u64>
+{
+    stat_field::<u64>(stat, 14)?.checked_add(stat_field(stat, 15)?)
+}
+
+/// The user and system CPU time in clock ticks of the waited-for children,
+/// fields 16 and 17 of /proc/[pid]/stat.
+pub(crate) fn parse_children_cpu_ticks(stat: &[u8]) -> Option<u64>
+{
+    stat_field::<u64>(stat, 16)?.checked_add(stat_field(stat, 17)?)
+}
+
+/// The CPU last run on, field 39 of /proc/[pid]/stat.
+pub(crate) fn parse_processor(stat: &[u8]) -> Option<u32>
+{
+    stat_field(stat, 39)
+}
+
+/// The size of a line like `Pss:   1234 kB` of /proc/[pid]/smaps_rollup,
+/// in bytes. The first line of the file, the address range of the rollup,
+/// never matches.
+pub(crate) fn parse_smaps_size(smaps: &[u8], field: &str) -> Option<u64>
+{
+    smaps.split(|&byte| byte == b'\n').find_map(|line| {
+        let value = line.strip_prefix(field.as_bytes())?.strip_prefix(b":")?;
+        String::from_utf8_lossy(first_field(value)).parse::<u64>().ok().map(|kib| kib * 1024)
+    })
+}
+
+/// The private and shared parts of the resident memory in
+/// /proc/[pid]/smaps_rollup, in bytes, each the sum of its clean and dirty
+/// pages.
+pub(crate) fn parse_smaps_split(smaps: &[u8]) -> Option<(u64, u64)>
+{
+    let size = |field: &str| parse_smaps_size(smaps, field);
+    Some((size("Private_Clean")? + size("Private_Dirty")?, size("Shared_Clean")? + size("Shared_Dirty")?))
+}
+
+/// The symbol in /proc/[pid]/wchan. The file has no trailing newline and
+/// holds `0` when the process is running or the kernel hides the symbol.
+pub(crate) fn parse_wchan(wchan: &[u8]) -> Option<String>
+{
+    match String::from_utf8_lossy(wchan).trim() {
+        "" | "0" => None,
+        symbol => Some(symbol.to_string()),
+    }
+}
+
+/// The file paths mapped in /proc/[pid]/maps. The path is the last column
+/// and the only one starting with a slash; it may contain spaces.
+pub(crate) fn parse_maps_paths(maps: &[u8]) -> impl Iterator<Item = &Path>
+{
+    maps.split(|&byte| byte == b'\n').filter_map(|line| {
+        let start = line.windows(2).position(|pair| pair == b" /")?;
+        Some(Path::new(OsStr::from_bytes(&line[start + 1 ..])))
+    })
+}
+
+/// `path` without the ` (deleted)` suffix the kernel adds to the link
+/// targets of removed files.
+pub(crate) fn strip_deleted(path: &Path) -> &Path
+{
+    let bytes = path.as_os_str().as_bytes();
+    match bytes.strip_suffix(b" (deleted)") {
+        Some(stripped) => Path::new(OsStr::from_bytes(stripped)),
+        None => path,
+    }
+}
+
+/// The inode of a /proc/[pid]/ns/`ns` link target like `mnt:[4026531841]`.
+pub(crate) fn parse_ns_inode(target: &Path, ns: &str) -> Option<u64>
+{
+    target.to_str()?.strip_prefix(ns)?.strip_prefix(":[")?.strip_suffix(']')?.parse().ok()
+}
+
+/// The cgroup path in /proc/[pid]/cgroup: the `0::` line of the unified
+/// hierarchy, or the `name=systemd` line on a v1 host.
+pub(crate) fn parse_cgroup(cgroup: &[u8]) -> Option<String>
+{
+    let cgroup = String::from_utf8_lossy(cgroup);
+    let mut systemd: Option<&str> = None;
+
+    for line in cgroup.lines() {
+        let mut fields = line.splitn(3, ':').skip(1);
+        let (controllers, path) = match (fields.next(), fields.next()) {
+            (Some(controllers), Some(path)) => (controllers, path),
+            _ => continue,
+        };
+        match controllers {
+            "" => return Some(path.to_string()),
+            "name=systemd" => systemd = Some(path),
+            _ => {}
+        }
+    }
+
+    systemd.map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn parse(status: &[u8]) -> Result<ProcessInfo, ParseError>
+    {
+        parse_proc_stat(42, status, StatusFields { state: true, threads: true, ..StatusFields::default() })
+    }
+
+    #[test]
+    fn full_status()
+    {
+        let proc_info = parse(b"Name:\tbash\nUmask:\t0022\nState:\tS (sleeping)\nTgid:\t42\nPid:\t42\nPPid:\t7\n\
+                               TracerPid:\t0\nUid:\t1000\t1000\t1000\t1000\nThreads:\t3\n").unwrap();
+        assert_eq!(proc_info, ProcessInfo {
+            uid: Some(1000),
+            tracer_pid: Some(0),
+            state: Some('S'),
+            threads: Some(3),
+            ..ProcessInfo::new(42, 7, "bash")
+        });
+    }
+
+    #[test]
+    fn truncated_status()
+    {
+        // The read stopped in the middle of the Pid line.
+        let error = parse(b"Name:\tbash\nState:\tS (sleeping)\nPi").unwrap_err();
+        assert_eq!((error.pid, error.reason.as_str()), (42, "missing Pid field"));
+
+        // The last line lost its newline but is complete.
+        let proc_info = parse(b"Name:\tbash\nPid:\t42\nPPid:\t7").unwrap();
+        assert_eq!((proc_info.pid, proc_info.ppid, proc_info.uid), (42, 7, None));
+
+        assert_eq!(parse(b"").unwrap_err().reason, "missing Pid field");
+        assert!(parse(b"Name:\tbash\nPid:\t4").unwrap_err().reason.contains("PPid"));
+    }
+
+    #[test]
+    fn missing_or_invalid_ppid()
+    {
+        let error = parse(b"Name:\tbash\nPid:\t42\nUid:\t0\t0\t0\t0\n").unwrap_err();
+        assert_eq!(error.reason, "missing PPid field");
+
+        let error = parse(b"Name:\tbash\nPid:\t42\nPPid:\t-1\n").unwrap_err();
+        assert!(error.reason.starts_with("invalid PPid field"), "{}", error.reason);
+        assert_eq!(error.to_string(), format!("malformed status of process 42: {}", error.reason));
+    }
+
+    #[test]
+    fn pid_fields_are_matched_by_whole_name()
+    {
+        // A traced process in a pid namespace: TracerPid, Tgid, Ngid and
+        // NSpid all end in "pid" or hold other pids, only Pid and PPid count.
+        let proc_info = parse(b"Name:\tgdb-target\nTgid:\t42\nNgid:\t0\nPid:\t42\nPPid:\t7\nTracerPid:\t99\n\
+                               Uid:\t0\t0\t0\t0\nNSpid:\t42\t1\nNSpgid:\t42\t1\nNSsid:\t7\t0\n").unwrap();
+        assert_eq!((proc_info.pid, proc_info.ppid, proc_info.tracer_pid), (42, 7, Some(99)));
+
+        // The same fields before Pid and PPid do not stand in for them.
+        let proc_info = parse(b"Name:\tx\nTracerPid:\t99\nNSpid:\t5\t1\nPid:\t42\nPPid:\t7\n").unwrap();
+        assert_eq!((proc_info.pid, proc_info.ppid, proc_info.tracer_pid), (42, 7, Some(99)));
+        let error = parse(b"Name:\tx\nTracerPid:\t99\nNSpid:\t5\t1\nPPid:\t7\n").unwrap_err();
+        assert_eq!(error.reason, "missing Pid field");
+    }
+
+    #[test]
+    fn unusual_names()
+    {
+        let name = |status: &[u8]| parse(status).unwrap().name.to_vec();
+
+        assert_eq!(name(b"Name:\tWeb Content\nPid:\t42\nPPid:\t1\n"), b"Web Content");
+        assert_eq!(name(b"Name:\t(sd-pam)\nPid:\t42\nPPid:\t1\n"), b"(sd-pam)");
+        assert_eq!(name(b"Name:\ta) b: (c\nPid:\t42\nPPid:\t1\n"), b"a) b: (c");
+        assert_eq!(name(b"Name:\t\tindented\nPid:\t42\nPPid:\t1\n"), b"\tindented");
+        assert_eq!(name(b"Name:\t\xff\xfeodd\nPid:\t42\nPPid:\t1\n"), b"\xff\xfeodd");
+        assert_eq!(name(b"Name:\t\nPid:\t42\nPPid:\t1\n"), b"");
+    }
+
+    #[test]
+    fn kernel_escapes_in_names()
+    {
+        let name = |status: &[u8]| parse(status).unwrap();
+
+        // A newline set with prctl comes as the two characters \n.
+        let broken = name(b"Name:\tline\\nbreak\nPid:\t42\nPPid:\t1\n");
+        assert_eq!(&*broken.name, b"line\nbreak");
+        assert_eq!(broken.display_name(), "line\\nbreak");
+
+        let slashed = name(b"Name:\ta\\\\b\\\\\nPid:\t42\nPPid:\t1\n");
+        assert_eq!(&*slashed.name, b"a\\b\\");
+        assert_eq!(slashed.display_name(), "a\\b\\");
+        // \\n is an escaped backslash followed by an n, not a newline.
+        assert_eq!(&*name(b"Name:\tc:\\\\new\nPid:\t42\nPPid:\t1\n").name, b"c:\\new");
+        // Sequences the kernel never writes stay as they are.
+        assert_eq!(&*name(b"Name:\t\\x41\\\nPid:\t42\nPPid:\t1\n").name, b"\\x41\\");
+
+        let escape = name(b"Name:\t\\e[31mred\nPid:\t42\nPPid:\t1\n");
+        assert_eq!(&*escape.name, b"\x1b[31mred");
+        assert_eq!(escape.display_name(), "\\u{1b}[31mred");
+
+        // The kernel cut the name at 15 bytes, in the middle of the eighth
+        // two-byte character.
+        let cut = name(b"Name:\t\xd0\xbf\xd1\x80\xd0\xb8\xd0\xb2\xd0\xb5\xd1\x82\xd0\xb8\xd0\nPid:\t42\nPPid:\t1\n");
+        assert_eq!(cut.name.len(), 15);
+        assert_eq!(cut.display_name(), "привети\u{fffd}");
+    }
+}
@@ -0,0 +1,94 @@
+use crate::process::ProcessInfo;
+use crate::user::UserTable;
+
+
+/// The headers `ps` gives the command column. The first three show the
+/// command line, COMM only the name.
+const COMMAND_HEADERS: [&str; 4] = ["COMMAND", "CMD", "ARGS", "COMM"];
+
+/// Parses the output of `ps`, like that of `ps -ef` or `ps -eo
+/// pid,ppid,user,stat,rss,args`, into a process list.
+///
+/// The first line names the columns. PID and PPID are needed, and the last
+/// column has to be the command, as it is the only one that may hold
+/// spaces. UID or USER, S or STAT and RSS are read when present, any other
+/// column is skipped. Users are looked up in `users`, numeric ones are
+/// taken as they are. Blank lines are ignored.
+pub fn parse_ps_output(output: &str, users: &UserTable) -> Result<Vec<ProcessInfo>, String>
+{
+    let mut lines = output.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or("no header line")?;
+    let columns: Vec<&str> = header.split_whitespace().collect();
+    let column = |names: &[&str]| columns.iter().position(|column| names.contains(column));
+
+    let command = *columns
+        .last()
+        .filter(|name| COMMAND_HEADERS.contains(name))
+        .ok_or("the last column is not COMMAND, CMD, ARGS or COMM")?;
+    let pid_column = column(&["PID"]).ok_or("no PID column")?;
+    let ppid_column = column(&["PPID"]).ok_or("no PPID column")?;
+    let user_column = column(&["UID", "USER"]);
+    let state_column = column(&["S", "STAT"]);
+    let rss_column = column(&["RSS"]);
+
+    let mut processes = Vec::new();
+    for (index, line) in lines {
+        let number = index + 1;
+        let (values, command_line) = split_row(line, columns.len() - 1)
+            .ok_or_else(|| format!("line {}: expected {} columns", number, columns.len()))?;
+        let id = |column: usize, name: &str| {
+            values[column].parse::<u32>().map_err(|_| format!("line {}: invalid {} \"{}\"", number, name, values[column]))
+        };
+
+        let mut proc_info = command_process(id(pid_column, "PID")?, id(ppid_column, "PPID")?, command, command_line);
+        proc_info.uid = user_column.and_then(|column| users.resolve(values[column]));
+        proc_info.state = state_column.and_then(|column| values[column].chars().next());
+        proc_info.rss = rss_column.and_then(|column| values[column].parse::<u64>().ok()).map(|rss| rss * 1024);
+        processes.push(proc_info);
+    }
+
+    Ok(processes)
+}
+
+/// Splits a line into its first `fields` values and the rest, the command.
+/// None if the line has fewer values or no command.
+fn split_row(line: &str, fields: usize) -> Option<(Vec<&str>, &str)>
+{
+    let mut rest = line.trim();
+    let mut values = Vec::with_capacity(fields);
+
+    for _ in 0 .. fields {
+        let end = rest.find(char::is_whitespace)?;
+        values.push(&rest[.. end]);
+        rest = rest[end ..].trim_start();
+    }
+
+    Some((values, rest)).filter(|_| !rest.is_empty())
+}
+
+/// The process of a ps line from the value of its `header` command column.
+/// Kernel threads and zombies, shown in brackets like `[kthreadd]` or
+/// `[sleep] <defunct>`, get their name and an empty command line.
+/// Otherwise COMM is the name, and the other headers give the command
+/// line, named after its first word without the directory, the dash of
+/// login shells and the colon of processes that retitle themselves like
+/// `sshd: alice`.
+fn command_process(pid: u32, ppid: u32, header: &str, command: &str) -> ProcessInfo
+{
+    let command = command.strip_suffix(" <defunct>").unwrap_or(command);
+    if let Some(name) = command.strip_prefix('[').and_then(|command| command.strip_suffix(']')) {
+        let mut proc_info = ProcessInfo::new(pid, ppid, name);
+        proc_info.cmdline = Some(Vec::new());
+        return proc_info;
+    }
+    if header == "COMM" {
+        return ProcessInfo::new(pid, ppid, command);
+    }
+
+    let args: Vec<&str> = command.split_whitespace().collect();
+    let program = args[0].rsplit('/').next().unwrap_or(args[0]);
+    let name = program.strip_prefix('-').unwrap_or(program).trim_end_matches(':');
+    let mut proc_info = ProcessInfo::new(pid, ppid, if name.is_empty() { program } else { name });
+    proc_info.cmdline = Some(args.iter().map(|arg| arg.as_bytes().to_vec()).collect());
+    proc_info
+}
@@ -0,0 +1,692 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io;
+use std::time::Duration;
+
+use ptree::print_config::{OutputKind, StyleWhen};
+use ptree::{Color, PrintConfig, Style, TreeItem};
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{PidFormat, PsTreePrintConfig, SortKey};
+use crate::diff::Change;
+use crate::duration::format_duration;
+use crate::group::ProcessGroup;
+use crate::limits::ResourceLimit;
+use crate::process::ProcessInfo;
+use crate::tree::{OmittedSubtrees, ProcessNode, ProcessTree};
+use crate::wrap::wrap_text;
+
+
+const FOREST_ROOT_LABEL: &str = "(all)";
+const TRACED_COLOR: Color = Color::Red;
+const NEW_COLOR: Color = Color::Green;
+const GONE_COLOR: Color = Color::Red;
+const CHANGED_COLOR: Color = Color::Yellow;
+/// Put between the processes of a collapsed chain.
+const CHAIN_SEPARATOR: &str = " ▸ ";
+const ASCII_CHAIN_SEPARATOR: &str = " > ";
+/// Put between the processes of the ancestor header.
+const ANCESTOR_SEPARATOR: &str = " › ";
+/// Stands for the part of the ancestor header that could not be read.
+const UNKNOWN_ANCESTORS: &str = "?";
+/// Put before the type of a namespace a process does not share with its
+/// parent.
+const NS_MARK: &str = "⊘";
+const ASCII_NS_MARK: &str = "!";
+/// Supplementary groups shown before the list is cut short.
+const MAX_SHOWN_GROUPS: usize = 5;
+
+/// The fewest columns `wrap` leaves a label, however deep it is drawn.
+const MIN_WRAP_WIDTH: usize = 16;
+/// The umask most processes inherit, left out unless asked for.
+const DEFAULT_UMASK: u32 = 0o022;
+
+impl ProcessTree
+{
+    /// Applies the top subtrees, depth limit, sort order, duplicate removal
+    /// and line limit of `config`, in that order.
+    pub fn apply_config(&mut self, config: &PsTreePrintConfig)
+    {
+        if let Some(count) = config.top {
+            self.keep_top_subtrees(count);
+        }
+        if let Some(max_depth) = config.depth {
+            self.prune_depth(max_depth);
+        }
+        if config.sort != SortKey::Pid {
+            self.sort_children(config.sort, config.collation);
+        }
+        if config.unique {
+            self.filter_unique();
+        }
+        if let Some(max_lines) = config.max_lines {
+            // The lines that are not processes take their share of the limit.
+            let all_line = self.groups.is_empty() && self.roots.len() > 1 && !config.forest;
+            let fixed = usize::from(all_line)
+                + usize::from(self.ancestor_header(config, false).is_some())
+                + usize::from(self.omitted_subtrees.is_some());
+            self.truncate(max_lines.saturating_sub(fixed));
+        }
+    }
+
+    /// Draws the tree on stdout.
+    pub fn print(&self, config: &PsTreePrintConfig) -> io::Result<()>
+    {
+        let styled = config.print_config.should_style_output(OutputKind::Stdout);
+        self.write_styled(io::stdout().lock(), config, styled)
+    }
+
+    /// Draws the tree into `out`.
+    pub fn write<W: io::Write>(&self, out: W, config: &PsTreePrintConfig) -> io::Result<()>
+    {
+        let styled = config.print_config.should_style_output(OutputKind::Unknown);
+        self.write_styled(out, config, styled)
+    }
+
+    /// Draws the ancestor header, the tree, the line counting the processes
+    /// cut by `max_lines` and the legend of the highlights.
+    fn write_styled<W: io::Write>(&self, mut out: W, config: &PsTreePrintConfig, styled: bool) -> io::Result<()>
+    {
+        if let Some(header) = self.ancestor_header(config, styled) {
+            writeln!(out, "{}", header)?;
+        }
+        if config.compat {
+            self.write_compat(&mut out, config)?;
+        } else if config.align_pids || config.upside_down || config.level_prefix {
+            Self::write_buffered(&self.items(config, styled), &mut out, config, styled)?;
+        } else {
+            // The styling is decided by the caller, as ptree would only look at `out`.
+            let mut print_config = config.print_config.clone();
+            print_config.styled = if styled { StyleWhen::Always } else { StyleWhen::Never };
+            let mut trees = self.items(config, styled);
+            if let Some(width) = config.wrap {
+                let prefixes = BranchPrefixes::new(&config.print_config);
+                let branch_style = styled.then_some(&config.print_config.branch);
+                for tree in &mut trees {
+                    tree.wrap(width, 0, "", &prefixes, branch_style);
+                }
+            }
+            for tree in trees {
+                ptree::write_tree_with(&tree, &mut out, &print_config)?;
+            }
+        }
+        if self.truncated_processes > 0 {
+            let text = format!(
+                "… output truncated, {} more {} (use --max-lines 0 for all)",
+                self.truncated_processes, if self.truncated_processes == 1 { "process" } else { "processes" },
+            );
+            match styled {
+                true => writeln!(out, "{}", Style { dimmed: true, ..config.print_config.leaf.clone() }.paint(text))?,
+                false => writeln!(out, "{}", text)?,
+            }
+        }
+        if styled && !config.compat && !config.highlights.is_empty() {
+            writeln!(out, "{}", Self::highlight_legend(config))?;
+        }
+        Ok(())
+    }
+
+    /// The line following a tree with highlights, every pattern in its
+    /// color like "highlights: postgres pgbouncer".
+    fn highlight_legend(config: &PsTreePrintConfig) -> String
+    {
+        let patterns: Vec<String> = config.highlights.iter()
+            .map(|highlight| {
+                let style = Style { foreground: Some(highlight.color.clone()), ..config.print_config.leaf.clone() };
+                style.paint(highlight.pattern.as_str()).to_string()
+            })
+            .collect();
+        format!("highlights: {}", patterns.join(" "))
+    }
+
+    /// Draws `trees` through a buffer, for the layouts ptree cannot draw
+    /// itself. With `align_pids` the pids go in a right-aligned column before
+    /// the branches, followed by their parent pids with `show_ppid`, as wide
+    /// as the largest of them, and lines that are not
+    /// processes, like group labels, leave the column blank. With
+    /// `upside_down` the lines of each tree are written in reverse, drawn
+    /// with the turn of the last child mirrored as it ends up first. With
+    /// `level_prefix` every line starts with its level, 1 for the top.
+    fn write_buffered<W: io::Write>(
+        trees: &[NodeItem],
+        mut out: W,
+        config: &PsTreePrintConfig,
+        styled: bool,
+    ) -> io::Result<()>
+    {
+        let lines: Vec<Vec<(Option<String>, usize)>> = trees.iter()
+            .map(|tree| {
+                let mut lines = Vec::new();
+                tree.collect_lines(1, config.show_ppid, &mut lines);
+                lines
+            })
+            .collect();
+        let width = lines.iter().flatten()
+            .filter_map(|(pid, _)| pid.as_ref())
+            .map(|pid| pid.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        // The styling is decided by the caller, as ptree would only look at the buffer.
+        let mut print_config = config.print_config.clone();
+        print_config.styled = if styled { StyleWhen::Always } else { StyleWhen::Never };
+        if config.upside_down {
+            print_config.characters.turn_right = mirrored_turn(&print_config.characters.turn_right);
+        }
+
+        for (tree, lines) in trees.iter().zip(lines) {
+            let mut buffer: Vec<u8> = Vec::new();
+            ptree::write_tree_with(tree, &mut buffer, &print_config)?;
+            let mut lines: Vec<_> = buffer.split_inclusive(|&byte| byte == b'\n')
+                .zip(lines)
+                .collect();
+            if config.upside_down {
+                lines.reverse();
+            }
+            for (line, (pid, level)) in lines {
+                if config.level_prefix {
+                    write!(out, "level {}: ", level)?;
+                }
+                if config.align_pids {
+                    match pid {
+                        Some(pid) => write!(out, "{:>width$} ", pid, width = width)?,
+                        None => write!(out, "{:width$} ", "", width = width)?,
+                    }
+                }
+                out.write_all(line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The line drawn above a single root that is not top-level, listing
+    /// the processes from the top down to it like "(init(1) › sshd(5))".
+    /// A chain that stops below the top starts with "?".
+    fn ancestor_header(&self, config: &PsTreePrintConfig, styled: bool) -> Option<String>
+    {
+        let root = match self.roots[..] {
+            [root] if config.ancestor_header && self.groups.is_empty() => &self.nodes[root].proc_info,
+            _ => return None,
+        };
+        if root.is_top_level() {
+            return None;
+        }
+        let chain = self.ancestry(root.pid)?;
+        let mut names: Vec<String> = Vec::new();
+        if !self.nodes[chain[0]].proc_info.is_top_level() {
+            names.push(UNKNOWN_ANCESTORS.to_string());
+        }
+        names.extend(chain.iter().map(|&index| {
+            let proc_info = &self.nodes[index].proc_info;
+            format!("{}({})", proc_info.display_name(), proc_info.pid)
+        }));
+        let separator = if config.print_config.characters.down.is_ascii() {
+            ASCII_CHAIN_SEPARATOR
+        } else {
+            ANCESTOR_SEPARATOR
+        };
+        let header = format!("({})", names.join(separator));
+        if styled {
+            Some(Style { dimmed: true, ..config.print_config.leaf.clone() }.paint(header).to_string())
+        } else {
+            Some(header)
+        }
+    }
+
+    fn items(&self, config: &PsTreePrintConfig, styled: bool) -> Vec<NodeItem<'_>>
+    {
+        let items = self.root_items(config, styled);
+        if self.groups.is_empty() && items.len() > 1 && !config.forest {
+            vec![NodeItem {
+                text: Cow::Borrowed(FOREST_ROOT_LABEL),
+                pid: None,
+                ppid: None,
+                style: None,
+                children: items,
+                continuation: Vec::new(),
+            }]
+        } else {
+            items
+        }
+    }
+
+    /// The groups, or the roots followed by the line of the subtrees cut
+    /// by `--top`, without the "(all)" line above them.
+    pub(crate) fn root_items(&self, config: &PsTreePrintConfig, styled: bool) -> Vec<NodeItem<'_>>
+    {
+        if !self.groups.is_empty() {
+            return self.groups.iter().map(|group| self.group_item(group, config, styled)).collect();
+        }
+        let mut items: Vec<NodeItem<'_>> = self.roots.iter()
+            .map(|&root| self.build_item(root, 0, config, styled))
+            .collect();
+        let omitted_roots = self.omitted_subtrees.filter(|omitted| omitted.parent.is_none());
+        items.extend(omitted_roots.map(|omitted| Self::omitted_item(&omitted, config, styled)));
+        items
+    }
+
+    /// The dimmed line standing for the subtrees cut by `--top`, like
+    /// "… 41 smaller subtrees (388 processes)".
+    fn omitted_item(omitted: &OmittedSubtrees, config: &PsTreePrintConfig, styled: bool) -> NodeItem<'static>
+    {
+        let plural = |count: usize, singular: &'static str, plural: &'static str| {
+            if count == 1 { singular } else { plural }
+        };
+        NodeItem {
+            text: Cow::Owned(format!(
+                "… {} smaller {} ({} {})",
+                omitted.subtrees, plural(omitted.subtrees, "subtree", "subtrees"),
+                omitted.processes, plural(omitted.processes, "process", "processes"),
+            )),
+            pid: None,
+            ppid: None,
+            style: styled.then(|| Style { dimmed: true, ..config.print_config.leaf.clone() }),
+            children: Vec::new(),
+            continuation: Vec::new(),
+        }
+    }
+
+    /// A group with its subgroups, then its subtrees. Subtrees whose parent
+    /// is outside the group hang below that parent, drawn dimmed in
+    /// parentheses for context.
+    fn group_item<'a>(&'a self, group: &'a ProcessGroup, config: &PsTreePrintConfig, styled: bool) -> NodeItem<'a>
+    {
+        let mut children: Vec<NodeItem<'a>> = group.subgroups.iter()
+            .map(|subgroup| self.group_item(subgroup, config, styled))
+            .collect();
+
+        let mut by_parent: Vec<(Option<usize>, Vec<usize>)> = Vec::new();
+        for &root in &group.roots {
+            let proc_info = &self.nodes[root].proc_info;
+            let parent = self.pid_index.get(&proc_info.ppid).copied()
+                .filter(|_| !proc_info.is_top_level() && proc_info.ppid != proc_info.pid);
+            match by_parent.iter_mut().find(|(known, _)| *known == parent) {
+                Some((_, roots)) => roots.push(root),
+                None => by_parent.push((parent, vec![root])),
+            }
+        }
+
+        for (parent, roots) in by_parent {
+            let items = roots.iter().map(|&root| self.build_item(root, 0, config, styled));
+            match parent {
+                Some(parent) => children.push(NodeItem {
+                    text: Cow::Owned(format!("({})", Self::label(&self.nodes[parent].proc_info, config))),
+                    pid: Some(self.nodes[parent].proc_info.pid),
+                    ppid: Some(self.nodes[parent].proc_info.ppid),
+                    style: styled.then(|| Style { dimmed: true, ..config.print_config.leaf.clone() }),
+                    children: items.collect(),
+                    continuation: Vec::new(),
+                }),
+                None => children.extend(items),
+            }
+        }
+
+        NodeItem {
+            text: Cow::Borrowed(&group.label),
+            pid: None,
+            ppid: None,
+            style: styled.then(|| Style { bold: true, ..config.print_config.leaf.clone() }),
+            children,
+            continuation: Vec::new(),
+        }
+    }
+
+    fn build_item(&self, index: usize, depth: usize, config: &PsTreePrintConfig, styled: bool) -> NodeItem<'_>
+    {
+        let mut last = index;
+        let mut text = Self::node_label(&self.nodes[last], config);
+        self.push_ns_marks(&mut text, last, config);
+        if config.collapse_chains {
+            let separator = if config.print_config.characters.down.is_ascii() {
+                ASCII_CHAIN_SEPARATOR
+            } else {
+                CHAIN_SEPARATOR
+            };
+            while let [only_child] = self.nodes[last].children[..] {
+                // The line of the cut subtrees needs its parent on a line of its own.
+                if self.omitted_subtrees.is_some_and(|omitted| omitted.parent == Some(last)) {
+                    break;
+                }
+                last = only_child;
+                let mut label = Self::node_label(&self.nodes[last], config);
+                self.push_ns_marks(&mut label, last, config);
+                let text = text.to_mut();
+                text.push_str(separator);
+                text.push_str(&label);
+            }
+        }
+        let node = &self.nodes[last];
+        let mut children: Vec<NodeItem<'_>> = node.children.iter()
+            .map(|&child| self.build_item(child, depth + 1, config, styled))
+            .collect();
+        if let Some(omitted) = self.omitted_subtrees.filter(|omitted| omitted.parent == Some(last)) {
+            children.push(Self::omitted_item(&omitted, config, styled));
+        }
+        NodeItem {
+            text,
+            pid: Some(self.nodes[index].proc_info.pid),
+            ppid: Some(self.nodes[index].proc_info.ppid),
+            style: if styled { Self::node_style(node, depth, config) } else { None },
+            children,
+            continuation: Vec::new(),
+        }
+    }
+
+    /// The style of a process that stands out from the other nodes, if any:
+    /// traced, then new, gone or changed, then highlighted, then colored by
+    /// state, then by `color_by`. Context nodes of a diff are dimmed.
+    fn node_style(node: &ProcessNode, depth: usize, config: &PsTreePrintConfig) -> Option<Style>
+    {
+        let proc_info = &node.proc_info;
+        if proc_info.tracer().is_some() {
+            return Some(Style {
+                foreground: Some(TRACED_COLOR),
+                bold: true,
+                ..config.print_config.leaf.clone()
+            });
+        }
+        let change_color = match node.change {
+            Some(Change::New) => Some(&NEW_COLOR),
+            Some(Change::Gone) => Some(&GONE_COLOR),
+            Some(Change::Changed { .. }) => Some(&CHANGED_COLOR),
+            Some(Change::Context) => return Some(Style { dimmed: true, ..config.print_config.leaf.clone() }),
+            None => None,
+        };
+        let highlight = config.highlights.iter()
+            .find(|highlight| highlight.pattern.is_match(&proc_info.display_name()))
+            .map(|highlight| &highlight.color);
+        let color = change_color
+            .or(highlight)
+            .or_else(|| proc_info.state.and_then(|state| config.state_colors.get(&state)))
+            .or_else(|| config.depth_color(depth))?;
+        Some(Style { foreground: Some(color.clone()), ..config.print_config.leaf.clone() })
+    }
+
+    /// Appends a mark like " ⊘mnt" to `label` for every namespace type of
+    /// `show_ns` the process of `index` does not share with its parent. A
+    /// process whose parent or either inode is unknown is not marked.
+    fn push_ns_marks(&self, label: &mut Cow<'_, str>, index: usize, config: &PsTreePrintConfig)
+    {
+        let proc_info = &self.nodes[index].proc_info;
+        let parent = self.pid_index.get(&proc_info.ppid).filter(|_| proc_info.ppid != proc_info.pid);
+        let inherited = parent.and_then(|&parent| self.nodes[parent].proc_info.namespaces.as_ref());
+        let (Some(own), Some(inherited)) = (&proc_info.namespaces, inherited) else { return };
+        let mark = if config.print_config.characters.down.is_ascii() { ASCII_NS_MARK } else { NS_MARK };
+        for ns in &config.show_ns {
+            if let (Some(own), Some(inherited)) = (own.get(ns), inherited.get(ns)) {
+                if own != inherited {
+                    let _ = write!(label.to_mut(), " {}{}", mark, ns);
+                }
+            }
+        }
+    }
+
+    /// The label of the process of `node`, marked `[new]` or `[gone]`, or
+    /// with the earlier values like `[S→D]` when it changed.
+    fn node_label<'a>(node: &'a ProcessNode, config: &PsTreePrintConfig) -> Cow<'a, str>
+    {
+        let proc_info = &node.proc_info;
+        let mut label = Self::label(proc_info, config);
+        match node.change {
+            Some(Change::New) => label.to_mut().push_str(" [new]"),
+            Some(Change::Gone) => label.to_mut().push_str(" [gone]"),
+            Some(Change::Changed { state, rss }) => {
+                if let (Some(before), Some(now)) = (state, proc_info.state) {
+                    let _ = write!(label.to_mut(), " [{}→{}]", before, now);
+                }
+                if let (Some(before), Some(now)) = (rss, proc_info.rss) {
+                    let unit = config.size_unit;
+                    let _ = write!(label.to_mut(), " [rss {}→{}]", unit.format(before), unit.format(now));
+                }
+            }
+            Some(Change::Context) | None => {}
+        }
+        label
+    }
+
+    /// The name of the process with what is shown of it around. The plain
+    /// name, the label of most processes, borrows the name of `proc_info`.
+    fn label<'a>(proc_info: &'a ProcessInfo, config: &PsTreePrintConfig) -> Cow<'a, str>
+    {
+        let name = proc_info.display_name();
+
+        let shows_pid = (config.show_pid || config.show_ppid) && !config.align_pids;
+        let pid_format = if shows_pid { Some(config.pid_format) } else { None };
+        let pid = pid_text(proc_info.pid, Some(proc_info.ppid).filter(|_| config.show_ppid));
+
+        let mut prefix = String::new();
+        if pid_format == Some(PidFormat::Brackets) {
+            let _ = write!(prefix, "[{}] ", pid);
+        }
+        let mut suffix = String::new();
+        if pid_format == Some(PidFormat::Parens) {
+            let _ = write!(suffix, "({})", pid);
+        }
+        if let Some(wchan) = &proc_info.wchan {
+            let _ = write!(suffix, " [{}]", wchan);
+        }
+        if let Some(umask) = proc_info.display_umask() {
+            if config.show_default_umask || proc_info.umask != Some(DEFAULT_UMASK) {
+                let _ = write!(suffix, " (umask {})", umask);
+            }
+        }
+        if let Some(groups) = proc_info.groups.as_ref().filter(|groups| config.show_groups && !groups.is_empty()) {
+            let mut names: Vec<String> = groups.iter()
+                .take(MAX_SHOWN_GROUPS)
+                .map(|&gid| config.group_names.name(gid).map_or_else(|| gid.to_string(), str::to_string))
+                .collect();
+            if groups.len() > MAX_SHOWN_GROUPS {
+                names.push("…".to_string());
+            }
+            let _ = write!(suffix, " ({})", names.join(","));
+        }
+        match proc_info.no_new_privs {
+            Some(no_new_privs) if config.mark_no_new_privs != Some(no_new_privs) => {}
+            Some(true) => suffix.push_str(" [nnp]"),
+            Some(false) => suffix.push_str(" [no nnp]"),
+            None => {}
+        }
+        match (proc_info.seccomp, proc_info.seccomp_filters) {
+            _ if !config.show_seccomp => {}
+            (Some(1), _) => suffix.push_str(" [seccomp:strict]"),
+            (Some(2), Some(filters)) if filters > 1 => {
+                let _ = write!(suffix, " [seccomp:filter x{}]", filters);
+            }
+            (Some(2), _) => suffix.push_str(" [seccomp:filter]"),
+            _ => {}
+        }
+        if let Some(memory) = format_memory(proc_info, config) {
+            let _ = write!(suffix, " {}={}", if config.pss { "pss" } else { "rss" }, memory);
+            if let Some((private, shared)) = proc_info.memory_split().filter(|_| config.memory_detail) {
+                let unit = config.size_unit;
+                let _ = write!(suffix, " (priv {}, shr {})", unit.format(private), unit.format(shared));
+            }
+        }
+        if let Some(peak_rss) = proc_info.peak_rss.filter(|_| config.show_peak_memory) {
+            let _ = write!(suffix, " peak={}", config.size_unit.format(peak_rss));
+        }
+        if let Some(peak_virtual) = proc_info.peak_virtual.filter(|_| config.show_peak_virtual) {
+            let _ = write!(suffix, " vpeak={}", config.size_unit.format(peak_virtual));
+        }
+        if let (Some(fd_size), Some(fd_limit)) = (proc_info.fd_size, proc_info.fd_limit) {
+            let limit = match fd_limit {
+                ResourceLimit::Limited(limit) => format_fd_count(limit),
+                ResourceLimit::Unlimited => fd_limit.to_string(),
+            };
+            let _ = write!(suffix, " [fds {}/{}]", format_fd_count(u64::from(fd_size)), limit);
+        }
+        if let Some(cpus_allowed) = &proc_info.cpus_allowed {
+            let _ = write!(suffix, " [cpus:{}]", cpus_allowed);
+        }
+        if let Some(processor) = proc_info.processor {
+            let _ = write!(suffix, " cpu={}", processor);
+        }
+        if let Some(cpu_time) = proc_info.cpu_time_ms {
+            // The reaped children are added in, marked with a plus.
+            let children = proc_info.children_cpu_time_ms.unwrap_or(0);
+            let total = Duration::from_millis(cpu_time.saturating_add(children));
+            let _ = write!(suffix, " time={}{}", format_duration(total), if children > 0 { "+" } else { "" });
+        }
+        if let Some(listen) = proc_info.listen.as_ref().filter(|listen| !listen.is_empty()) {
+            let _ = write!(suffix, " ({})", listen.join(","));
+        }
+        if let Some(tracer) = proc_info.tracer() {
+            let _ = write!(suffix, " (traced by {})", tracer);
+        }
+        if prefix.is_empty() && suffix.is_empty() {
+            return name;
+        }
+        Cow::Owned(prefix + &name + &suffix)
+    }
+}
+
+/// Formats a pid, followed by the pid of its parent when given: `812`,
+/// `812<1`.
+fn pid_text(pid: u32, ppid: Option<u32>) -> String
+{
+    match ppid {
+        Some(ppid) => format!("{}<{}", pid, ppid),
+        None => pid.to_string(),
+    }
+}
+
+/// Formats the memory of a process in the unit of `config`: its resident
+/// memory, or its proportional set size with [`PsTreePrintConfig::pss`],
+/// then followed by "*" when the resident memory stands in for it.
+pub(crate) fn format_memory(proc_info: &ProcessInfo, config: &PsTreePrintConfig) -> Option<String>
+{
+    let (bytes, fallback) = proc_info.memory(config.pss)?;
+    let mut memory = config.size_unit.format(bytes);
+    if fallback {
+        memory.push('*');
+    }
+    Some(memory)
+}
+
+/// Formats a descriptor count, in units of 1024 from 10240 up: `64`, `31k`.
+fn format_fd_count(count: u64) -> String
+{
+    if count >= 10 * 1024 {
+        format!("{}k", count / 1024)
+    } else {
+        count.to_string()
+    }
+}
+
+/// A drawn node. Unlike ptree's StringItem it can carry a style of its own,
+/// used instead of the leaf style of the print config, and borrow its text
+/// from the tree.
+#[derive(Clone)]
+pub(crate) struct NodeItem<'a>
+{
+    pub(crate) text: Cow<'a, str>,
+    /// The pid of the process drawn, None for labels.
+    pub(crate) pid: Option<u32>,
+    /// The pid of its parent, None for labels.
+    ppid: Option<u32>,
+    style: Option<Style>,
+    pub(crate) children: Vec<NodeItem<'a>>,
+    /// The lines the text goes on with `wrap`, each with the branches drawn
+    /// before it, already styled.
+    continuation: Vec<(String, String)>,
+}
+
+impl NodeItem<'_>
+{
+    /// Wraps the text of this item and of the items below it to `width`
+    /// columns, the item being drawn after `column` columns of branches.
+    /// Its continuation lines start with `branches`, the ones below it, so
+    /// the lines of its ancestors run on past them and no continuation line
+    /// has a branch of its own.
+    fn wrap(&mut self, width: usize, column: usize, branches: &str, prefixes: &BranchPrefixes, branch_style: Option<&Style>)
+    {
+        let lines = wrap_text(&self.text, width.saturating_sub(column).max(MIN_WRAP_WIDTH));
+        if lines.len() > 1 {
+            let painted = branch_style.map_or_else(|| branches.to_string(), |style| style.paint(branches).to_string());
+            let continuation = lines[1..].iter().map(|line| (painted.clone(), line.to_string())).collect();
+            let first = lines[0].to_string();
+            self.continuation = continuation;
+            self.text = Cow::Owned(first);
+        }
+        let count = self.children.len();
+        for (index, child) in self.children.iter_mut().enumerate() {
+            let (prefix, below) = match index + 1 == count {
+                true => (&prefixes.last_regular, &prefixes.last_child),
+                false => (&prefixes.regular, &prefixes.child),
+            };
+            let child_branches = format!("{}{}", branches, below);
+            child.wrap(width, column + prefix.width(), &child_branches, prefixes, branch_style);
+        }
+    }
+
+    /// Appends the pid, with the parent pid if `with_ppid`, and depth of the
+    /// lines of this item in drawing order, the item being at `depth`.
+    fn collect_lines(&self, depth: usize, with_ppid: bool, lines: &mut Vec<(Option<String>, usize)>)
+    {
+        lines.push((self.pid.map(|pid| pid_text(pid, self.ppid.filter(|_| with_ppid))), depth));
+        for child in &self.children {
+            child.collect_lines(depth + 1, with_ppid, lines);
+        }
+    }
+}
+
+/// The branches ptree draws before an item and below it, depending on
+/// whether the item is the last child of its parent.
+struct BranchPrefixes
+{
+    regular: String,
+    child: String,
+    last_regular: String,
+    last_child: String,
+}
+
+impl BranchPrefixes
+{
+    /// The branches of `print_config`, built like ptree does.
+    fn new(print_config: &PrintConfig) -> BranchPrefixes
+    {
+        let characters = &print_config.characters;
+        let run = print_config.indent.saturating_sub(1 + print_config.padding);
+        let padding = characters.empty.repeat(print_config.padding);
+        BranchPrefixes {
+            regular: format!("{}{}{}", characters.down_and_right, characters.right.repeat(run), padding),
+            child: format!("{}{}{}", characters.down, characters.empty.repeat(run), padding),
+            last_regular: format!("{}{}{}", characters.turn_right, characters.right.repeat(run), padding),
+            last_child: format!("{}{}{}", characters.empty, characters.empty.repeat(run), padding),
+        }
+    }
+}
+
+/// The branch that turns down into the first child, for a tree drawn upside
+/// down, from the one that turns up into the last child.
+fn mirrored_turn(turn_right: &str) -> String
+{
+    match turn_right {
+        "└" => String::from("┌"),
+        "╰" => String::from("╭"),
+        "┗" => String::from("┏"),
+        "╚" => String::from("╔"),
+        "`" => String::from(","),
+        other => other.to_string(),
+    }
+}
+
+impl<'a> TreeItem for NodeItem<'a>
+{
+    type Child = NodeItem<'a>;
+
+    fn write_self<W: io::Write>(&self, f: &mut W, style: &Style) -> io::Result<()>
+    {
+        let style = self.style.as_ref().unwrap_or(style);
+        write!(f, "{}", style.paint(&self.text))?;
+        for (branches, text) in &self.continuation {
+            write!(f, "\n{}{}", branches, style.paint(text))?;
+        }
+        Ok(())
+    }
+
+    fn children(&self) -> Cow<'_, [NodeItem<'a>]>
+    {
+        Cow::from(&self.children[..])
+    }
+}
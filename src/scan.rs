@@ -0,0 +1,605 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use crate::cpu::{parse_cpu_list, parse_online_cpus};
+use crate::error::PsTreeError;
+use crate::limits::{parse_soft_limit, ResourceLimit};
+use crate::net::{read_listeners, socket_inode, Listener};
+use crate::process::{
+    parse_cgroup, parse_children_cpu_ticks, parse_cmdline, parse_cpu_ticks, parse_maps_paths, parse_nice, parse_proc_stat,
+    parse_ns_inode, parse_processor, parse_session, parse_smaps_size, parse_smaps_split, parse_start_time, parse_wchan,
+    split_stat, strip_deleted, ParseError, ProcessInfo, StatusFields,
+};
+use crate::source::{LinuxProcfs, ProcSource};
+
+
+/// The row of /proc/[pid]/limits with the open files limit.
+const OPEN_FILES_LIMIT: &str = "Max open files";
+/// The /proc/[pid]/stat fields besides the name whose change makes a cached
+/// process stale: the state, parent, process group, session, thread count
+/// and resident set size.
+const WATCHED_STAT_FIELDS: [usize; 6] = [3, 4, 5, 6, 20, 24];
+/// The clock ticks per second assumed when sysconf cannot tell.
+const DEFAULT_CLOCK_TICKS: u64 = 100;
+/// Most processes kept by [`ScanCache::default`].
+pub const DEFAULT_SCAN_CACHE_CAPACITY: usize = 1 << 16;
+
+/// What to read while scanning.
+#[derive(Clone, Debug)]
+pub struct ScanOptions
+{
+    /// Only read these processes and their descendants. Empty, or containing
+    /// 0, reads every process.
+    pub root_pids: Vec<u32>,
+    /// When only the descendants of `root_pids` are read, also read the
+    /// ancestors of each root.
+    pub read_ancestors: bool,
+    /// Number of worker threads, the number of CPUs by default.
+    pub jobs: usize,
+    /// Read the command line of every process.
+    pub read_cmdline: bool,
+    /// Read the start time of every process from its stat file.
+    pub read_start_time: bool,
+    /// Read the session id of every process from its stat file.
+    pub read_session: bool,
+    /// Read the nice value of every process from its stat file.
+    pub read_nice: bool,
+    /// Read the cgroup path of every process.
+    pub read_cgroup: bool,
+    /// Read the open files limit of every process and the size of its file
+    /// descriptor table, keeping both only when the table takes at least
+    /// this percentage of the limit. 0 keeps them for every process.
+    pub fd_pressure: Option<u32>,
+    /// Read the CPU every process last ran on.
+    pub read_processor: bool,
+    /// Read the CPU time every process used.
+    pub read_cpu_time: bool,
+    /// Read the CPU time of the children every process waited for.
+    pub read_children_cpu_time: bool,
+    /// The clock ticks per second the CPU times of the stat files count,
+    /// sysconf(_SC_CLK_TCK) by default.
+    pub clock_ticks: u64,
+    /// Read the wait channel of every process.
+    pub read_wchan: bool,
+    /// Read the proportional set size of every process from its
+    /// smaps_rollup, which takes the kernel a walk over every mapping and
+    /// is far slower than the status file. Processes whose file cannot be
+    /// read, like those of other users, are left without. With
+    /// [`StatusFields::memory_split`] the private and shared memory are
+    /// taken from the file as well, those of status being kept when it
+    /// cannot be read.
+    pub read_pss: bool,
+    /// Find the listening sockets of every process through its file
+    /// descriptors. Processes whose descriptors cannot be read are left
+    /// without.
+    pub read_listen: bool,
+    /// Check whether every process has this file open, or a file below it
+    /// when it is a directory. The path should be canonical, as the kernel
+    /// reports resolved paths.
+    pub open_file: Option<PathBuf>,
+    /// Read the exe link of every process. Processes whose link cannot be
+    /// read, like kernel threads or those of other users, are left without.
+    pub read_exe: bool,
+    /// The namespace types whose inodes to read for every process, like
+    /// `mnt`. Links that cannot be read are left out of the map.
+    pub namespaces: Vec<String>,
+    /// The optional status fields to read.
+    pub status_fields: StatusFields,
+}
+
+impl Default for ScanOptions
+{
+    fn default() -> ScanOptions
+    {
+        ScanOptions {
+            root_pids: Vec::new(),
+            read_ancestors: false,
+            jobs: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            read_cmdline: false,
+            read_start_time: false,
+            read_session: false,
+            read_nice: false,
+            read_cgroup: false,
+            fd_pressure: None,
+            read_processor: false,
+            read_cpu_time: false,
+            read_children_cpu_time: false,
+            clock_ticks: clock_ticks(),
+            read_wchan: false,
+            read_pss: false,
+            read_listen: false,
+            open_file: None,
+            read_exe: false,
+            namespaces: Vec::new(),
+            status_fields: StatusFields::default(),
+        }
+    }
+}
+
+/// The clock ticks per second of the running kernel.
+fn clock_ticks() -> u64
+{
+    // SAFETY: sysconf has no memory safety requirements.
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    u64::try_from(ticks).ok().filter(|&ticks| ticks > 0).unwrap_or(DEFAULT_CLOCK_TICKS)
+}
+
+/// The processes of the previous scan, for [`scan_source_cached`] to reuse
+/// those whose stat line did not change.
+///
+/// A process is recognized by its pid and start time, so a reused pid is
+/// read again, and is read again as well when its name, state, parent,
+/// process group, session, thread count or resident set size changed. A
+/// change that shows in none of them, like a setuid call, goes unnoticed
+/// until one of them changes.
+#[derive(Clone, Debug)]
+pub struct ScanCache
+{
+    processes: HashMap<u32, CachedProcess>,
+    capacity: usize,
+}
+
+#[derive(Clone, Debug)]
+struct CachedProcess
+{
+    key: StatKey,
+    proc_info: ProcessInfo,
+}
+
+/// The start time and the watched fields of a stat line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct StatKey
+{
+    start_time: u64,
+    watched: Vec<u8>,
+}
+
+impl ScanCache
+{
+    /// An empty cache keeping at most `capacity` processes. Processes past
+    /// it are read in full on every scan.
+    pub fn new(capacity: usize) -> ScanCache
+    {
+        ScanCache { processes: HashMap::new(), capacity }
+    }
+
+    /// The number of processes kept.
+    pub fn len(&self) -> usize
+    {
+        self.processes.len()
+    }
+
+    /// Whether no process is kept.
+    pub fn is_empty(&self) -> bool
+    {
+        self.processes.is_empty()
+    }
+}
+
+impl Default for ScanCache
+{
+    fn default() -> ScanCache
+    {
+        ScanCache::new(DEFAULT_SCAN_CACHE_CAPACITY)
+    }
+}
+
+/// Shares one allocation between the names that are equal, like those of
+/// the thousands of kworker and apache2 processes of a busy host.
+#[derive(Debug, Default)]
+struct NamePool(HashSet<Arc<[u8]>>);
+
+impl NamePool
+{
+    fn intern(&mut self, name: &Arc<[u8]>) -> Arc<[u8]>
+    {
+        match self.0.get(&name[..]) {
+            Some(shared) => Arc::clone(shared),
+            None => {
+                self.0.insert(Arc::clone(name));
+                Arc::clone(name)
+            }
+        }
+    }
+}
+
+/// The result of a scan: the processes sorted by pid, the status files
+/// that could not be parsed and the processes whose status could not be
+/// opened.
+#[derive(Debug)]
+pub struct ScanOutput
+{
+    /// Every process that was read successfully, sorted by pid.
+    pub processes: Vec<ProcessInfo>,
+    /// The entries that were skipped because their status was malformed.
+    pub errors: Vec<ParseError>,
+    /// The entries that were skipped before their status was read, sorted
+    /// by pid.
+    pub skipped: Vec<SkippedProcess>,
+}
+
+/// A process that was listed but whose status could not be opened.
+#[derive(Debug)]
+pub struct SkippedProcess
+{
+    /// Pid of the process.
+    pub pid: u32,
+    /// Why its status could not be opened.
+    pub reason: SkipReason,
+}
+
+/// Why the status of a [`SkippedProcess`] could not be opened.
+#[derive(Debug)]
+pub enum SkipReason
+{
+    /// The process exited after it was listed.
+    Vanished,
+    /// The status is there but could not be opened, like when access is
+    /// denied.
+    Unreadable(io::Error),
+}
+
+/// What [`read_process`] returns: the process or its parse error with its
+/// cache key, or why it was skipped.
+type ReadResult = Result<(Result<ProcessInfo, ParseError>, Option<StatKey>), SkippedProcess>;
+
+/// Scans every process in `/proc`, skipping malformed entries.
+pub fn scan() -> crate::Result<Vec<ProcessInfo>>
+{
+    scan_with(&ScanOptions::default()).map(|output| output.processes)
+}
+
+/// Scans `/proc` with `options`, see [`scan_source`].
+pub fn scan_with(options: &ScanOptions) -> crate::Result<ScanOutput>
+{
+    scan_source(&LinuxProcfs::new("/proc"), options)
+}
+
+/// Scans `source` with up to `options.jobs` worker threads. Processes that vanish
+/// while being read or cannot be opened are returned in [`ScanOutput::skipped`],
+/// malformed ones in [`ScanOutput::errors`]. With `root_pids` only those processes
+/// and their descendants are read, unless the source cannot list children and a full
+/// scan is needed.
+pub fn scan_source<S: ProcSource + Sync>(source: &S, options: &ScanOptions) -> crate::Result<ScanOutput>
+{
+    scan_incrementally(source, options, None)
+}
+
+/// Scans `source` like [`scan_source`], but only reads the stat line of the
+/// processes in `cache` and reuses what was read of them before unless it
+/// changed, see [`ScanCache`]. The cache is then replaced by the processes
+/// of this scan, dropping those that exited. `options` should be the same
+/// on every scan with one cache.
+pub fn scan_source_cached<S: ProcSource + Sync>(
+    source: &S,
+    options: &ScanOptions,
+    cache: &mut ScanCache,
+) -> crate::Result<ScanOutput>
+{
+    scan_incrementally(source, options, Some(cache))
+}
+
+fn scan_incrementally<S: ProcSource + Sync>(
+    source: &S,
+    options: &ScanOptions,
+    cache: Option<&mut ScanCache>,
+) -> crate::Result<ScanOutput>
+{
+    let root_pids = &options.root_pids;
+
+    let descendants = if !root_pids.is_empty() && !root_pids.contains(&0) {
+        find_descendants(source, root_pids)
+    } else {
+        None
+    };
+
+    let subtree_scan = descendants.is_some();
+    let pids: Vec<u32> = match descendants {
+        Some(pids) => pids,
+        None => source.list_pids()
+            .map_err(|why| PsTreeError::ProcUnreadable(source.root().to_path_buf(), why))?,
+    };
+
+    let online_cpus = if options.status_fields.cpus_allowed {
+        source.read_cpuinfo().ok()
+            .map(|cpuinfo| parse_online_cpus(&cpuinfo))
+            .filter(|online_cpus| !online_cpus.is_empty())
+    } else {
+        None
+    };
+    let listeners = if options.read_listen { Some(read_listeners(source)) } else { None };
+    let listeners = listeners.as_ref();
+    let chunk_size = pids.len().div_ceil(options.jobs.max(1)).max(1);
+    let cached = cache.as_deref().map(|cache| &cache.processes);
+
+    let results: Vec<ReadResult> = thread::scope(|scope| {
+        let workers: Vec<_> = pids.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || {
+                chunk.iter()
+                    .map(|&pid| read_process(source, pid, options, listeners, cached))
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    });
+
+    let mut processes: Vec<ProcessInfo> = Vec::new();
+    let mut errors: Vec<ParseError> = Vec::new();
+    let mut skipped: Vec<SkippedProcess> = Vec::new();
+    let mut kept: HashMap<u32, CachedProcess> = HashMap::new();
+    let capacity = cache.as_deref().map_or(0, |cache| cache.capacity);
+    let mut names = NamePool::default();
+
+    for result in results {
+        let (result, key) = match result {
+            Ok(read) => read,
+            Err(skip) => {
+                skipped.push(skip);
+                continue;
+            }
+        };
+        match result {
+            Ok(mut proc_info) => {
+                proc_info.name = names.intern(&proc_info.name);
+                if let Some(key) = key.filter(|_| kept.len() < capacity) {
+                    kept.insert(proc_info.pid, CachedProcess { key, proc_info: proc_info.clone() });
+                }
+                processes.push(proc_info);
+            }
+            Err(why) => errors.push(why),
+        }
+    }
+    if let Some(cache) = cache {
+        cache.processes = kept;
+    }
+    if subtree_scan && options.read_ancestors {
+        read_ancestors(source, options, listeners, &mut processes);
+    }
+    if let Some(online_cpus) = &online_cpus {
+        for proc_info in &mut processes {
+            let unpinned = proc_info.cpus_allowed.as_deref()
+                .and_then(parse_cpu_list)
+                .is_some_and(|allowed| allowed.is_superset(online_cpus));
+            if unpinned {
+                proc_info.cpus_allowed = None;
+            }
+        }
+    }
+
+    if processes.is_empty() {
+        return Err(PsTreeError::NoProcesses(source.root().to_path_buf()));
+    }
+
+    processes.sort_by_key(|proc_info| proc_info.pid);
+    skipped.sort_by_key(|skip| skip.pid);
+    Ok(ScanOutput { processes, errors, skipped })
+}
+
+/// Adds the ancestors of every root of `options` to `processes`, walking
+/// up until a top-level process, one already read or one that cannot be.
+fn read_ancestors<S: ProcSource>(
+    source: &S,
+    options: &ScanOptions,
+    listeners: Option<&HashMap<u64, Listener>>,
+    processes: &mut Vec<ProcessInfo>,
+)
+{
+    let mut known: HashSet<u32> = processes.iter().map(|proc_info| proc_info.pid).collect();
+    for &root_pid in &options.root_pids {
+        let mut ppid = processes.iter()
+            .find(|proc_info| proc_info.pid == root_pid)
+            .map_or(0, |proc_info| proc_info.ppid);
+        while ppid != 0 && known.insert(ppid) {
+            match read_process(source, ppid, options, listeners, None) {
+                Ok((Ok(proc_info), _)) => {
+                    ppid = proc_info.ppid;
+                    processes.push(proc_info);
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+/// Reads one process, or why it was skipped if its status could not be
+/// opened, along with the key to cache it under when `cached` is given. A
+/// process of `cached` under the same key is reused instead of read again.
+fn read_process<S: ProcSource>(
+    source: &S,
+    pid: u32,
+    options: &ScanOptions,
+    listeners: Option<&HashMap<u64, Listener>>,
+    cached: Option<&HashMap<u32, CachedProcess>>,
+) -> ReadResult
+{
+    let reads_stat = options.read_start_time || options.read_session || options.read_nice || options.read_processor
+        || options.read_cpu_time || options.read_children_cpu_time;
+    let stat = if reads_stat || cached.is_some() { source.read_stat(pid).ok() } else { None };
+    let key = cached.and(stat.as_deref()).and_then(stat_key);
+    let reused = key.as_ref()
+        .and_then(|key| cached?.get(&pid).filter(|process| process.key == *key));
+    let result = match reused {
+        Some(process) => Ok(process.proc_info.clone()),
+        None => read_process_files(source, pid, options)?,
+    };
+    let result = result.map(|mut proc_info| {
+        read_volatile_fields(source, &mut proc_info, stat.as_deref(), options, listeners);
+        proc_info
+    });
+    Ok((result, key))
+}
+
+/// The key of a process in a [`ScanCache`], None if `stat` is malformed.
+fn stat_key(stat: &[u8]) -> Option<StatKey>
+{
+    let (name, fields) = split_stat(stat)?;
+    let fields: Vec<&[u8]> = fields.collect();
+    let mut watched = name.to_vec();
+    for field in WATCHED_STAT_FIELDS {
+        watched.push(b' ');
+        watched.extend_from_slice(fields.get(field - 3)?);
+    }
+    Some(StatKey { start_time: parse_start_time(stat)?, watched })
+}
+
+/// Reads the status of a process and the files that rarely change after
+/// it started, or why it was skipped if its status could not be opened.
+fn read_process_files<S: ProcSource>(
+    source: &S,
+    pid: u32,
+    options: &ScanOptions,
+) -> Result<Result<ProcessInfo, ParseError>, SkippedProcess>
+{
+    let status = match source.read_status(pid) {
+        Ok(status) => status,
+        // The kernel answers ESRCH instead for a process caught while exiting.
+        Err(why) if why.kind() == io::ErrorKind::NotFound || why.raw_os_error() == Some(libc::ESRCH) => {
+            return Err(SkippedProcess { pid, reason: SkipReason::Vanished });
+        }
+        Err(why) => return Err(SkippedProcess { pid, reason: SkipReason::Unreadable(why) }),
+    };
+    let fields = StatusFields { fd_size: options.status_fields.fd_size || options.fd_pressure.is_some(), ..options.status_fields };
+    Ok(parse_proc_stat(pid, status, fields).map(|mut proc_info| {
+        if options.read_cmdline {
+            proc_info.cmdline = source.read_cmdline(pid).ok().map(|cmdline| parse_cmdline(&cmdline));
+        }
+        if options.read_cgroup {
+            proc_info.cgroup = source.read_cgroup(pid).ok().and_then(|cgroup| parse_cgroup(&cgroup));
+        }
+        if let Some(percent) = options.fd_pressure {
+            let limit = source.read_limits(pid).ok()
+                .and_then(|limits| parse_soft_limit(&String::from_utf8_lossy(&limits), OPEN_FILES_LIMIT));
+            let pressured = match (proc_info.fd_size, limit) {
+                (Some(fd_size), Some(ResourceLimit::Limited(limit))) => u64::from(fd_size) * 100 >= u64::from(percent) * limit,
+                (Some(_), Some(ResourceLimit::Unlimited)) => percent == 0,
+                _ => false,
+            };
+            if pressured {
+                proc_info.fd_limit = limit;
+            } else {
+                proc_info.fd_size = None;
+            }
+        }
+        proc_info
+    }))
+}
+
+/// Reads the fields of `proc_info` that are read again even when it is
+/// reused from a [`ScanCache`]: those of its `stat` line, its wait channel,
+/// proportional set size, exe link and namespaces, and the sockets and files
+/// it holds open.
+fn read_volatile_fields<S: ProcSource>(
+    source: &S,
+    proc_info: &mut ProcessInfo,
+    stat: Option<&[u8]>,
+    options: &ScanOptions,
+    listeners: Option<&HashMap<u64, Listener>>,
+)
+{
+    let pid = proc_info.pid;
+    if options.read_start_time {
+        proc_info.start_time = stat.and_then(parse_start_time);
+    }
+    if options.read_session {
+        proc_info.session = stat.and_then(parse_session);
+    }
+    if options.read_nice {
+        proc_info.nice = stat.and_then(parse_nice);
+    }
+    if options.read_processor {
+        proc_info.processor = stat.and_then(parse_processor);
+    }
+    let milliseconds = |ticks: u64| ticks.saturating_mul(1000) / options.clock_ticks.max(1);
+    if options.read_cpu_time {
+        proc_info.cpu_time_ms = stat.and_then(parse_cpu_ticks).map(milliseconds);
+    }
+    if options.read_children_cpu_time {
+        proc_info.children_cpu_time_ms = stat.and_then(parse_children_cpu_ticks).map(milliseconds);
+    }
+    if options.read_wchan {
+        proc_info.wchan = source.read_wchan(pid).ok().and_then(|wchan| parse_wchan(&wchan));
+    }
+    if options.read_pss {
+        let smaps = source.read_smaps_rollup(pid).ok();
+        proc_info.pss = smaps.as_deref().and_then(|smaps| parse_smaps_size(smaps, "Pss"));
+        if let Some(smaps) = smaps.filter(|_| options.status_fields.memory_split) {
+            let split = parse_smaps_split(&smaps);
+            proc_info.private_memory = split.map(|(private, _)| private);
+            proc_info.shared_memory = split.map(|(_, shared)| shared);
+        }
+    }
+    if options.read_exe {
+        proc_info.exe = source.read_exe(pid).ok();
+    }
+    if !options.namespaces.is_empty() {
+        proc_info.namespaces = Some(options.namespaces.iter()
+            .filter_map(|ns| {
+                let target = source.read_ns(pid, ns).ok()?;
+                Some((ns.clone(), parse_ns_inode(&target, ns)?))
+            })
+            .collect());
+    }
+    let fd_targets = if listeners.is_some() || options.open_file.is_some() {
+        source.read_fd_targets(pid).ok()
+    } else {
+        None
+    };
+    if let Some(listeners) = listeners {
+        proc_info.listen = fd_targets.as_ref().map(|targets| {
+            let mut listening: Vec<&Listener> = targets.iter()
+                .filter_map(|target| listeners.get(&socket_inode(target)?))
+                .collect();
+            listening.sort();
+            listening.dedup();
+            listening.into_iter().map(Listener::label).collect()
+        });
+    }
+    if let Some(open_file) = &options.open_file {
+        proc_info.holds_file = holds_file(source, pid, fd_targets.as_deref(), open_file);
+    }
+}
+
+/// Whether `pid` has `path` open through `fd_targets` or its memory maps.
+/// None if neither could be read, typically for lack of permission.
+fn holds_file<S: ProcSource>(source: &S, pid: u32, fd_targets: Option<&[PathBuf]>, path: &Path) -> Option<bool>
+{
+    let maps = source.read_maps(pid).ok();
+    if fd_targets.is_none() && maps.is_none() {
+        return None;
+    }
+
+    let is_open = |target: &Path| strip_deleted(target).starts_with(path);
+    Some(
+        fd_targets.unwrap_or_default().iter().any(|target| is_open(target))
+            || maps.as_deref().is_some_and(|maps| parse_maps_paths(maps).any(is_open))
+    )
+}
+
+/// Collects `root_pids` and all of their descendants by following the
+/// children lists. Returns None if a root has no children list to start from.
+fn find_descendants<S: ProcSource>(source: &S, root_pids: &[u32]) -> Option<Vec<u32>>
+{
+    let mut pids: Vec<u32> = Vec::new();
+    let mut visited: HashSet<u32> = HashSet::new();
+
+    for &root_pid in root_pids {
+        if !visited.insert(root_pid) {
+            continue;
+        }
+        let mut queue = source.read_children(root_pid)?;
+        pids.push(root_pid);
+
+        while let Some(pid) = queue.pop() {
+            if visited.insert(pid) {
+                pids.push(pid);
+                queue.extend(source.read_children(pid).unwrap_or_default());
+            }
+        }
+    }
+
+    Some(pids)
+}
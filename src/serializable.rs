@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+#[cfg(feature = "serde")]
+use std::io;
+#[cfg(feature = "serde")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "serde")]
+use crate::config::PsTreePrintConfig;
+use crate::group::ProcessGroup;
+use crate::process::ProcessInfo;
+#[cfg(feature = "serde")]
+use crate::render::NodeItem;
+use crate::tree::{ProcessNode, ProcessTree};
+
+
+/// An owned, nested copy of a tree node, meant for serialization.
+///
+/// The field names are part of the serialized format and stay stable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableNode
+{
+    /// The process of this node.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub process: ProcessInfo,
+    /// Number of descendants removed by depth pruning.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hidden_descendants: usize,
+    /// The child nodes, in drawing order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub children: Vec<SerializableNode>,
+}
+
+/// An owned copy of a [`ProcessGroup`], meant for serialization. Groups
+/// carry a `group` label and no pid, so they cannot be mistaken for
+/// processes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializableGroup
+{
+    /// The label of the group.
+    pub group: String,
+    /// Nested groups.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub subgroups: Vec<SerializableGroup>,
+    /// The process subtrees directly in the group.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub processes: Vec<SerializableNode>,
+}
+
+impl ProcessTree
+{
+    /// Copies the tree into nested nodes, one per root.
+    pub fn to_serializable(&self) -> Vec<SerializableNode>
+    {
+        self.roots.iter().map(|&root| self.serializable_node(root)).collect()
+    }
+
+    /// Copies the groups of a grouped tree, see [`ProcessTree::group_by`].
+    /// Empty if the tree is not grouped; [`to_serializable`](Self::to_serializable)
+    /// still lists the processes without their groups.
+    pub fn to_serializable_groups(&self) -> Vec<SerializableGroup>
+    {
+        self.groups.iter().map(|group| self.serializable_group(group)).collect()
+    }
+
+    fn serializable_group(&self, group: &ProcessGroup) -> SerializableGroup
+    {
+        SerializableGroup {
+            group: group.label.clone(),
+            subgroups: group.subgroups.iter().map(|subgroup| self.serializable_group(subgroup)).collect(),
+            processes: group.roots.iter().map(|&root| self.serializable_node(root)).collect(),
+        }
+    }
+
+    fn serializable_node(&self, index: usize) -> SerializableNode
+    {
+        let node = &self.nodes[index];
+        SerializableNode {
+            process: node.proc_info.clone(),
+            hidden_descendants: node.hidden_descendants,
+            children: node.children.iter().map(|&child| self.serializable_node(child)).collect(),
+        }
+    }
+
+    /// Rebuilds a tree from nodes produced by [`to_serializable`](Self::to_serializable),
+    /// keeping their structure instead of relinking by ppid.
+    pub fn from_serializable(roots: Vec<SerializableNode>) -> ProcessTree
+    {
+        let mut tree = ProcessTree {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            pid_index: HashMap::new(),
+            broken_cycles: Vec::new(),
+            groups: Vec::new(),
+            omitted_subtrees: None,
+            truncated_processes: 0,
+            matches: None,
+        };
+
+        for root in roots {
+            let index = tree.push_serializable(root);
+            tree.roots.push(index);
+        }
+
+        tree
+    }
+
+    fn push_serializable(&mut self, node: SerializableNode) -> usize
+    {
+        let index = self.nodes.len();
+        self.pid_index.insert(node.process.pid, index);
+
+        let mut process_node = ProcessNode::new(node.process);
+        process_node.hidden_descendants = node.hidden_descendants;
+        self.nodes.push(process_node);
+
+        for child in node.children {
+            let child_index = self.push_serializable(child);
+            self.nodes[index].add_child(child_index);
+        }
+
+        index
+    }
+}
+
+/// One line of newline delimited JSON, see [`ProcessTree::write_json_frame`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonFrame<'a>
+{
+    timestamp: f64,
+    tree: &'a [SerializableNode],
+}
+
+/// An entry of the `tree -J` format, see [`ProcessTree::write_tree_json`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct TreeJsonEntry<'a>
+{
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    contents: Vec<TreeJsonEntry<'a>>,
+}
+
+/// The last element of the `tree -J` format, counting the processes with
+/// contents as directories and the others as files.
+#[cfg(feature = "serde")]
+#[derive(Default, serde::Serialize)]
+struct TreeJsonReport
+{
+    #[serde(rename = "type")]
+    kind: &'static str,
+    directories: usize,
+    files: usize,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> TreeJsonEntry<'a>
+{
+    fn new(item: &'a NodeItem, report: &mut TreeJsonReport) -> TreeJsonEntry<'a>
+    {
+        match (item.pid, item.children.is_empty()) {
+            (None, _) => {}
+            (Some(_), true) => report.files += 1,
+            (Some(_), false) => report.directories += 1,
+        }
+        TreeJsonEntry {
+            kind: if item.pid.is_some() { "process" } else { "label" },
+            name: &item.text,
+            pid: item.pid,
+            contents: item.children.iter().map(|child| TreeJsonEntry::new(child, report)).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ProcessTree
+{
+    /// Writes the drawn tree as a JSON array of [`SerializableNode`] on one
+    /// line.
+    pub fn write_json<W: io::Write>(&self, mut out: W) -> io::Result<()>
+    {
+        serde_json::to_writer(&mut out, &self.to_serializable())?;
+        writeln!(out)
+    }
+
+    /// Writes the drawn tree on one line in the JSON format of `tree -J`:
+    /// an array of the roots, each entry an object with `type` "process",
+    /// the label of the drawing as `name`, the `pid` and the children as
+    /// `contents`, left out for leaves, followed by a `report` object.
+    /// Lines that are not processes, like the one of the subtrees cut by
+    /// `--top`, have `type` "label" and no pid. The report counts the
+    /// processes with contents as `directories` and the others as `files`.
+    pub fn write_tree_json<W: io::Write>(&self, mut out: W, config: &PsTreePrintConfig) -> io::Result<()>
+    {
+        let items = self.root_items(config, false);
+        let mut report = TreeJsonReport { kind: "report", ..TreeJsonReport::default() };
+        let entries: Vec<TreeJsonEntry> = items.iter().map(|item| TreeJsonEntry::new(item, &mut report)).collect();
+        out.write_all(b"[")?;
+        for entry in &entries {
+            serde_json::to_writer(&mut out, entry)?;
+            out.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut out, &report)?;
+        writeln!(out, "]")
+    }
+
+    /// Writes the drawn tree as one line of newline delimited JSON, an
+    /// object with the Unix time of `time` in seconds as `timestamp` and the
+    /// array [`write_json`](Self::write_json) writes as `tree`, then flushes
+    /// `out` so readers see the line at once.
+    pub fn write_json_frame<W: io::Write>(&self, mut out: W, time: SystemTime) -> io::Result<()>
+    {
+        let timestamp = time.duration_since(UNIX_EPOCH).map_or(0.0, |since| since.as_millis() as f64 / 1000.0);
+        serde_json::to_writer(&mut out, &JsonFrame { timestamp, tree: &self.to_serializable() })?;
+        writeln!(out)?;
+        out.flush()
+    }
+
+    /// Writes every process of the tree, drawn or not, as a JSON snapshot
+    /// that [`read_snapshot`](Self::read_snapshot) reads back.
+    pub fn write_snapshot<W: io::Write>(&self, out: W) -> io::Result<()>
+    {
+        // Nodes cut from the drawn tree keep their own children, so the
+        // subtrees of the nodes that are nobody's child hold every node once.
+        let mut is_child = vec![false; self.nodes.len()];
+        for node in &self.nodes {
+            for &child in &node.children {
+                is_child[child] = true;
+            }
+        }
+        let roots: Vec<usize> = (0 .. self.nodes.len()).filter(|&index| !is_child[index]).collect();
+        let nodes: Vec<SerializableNode> = roots.iter().map(|&root| self.serializable_node(root)).collect();
+        serde_json::to_writer(out, &nodes).map_err(io::Error::from)
+    }
+
+    /// Reads a snapshot written by [`write_snapshot`](Self::write_snapshot).
+    pub fn read_snapshot<R: io::Read>(input: R) -> io::Result<ProcessTree>
+    {
+        let nodes: Vec<SerializableNode> = serde_json::from_reader(input).map_err(io::Error::from)?;
+        Ok(ProcessTree::from_serializable(nodes))
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::tree::ProcessTree;
+
+
+/// Largest signal number Linux accepts, the last real-time signal.
+const MAX_SIGNAL: i32 = 64;
+
+const SIGNAL_NAMES: [(&str, i32); 31] = [
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("STKFLT", libc::SIGSTKFLT),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("URG", libc::SIGURG),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH),
+    ("IO", libc::SIGIO),
+    ("PWR", libc::SIGPWR),
+    ("SYS", libc::SIGSYS),
+];
+
+/// A signal that can be sent to a process, parsed from a name like `TERM`
+/// or `SIGTERM` in any case, or from its number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signal(i32);
+
+impl Signal
+{
+    /// The signal number, e.g. 15 for TERM.
+    pub fn number(self) -> i32
+    {
+        self.0
+    }
+
+    /// The name without the SIG prefix, if the signal has one.
+    pub fn name(self) -> Option<&'static str>
+    {
+        SIGNAL_NAMES.iter().find(|&&(_, number)| number == self.0).map(|&(name, _)| name)
+    }
+
+    /// Sends the signal to `pid`. Signal 0 only checks that the process
+    /// exists and may be signaled.
+    pub fn send(self, pid: u32) -> io::Result<()>
+    {
+        let pid = libc::pid_t::try_from(pid).map_err(|_| io::Error::from_raw_os_error(libc::ESRCH))?;
+        // SAFETY: kill has no memory safety requirements.
+        if unsafe { libc::kill(pid, self.0) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+/// Delivers signals to processes. [`Kill`] sends them for real, tests can
+/// record them instead.
+pub trait SignalSender
+{
+    /// Sends `signal` to `pid`.
+    fn send(&mut self, pid: u32, signal: Signal) -> io::Result<()>;
+}
+
+/// Sends signals with kill(2).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Kill;
+
+impl SignalSender for Kill
+{
+    fn send(&mut self, pid: u32, signal: Signal) -> io::Result<()>
+    {
+        signal.send(pid)
+    }
+}
+
+/// Sends `signal` to every pid of `pids` in order, going on after
+/// failures. Returns the pids that could not be signaled with the error.
+pub fn send_all<S: SignalSender>(sender: &mut S, signal: Signal, pids: &[u32]) -> Vec<(u32, io::Error)>
+{
+    pids.iter()
+        .filter_map(|&pid| sender.send(pid, signal).err().map(|why| (pid, why)))
+        .collect()
+}
+
+/// Stopping or continuing every process of a tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Freeze
+{
+    /// Stop the processes with SIGSTOP.
+    Pause,
+    /// Continue the processes with SIGCONT.
+    Resume,
+}
+
+impl Freeze
+{
+    /// SIGSTOP or SIGCONT.
+    pub fn signal(self) -> Signal
+    {
+        match self {
+            Freeze::Pause => Signal(libc::SIGSTOP),
+            Freeze::Resume => Signal(libc::SIGCONT),
+        }
+    }
+
+    /// The node indices of the tree in the order they are signaled: children
+    /// before their parents when pausing, so no running parent sees a
+    /// stopped child and starts another, parents first when resuming.
+    pub fn order(self, tree: &ProcessTree) -> Vec<usize>
+    {
+        match self {
+            Freeze::Pause => tree.iter_post_order().map(|(_, index)| index).collect(),
+            Freeze::Resume => tree.iter_dfs().map(|(_, index)| index).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Freeze
+{
+    /// Formats as the verb, `pause` or `resume`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str(match self {
+            Freeze::Pause => "pause",
+            Freeze::Resume => "resume",
+        })
+    }
+}
+
+impl FromStr for Signal
+{
+    type Err = String;
+
+    fn from_str(signal: &str) -> Result<Signal, String>
+    {
+        if let Ok(number) = signal.parse::<i32>() {
+            return match number {
+                0 ..= MAX_SIGNAL => Ok(Signal(number)),
+                _ => Err(format!("invalid signal number {} (expected 0 to {})", number, MAX_SIGNAL)),
+            };
+        }
+
+        let upper = signal.to_ascii_uppercase();
+        let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+        SIGNAL_NAMES.iter()
+            .find(|&&(known, _)| known == name)
+            .map(|&(_, number)| Signal(number))
+            .ok_or_else(|| format!("unknown signal '{}'", signal))
+    }
+}
+
+impl fmt::Display for Signal
+{
+    /// Formats as the name without the SIG prefix, or as the number for
+    /// signals without a name.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
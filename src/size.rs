@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+
+/// Suffixes of the powers of 1024 after plain bytes.
+const SIZE_UNITS: [char; 5] = ['K', 'M', 'G', 'T', 'P'];
+
+/// Formats a number of bytes in the largest unit that keeps it at 1 or
+/// more, with one decimal below 10: `512B`, `1.1G`, `245M`.
+pub fn format_size(bytes: u64) -> String
+{
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Move up before the rounding below would print 1024 of a unit.
+    while size >= 1023.5 && unit + 1 < SIZE_UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if size < 9.95 {
+        format!("{:.1}{}", size, SIZE_UNITS[unit])
+    } else {
+        format!("{:.0}{}", size, SIZE_UNITS[unit])
+    }
+}
+
+/// How sizes are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeUnit
+{
+    /// The largest unit that keeps the size at 1 or more, see
+    /// [`format_size`].
+    Human,
+    /// Whole kilobytes without a unit, as procfs reports them: `1153433`.
+    Raw,
+    /// Kilobytes, rounded: `1153433K`.
+    Kib,
+    /// Megabytes with one decimal: `1126.4M`.
+    Mib,
+}
+
+impl SizeUnit
+{
+    /// Formats a number of bytes in this unit.
+    pub fn format(self, bytes: u64) -> String
+    {
+        match self {
+            SizeUnit::Human => format_size(bytes),
+            SizeUnit::Raw => (bytes / 1024).to_string(),
+            SizeUnit::Kib => format!("{}K", bytes / 1024 + u64::from(bytes % 1024 >= 512)),
+            SizeUnit::Mib => format!("{:.1}M", bytes as f64 / (1024.0 * 1024.0)),
+        }
+    }
+}
+
+impl FromStr for SizeUnit
+{
+    type Err = String;
+
+    fn from_str(unit: &str) -> Result<SizeUnit, String>
+    {
+        match unit {
+            "human" => Ok(SizeUnit::Human),
+            "raw"   => Ok(SizeUnit::Raw),
+            "kib"   => Ok(SizeUnit::Kib),
+            "mib"   => Ok(SizeUnit::Mib),
+            _ => Err(format!("invalid unit '{}' (expected human, raw, kib or mib)", unit)),
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::process::ProcessInfo;
+
+
+/// Where process information is read from.
+pub trait ProcSource
+{
+    /// Reader over a status file.
+    type Status: BufRead;
+
+    /// The location shown in error messages.
+    fn root(&self) -> &Path;
+
+    /// The pids of every process the source knows about.
+    fn list_pids(&self) -> io::Result<Vec<u32>>;
+
+    /// Opens /proc/[pid]/status.
+    fn read_status(&self, pid: u32) -> io::Result<Self::Status>;
+
+    /// Reads /proc/[pid]/stat.
+    fn read_stat(&self, pid: u32) -> io::Result<Vec<u8>>;
+
+    /// Reads /proc/[pid]/cmdline.
+    fn read_cmdline(&self, pid: u32) -> io::Result<Vec<u8>>;
+
+    /// Reads /proc/[pid]/cgroup.
+    fn read_cgroup(&self, pid: u32) -> io::Result<Vec<u8>>;
+
+    /// The targets of the /proc/[pid]/fd symlinks, like `/var/log/syslog`
+    /// or `socket:[12345]`. Descriptors closed while listing are skipped.
+    fn read_fd_targets(&self, pid: u32) -> io::Result<Vec<PathBuf>>;
+
+    /// The target of the /proc/[pid]/exe symlink, like `/usr/bin/bash`.
+    fn read_exe(&self, pid: u32) -> io::Result<PathBuf>;
+
+    /// The target of the /proc/[pid]/ns/`ns` symlink, like `mnt:[4026531841]`.
+    fn read_ns(&self, pid: u32, ns: &str) -> io::Result<PathBuf>;
+
+    /// Reads /proc/[pid]/limits.
+    fn read_limits(&self, pid: u32) -> io::Result<Vec<u8>>;
+
+    /// Reads /proc/[pid]/wchan.
+    fn read_wchan(&self, pid: u32) -> io::Result<Vec<u8>>;
+
+    /// Reads /proc/[pid]/maps.
+    fn read_maps(&self, pid: u32) -> io::Result<Vec<u8>>;
+
+    /// Reads /proc/[pid]/smaps_rollup.
+    fn read_smaps_rollup(&self, pid: u32) -> io::Result<Vec<u8>>;
+
+    /// Reads /proc/cpuinfo.
+    fn read_cpuinfo(&self) -> io::Result<Vec<u8>>;
+
+    /// Reads /proc/net/`table`, e.g. "tcp".
+    fn read_net(&self, table: &str) -> io::Result<Vec<u8>>;
+
+    /// The pids listed in /proc/[pid]/task/*/children, or None when the
+    /// process is gone or the source cannot list children.
+    fn read_children(&self, _pid: u32) -> Option<Vec<u32>>
+    {
+        None
+    }
+}
+
+/// Parses a /proc entry name as a pid without going through a String.
+pub(crate) fn parse_pid_dir_name(name: &OsStr) -> Option<u32>
+{
+    let digits = name.as_bytes();
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    digits.iter().try_fold(0u32, |pid, &digit| pid.checked_mul(10)?.checked_add(u32::from(digit - b'0')))
+}
+
+pub(crate) fn parse_children(children: &[u8]) -> impl Iterator<Item = u32> + '_
+{
+    children.split(u8::is_ascii_whitespace)
+        .filter_map(|pid| parse_pid_dir_name(OsStr::from_bytes(pid)))
+}
+
+/// Name, Pid and PPid sit in the first lines of status, so a small buffer
+/// usually gets them with a single read.
+const STATUS_BUFFER_SIZE: usize = 256;
+
+/// A procfs mount, `/proc` on a normal system.
+#[derive(Clone, Debug)]
+pub struct LinuxProcfs
+{
+    /// The mount point.
+    pub root: PathBuf,
+}
+
+impl LinuxProcfs
+{
+    /// A source reading the procfs mounted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> LinuxProcfs
+    {
+        LinuxProcfs { root: root.into() }
+    }
+
+    fn pid_path(&self, pid: u32) -> PathBuf
+    {
+        self.root.join(pid.to_string())
+    }
+}
+
+impl ProcSource for LinuxProcfs
+{
+    type Status = BufReader<File>;
+
+    fn root(&self) -> &Path
+    {
+        &self.root
+    }
+
+    fn list_pids(&self) -> io::Result<Vec<u32>>
+    {
+        Ok(fs::read_dir(&self.root)?
+            .filter_map(Result::ok)
+            .filter_map(|entry| parse_pid_dir_name(&entry.file_name()))
+            .collect())
+    }
+
+    fn read_status(&self, pid: u32) -> io::Result<BufReader<File>>
+    {
+        let status = File::open(self.pid_path(pid).join("status"))?;
+        Ok(BufReader::with_capacity(STATUS_BUFFER_SIZE, status))
+    }
+
+    fn read_stat(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.pid_path(pid).join("stat"))
+    }
+
+    fn read_cmdline(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.pid_path(pid).join("cmdline"))
+    }
+
+    fn read_cgroup(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.pid_path(pid).join("cgroup"))
+    }
+
+    fn read_fd_targets(&self, pid: u32) -> io::Result<Vec<PathBuf>>
+    {
+        Ok(fs::read_dir(self.pid_path(pid).join("fd"))?
+            .filter_map(Result::ok)
+            .filter_map(|fd| fs::read_link(fd.path()).ok())
+            .collect())
+    }
+
+    fn read_exe(&self, pid: u32) -> io::Result<PathBuf>
+    {
+        fs::read_link(self.pid_path(pid).join("exe"))
+    }
+
+    fn read_ns(&self, pid: u32, ns: &str) -> io::Result<PathBuf>
+    {
+        fs::read_link(self.pid_path(pid).join("ns").join(ns))
+    }
+
+    fn read_limits(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.pid_path(pid).join("limits"))
+    }
+
+    fn read_wchan(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.pid_path(pid).join("wchan"))
+    }
+
+    fn read_maps(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.pid_path(pid).join("maps"))
+    }
+
+    fn read_smaps_rollup(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.pid_path(pid).join("smaps_rollup"))
+    }
+
+    fn read_cpuinfo(&self) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.root.join("cpuinfo"))
+    }
+
+    fn read_net(&self, table: &str) -> io::Result<Vec<u8>>
+    {
+        fs::read(self.root.join("net").join(table))
+    }
+
+    fn read_children(&self, pid: u32) -> Option<Vec<u32>>
+    {
+        let task_dir = fs::read_dir(self.pid_path(pid).join("task")).ok()?;
+        let mut children: Option<Vec<u32>> = None;
+
+        for task in task_dir.filter_map(Result::ok) {
+            if let Ok(task_children) = fs::read(task.path().join("children")) {
+                children.get_or_insert_with(Vec::new).extend(parse_children(&task_children));
+            }
+        }
+
+        children
+    }
+}
+
+/// An in-memory source for tests. Files are stored per pid under their
+/// name in /proc/[pid]; a "children" file stands for all the
+/// task/*/children files of the process, an "fd" file lists the fd
+/// targets one per line, an "exe" file holds the exe link target and an
+/// "ns/mnt" file the target of the mnt namespace link.
+#[derive(Clone, Debug, Default)]
+pub struct MockSource
+{
+    root: PathBuf,
+    files: BTreeMap<u32, HashMap<String, Vec<u8>>>,
+    net: HashMap<String, Vec<u8>>,
+    cpuinfo: Option<Vec<u8>>,
+}
+
+impl MockSource
+{
+    /// An empty source.
+    pub fn new() -> MockSource
+    {
+        MockSource { root: PathBuf::from("(mock)"), ..MockSource::default() }
+    }
+
+    /// Stores `contents` as /proc/[pid]/`file`.
+    pub fn insert(&mut self, pid: u32, file: &str, contents: impl Into<Vec<u8>>)
+    {
+        self.files.entry(pid).or_default().insert(file.to_string(), contents.into());
+    }
+
+    /// Stores `contents` as /proc/net/`table`.
+    pub fn insert_net(&mut self, table: &str, contents: impl Into<Vec<u8>>)
+    {
+        self.net.insert(table.to_string(), contents.into());
+    }
+
+    /// Stores `contents` as /proc/cpuinfo.
+    pub fn set_cpuinfo(&mut self, contents: impl Into<Vec<u8>>)
+    {
+        self.cpuinfo = Some(contents.into());
+    }
+
+    /// Stores a minimal status file for `proc_info`.
+    pub fn add_process(&mut self, proc_info: &ProcessInfo)
+    {
+        let mut status = b"Name:\t".to_vec();
+        for &byte in proc_info.name.iter() {
+            match byte {
+                b'\\' => status.extend_from_slice(b"\\\\"),
+                b'\n' => status.extend_from_slice(b"\\n"),
+                _ => status.push(byte),
+            }
+        }
+        status.push(b'\n');
+        if let Some(umask) = proc_info.umask {
+            status.extend_from_slice(format!("Umask:\t{:04o}\n", umask).as_bytes());
+        }
+        if let Some(state) = proc_info.state {
+            status.extend_from_slice(format!("State:\t{}\n", state).as_bytes());
+        }
+        status.extend_from_slice(format!("Pid:\t{}\nPPid:\t{}\n", proc_info.pid, proc_info.ppid).as_bytes());
+        if let Some(tracer_pid) = proc_info.tracer_pid {
+            status.extend_from_slice(format!("TracerPid:\t{}\n", tracer_pid).as_bytes());
+        }
+        if let Some(uid) = proc_info.uid {
+            status.extend_from_slice(format!("Uid:\t{0}\t{0}\t{0}\t{0}\n", uid).as_bytes());
+        }
+        if let Some(threads) = proc_info.threads {
+            status.extend_from_slice(format!("Threads:\t{}\n", threads).as_bytes());
+        }
+        for (field, size) in [("VmPeak", proc_info.peak_virtual), ("VmHWM", proc_info.peak_rss), ("VmRSS", proc_info.rss)] {
+            if let Some(size) = size {
+                status.extend_from_slice(format!("{}:\t{:>8} kB\n", field, size / 1024).as_bytes());
+            }
+        }
+        if let Some(fd_size) = proc_info.fd_size {
+            status.extend_from_slice(format!("FDSize:\t{}\n", fd_size).as_bytes());
+        }
+        if let Some(groups) = &proc_info.groups {
+            let groups: Vec<String> = groups.iter().map(u32::to_string).collect();
+            status.extend_from_slice(format!("Groups:\t{}\n", groups.join(" ")).as_bytes());
+        }
+        if let Some(no_new_privs) = proc_info.no_new_privs {
+            status.extend_from_slice(format!("NoNewPrivs:\t{}\n", u8::from(no_new_privs)).as_bytes());
+        }
+        if let Some(seccomp) = proc_info.seccomp {
+            status.extend_from_slice(format!("Seccomp:\t{}\n", seccomp).as_bytes());
+        }
+        if let Some(seccomp_filters) = proc_info.seccomp_filters {
+            status.extend_from_slice(format!("Seccomp_filters:\t{}\n", seccomp_filters).as_bytes());
+        }
+        if let Some(cpus_allowed) = &proc_info.cpus_allowed {
+            status.extend_from_slice(format!("Cpus_allowed_list:\t{}\n", cpus_allowed).as_bytes());
+        }
+        self.insert(proc_info.pid, "status", status);
+    }
+
+    /// Removes every file of `pid`.
+    pub fn remove(&mut self, pid: u32)
+    {
+        self.files.remove(&pid);
+    }
+
+    fn read(&self, pid: u32, file: &str) -> io::Result<&[u8]>
+    {
+        self.files.get(&pid)
+            .and_then(|files| files.get(file))
+            .map(Vec::as_slice)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+}
+
+impl ProcSource for MockSource
+{
+    type Status = io::Cursor<Vec<u8>>;
+
+    fn root(&self) -> &Path
+    {
+        &self.root
+    }
+
+    fn list_pids(&self) -> io::Result<Vec<u32>>
+    {
+        Ok(self.files.keys().copied().collect())
+    }
+
+    fn read_status(&self, pid: u32) -> io::Result<io::Cursor<Vec<u8>>>
+    {
+        self.read(pid, "status").map(|status| io::Cursor::new(status.to_vec()))
+    }
+
+    fn read_stat(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "stat").map(<[u8]>::to_vec)
+    }
+
+    fn read_cmdline(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "cmdline").map(<[u8]>::to_vec)
+    }
+
+    fn read_cgroup(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "cgroup").map(<[u8]>::to_vec)
+    }
+
+    fn read_fd_targets(&self, pid: u32) -> io::Result<Vec<PathBuf>>
+    {
+        let fds = self.read(pid, "fd")?;
+        Ok(fds.split(|&byte| byte == b'\n')
+            .filter(|target| !target.is_empty())
+            .map(|target| PathBuf::from(OsStr::from_bytes(target)))
+            .collect())
+    }
+
+    fn read_exe(&self, pid: u32) -> io::Result<PathBuf>
+    {
+        self.read(pid, "exe").map(|target| PathBuf::from(OsStr::from_bytes(target)))
+    }
+
+    fn read_ns(&self, pid: u32, ns: &str) -> io::Result<PathBuf>
+    {
+        self.read(pid, &format!("ns/{}", ns)).map(|target| PathBuf::from(OsStr::from_bytes(target)))
+    }
+
+    fn read_limits(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "limits").map(<[u8]>::to_vec)
+    }
+
+    fn read_wchan(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "wchan").map(<[u8]>::to_vec)
+    }
+
+    fn read_maps(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "maps").map(<[u8]>::to_vec)
+    }
+
+    fn read_smaps_rollup(&self, pid: u32) -> io::Result<Vec<u8>>
+    {
+        self.read(pid, "smaps_rollup").map(<[u8]>::to_vec)
+    }
+
+    fn read_cpuinfo(&self) -> io::Result<Vec<u8>>
+    {
+        self.cpuinfo.clone().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn read_net(&self, table: &str) -> io::Result<Vec<u8>>
+    {
+        self.net.get(table).cloned().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn read_children(&self, pid: u32) -> Option<Vec<u32>>
+    {
+        self.read(pid, "children").ok().map(|children| parse_children(children).collect())
+    }
+}
@@ -0,0 +1,69 @@
+use std::str::FromStr;
+
+use ptree::Style;
+
+
+/// The attribute names accepted by [`TextStyle::from_str`].
+const ATTRIBUTES: [&str; 6] = ["bold", "dimmed", "italic", "underline", "blink", "reverse"];
+
+/// Text attributes drawn on top of the colors of a style.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextStyle
+{
+    /// Bold, or bright on some terminals.
+    pub bold: bool,
+    /// Faint.
+    pub dimmed: bool,
+    /// Italic.
+    pub italic: bool,
+    /// Underlined.
+    pub underline: bool,
+    /// Blinking.
+    pub blink: bool,
+    /// Foreground and background swapped.
+    pub reverse: bool,
+}
+
+impl TextStyle
+{
+    /// Turns the attributes of `self` on in `style`, keeping its colors and
+    /// the attributes it already has.
+    pub fn apply(&self, style: &mut Style)
+    {
+        style.bold |= self.bold;
+        style.dimmed |= self.dimmed;
+        style.italic |= self.italic;
+        style.underline |= self.underline;
+        style.blink |= self.blink;
+        style.reverse |= self.reverse;
+    }
+}
+
+impl FromStr for TextStyle
+{
+    type Err = String;
+
+    /// Parses a comma separated list of attributes like "bold,underline", in
+    /// any case.
+    fn from_str(attributes: &str) -> Result<TextStyle, String>
+    {
+        let mut style = TextStyle::default();
+        for attribute in attributes.split(',') {
+            let flag = match attribute.trim().to_lowercase().as_str() {
+                "bold" => &mut style.bold,
+                "dimmed" => &mut style.dimmed,
+                "italic" => &mut style.italic,
+                "underline" => &mut style.underline,
+                "blink" => &mut style.blink,
+                "reverse" => &mut style.reverse,
+                _ => return Err(format!(
+                    "invalid style attribute '{}' (expected a comma separated list of {})",
+                    attribute.trim(),
+                    ATTRIBUTES.join(", "),
+                )),
+            };
+            *flag = true;
+        }
+        Ok(style)
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::tree::ProcessTree;
+
+
+/// Counts over the processes of a tree that are drawn, see
+/// [`ProcessTree::summary`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeSummary
+{
+    /// Number of processes drawn.
+    pub processes: usize,
+    /// Number of processes in the tree, drawn or not.
+    pub total_processes: usize,
+    /// Sum of the thread counts of the drawn processes, if they were read.
+    pub threads: Option<u64>,
+    /// Number of drawn zombie processes, if the states were read.
+    pub zombies: Option<usize>,
+    /// Depth of the deepest drawn process, roots have depth 0.
+    pub max_depth: u32,
+}
+
+/// Facts about the shape of the drawn part of a tree, see
+/// [`ProcessTree::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeStats
+{
+    /// Number of processes drawn.
+    pub processes: usize,
+    /// Sum of the thread counts of the drawn processes, if they were read.
+    pub threads: Option<u64>,
+    /// Depth of the deepest drawn process, roots have depth 0.
+    pub max_depth: u32,
+    /// The pid with the most drawn children and their number, the lowest
+    /// pid on a tie. None when no process has children.
+    pub widest: Option<(u32, usize)>,
+    /// Number of drawn processes without drawn children.
+    pub leaves: usize,
+    /// Number of distinct uids among the drawn processes.
+    pub users: usize,
+    /// The pid below the roots with the largest subtree and its size, the
+    /// process itself included. The lowest pid wins a tie.
+    pub largest_subtree: Option<(u32, usize)>,
+}
+
+impl ProcessTree
+{
+    /// Counts the processes reachable from the roots, which are the ones
+    /// drawn once the roots are selected and the config is applied.
+    pub fn summary(&self) -> TreeSummary
+    {
+        let mut summary = TreeSummary { total_processes: self.nodes.len(), ..TreeSummary::default() };
+
+        for (depth, index) in self.iter_dfs() {
+            let proc_info = &self.nodes[index].proc_info;
+            summary.processes += 1;
+            summary.max_depth = summary.max_depth.max(depth);
+            if let Some(threads) = proc_info.threads {
+                *summary.threads.get_or_insert(0) += u64::from(threads);
+            }
+            if proc_info.state.is_some() {
+                *summary.zombies.get_or_insert(0) += usize::from(proc_info.is_zombie());
+            }
+        }
+
+        summary
+    }
+
+    /// Measures the processes reachable from the roots in one walk, which
+    /// visits every subtree before its root so its size is known there.
+    pub fn stats(&self) -> TreeStats
+    {
+        let mut stats = TreeStats::default();
+        let mut subtree_sizes = vec![0usize; self.nodes.len()];
+        let mut uids: HashSet<u32> = HashSet::new();
+
+        for (depth, index) in self.iter_post_order() {
+            let node = &self.nodes[index];
+            let pid = node.proc_info.pid;
+            stats.processes += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            if let Some(threads) = node.proc_info.threads {
+                *stats.threads.get_or_insert(0) += u64::from(threads);
+            }
+            uids.extend(node.proc_info.uid);
+
+            let children = node.children.len();
+            if children == 0 {
+                stats.leaves += 1;
+            } else if stats.widest.is_none_or(|widest| beats(widest, (pid, children))) {
+                stats.widest = Some((pid, children));
+            }
+            let size = 1 + node.children.iter().map(|&child| subtree_sizes[child]).sum::<usize>();
+            subtree_sizes[index] = size;
+            if depth > 0 && stats.largest_subtree.is_none_or(|largest| beats(largest, (pid, size))) {
+                stats.largest_subtree = Some((pid, size));
+            }
+        }
+        stats.users = uids.len();
+
+        stats
+    }
+}
+
+/// Whether the `(pid, count)` pair `challenger` replaces `best`: a larger
+/// count or, on a tie, a lower pid.
+fn beats(best: (u32, usize), challenger: (u32, usize)) -> bool
+{
+    (challenger.1, std::cmp::Reverse(challenger.0)) > (best.1, std::cmp::Reverse(best.0))
+}
+
+fn plural(count: impl Into<u64>, singular: &str, plural: &str) -> String
+{
+    let count = count.into();
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+impl fmt::Display for TreeSummary
+{
+    /// Formats as `52 processes shown (of 214), 300 threads, 3 zombies, max depth 9`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", plural(self.processes as u64, "process", "processes"))?;
+        if self.processes != self.total_processes {
+            write!(f, " shown (of {})", self.total_processes)?;
+        }
+        if let Some(threads) = self.threads {
+            write!(f, ", {}", plural(threads, "thread", "threads"))?;
+        }
+        if let Some(zombies) = self.zombies {
+            write!(f, ", {}", plural(zombies as u64, "zombie", "zombies"))?;
+        }
+        write!(f, ", max depth {}", self.max_depth)
+    }
+}
+
+impl fmt::Display for TreeStats
+{
+    /// Formats one `name: value` line per fact, like `widest: 30 with 3 children`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        writeln!(f, "processes: {}", self.processes)?;
+        if let Some(threads) = self.threads {
+            writeln!(f, "threads: {}", threads)?;
+        }
+        writeln!(f, "max depth: {}", self.max_depth)?;
+        if let Some((pid, children)) = self.widest {
+            writeln!(f, "widest: {} with {}", pid, plural(children as u64, "child", "children"))?;
+        }
+        writeln!(f, "leaves: {}", self.leaves)?;
+        writeln!(f, "users: {}", self.users)?;
+        if let Some((pid, size)) = self.largest_subtree {
+            writeln!(f, "largest subtree: {} with {}", pid, plural(size as u64, "process", "processes"))?;
+        }
+        Ok(())
+    }
+}
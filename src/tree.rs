@@ -0,0 +1,624 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::collation::Collation;
+use crate::config::SortKey;
+use crate::diff::Change;
+use crate::error::PsTreeError;
+use crate::group::{group_roots_in_order, ProcessGroup};
+use crate::process::ProcessInfo;
+
+
+/// The scanned processes, stored in an arena. Nodes refer to their children
+/// by index into `nodes`.
+pub struct ProcessTree
+{
+    /// Every process of the tree, in the order of the list it was built from.
+    pub nodes: Vec<ProcessNode>,
+    /// Indices of the nodes drawn at the top of the tree.
+    pub roots: Vec<usize>,
+    /// Maps a pid to its index in `nodes`.
+    pub pid_index: HashMap<u32, usize>,
+    /// The parent cycles that were cut while linking, as lists of pids.
+    pub broken_cycles: Vec<Vec<u32>>,
+    /// Synthetic groups drawn above the roots, see [`ProcessTree::group_by`].
+    /// Empty unless the tree was grouped.
+    pub groups: Vec<ProcessGroup>,
+    /// The subtrees cut by [`ProcessTree::keep_top_subtrees`], if any.
+    pub omitted_subtrees: Option<OmittedSubtrees>,
+    /// Number of processes cut by [`ProcessTree::truncate`].
+    pub truncated_processes: usize,
+    /// Which nodes passed every filter of [`ProcessTree::retain_matches`],
+    /// by index. None until a filter is applied. The ancestors kept only to
+    /// draw the matches are not marked.
+    pub matches: Option<Vec<bool>>,
+}
+
+/// A process and the indices of its children.
+pub struct ProcessNode
+{
+    /// The process this node stands for.
+    pub proc_info: ProcessInfo,
+    /// Indices of the child nodes in [`ProcessTree::nodes`].
+    pub children: Vec<usize>,
+    /// Number of descendants removed by [`ProcessTree::prune_depth`].
+    pub hidden_descendants: usize,
+    /// How the process differs from an earlier scan, set by
+    /// [`ProcessTree::mark_changes`] and
+    /// [`TreeDiff::gone_tree`](crate::TreeDiff::gone_tree).
+    pub change: Option<Change>,
+}
+
+/// The smaller subtrees left out by [`ProcessTree::keep_top_subtrees`],
+/// drawn as one line after the kept ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OmittedSubtrees
+{
+    /// The node whose children were cut, None when the roots were.
+    pub parent: Option<usize>,
+    /// Number of subtrees cut.
+    pub subtrees: usize,
+    /// Number of processes in the cut subtrees, their roots included.
+    pub processes: usize,
+}
+
+impl ProcessTree
+{
+    /// Links every process to its parent. Processes with ppid 0, processes
+    /// whose parent is not in the list and processes whose parent link would
+    /// close a cycle become roots. Of a pid listed twice, only the first
+    /// entry is kept.
+    pub fn from_process_list(proc_info_vec: Vec<ProcessInfo>) -> ProcessTree
+    {
+        let mut seen: HashSet<u32> = HashSet::with_capacity(proc_info_vec.len());
+        let proc_info_vec: Vec<ProcessInfo> = proc_info_vec.into_iter()
+            .filter(|proc_info| seen.insert(proc_info.pid))
+            .collect();
+
+        let pid_index: HashMap<u32, usize> = proc_info_vec.iter().enumerate()
+            .map(|(index, proc_info)| (proc_info.pid, index))
+            .collect();
+
+        let mut parent_map: HashMap<u32, u32> = proc_info_vec.iter()
+            .filter(|proc_info| !proc_info.is_top_level() && pid_index.contains_key(&proc_info.ppid))
+            .map(|proc_info| (proc_info.pid, proc_info.ppid))
+            .collect();
+
+        let broken_cycles = break_parent_cycles(&proc_info_vec, &mut parent_map);
+
+        let parents: Vec<Option<usize>> = proc_info_vec.iter()
+            .map(|proc_info| parent_map.get(&proc_info.pid).map(|ppid| pid_index[ppid]))
+            .collect();
+
+        let mut nodes: Vec<ProcessNode> = proc_info_vec.into_iter().map(ProcessNode::new).collect();
+        let mut roots: Vec<usize> = Vec::new();
+
+        for (index, parent) in parents.into_iter().enumerate() {
+            match parent {
+                Some(parent) => nodes[parent].add_child(index),
+                None => roots.push(index),
+            }
+        }
+
+        ProcessTree {
+            nodes,
+            roots,
+            pid_index,
+            broken_cycles,
+            groups: Vec::new(),
+            omitted_subtrees: None,
+            truncated_processes: 0,
+            matches: None,
+        }
+    }
+
+    /// The pids of the processes that are roots because their parent is not
+    /// in the tree, like when it exited or could not be read.
+    pub fn orphans(&self) -> Vec<u32>
+    {
+        self.nodes.iter()
+            .map(|node| &node.proc_info)
+            .filter(|proc_info| !proc_info.is_top_level() && !self.pid_index.contains_key(&proc_info.ppid))
+            .map(|proc_info| proc_info.pid)
+            .collect()
+    }
+
+    /// Makes the processes `pids` the roots of the tree, in the given order.
+    /// Pid 0 stands for every current root. Repeated pids are drawn once, and
+    /// pids inside the subtree of another requested pid are skipped and
+    /// returned. Fails with every pid that is not in the tree.
+    pub fn set_roots(&mut self, pids: &[u32]) -> crate::Result<Vec<u32>>
+    {
+        let mut seen: HashSet<u32> = HashSet::new();
+        let missing: Vec<u32> = pids.iter().copied()
+            .filter(|pid| *pid != 0 && !self.pid_index.contains_key(pid) && seen.insert(*pid))
+            .collect();
+        if !missing.is_empty() {
+            return Err(PsTreeError::ProcessNotFound(missing));
+        }
+
+        let mut indices: Vec<usize> = Vec::new();
+        for &pid in pids {
+            match pid {
+                0 => indices.extend(self.roots.iter().copied()),
+                _ => indices.push(self.pid_index[&pid]),
+            }
+        }
+
+        let skipped = self.set_root_nodes(indices);
+        Ok(skipped.into_iter().map(|index| self.nodes[index].proc_info.pid).collect())
+    }
+
+    /// Indices of the processes matching `predicate`, in pid order for a
+    /// tree built from a scan.
+    pub fn find_all<P: FnMut(&ProcessInfo) -> bool>(&self, mut predicate: P) -> Vec<usize>
+    {
+        (0 .. self.nodes.len()).filter(|&index| predicate(&self.nodes[index].proc_info)).collect()
+    }
+
+    /// Makes the nodes at `indices` the roots of the tree. Nodes that lie
+    /// below another of the given nodes are not made roots, they are drawn
+    /// in that node's subtree; their indices are returned.
+    pub fn set_root_nodes(&mut self, indices: Vec<usize>) -> Vec<usize>
+    {
+        let requested: HashSet<usize> = indices.iter().copied().collect();
+        let nested: HashSet<usize> = indices.iter()
+            .flat_map(|&index| DfsIter::new(self, &[index]).skip(1))
+            .map(|(_, index)| index)
+            .filter(|index| requested.contains(index))
+            .collect();
+
+        let mut seen: HashSet<usize> = HashSet::new();
+        let (roots, skipped): (Vec<usize>, Vec<usize>) = indices.into_iter()
+            .filter(|&index| seen.insert(index))
+            .partition(|index| !nested.contains(index));
+
+        self.roots = roots;
+        skipped
+    }
+
+    /// Replaces every root by its children, in order, so the tree is drawn
+    /// as a forest without its top level. Groups are not looked at.
+    pub fn hide_roots(&mut self)
+    {
+        self.roots = self.roots.iter().flat_map(|&root| self.nodes[root].children.iter().copied()).collect();
+    }
+
+    /// Roots the tree on the processes of user `uid` whose parent belongs to
+    /// someone else, like `pstree USER`. Only processes below the current
+    /// roots are considered. Fails if the user has none of them.
+    pub fn set_user_roots(&mut self, uid: u32) -> crate::Result<()>
+    {
+        let owned: Vec<usize> = self.iter_dfs()
+            .map(|(_, index)| index)
+            .filter(|&index| self.nodes[index].proc_info.uid == Some(uid))
+            .collect();
+
+        if owned.is_empty() {
+            return Err(PsTreeError::NoUserProcess(uid));
+        }
+
+        self.set_root_nodes(owned);
+        Ok(())
+    }
+
+    /// Keeps only the nodes below the roots that match `predicate`, together
+    /// with their ancestors up to the roots. Returns the number of matching
+    /// nodes; with none, the tree is left without roots.
+    ///
+    /// The nodes that also matched the earlier filters are recorded in
+    /// [`matches`](Self::matches), which tells them apart from the ancestors
+    /// kept only to draw them.
+    pub fn retain_matches<P: FnMut(&ProcessInfo) -> bool>(&mut self, mut predicate: P) -> usize
+    {
+        let mut keep: Vec<bool> = vec![false; self.nodes.len()];
+        let mut matched: Vec<bool> = vec![false; self.nodes.len()];
+        let mut matches = 0;
+
+        for (_, index) in self.iter_post_order() {
+            let node = &self.nodes[index];
+            if predicate(&node.proc_info) {
+                matches += 1;
+                keep[index] = true;
+                matched[index] = self.matches.as_ref().is_none_or(|earlier| earlier[index]);
+            } else {
+                keep[index] = node.children.iter().any(|&child| keep[child]);
+            }
+        }
+        self.matches = Some(matched);
+
+        for (index, node) in self.nodes.iter_mut().enumerate() {
+            if keep[index] {
+                node.children.retain(|&child| keep[child]);
+            }
+        }
+        self.roots.retain(|&root| keep[root]);
+        matches
+    }
+
+    /// Marks the nodes that passed the filters of
+    /// [`retain_matches`](Self::retain_matches) and the nodes below them, by
+    /// index: the processes a filter selects, without the ancestors kept to
+    /// draw them. Every node is marked when no filter was applied.
+    pub fn selected(&self) -> Vec<bool>
+    {
+        let matches = match &self.matches {
+            Some(matches) => matches,
+            None => return vec![true; self.nodes.len()],
+        };
+        let mut selected = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = (0 .. self.nodes.len()).filter(|&index| matches[index]).collect();
+
+        while let Some(index) = stack.pop() {
+            if !std::mem::replace(&mut selected[index], true) {
+                stack.extend(&self.nodes[index].children);
+            }
+        }
+        selected
+    }
+
+    /// Walks the tree depth-first from the roots, yielding `(depth, index)`
+    /// pairs in drawing order. Roots have depth 0.
+    pub fn iter_dfs(&self) -> DfsIter<'_>
+    {
+        DfsIter::new(self, &self.roots)
+    }
+
+    /// Walks the tree breadth-first from the roots, yielding `(depth, index)`
+    /// pairs level by level.
+    pub fn iter_bfs(&self) -> BfsIter<'_>
+    {
+        BfsIter {
+            tree: self,
+            queue: self.roots.iter().map(|&root| (0, root)).collect(),
+        }
+    }
+
+    /// Walks the tree depth-first from the roots, yielding every node after
+    /// all of its descendants. Siblings keep their drawing order.
+    pub fn iter_post_order(&self) -> PostOrderIter<'_>
+    {
+        PostOrderIter {
+            tree: self,
+            stack: self.roots.iter().rev().map(|&root| (0, root, false)).collect(),
+        }
+    }
+
+    /// The node of process `pid`, if it was scanned.
+    pub fn find(&self, pid: u32) -> Option<&ProcessNode>
+    {
+        self.pid_index.get(&pid).map(|&index| &self.nodes[index])
+    }
+
+    /// The chain of processes from the top of the tree down to `pid`, as node
+    /// indices ending with the node of `pid`. The chain follows the parent
+    /// pids, so it does not depend on the roots that were selected.
+    pub fn ancestry(&self, pid: u32) -> Option<Vec<usize>>
+    {
+        let mut chain = vec![*self.pid_index.get(&pid)?];
+        let mut seen: HashSet<u32> = HashSet::from([pid]);
+
+        loop {
+            let proc_info = &self.nodes[*chain.last().unwrap()].proc_info;
+            match self.pid_index.get(&proc_info.ppid) {
+                Some(&parent) if !proc_info.is_top_level() && seen.insert(proc_info.ppid) => chain.push(parent),
+                _ => break,
+            }
+        }
+
+        chain.reverse();
+        Some(chain)
+    }
+
+    /// Copies the process `pid` and its descendants into a new tree rooted
+    /// on that process.
+    pub fn subtree(&self, pid: u32) -> Option<ProcessTree>
+    {
+        let root = *self.pid_index.get(&pid)?;
+        let indices: Vec<usize> = DfsIter::new(self, &[root]).map(|(_, index)| index).collect();
+        let new_index: HashMap<usize, usize> = indices.iter().enumerate()
+            .map(|(new, &old)| (old, new))
+            .collect();
+
+        let nodes: Vec<ProcessNode> = indices.iter()
+            .map(|&index| {
+                let node = &self.nodes[index];
+                ProcessNode {
+                    proc_info: node.proc_info.clone(),
+                    children: node.children.iter().map(|child| new_index[child]).collect(),
+                    hidden_descendants: node.hidden_descendants,
+                    change: node.change,
+                }
+            })
+            .collect();
+
+        let pid_index = nodes.iter().enumerate()
+            .map(|(index, node)| (node.proc_info.pid, index))
+            .collect();
+
+        Some(ProcessTree {
+            nodes,
+            roots: vec![0],
+            pid_index,
+            broken_cycles: Vec::new(),
+            groups: Vec::new(),
+            omitted_subtrees: None,
+            truncated_processes: 0,
+            matches: None,
+        })
+    }
+
+    /// Number of descendants of a node, including the ones removed by depth pruning.
+    pub fn descendant_count(&self, index: usize) -> usize
+    {
+        DfsIter::new(self, &[index])
+            .map(|(depth, node)| self.nodes[node].hidden_descendants + usize::from(depth > 0))
+            .sum()
+    }
+
+    /// Cuts every node more than `max_depth` levels below its root. The cut
+    /// nodes' descendants are remembered in `hidden_descendants`.
+    pub fn prune_depth(&mut self, max_depth: u32)
+    {
+        let cut: Vec<(usize, usize)> = self.iter_dfs()
+            .filter(|&(depth, _)| depth == max_depth)
+            .map(|(_, index)| (index, self.descendant_count(index)))
+            .collect();
+
+        for (index, hidden_descendants) in cut {
+            let node = &mut self.nodes[index];
+            node.hidden_descendants = hidden_descendants;
+            node.children.clear();
+        }
+    }
+
+    /// Keeps the `count` largest subtrees below the root, or of the roots
+    /// when there are several, and records the rest in `omitted_subtrees`.
+    /// Subtrees are measured in processes, hidden descendants included, and
+    /// equal sizes are ranked by ascending pid. The kept subtrees stay in
+    /// their order. Groups are not looked at.
+    pub fn keep_top_subtrees(&mut self, count: usize)
+    {
+        let parent = match self.roots[..] {
+            [root] => Some(root),
+            _ => None,
+        };
+        let mut siblings = match parent {
+            Some(root) => std::mem::take(&mut self.nodes[root].children),
+            None => std::mem::take(&mut self.roots),
+        };
+
+        let mut by_size: Vec<(usize, usize)> = siblings.iter()
+            .map(|&index| (index, self.descendant_count(index) + 1))
+            .collect();
+        by_size.sort_by(|&(a, a_size), &(b, b_size)| {
+            b_size.cmp(&a_size).then(self.nodes[a].proc_info.pid.cmp(&self.nodes[b].proc_info.pid))
+        });
+
+        let omitted = &by_size[count.min(by_size.len())..];
+        if !omitted.is_empty() {
+            let omitted_indices: HashSet<usize> = omitted.iter().map(|&(index, _)| index).collect();
+            siblings.retain(|index| !omitted_indices.contains(index));
+            self.omitted_subtrees = Some(OmittedSubtrees {
+                parent,
+                subtrees: omitted.len(),
+                processes: omitted.iter().map(|&(_, size)| size).sum(),
+            });
+        }
+
+        match parent {
+            Some(root) => self.nodes[root].children = siblings,
+            None => self.roots = siblings,
+        }
+    }
+
+    /// Keeps the first `max_processes` processes of a breadth-first walk and
+    /// cuts the others, so whole subtrees go from the deepest levels first,
+    /// and adds how many were cut to `truncated_processes`. Processes hidden
+    /// by depth pruning are not counted. Groups are not looked at.
+    pub fn truncate(&mut self, max_processes: usize)
+    {
+        let drawn: Vec<usize> = self.iter_bfs().map(|(_, index)| index).collect();
+        if drawn.len() <= max_processes {
+            return;
+        }
+        let kept: HashSet<usize> = drawn[..max_processes].iter().copied().collect();
+        self.roots.retain(|index| kept.contains(index));
+        for &index in &kept {
+            self.nodes[index].children.retain(|child| kept.contains(child));
+        }
+        self.truncated_processes += drawn.len() - max_processes;
+    }
+
+    /// Reorders the roots and the children of every node by `key`, names
+    /// compared in `collation`. Groups keep their order, only the roots
+    /// within each group are sorted.
+    pub fn sort_children(&mut self, key: SortKey, collation: Collation)
+    {
+        let mut groups = std::mem::take(&mut self.groups);
+        if groups.is_empty() {
+            let mut roots = std::mem::take(&mut self.roots);
+            self.sort_indices(&mut roots, key, collation);
+            self.roots = roots;
+        } else {
+            self.sort_group_roots(&mut groups, key, collation);
+            self.roots = group_roots_in_order(&groups);
+        }
+        self.groups = groups;
+
+        let indices: Vec<usize> = self.iter_dfs().map(|(_, index)| index).collect();
+
+        for index in indices {
+            let mut children = std::mem::take(&mut self.nodes[index].children);
+            self.sort_indices(&mut children, key, collation);
+            self.nodes[index].children = children;
+        }
+    }
+
+    fn sort_group_roots(&self, groups: &mut [ProcessGroup], key: SortKey, collation: Collation)
+    {
+        for group in groups {
+            self.sort_indices(&mut group.roots, key, collation);
+            self.sort_group_roots(&mut group.subgroups, key, collation);
+        }
+    }
+
+    fn sort_indices(&self, indices: &mut [usize], key: SortKey, collation: Collation)
+    {
+        match key {
+            SortKey::Pid => indices.sort_by_key(|&index| self.nodes[index].proc_info.pid),
+            SortKey::Name => indices.sort_by(|&a, &b| {
+                let (a, b) = (&self.nodes[a].proc_info, &self.nodes[b].proc_info);
+                collation.compare(&a.name, &b.name).then(a.pid.cmp(&b.pid))
+            }),
+        }
+    }
+
+    /// Removes leaves that repeat the name of the sibling before them.
+    pub fn filter_unique(&mut self)
+    {
+        let indices: Vec<usize> = self.iter_dfs().map(|(_, index)| index).collect();
+
+        for index in indices {
+            let mut children = std::mem::take(&mut self.nodes[index].children);
+            children.dedup_by(|p1, p2| {
+                self.nodes[*p1].is_leaf() &&
+                self.nodes[*p1].proc_info.name == self.nodes[*p2].proc_info.name
+            });
+            self.nodes[index].children = children;
+        }
+    }
+}
+
+impl ProcessNode 
+{
+    /// Creates a node without children.
+    pub fn new(proc_info: ProcessInfo) -> ProcessNode
+    {
+        ProcessNode 
+        { 
+            proc_info, 
+            children: Vec::new(),
+            hidden_descendants: 0,
+            change: None,
+        }
+    }
+
+    /// Appends the node at `child` to the children of this node.
+    pub fn add_child(&mut self, child: usize)
+    {
+        self.children.push(child);
+    }
+
+    /// True if the node has no children, shown or pruned.
+    pub fn is_leaf(&self) -> bool
+    {
+        self.children.is_empty() && self.hidden_descendants == 0
+    }
+
+}
+
+/// Depth-first iterator over a [`ProcessTree`], see [`ProcessTree::iter_dfs`].
+pub struct DfsIter<'a>
+{
+    tree: &'a ProcessTree,
+    stack: Vec<(u32, usize)>,
+}
+
+impl<'a> DfsIter<'a>
+{
+    pub(crate) fn new(tree: &'a ProcessTree, starts: &[usize]) -> DfsIter<'a>
+    {
+        DfsIter {
+            tree,
+            stack: starts.iter().rev().map(|&start| (0, start)).collect(),
+        }
+    }
+}
+
+impl Iterator for DfsIter<'_>
+{
+    type Item = (u32, usize);
+
+    fn next(&mut self) -> Option<(u32, usize)>
+    {
+        let (depth, index) = self.stack.pop()?;
+        self.stack.extend(self.tree.nodes[index].children.iter().rev().map(|&child| (depth + 1, child)));
+        Some((depth, index))
+    }
+}
+
+/// Breadth-first iterator over a [`ProcessTree`], see [`ProcessTree::iter_bfs`].
+pub struct BfsIter<'a>
+{
+    tree: &'a ProcessTree,
+    queue: VecDeque<(u32, usize)>,
+}
+
+impl Iterator for BfsIter<'_>
+{
+    type Item = (u32, usize);
+
+    fn next(&mut self) -> Option<(u32, usize)>
+    {
+        let (depth, index) = self.queue.pop_front()?;
+        self.queue.extend(self.tree.nodes[index].children.iter().map(|&child| (depth + 1, child)));
+        Some((depth, index))
+    }
+}
+
+/// Post-order iterator over a [`ProcessTree`], see [`ProcessTree::iter_post_order`].
+pub struct PostOrderIter<'a>
+{
+    tree: &'a ProcessTree,
+    /// Pending nodes, flagged once their children have been pushed above them.
+    stack: Vec<(u32, usize, bool)>,
+}
+
+impl Iterator for PostOrderIter<'_>
+{
+    type Item = (u32, usize);
+
+    fn next(&mut self) -> Option<(u32, usize)>
+    {
+        loop {
+            let (depth, index, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some((depth, index));
+            }
+            self.stack.push((depth, index, true));
+            self.stack.extend(self.tree.nodes[index].children.iter().rev().map(|&child| (depth + 1, child, false)));
+        }
+    }
+}
+
+/// Cuts the parent link that closes each cycle in `parent_map`, so the last
+/// process of the cycle becomes a root. Returns the pids of every cycle found.
+fn break_parent_cycles(proc_info_vec: &[ProcessInfo], parent_map: &mut HashMap<u32, u32>) -> Vec<Vec<u32>>
+{
+    let mut finished: HashSet<u32> = HashSet::new();
+    let mut cycles: Vec<Vec<u32>> = Vec::new();
+
+    for proc_info in proc_info_vec {
+        let mut path: Vec<u32> = Vec::new();
+        let mut path_index: HashMap<u32, usize> = HashMap::new();
+        let mut current = Some(proc_info.pid);
+
+        while let Some(pid) = current {
+            if finished.contains(&pid) {
+                break;
+            }
+            if let Some(&start) = path_index.get(&pid) {
+                parent_map.remove(path.last().unwrap());
+                cycles.push(path[start ..].to_vec());
+                break;
+            }
+            path_index.insert(pid, path.len());
+            path.push(pid);
+            current = parent_map.get(&pid).copied();
+        }
+
+        finished.extend(path);
+    }
+
+    cycles
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+
+/// User names and ids read from a passwd file.
+#[derive(Clone, Debug, Default)]
+pub struct UserTable
+{
+    by_name: HashMap<String, u32>,
+    by_uid: HashMap<u32, String>,
+}
+
+impl UserTable
+{
+    /// Reads /etc/passwd. A missing or unreadable file gives an empty table.
+    pub fn load() -> UserTable
+    {
+        UserTable::from_passwd(Path::new("/etc/passwd"))
+    }
+
+    /// Reads a passwd formatted file.
+    pub fn from_passwd(path: &Path) -> UserTable
+    {
+        fs::read(path).map(|contents| UserTable::parse(&String::from_utf8_lossy(&contents))).unwrap_or_default()
+    }
+
+    /// Parses passwd lines like `alice:x:1000:1000::/home/alice:/bin/sh`,
+    /// skipping the ones without a numeric uid. The first entry of a name or
+    /// uid wins, as with getpwnam and getpwuid.
+    pub fn parse(contents: &str) -> UserTable
+    {
+        let mut table = UserTable::default();
+
+        for line in contents.lines() {
+            let mut fields = line.split(':');
+            let (name, uid) = match (fields.next(), fields.nth(1).and_then(|uid| uid.parse::<u32>().ok())) {
+                (Some(name), Some(uid)) if !name.is_empty() => (name, uid),
+                _ => continue,
+            };
+            table.by_name.entry(name.to_string()).or_insert(uid);
+            table.by_uid.entry(uid).or_insert_with(|| name.to_string());
+        }
+
+        table
+    }
+
+    /// The uid of the user called `name`.
+    pub fn uid(&self, name: &str) -> Option<u32>
+    {
+        self.by_name.get(name).copied()
+    }
+
+    /// The name of the user with id `uid`.
+    pub fn name(&self, uid: u32) -> Option<&str>
+    {
+        self.by_uid.get(&uid).map(String::as_str)
+    }
+
+    /// Resolves a user name, or failing that a numeric uid.
+    pub fn resolve(&self, user: &str) -> Option<u32>
+    {
+        self.uid(user).or_else(|| user.parse().ok())
+    }
+
+    /// Adds the user `name` with id `uid`. Like with [`parse`](Self::parse),
+    /// a name or uid already in the table keeps its first entry.
+    pub fn insert(&mut self, name: String, uid: u32)
+    {
+        self.by_uid.entry(uid).or_insert_with(|| name.clone());
+        self.by_name.entry(name).or_insert(uid);
+    }
+}
+
+/// Group names and ids read from a group file. Group lines start like
+/// passwd lines, `docker:x:998:alice`, so they are parsed the same way.
+#[derive(Clone, Debug, Default)]
+pub struct GroupTable(UserTable);
+
+impl GroupTable
+{
+    /// Reads /etc/group. A missing or unreadable file gives an empty table.
+    pub fn load() -> GroupTable
+    {
+        GroupTable::from_group(Path::new("/etc/group"))
+    }
+
+    /// Reads a group formatted file.
+    pub fn from_group(path: &Path) -> GroupTable
+    {
+        GroupTable(UserTable::from_passwd(path))
+    }
+
+    /// Parses group lines, skipping the ones without a numeric gid.
+    pub fn parse(contents: &str) -> GroupTable
+    {
+        GroupTable(UserTable::parse(contents))
+    }
+
+    /// The gid of the group called `name`.
+    pub fn gid(&self, name: &str) -> Option<u32>
+    {
+        self.0.uid(name)
+    }
+
+    /// The name of the group with id `gid`.
+    pub fn name(&self, gid: u32) -> Option<&str>
+    {
+        self.0.name(gid)
+    }
+
+    /// Resolves a group name, or failing that a numeric gid.
+    pub fn resolve(&self, group: &str) -> Option<u32>
+    {
+        self.0.resolve(group)
+    }
+}
@@ -0,0 +1,42 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+
+/// Splits `text` into lines at most `width` columns wide, breaking at the
+/// last space that fits, which is left out, or between two characters for
+/// a word longer than a line. Wide characters count two columns, combining
+/// marks stay with the character before them and no character is split.
+pub(crate) fn wrap_text(text: &str, width: usize) -> Vec<&str>
+{
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut rest = text;
+    while rest.width() > width {
+        let mut used = 0;
+        let mut end = 0;
+        let mut last_space = None;
+        for (index, ch) in rest.char_indices() {
+            let ch_width = ch.width().unwrap_or(0);
+            if used + ch_width > width {
+                break;
+            }
+            used += ch_width;
+            end = index + ch.len_utf8();
+            if ch == ' ' && index > 0 {
+                last_space = Some(index);
+            }
+        }
+        // A character wider than the whole line still has to go somewhere.
+        if end == 0 {
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        let (line, next) = match last_space {
+            _ if rest[end..].starts_with(' ') => (&rest[..end], &rest[end + 1..]),
+            Some(space) => (&rest[..space], &rest[space + 1..]),
+            None => (&rest[..end], &rest[end..]),
+        };
+        lines.push(line);
+        rest = next;
+    }
+    lines.push(rest);
+    lines
+}
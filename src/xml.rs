@@ -0,0 +1,152 @@
+use std::fmt::{Display, Write as _};
+use std::io;
+
+use crate::process::ProcessInfo;
+use crate::tree::ProcessTree;
+
+
+/// The element holding the drawn tree.
+const ROOT_ELEMENT: &str = "processes";
+
+/// What is known of the capture, written as attributes of the root
+/// element by [`ProcessTree::write_xml`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct XmlMetadata
+{
+    /// The name of the scanned host.
+    pub hostname: Option<String>,
+    /// The time of the scan, in seconds since the Unix epoch.
+    pub timestamp: Option<u64>,
+}
+
+impl ProcessTree
+{
+    /// Writes the drawn tree as an XML document: a `processes` element with
+    /// the attributes of `metadata`, holding one nested `process` element
+    /// per process.
+    ///
+    /// A process has `pid`, `ppid` and `name` attributes, then one
+    /// attribute per optional field that was read, named like its JSON
+    /// field. The command line, listening sockets and supplementary groups
+    /// are `arg`, `listen` and `group` elements before the child processes.
+    pub fn write_xml<W: io::Write>(&self, mut out: W, metadata: &XmlMetadata) -> io::Result<()>
+    {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        let mut root = String::from(ROOT_ELEMENT);
+        push_optional(&mut root, "hostname", metadata.hostname.as_ref());
+        push_optional(&mut root, "timestamp", metadata.timestamp);
+        if self.roots.is_empty() {
+            return writeln!(out, "<{}/>", root);
+        }
+        writeln!(out, "<{}>", root)?;
+        for &root in &self.roots {
+            self.write_xml_node(&mut out, root, 1)?;
+        }
+        writeln!(out, "</{}>", ROOT_ELEMENT)
+    }
+
+    fn write_xml_node<W: io::Write>(&self, out: &mut W, index: usize, depth: usize) -> io::Result<()>
+    {
+        let node = &self.nodes[index];
+        let proc_info = &node.proc_info;
+        let indent = "  ".repeat(depth);
+
+        let mut elements = Vec::new();
+        for arg in proc_info.cmdline.iter().flatten() {
+            elements.push(format!("<arg>{}</arg>", escape_xml(&String::from_utf8_lossy(arg))));
+        }
+        for listen in proc_info.listen.iter().flatten() {
+            elements.push(format!("<listen>{}</listen>", escape_xml(listen)));
+        }
+        for gid in proc_info.groups.iter().flatten() {
+            elements.push(format!(r#"<group gid="{}"/>"#, gid));
+        }
+
+        let mut tag = String::from("process");
+        push_attributes(&mut tag, proc_info);
+        if node.hidden_descendants > 0 {
+            push_attribute(&mut tag, "hidden_descendants", node.hidden_descendants);
+        }
+        if elements.is_empty() && node.children.is_empty() {
+            return writeln!(out, "{}<{}/>", indent, tag);
+        }
+        writeln!(out, "{}<{}>", indent, tag)?;
+        for element in elements {
+            writeln!(out, "{}  {}", indent, element)?;
+        }
+        for &child in &node.children {
+            self.write_xml_node(out, child, depth + 1)?;
+        }
+        writeln!(out, "{}</process>", indent)
+    }
+}
+
+/// Appends the attributes of the fields of `proc_info` that were read.
+fn push_attributes(tag: &mut String, proc_info: &ProcessInfo)
+{
+    push_attribute(tag, "pid", proc_info.pid);
+    push_attribute(tag, "ppid", proc_info.ppid);
+    push_attribute(tag, "name", String::from_utf8_lossy(&proc_info.name));
+    push_optional(tag, "uid", proc_info.uid);
+    push_optional(tag, "state", proc_info.state);
+    push_optional(tag, "start_time", proc_info.start_time);
+    push_optional(tag, "nice", proc_info.nice);
+    push_optional(tag, "session", proc_info.session);
+    push_optional(tag, "threads", proc_info.threads);
+    push_optional(tag, "tracer_pid", proc_info.tracer_pid);
+    push_optional(tag, "no_new_privs", proc_info.no_new_privs);
+    push_optional(tag, "seccomp", proc_info.seccomp);
+    push_optional(tag, "seccomp_filters", proc_info.seccomp_filters);
+    push_optional(tag, "rss", proc_info.rss);
+    push_optional(tag, "peak_rss", proc_info.peak_rss);
+    push_optional(tag, "peak_virtual", proc_info.peak_virtual);
+    push_optional(tag, "pss", proc_info.pss);
+    push_optional(tag, "private_memory", proc_info.private_memory);
+    push_optional(tag, "shared_memory", proc_info.shared_memory);
+    push_optional(tag, "umask", proc_info.display_umask());
+    push_optional(tag, "fd_size", proc_info.fd_size);
+    push_optional(tag, "fd_limit", proc_info.fd_limit);
+    push_optional(tag, "cpus_allowed", proc_info.cpus_allowed.as_ref());
+    push_optional(tag, "processor", proc_info.processor);
+    push_optional(tag, "cpu_time_ms", proc_info.cpu_time_ms);
+    push_optional(tag, "children_cpu_time_ms", proc_info.children_cpu_time_ms);
+    push_optional(tag, "wchan", proc_info.wchan.as_ref());
+    push_optional(tag, "holds_file", proc_info.holds_file);
+    push_optional(tag, "cgroup", proc_info.cgroup.as_ref());
+}
+
+fn push_optional(tag: &mut String, name: &str, value: Option<impl Display>)
+{
+    if let Some(value) = value {
+        push_attribute(tag, name, value);
+    }
+}
+
+fn push_attribute(tag: &mut String, name: &str, value: impl Display)
+{
+    let _ = write!(tag, r#" {}="{}""#, name, escape_xml(&value.to_string()));
+}
+
+/// Escapes `text` for attribute values and element content. Line breaks
+/// and tabs become character references so attribute normalization keeps
+/// them, and the other control characters, which XML 1.0 cannot carry at
+/// all, become U+FFFD.
+fn escape_xml(text: &str) -> String
+{
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => {
+                let _ = write!(escaped, "&#{};", u32::from(c));
+            }
+            c if c < ' ' => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,89 @@
+extern crate rusty_pstree;
+
+
+use std::path::PathBuf;
+
+use rusty_pstree::{Anonymizer, ProcessInfo, ProcessTree, UserTable};
+
+fn process(pid: u32, ppid: u32, name: &str, uid: u32) -> ProcessInfo
+{
+    let mut proc_info = ProcessInfo::new(pid, ppid, name);
+    proc_info.uid = Some(uid);
+    proc_info
+}
+
+#[test]
+fn equal_names_share_a_placeholder()
+{
+    let mut tree = ProcessTree::from_process_list(vec![
+        process(1, 0, "systemd", 0),
+        process(40, 1, "postgres", 70),
+        process(41, 40, "postgres", 70),
+        process(42, 40, "backup.sh", 1000),
+    ]);
+    let mut anonymizer = Anonymizer::new();
+    tree.anonymize(&mut anonymizer);
+
+    let names: Vec<String> = tree.nodes.iter().map(|node| node.proc_info.display_name().into_owned()).collect();
+    assert_eq!(names, ["proc-01", "proc-02", "proc-02", "proc-03"]);
+
+    // Later trees reuse the placeholders of the names already met.
+    let mut later = ProcessTree::from_process_list(vec![process(1, 0, "systemd", 0), process(50, 1, "cron", 0)]);
+    later.anonymize(&mut anonymizer);
+    let names: Vec<String> = later.nodes.iter().map(|node| node.proc_info.display_name().into_owned()).collect();
+    assert_eq!(names, ["proc-01", "proc-04"]);
+}
+
+#[test]
+fn revealing_fields_are_dropped_and_the_rest_kept()
+{
+    let mut proc_info = process(812, 1, "postgres", 70);
+    proc_info.cmdline = Some(vec![b"postgres".to_vec(), b"-D".to_vec(), b"/srv/acme/db".to_vec()]);
+    proc_info.exe = Some(PathBuf::from("/usr/lib/postgresql/14/bin/postgres"));
+    proc_info.cgroup = Some("/system.slice/postgresql@14-main.service".to_string());
+    proc_info.state = Some('S');
+    proc_info.rss = Some(1 << 20);
+    Anonymizer::new().anonymize(&mut proc_info);
+
+    assert_eq!((proc_info.cmdline, proc_info.exe, proc_info.cgroup), (None, None, None));
+    assert_eq!((proc_info.pid, proc_info.ppid, proc_info.uid), (812, 1, Some(70)));
+    assert_eq!((proc_info.state, proc_info.rss), (Some('S'), Some(1 << 20)));
+}
+
+#[test]
+fn users_are_numbered_in_the_order_they_are_met()
+{
+    let mut anonymizer = Anonymizer::new();
+    for (pid, uid) in [(1, 0), (2, 1000), (3, 0), (4, 33)] {
+        anonymizer.anonymize(&mut process(pid, 0, "worker", uid));
+    }
+    let users = anonymizer.user_names();
+    assert_eq!([users.name(0), users.name(1000), users.name(33)], [Some("user-1"), Some("user-2"), Some("user-3")]);
+    assert_eq!(users.name(70), None);
+}
+
+#[test]
+fn the_map_tells_what_every_placeholder_stands_for()
+{
+    let mut anonymizer = Anonymizer::new();
+    anonymizer.anonymize(&mut process(1, 0, "systemd", 0));
+    anonymizer.anonymize(&mut process(30, 1, "say \"hi\"", 1000));
+
+    let mut map = Vec::new();
+    anonymizer.write_map(&mut map, &UserTable::parse("root:x:0:0::/root:/bin/sh\n")).unwrap();
+    assert_eq!(String::from_utf8(map).unwrap(), r#"{
+  "names": {
+    "proc-01": "systemd",
+    "proc-02": "say \"hi\""
+  },
+  "users": {
+    "user-1": "root",
+    "user-2": "1000"
+  }
+}
+"#);
+
+    let mut map = Vec::new();
+    Anonymizer::new().write_map(&mut map, &UserTable::default()).unwrap();
+    assert_eq!(String::from_utf8(map).unwrap(), "{\n  \"names\": {},\n  \"users\": {}\n}\n");
+}
@@ -0,0 +1,96 @@
+extern crate rusty_pstree;
+
+#[path = "support/fake_procfs.rs"]
+mod fake_procfs;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rusty_pstree::{scan_source, ArchiveSource, LinuxProcfs, ProcSource, ProcessTree, PsTreePrintConfig, ScanOptions};
+
+use fake_procfs::{FakeProcess, FakeProcfs};
+
+/// Archives `members` of `dir` with tar, adding `flags` like "-z", next to
+/// `procfs` with the extension `extension`. Returns the path of the archive.
+fn tar(procfs: &FakeProcfs, extension: &str, flags: &[&str], dir: &Path, members: &str) -> PathBuf
+{
+    let path = procfs.path().with_extension(extension);
+    let status = Command::new("tar")
+        .args(flags)
+        .arg("-cf")
+        .arg(&path)
+        .arg("-C")
+        .arg(dir)
+        .arg(members)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    path
+}
+
+fn draw<S: ProcSource + Sync>(source: &S) -> (String, usize)
+{
+    let options = ScanOptions { read_cmdline: true, read_cgroup: true, read_listen: true, ..ScanOptions::default() };
+    let output = scan_source(source, &options).unwrap();
+    let mut tree: Vec<u8> = Vec::new();
+    ProcessTree::from_process_list(output.processes).write(&mut tree, &PsTreePrintConfig::new()).unwrap();
+    (String::from_utf8(tree).unwrap(), output.errors.len())
+}
+
+#[test]
+fn archive_draws_the_same_tree_as_the_directory()
+{
+    let procfs = FakeProcfs::generate(200, 3);
+    procfs.add_process(&FakeProcess::new(201, 7, "sshd"));
+    procfs.add_fd(201, 3, "socket:[4242]");
+    procfs.write_net("tcp", "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 4242 1 0 100 0 0 10 0
+");
+    // A process that exited, one whose cmdline is gone and a malformed one.
+    fs::remove_file(procfs.path().join("12/status")).unwrap();
+    fs::remove_file(procfs.path().join("40/cmdline")).unwrap();
+    procfs.write_file(90, "status", b"Name:\tbroken\nPid:\tninety\n");
+
+    let expected = draw(&LinuxProcfs::new(procfs.path()));
+    assert_eq!(expected.1, 1);
+    assert!(expected.0.contains("sshd (:22)"), "{}", expected.0);
+
+    let gzipped = tar(&procfs, "tar.gz", &["-z"], procfs.path(), ".");
+    assert_eq!(draw(&ArchiveSource::open(&gzipped).unwrap()), expected);
+
+    // Archived from above, every path starts with the directory name.
+    let name = procfs.path().file_name().unwrap().to_str().unwrap();
+    let plain = tar(&procfs, "tar", &[], procfs.path().parent().unwrap(), name);
+    assert_eq!(draw(&ArchiveSource::open(&plain).unwrap()), expected);
+
+    fs::remove_file(gzipped).unwrap();
+    fs::remove_file(plain).unwrap();
+}
+
+#[test]
+fn archives_are_recognized_by_extension_or_magic()
+{
+    let procfs = FakeProcfs::generate(3, 2);
+    let archive = tar(&procfs, "gz", &["-z"], procfs.path(), ".");
+    let unnamed = procfs.path().with_extension("snapshot");
+    fs::rename(&archive, &unnamed).unwrap();
+
+    assert!(ArchiveSource::is_archive(&unnamed));
+    assert!(ArchiveSource::is_archive(Path::new("/nonexistent/proc.tar.gz")));
+    assert!(!ArchiveSource::is_archive(procfs.path()));
+    assert_eq!(ArchiveSource::open(&unnamed).unwrap().list_pids().unwrap(), vec![1, 2, 3]);
+
+    fs::remove_file(unnamed).unwrap();
+}
+
+#[test]
+fn corrupt_archives_are_an_error()
+{
+    let error = ArchiveSource::from_bytes("garbage.tar", &[b'x'; 1024]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    let error = ArchiveSource::from_bytes("short.tar.gz", &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,2164 @@
+//! Golden tests running the binary against a fixture procfs.
+
+#[path = "support/fake_procfs.rs"]
+mod fake_procfs;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use regex::Regex;
+
+use fake_procfs::{FakeProcess, FakeProcfs};
+
+
+/// init (1) with sshd -> bash -> sleep x3, where one sleep has a lower pid
+/// than its parent, and kthreadd (2) with a kernel worker. bash and two of
+/// the sleeps belong to uid 1000, everything else to root.
+fn fixture() -> FakeProcfs
+{
+    let procfs = FakeProcfs::new();
+
+    procfs.add_process(&FakeProcess::new(1, 0, "init"));
+    procfs.add_process(&FakeProcess::new(5, 1, "sshd"));
+    procfs.add_process(&FakeProcess::new(32, 30, "sleep"));
+
+    let mut bash = FakeProcess::new(30, 5, "bash");
+    bash.uid = 1000;
+    procfs.add_process(&bash);
+    for pid in [20, 31] {
+        let mut sleep = FakeProcess::new(pid, 30, "sleep");
+        sleep.uid = 1000;
+        procfs.add_process(&sleep);
+    }
+
+    let mut kthreadd = FakeProcess::new(2, 0, "kthreadd");
+    kthreadd.cmdline.clear();
+    procfs.add_process(&kthreadd);
+
+    let mut kworker = FakeProcess::new(10, 2, "kworker/0:1");
+    kworker.cmdline.clear();
+    kworker.state = 'I';
+    procfs.add_process(&kworker);
+
+    procfs
+}
+
+fn run(procfs: &FakeProcfs, args: &[&str]) -> (String, String, i32)
+{
+    run_at(procfs.path(), args)
+}
+
+fn run_at(proc_root: &Path, args: &[&str]) -> (String, String, i32)
+{
+    run_with_env(proc_root, args, &[])
+}
+
+fn run_with_env(proc_root: &Path, args: &[&str], vars: &[(&str, &str)]) -> (String, String, i32)
+{
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rusty-pstree"));
+    for (name, _) in env::vars().filter(|(name, _)| name.starts_with("PSTREE_")) {
+        command.env_remove(name);
+    }
+
+    let output = command
+        .envs(vars.iter().copied())
+        .arg("--proc-root")
+        .arg(proc_root)
+        .args(args)
+        .output()
+        .unwrap();
+
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+        output.status.code().unwrap(),
+    )
+}
+
+fn assert_output(args: &[&str], expected: &str)
+{
+    let (stdout, stderr, code) = run(&fixture(), args);
+    assert_eq!(stdout, expected, "stdout of {:?}", args);
+    assert_eq!(stderr, "", "stderr of {:?}", args);
+    assert_eq!(code, 0, "exit code of {:?}", args);
+}
+
+#[test]
+fn default_output()
+{
+    assert_output(&[], "\
+(all)
+├─ init
+│  └─ sshd
+│     └─ bash
+│        ├─ sleep
+│        ├─ sleep
+│        └─ sleep
+└─ kthreadd
+   └─ kworker/0:1
+");
+}
+
+#[test]
+fn show_pid()
+{
+    assert_output(&["--show-pid"], "\
+(all)
+├─ [1] init
+│  └─ [5] sshd
+│     └─ [30] bash
+│        ├─ [20] sleep
+│        ├─ [31] sleep
+│        └─ [32] sleep
+└─ [2] kthreadd
+   └─ [10] kworker/0:1
+");
+}
+
+#[test]
+fn show_ppid_follows_every_pid()
+{
+    let expected = "\
+(init(1) › sshd(5) › bash(30))
+[30<5] bash
+├─ [20<30] sleep
+├─ [31<30] sleep
+└─ [32<30] sleep
+";
+    assert_output(&["--show-pid", "--show-ppid", "-r", "30"], expected);
+    assert_output(&["--show-ppid", "-r", "30"], expected);
+    assert_output(&["-p", "--show-ppid", "--pid-format", "parens", "-r", "5", "-d", "1"], "(init(1) › sshd(5))\nsshd(5<1)\n└─ bash(30<5)\n");
+    assert_output(&["-p", "--show-ppid", "--collapse-chains", "-r", "5", "-d", "1"], "(init(1) › sshd(5))\n[5<1] sshd ▸ [30<5] bash\n");
+    assert_output(&["--align-pids", "--show-ppid", "-r", "5", "-d", "1"], "(init(1) › sshd(5))\n 5<1 sshd\n30<5 └─ bash\n");
+}
+
+#[test]
+fn pid_format_parens_matches_pstree()
+{
+    // The labels of `pstree -p -A` for the same processes.
+    assert_output(&["-p", "--pid-format", "parens", "--ascii", "-r", "1"], "\
+init(1)
+`- sshd(5)
+   `- bash(30)
+      |- sleep(20)
+      |- sleep(31)
+      `- sleep(32)
+");
+
+    // What scripts written for pstree look for in every line.
+    let label = Regex::new(r"^[ |`-]*(?P<name>[^()\s]+)\((?P<pid>\d+)\)$").unwrap();
+    let (stdout, _, _) = run(&fixture(), &["-p", "--pid-format", "parens", "--forest", "--ascii"]);
+    let labels: Vec<(String, u32)> = stdout.lines()
+        .map(|line| label.captures(line).unwrap_or_else(|| panic!("{:?} is not name(pid)", line)))
+        .map(|captures| (captures["name"].to_string(), captures["pid"].parse().unwrap()))
+        .collect();
+    assert_eq!(labels[0], ("init".to_string(), 1));
+    assert_eq!(labels[7], ("kworker/0:1".to_string(), 10));
+    assert_eq!(labels.len(), 8);
+
+    assert_output(&["--pid-format", "parens", "-r", "30", "-d", "0"], "(init(1) › sshd(5) › bash(30))\nbash\n");
+}
+
+#[test]
+fn unique()
+{
+    assert_output(&["--unique"], "\
+(all)
+├─ init
+│  └─ sshd
+│     └─ bash
+│        └─ sleep
+└─ kthreadd
+   └─ kworker/0:1
+");
+}
+
+#[test]
+fn depth()
+{
+    assert_output(&["--depth", "1"], "\
+(all)
+├─ init
+│  └─ sshd
+└─ kthreadd
+   └─ kworker/0:1
+");
+}
+
+#[test]
+fn root_pid()
+{
+    assert_output(&["--root-pid", "5", "-p"], "\
+(init(1) › sshd(5))
+[5] sshd
+└─ [30] bash
+   ├─ [20] sleep
+   ├─ [31] sleep
+   └─ [32] sleep
+");
+}
+
+#[test]
+fn root_pid_with_children_files()
+{
+    let procfs = fixture();
+    procfs.write_file(5, "task/5/children", b"30 ");
+    procfs.write_file(30, "task/30/children", b"20 31 ");
+    procfs.write_file(30, "task/33/children", b"32 ");
+    for pid in [20, 31, 32] {
+        procfs.write_file(pid, &format!("task/{}/children", pid), b"");
+    }
+
+    let (stdout, _, code) = run(&procfs, &["--root-pid", "5", "-p"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+(init(1) › sshd(5))
+[5] sshd
+└─ [30] bash
+   ├─ [20] sleep
+   ├─ [31] sleep
+   └─ [32] sleep
+");
+}
+
+#[test]
+fn ancestor_header_lists_the_chain_above_the_root()
+{
+    assert_output(&["-r", "30", "-d", "0", "--ascii"], "(init(1) > sshd(5) > bash(30))\nbash\n");
+    assert_output(&["-r", "30", "-d", "0", "--no-ancestor-header"], "bash\n");
+    assert_output(&["-r", "1", "-d", "0"], "init\n");
+
+    let (stdout, _, _) = run(&fixture(), &["-r", "30", "--output", "metrics"]);
+    assert!(stdout.starts_with("# "), "{}", stdout);
+
+    let procfs = fixture();
+    procfs.add_process(&FakeProcess::new(5, 99, "sshd"));
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "-d", "0"]);
+    assert_eq!(stdout, "(? › sshd(5) › bash(30))\nbash\n");
+}
+
+#[test]
+fn proc_root_may_be_an_archive()
+{
+    let procfs = fixture();
+    let archive = procfs.path().with_extension("tgz");
+    let status = Command::new("tar").arg("-czf").arg(&archive).arg("-C").arg(procfs.path()).arg(".").status().unwrap();
+    assert!(status.success());
+
+    let expected = run(&procfs, &["-p", "--sort", "name"]);
+    assert_eq!(run_with_env(&archive, &["-p", "--sort", "name"], &[]), expected);
+    for args in [&["--signal", "TERM", "--dry-run"][..], &["--renice", "+1", "--yes"], &["--pause"], &["--resume"]] {
+        let (stdout, stderr, code) = run_with_env(&archive, args, &[]);
+        assert_eq!((stdout.as_str(), code), ("", 2), "{:?}", args);
+        assert!(stderr.contains("cannot act on an archive"), "{}", stderr);
+    }
+    fs::remove_file(archive).unwrap();
+}
+
+#[test]
+fn missing_root_pid()
+{
+    let (stdout, stderr, code) = run(&fixture(), &["--root-pid", "99"]);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "rusty-pstree: process 99 does not exist\n");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn unreadable_proc_root()
+{
+    let procfs = fixture();
+    let (_, stderr, code) = run_at(&procfs.path().join("missing"), &[]);
+    assert!(stderr.starts_with("rusty-pstree: unable to read"), "{}", stderr);
+    assert_eq!(code, 3);
+}
+
+#[test]
+fn empty_proc_root()
+{
+    let (_, stderr, code) = run(&FakeProcfs::new(), &[]);
+    assert!(stderr.starts_with("rusty-pstree: no processes found"), "{}", stderr);
+    assert_eq!(code, 3);
+}
+
+#[test]
+fn malformed_status_is_skipped()
+{
+    let procfs = fixture();
+    procfs.write_file(40, "status", b"Name:\tbroken\nPid:\tforty\n");
+
+    let (stdout, stderr, code) = run(&procfs, &["--root-pid", "2"]);
+    assert_eq!(stdout, "kthreadd\n└─ kworker/0:1\n");
+    assert_eq!(stderr, "rusty-pstree: skipped 1 malformed process entries\n");
+    assert_eq!(code, 0);
+
+    let (_, stderr, _) = run(&procfs, &["--root-pid", "2", "--quiet"]);
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn verbose_reports_skipped_processes_by_reason()
+{
+    let procfs = fixture();
+    procfs.write_file(40, "status", b"Name:\tbroken\nPid:\tforty\n");
+    procfs.write_file(41, "stat", b"");
+    procfs.add_process(&FakeProcess::new(50, 49, "orphan"));
+    fs::create_dir_all(procfs.path().join("42")).unwrap();
+    std::os::unix::fs::symlink("status", procfs.path().join("42/status")).unwrap();
+
+    let (stdout, stderr, code) = run(&procfs, &["--root-pid", "2", "--verbose"]);
+    assert_eq!(stdout, "kthreadd\n└─ kworker/0:1\n");
+    assert_eq!(
+        stderr,
+        "rusty-pstree: read 9 processes\n\
+         rusty-pstree: skipped 1 process that exited during the scan\n\
+         rusty-pstree: skipped 1 process whose status could not be opened\n\
+         rusty-pstree: skipped 1 process with a malformed status\n\
+         rusty-pstree: drew 1 process without a parent as roots\n"
+    );
+    assert_eq!(code, 0);
+
+    let (_, stderr, _) = run(&procfs, &["--root-pid", "2", "-vv"]);
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines[2], "rusty-pstree:   41");
+    assert!(lines[4].starts_with("rusty-pstree:   42: "), "{}", stderr);
+    assert!(lines[6].starts_with("rusty-pstree:   40: "), "{}", stderr);
+    assert_eq!(lines[8], "rusty-pstree:   50: parent 49 not found");
+
+    let (_, _, code) = run(&procfs, &["--verbose", "--quiet"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn root_pid_keywords_resolve_from_the_running_process()
+{
+    let procfs = fixture();
+    procfs.add_process(&FakeProcess::new(std::process::id(), 5, "cargo-test"));
+
+    for args in [&["--root-pid", "parent"][..], &["parent"]] {
+        let (stdout, stderr, code) = run(&procfs, &[args, &["--no-ancestor-header"]].concat());
+        assert_eq!((stdout.as_str(), stderr.as_str(), code), ("cargo-test\n", "", 0), "{:?}", args);
+    }
+
+    let (stdout, stderr, code) = run(&procfs, &["--root-pid", "self"]);
+    assert_eq!((stdout.as_str(), code), ("", 1));
+    assert!(stderr.starts_with("rusty-pstree: 'self' is process "), "{}", stderr);
+    assert!(stderr.ends_with(", which does not exist anymore\n"), "{}", stderr);
+
+    let (_, stderr, code) = run(&procfs, &["--root-pid", "parents"]);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("invalid pid 'parents' (expected a number, self, parent or session)"), "{}", stderr);
+}
+
+#[test]
+fn usage_error()
+{
+    let (_, _, code) = run(&fixture(), &["--node-color", "nope"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn bool_env_values()
+{
+    let procfs = fixture();
+    let args = ["--root-pid", "2"];
+
+    for value in ["1", "true", "yes", "YES"] {
+        let (stdout, _, code) = run_with_env(procfs.path(), &args, &[("PSTREE_SHOW_PID", value)]);
+        assert_eq!((stdout.as_str(), code), ("[2] kthreadd\n└─ [10] kworker/0:1\n", 0), "{}", value);
+    }
+
+    for value in ["0", "false", "no"] {
+        let (stdout, _, code) = run_with_env(procfs.path(), &args, &[("PSTREE_SHOW_PID", value)]);
+        assert_eq!((stdout.as_str(), code), ("kthreadd\n└─ kworker/0:1\n", 0), "{}", value);
+    }
+
+    for value in ["", "maybe"] {
+        let (stdout, stderr, code) = run_with_env(procfs.path(), &args, &[("PSTREE_SHOW_PID", value)]);
+        assert_eq!(stdout, "");
+        assert!(stderr.contains("PSTREE_SHOW_PID"), "{}", stderr);
+        assert_eq!(code, 2);
+    }
+
+    let (stdout, _, code) = run_with_env(procfs.path(), &args, &[("PSTREE_ALIGN_PIDS", "yes")]);
+    assert_eq!((stdout.as_str(), code), (" 2 kthreadd\n10 └─ kworker/0:1\n", 0));
+
+    for value in ["", "maybe"] {
+        let (stdout, stderr, code) = run_with_env(procfs.path(), &args, &[("PSTREE_ALIGN_PIDS", value)]);
+        assert_eq!(stdout, "");
+        assert!(stderr.contains("PSTREE_ALIGN_PIDS"), "{}", stderr);
+        assert_eq!(code, 2);
+    }
+}
+
+#[test]
+fn env_defaults_and_flag_precedence()
+{
+    let procfs = fixture();
+
+    let (stdout, _, _) = run_with_env(procfs.path(), &["-r", "30", "-p"], &[("PSTREE_SORT", "name"), ("PSTREE_UNIQUE", "1")]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\n[30] bash\n└─ [20] sleep\n");
+
+    let (stdout, _, _) = run_with_env(procfs.path(), &["-r", "2", "--indent", "3"], &[("PSTREE_INDENT", "6")]);
+    assert_eq!(stdout, "kthreadd\n└─ kworker/0:1\n");
+
+    let (stdout, _, _) = run_with_env(procfs.path(), &["-r", "2"], &[("PSTREE_INDENT", "6")]);
+    assert_eq!(stdout, "kthreadd\n└──── kworker/0:1\n");
+
+    let (_, _, code) = run_with_env(procfs.path(), &["-r", "2"], &[("PSTREE_NODE_COLOR", "nope")]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn root_name_draws_every_match()
+{
+    assert_output(&["--root-name", "^sl", "-p"], "\
+(all)
+├─ [20] sleep
+├─ [31] sleep
+└─ [32] sleep
+");
+}
+
+#[test]
+fn root_name_skips_nested_matches()
+{
+    assert_output(&["--root-name", "^s", "-p", "-u"], "\
+[5] sshd
+└─ [30] bash
+   └─ [20] sleep
+");
+}
+
+#[test]
+fn root_name_first_picks_oldest()
+{
+    let procfs = fixture();
+    let mut sleep = FakeProcess::new(31, 30, "sleep");
+    sleep.start_time = 500;
+    procfs.add_process(&sleep);
+
+    let (stdout, _, code) = run(&procfs, &["--root-name", "sleep", "--first", "-p"]);
+    assert_eq!(stdout, "[31] sleep\n");
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn root_name_matches_args()
+{
+    assert_output(&["--root-name", "^/usr/bin/bash$", "--args", "-p"], "\
+[30] bash
+├─ [20] sleep
+├─ [31] sleep
+└─ [32] sleep
+");
+}
+
+#[test]
+fn root_name_without_match()
+{
+    let (stdout, stderr, code) = run(&fixture(), &["--root-name", "nginx"]);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "rusty-pstree: no process matches 'nginx'\n");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn root_name_options_need_root_name()
+{
+    for args in [
+        &["--first"][..],
+        &["--args"],
+        &["--root-name", "bash", "--root-pid", "1"],
+        &["--root-name-fuzzy", "bash", "--root-name", "bash"],
+    ] {
+        let (_, _, code) = run(&fixture(), args);
+        assert_eq!(code, 2, "{:?}", args);
+    }
+}
+
+#[test]
+fn root_name_fuzzy_roots_on_the_best_match_and_reports_it()
+{
+    let procfs = fixture();
+    let (stdout, stderr, code) = run(&procfs, &["--root-name-fuzzy", "kthred"]);
+    assert_eq!(
+        (stdout.as_str(), stderr.as_str(), code),
+        ("kthreadd\n└─ kworker/0:1\n", "rusty-pstree: rooting at 'kthreadd' (pid 2)\n", 0)
+    );
+
+    let (stdout, stderr, _) = run(&procfs, &["--root-name-fuzzy", "bazh", "-q", "-p"]);
+    assert_eq!((stdout.as_str(), stderr.as_str()), ("[30] bash\n├─ [20] sleep\n├─ [31] sleep\n└─ [32] sleep\n", ""));
+
+    let (stdout, stderr, code) = run(&procfs, &["--root-name-fuzzy", "zzzz"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: no process matches 'zzzz'\n", 1));
+}
+
+#[test]
+fn root_name_fuzzy_refuses_to_guess_between_equal_matches()
+{
+    let procfs = fixture();
+    let (stdout, stderr, code) = run(&procfs, &["--root-name-fuzzy", "slep"]);
+    assert_eq!((stdout.as_str(), code), ("", 2));
+    assert_eq!(
+        stderr,
+        "rusty-pstree: 'slep' matches 3 processes equally well: 'sleep' (pid 20), 'sleep' (pid 31), 'sleep' (pid 32) \
+         (use --first to pick the oldest)\n"
+    );
+
+    let mut sleep = FakeProcess::new(32, 30, "sleep");
+    sleep.start_time = 500;
+    procfs.add_process(&sleep);
+    let (stdout, stderr, _) = run(&procfs, &["--root-name-fuzzy", "slep", "--first", "-p"]);
+    assert_eq!((stdout.as_str(), stderr.as_str()), ("[32] sleep\n", "rusty-pstree: rooting at 'sleep' (pid 32)\n"));
+}
+
+#[test]
+fn multiple_root_pids()
+{
+    assert_output(&["-r", "2,30", "-r", "2", "-p"], "\
+(all)
+├─ [2] kthreadd
+│  └─ [10] kworker/0:1
+└─ [30] bash
+   ├─ [20] sleep
+   ├─ [31] sleep
+   └─ [32] sleep
+");
+    assert_output(&["-r", "30", "-r", "2", "--forest", "--sort", "name", "-u"], "\
+bash
+└─ sleep
+kthreadd
+└─ kworker/0:1
+");
+}
+
+#[test]
+fn nested_root_pid_is_skipped()
+{
+    let (stdout, stderr, code) = run(&fixture(), &["-r", "5", "-r", "20", "-p", "-d", "1"]);
+    assert_eq!(stdout, "(init(1) › sshd(5))\n[5] sshd\n└─ [30] bash\n");
+    assert_eq!(stderr, "rusty-pstree: process 20 is drawn inside another requested subtree, skipping it as a root\n");
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn missing_root_pids_are_listed()
+{
+    let (stdout, stderr, code) = run(&fixture(), &["-r", "98,5,99"]);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "rusty-pstree: processes 98, 99 do not exist\n");
+    assert_eq!(code, 1);
+
+    let (_, stderr, _) = run(&fixture(), &["-r", "99998,99997,99998"]);
+    assert_eq!(stderr, "rusty-pstree: processes 99998, 99997 do not exist\n");
+}
+
+#[test]
+fn positional_pid()
+{
+    assert_output(&["5", "-p", "-d", "1"], "(init(1) › sshd(5))\n[5] sshd\n└─ [30] bash\n");
+}
+
+#[test]
+fn all_digit_positional_is_a_pid()
+{
+    let (stdout, stderr, code) = run(&fixture(), &["1000"]);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "rusty-pstree: process 1000 does not exist\n");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn user_roots_where_parent_differs()
+{
+    assert_output(&["--user", "1000", "-p"], "\
+[30] bash
+├─ [20] sleep
+├─ [31] sleep
+└─ [32] sleep
+");
+    assert_output(&["root", "-p", "-d", "0"], "\
+(all)
+├─ [1] init
+└─ [2] kthreadd
+");
+}
+
+#[test]
+fn positional_conflicts_and_errors()
+{
+    for args in [&["5", "-r", "1"][..], &["root", "--user", "root"], &["bash", "--root-name", "bash"]] {
+        let (_, _, code) = run(&fixture(), args);
+        assert_eq!(code, 2, "{:?}", args);
+    }
+
+    let (_, stderr, code) = run(&fixture(), &["no-such-user-here"]);
+    assert!(stderr.contains("unknown user 'no-such-user-here'"), "{}", stderr);
+    assert_eq!(code, 2);
+
+    let (_, stderr, code) = run(&fixture(), &["--user", "4242"]);
+    assert_eq!(stderr, "rusty-pstree: no process of user 4242 found\n");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn man_page_documents_every_long_option()
+{
+    let (help, _, _) = run(&fixture(), &["--help"]);
+    let (page, _, code) = run(&fixture(), &["--generate-man"]);
+    assert_eq!(code, 0);
+    assert!(page.starts_with(".ie"), "{}", page);
+
+    let long_options: Vec<&str> = help.lines()
+        .map(str::trim_start)
+        .filter(|line| line.starts_with('-'))
+        .filter_map(|line| line.split([' ', ',', '[']).find(|word| word.starts_with("--")))
+        .collect();
+    assert!(long_options.len() > 20, "{:?}", long_options);
+
+    let entries: Vec<&str> = page.split(".TP\n").skip(1).collect();
+    for option in long_options {
+        let escaped = option.replace('-', "\\-");
+        let entry = entries.iter()
+            .find(|entry| entry.lines().next().is_some_and(|line| line.contains(&format!("{}\\fR", escaped))))
+            .unwrap_or_else(|| panic!("{} is missing from the man page", option));
+        let help_text = entry.lines().nth(1).unwrap_or("");
+        assert!(!help_text.is_empty() && !help_text.starts_with('.'), "{} has no help text", option);
+    }
+
+    let full = fs::File::create("/dev/full").unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_rusty-pstree")).arg("--generate-man").stdout(full).status().unwrap();
+    assert_eq!(status.code(), Some(3));
+}
+
+#[test]
+fn summary_counts_the_printed_tree()
+{
+    let procfs = fixture();
+    let mut bash = FakeProcess::new(30, 5, "bash");
+    bash.uid = 1000;
+    bash.threads = 4;
+    procfs.add_process(&bash);
+    let mut zombie = FakeProcess::new(33, 30, "defunct");
+    zombie.state = 'Z';
+    procfs.add_process(&zombie);
+
+    let (stdout, _, code) = run(&procfs, &["--summary"]);
+    assert_eq!(code, 0);
+    assert!(stdout.ends_with("\n9 processes, 12 threads, 1 zombie, max depth 3\n"), "{}", stdout);
+
+    let (stdout, _, _) = run(&procfs, &["--summary", "-r", "5", "-d", "1"]);
+    assert_eq!(stdout, "(init(1) › sshd(5))\nsshd\n└─ bash\n2 processes shown (of 9), 5 threads, 0 zombies, max depth 1\n");
+
+    let (stdout, _, _) = run(&procfs, &["--summary", "--user", "1000"]);
+    assert!(stdout.ends_with("\n5 processes shown (of 9), 8 threads, 1 zombie, max depth 1\n"), "{}", stdout);
+}
+
+#[test]
+fn stats_replace_the_tree()
+{
+    let procfs = fixture();
+    let (stdout, _, code) = run(&procfs, &["--stats"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+processes: 8
+threads: 8
+max depth: 3
+widest: 30 with 3 children
+leaves: 4
+users: 2
+largest subtree: 5 with 5 processes
+");
+
+    let (stdout, _, _) = run(&procfs, &["--stats", "-r", "30", "-d", "0"]);
+    assert_eq!(stdout, "processes: 1\nthreads: 1\nmax depth: 0\nleaves: 1\nusers: 1\n");
+}
+
+#[test]
+fn summary_counts_the_whole_system_after_a_subtree_scan()
+{
+    let procfs = fixture();
+    procfs.write_file(5, "task/5/children", b"30 ");
+    procfs.write_file(30, "task/30/children", b"20 31 ");
+    procfs.write_file(30, "task/33/children", b"32 ");
+    for pid in [20, 31, 32] {
+        procfs.write_file(pid, &format!("task/{}/children", pid), b"");
+    }
+
+    let (stdout, _, code) = run(&procfs, &["-r", "5", "--summary"]);
+    assert_eq!(code, 0);
+    assert!(stdout.ends_with("\n5 processes shown (of 8), 5 threads, 0 zombies, max depth 2\n"), "{}", stdout);
+}
+
+#[test]
+fn collapsed_chains_are_still_separate_processes()
+{
+    assert_output(&["--collapse-chains", "-r", "5"], "(init(1) › sshd(5))\nsshd ▸ bash\n├─ sleep\n├─ sleep\n└─ sleep\n");
+    assert_output(&["--collapse-chains", "-r", "5", "--count"], "5\n");
+    assert_output(&["--collapse-chains", "-r", "5", "-d", "1", "-p"], "(init(1) › sshd(5))\n[5] sshd ▸ [30] bash\n");
+}
+
+#[test]
+fn max_lines_counts_the_processes_left_out()
+{
+    assert_output(&["--max-lines", "4", "-r", "1"], "\
+init
+└─ sshd
+   └─ bash
+      └─ sleep
+… output truncated, 2 more processes (use --max-lines 0 for all)
+");
+    assert_output(&["--max-lines", "3"], "\
+(all)
+├─ init
+└─ kthreadd
+… output truncated, 6 more processes (use --max-lines 0 for all)
+");
+    assert_output(&["--max-lines", "0", "-r", "30", "--no-ancestor-header"], "bash\n├─ sleep\n├─ sleep\n└─ sleep\n");
+}
+
+#[test]
+fn top_ranks_what_the_filters_keep()
+{
+    let procfs = fixture();
+    procfs.add_process(&FakeProcess::new(40, 1, "cron"));
+    procfs.add_process(&FakeProcess::new(41, 40, "sh"));
+
+    let (stdout, _, _) = run(&procfs, &["--top", "1"]);
+    assert_eq!(stdout, "(all)\n├─ init\n│  ├─ sshd\n│  │  └─ bash\n│  │     ├─ sleep\n│  │     ├─ sleep\n│  │     └─ sleep\n\
+│  └─ cron\n│     └─ sh\n└─ … 1 smaller subtree (2 processes)\n");
+
+    let (stdout, _, code) = run(&procfs, &["--top", "1", "-r", "1"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "init\n├─ sshd\n│  └─ bash\n│     ├─ sleep\n│     ├─ sleep\n│     └─ sleep\n└─ … 1 smaller subtree (2 processes)\n");
+
+    // --user leaves bash as the only root, so its children are ranked.
+    let (stdout, _, _) = run(&procfs, &["--top", "1", "--user", "1000", "-p", "--summary"]);
+    assert_eq!(stdout, "\
+[30] bash
+├─ [20] sleep
+└─ … 2 smaller subtrees (2 processes)
+2 processes shown (of 10), 2 threads, 0 zombies, max depth 1
+");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn diff_against_a_saved_snapshot()
+{
+    let procfs = fixture();
+    let snapshot = procfs.path().join("snapshot.json");
+    let snapshot = snapshot.to_str().unwrap();
+    let (stdout, _, code) = run(&procfs, &["--save", snapshot, "-r", "5"]);
+    assert_eq!((stdout.as_str(), code), ("(init(1) › sshd(5))\nsshd\n└─ bash\n   ├─ sleep\n   ├─ sleep\n   └─ sleep\n", 0));
+
+    let (stdout, _, _) = run(&procfs, &["--diff", snapshot, "--diff-only"]);
+    assert_eq!(stdout, "no changes\n");
+
+    // 31 exits, its pid goes to another process and sshd starts a child.
+    fs::remove_dir_all(procfs.path().join("31")).unwrap();
+    let mut reused = FakeProcess::new(31, 30, "sleep");
+    reused.start_time = 9000;
+    procfs.add_process(&reused);
+    procfs.add_process(&FakeProcess::new(40, 5, "sftp-server"));
+
+    let (stdout, _, code) = run(&procfs, &["--diff", snapshot, "-r", "5", "-p"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+(init(1) › sshd(5))
+[5] sshd
+├─ [30] bash
+│  ├─ [20] sleep
+│  ├─ [31] sleep [new]
+│  └─ [32] sleep
+└─ [40] sftp-server [new]
+(gone)
+└─ [31] sleep [gone]
+");
+
+    let (stdout, _, _) = run(&procfs, &["--diff", snapshot, "--diff-only", "-d", "3"]);
+    assert_eq!(stdout, "\
+init
+└─ sshd
+   ├─ bash
+   │  └─ sleep [new]
+   └─ sftp-server [new]
+(gone)
+└─ sleep [gone]
+");
+
+    let (_, stderr, code) = run(&procfs, &["--diff", "/nonexistent/snapshot.json"]);
+    assert!(stderr.starts_with("rusty-pstree: unable to read snapshot \"/nonexistent/snapshot.json\""), "{}", stderr);
+    assert_eq!(code, 3);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn snapshots_go_through_stdin_and_stdout()
+{
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let procfs = fixture();
+    let (snapshot, _, code) = run(&procfs, &["--save", "-"]);
+    assert_eq!(code, 0);
+    assert!(snapshot.starts_with("[{\"pid\":1,"), "{}", snapshot);
+
+    // Nothing is scanned, so --proc-root may be empty.
+    let empty = FakeProcfs::new();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rusty-pstree"))
+        .arg("--proc-root")
+        .arg(empty.path())
+        .args(["--load", "-", "-r", "30", "-p"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(snapshot.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\
+(init(1) › sshd(5) › bash(30))
+[30] bash
+├─ [20] sleep
+├─ [31] sleep
+└─ [32] sleep
+");
+
+    let (_, stderr, code) = run(&procfs, &["--load", "-", "--diff", "-"]);
+    assert!(stderr.contains("only one of --load and --diff can read stdin"), "{}", stderr);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn ps_output_is_drawn_from_a_file_or_stdin()
+{
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let ps_output = "\
+  PID  PPID S COMMAND
+    1     0 S /sbin/init splash
+    2     0 S [kthreadd]
+  512     1 S sshd: /usr/sbin/sshd -D
+ 1234   512 S -bash
+ 1240  1234 Z [sleep] <defunct>
+";
+    // Nothing is scanned, so --proc-root may be empty.
+    let empty = FakeProcfs::new();
+    let path = empty.path().join("ps.txt");
+    fs::write(&path, ps_output).unwrap();
+    let (stdout, stderr, code) = run(&empty, &["--from-ps", path.to_str().unwrap(), "-p"]);
+    assert_eq!(stderr, "");
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+(all)
+├─ [1] init
+│  └─ [512] sshd
+│     └─ [1234] bash
+│        └─ [1240] sleep
+└─ [2] kthreadd
+");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rusty-pstree"))
+        .arg("--proc-root")
+        .arg(empty.path())
+        .args(["--from-ps", "-", "--args", "--root-name", "splash"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(ps_output.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "init\n└─ sshd\n   └─ bash\n      └─ sleep\n");
+
+    fs::write(&path, "PID COMMAND\n1 init\n").unwrap();
+    let (_, stderr, code) = run(&empty, &["--from-ps", path.to_str().unwrap()]);
+    assert_eq!(stderr, format!("rusty-pstree: unable to read ps output \"{}\": no PPID column\n", path.display()));
+    assert_eq!(code, 3);
+}
+
+#[test]
+fn org_output_puts_every_root_at_the_top()
+{
+    assert_output(
+        &["--output", "org", "--org-max-level", "2"],
+        "* init\n** sshd\n- bash\n  - sleep\n  - sleep\n  - sleep\n* kthreadd\n** kworker/0:1\n",
+    );
+    let (_, stderr, code) = run(&fixture(), &["--output", "yaml"]);
+    assert!(stderr.contains("\"yaml\" isn't a valid value for '--output <FORMAT>'"), "{}", stderr);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn forest_output_lays_out_columns_like_ps()
+{
+    let procfs = fixture();
+    procfs.add_process(&FakeProcess::new(12345, 32, "worker"));
+    let mut helper = FakeProcess::new(12346, 12345, "helper");
+    helper.state = 'R';
+    procfs.add_process(&helper);
+
+    let (stdout, stderr, code) = run(&procfs, &["--output", "forest", "--columns", "pid,ppid,stat"]);
+    assert_eq!((stderr.as_str(), code), ("", 0));
+    assert_eq!(stdout, "  PID  PPID STAT COMMAND
+    1     0 S    init
+    5     1 S     \\_ sshd
+   30     5 S       \\_ bash
+   20    30 S         \\_ sleep
+   31    30 S         \\_ sleep
+   32    30 S         \\_ sleep
+12345    32 S           \\_ worker
+12346 12345 R             \\_ helper
+    2     0 S    kthreadd
+   10     2 I     \\_ kworker/0:1
+");
+
+    let (_, stderr, code) = run(&procfs, &["--output", "forest", "--columns", "pid,cpu"]);
+    assert!(stderr.contains("invalid column 'cpu' (expected pid, ppid, user, stat or rss)"), "{}", stderr);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn compat_output_matches_the_classic_pstree()
+{
+    let procfs = fixture();
+    let columns = [("COLUMNS", "132")];
+
+    let (stdout, _, code) = run_with_env(procfs.path(), &["--compat"], &columns);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "init───sshd───bash───3*[sleep]\nkthreadd───kworker/0:1\n");
+
+    let (stdout, _, _) = run_with_env(procfs.path(), &["--compat", "-p"], &columns);
+    assert_eq!(stdout, "\
+init(1)───sshd(5)───bash(30)─┬─sleep(20)
+                             ├─sleep(31)
+                             └─sleep(32)
+kthreadd(2)───kworker/0:1(10)
+");
+
+    let (stdout, _, _) = run_with_env(procfs.path(), &["--compat", "-p", "-A"], &columns);
+    assert_eq!(stdout, "\
+init(1)---sshd(5)---bash(30)-+-sleep(20)
+                             |-sleep(31)
+                             `-sleep(32)
+kthreadd(2)---kworker/0:1(10)
+");
+
+    let (stdout, _, _) = run_with_env(procfs.path(), &["--compat", "-p", "-r", "1"], &[("COLUMNS", "20")]);
+    assert_eq!(stdout, "\
+init(1)───sshd(5)───+
+                    +
+                    +
+");
+}
+
+#[test]
+fn compat_output_compacts_equal_subtrees()
+{
+    let procfs = FakeProcfs::new();
+    procfs.add_process(&FakeProcess::new(1, 0, "init"));
+    for (pid, ppid, name) in [(10, 1, "getty"), (11, 1, "getty"), (20, 1, "sshd"), (21, 20, "bash"), (22, 1, "cron")] {
+        procfs.add_process(&FakeProcess::new(pid, ppid, name));
+    }
+    for (pid, ppid, name) in [(23, 1, "sshd"), (24, 23, "bash"), (30, 1, "getty"), (31, 22, "sh"), (32, 31, "backup")] {
+        procfs.add_process(&FakeProcess::new(pid, ppid, name));
+    }
+
+    let (stdout, _, code) = run_with_env(procfs.path(), &["--compat"], &[("COLUMNS", "132")]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+init─┬─3*[getty]
+     ├─2*[sshd───bash]
+     └─cron───sh───backup
+");
+}
+
+#[test]
+fn wrap_aligns_long_labels_under_their_start()
+{
+    let procfs = fixture();
+    procfs.add_process(&FakeProcess::new(31, 30, "worker --config /etc/app/settings.conf --verbose"));
+    let expected = "\
+init
+└─ sshd
+   └─ bash
+      ├─ sleep
+      ├─ worker --config
+      │  /etc/app/settings.con
+      │  f --verbose
+      └─ sleep
+";
+
+    let (stdout, _, code) = run(&procfs, &["-r", "1", "--wrap=30"]);
+    assert_eq!((stdout.as_str(), code), (expected, 0));
+    let (stdout, _, _) = run_with_env(procfs.path(), &["-r", "1", "--wrap"], &[("COLUMNS", "30")]);
+    assert_eq!(stdout, expected);
+
+    // Labels that fit are left alone, however deep.
+    let (stdout, _, _) = run(&procfs, &["-r", "1", "--wrap=80"]);
+    assert!(stdout.contains("├─ worker --config /etc/app/settings.conf --verbose\n"), "{}", stdout);
+}
+
+#[test]
+fn wrap_counts_wide_characters_twice_and_never_splits_them()
+{
+    let procfs = fixture();
+    procfs.add_process(&FakeProcess::new(10, 2, "データ転送ワーカー起動"));
+    procfs.add_process(&FakeProcess::new(11, 2, "idle"));
+
+    let (stdout, _, code) = run(&procfs, &["-r", "2", "--wrap=20"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+kthreadd
+├─ データ転送ワーカ
+│  ー起動
+└─ idle
+");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "2", "--wrap=20", "-A"]);
+    assert_eq!(stdout, "kthreadd\n|- データ転送ワーカ\n|  ー起動\n`- idle\n");
+}
+
+#[test]
+fn escaped_names_never_reach_the_terminal_raw()
+{
+    let procfs = fixture();
+    procfs.write_file(32, "status", b"Name:\tclear\\e[2J\\\\\nPid:\t32\nPPid:\t30\nUid:\t0\t0\t0\t0\n");
+    let (stdout, _, code) = run(&procfs, &["-r", "30"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep\n├─ sleep\n└─ clear\\u{1b}[2J\\\n");
+}
+
+#[test]
+fn exe_prefix_matches_executables_below_a_directory()
+{
+    let procfs = fixture();
+    let install = fs::canonicalize(procfs.path()).unwrap().join("opt/app");
+    fs::create_dir_all(install.join("bin")).unwrap();
+    procfs.set_exe(30, &install.join("bin/bash"));
+    procfs.set_exe(31, Path::new(&format!("{}/lib/helper (deleted)", install.display())));
+    procfs.set_exe(32, &install.with_file_name("application/sleep"));
+    procfs.set_exe(10, Path::new("/usr/lib/worker"));
+
+    // The prefix is compared once resolved, and only whole components match.
+    let prefix = install.join("bin/..");
+    let (stdout, _, code) = run(&procfs, &["--exe-prefix", prefix.to_str().unwrap(), "-p"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "[1] init\n└─ [5] sshd\n   └─ [30] bash\n      └─ [31] sleep\n");
+
+    let (stdout, _, _) = run(&procfs, &["--exe-prefix", install.to_str().unwrap(), "--exe-prefix", "/usr/lib", "-p"]);
+    assert_eq!(stdout, "\
+(all)
+├─ [1] init
+│  └─ [5] sshd
+│     └─ [30] bash
+│        └─ [31] sleep
+└─ [2] kthreadd
+   └─ [10] kworker/0:1
+");
+
+    let (_, stderr, code) = run(&procfs, &["--exe-prefix", "/nonexistent"]);
+    assert_eq!(stderr, "rusty-pstree: no /nonexistent process found\n");
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn show_ns_marks_where_a_namespace_starts()
+{
+    let procfs = fixture();
+    for pid in [1, 5, 30, 20, 31] {
+        procfs.set_ns(pid, "mnt", 4026531841);
+        procfs.set_ns(pid, "pid", 4026531836);
+    }
+    procfs.set_ns(30, "mnt", 4026532301);
+    procfs.set_ns(31, "mnt", 4026532302);
+    procfs.set_ns(31, "pid", 4026532303);
+    procfs.set_ns(32, "mnt", 4026532999);
+
+    // The last sleep cannot be compared, its pid namespace link is missing.
+    let (stdout, _, code) = run(&procfs, &["-r", "5", "--show-ns", "mnt,pid"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+(init(1) › sshd(5))
+sshd
+└─ bash ⊘mnt
+   ├─ sleep ⊘mnt
+   ├─ sleep ⊘mnt ⊘pid
+   └─ sleep ⊘mnt
+");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--show-ns", "pid", "--ascii"]);
+    assert_eq!(stdout, "(init(1) > sshd(5) > bash(30))\nbash\n|- sleep\n|- sleep !pid\n`- sleep\n");
+}
+
+#[test]
+fn name_sort_compares_numbers_by_value()
+{
+    let procfs = FakeProcfs::new();
+    procfs.add_process(&FakeProcess::new(1, 0, "pool"));
+    for (pid, name) in [(2, "worker-10"), (3, "worker-2"), (4, "worker-1")] {
+        procfs.add_process(&FakeProcess::new(pid, 1, name));
+    }
+    let (stdout, _, _) = run(&procfs, &["--sort", "name"]);
+    assert_eq!(stdout, "pool\n├─ worker-1\n├─ worker-2\n└─ worker-10\n");
+    let (stdout, _, _) = run(&procfs, &["--sort", "name", "--bytewise-sort"]);
+    assert_eq!(stdout, "pool\n├─ worker-1\n├─ worker-10\n└─ worker-2\n");
+}
+
+#[test]
+fn cpu_time_adds_the_reaped_children_with_a_plus()
+{
+    // SAFETY: sysconf has no memory safety requirements.
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as u64;
+    let procfs = FakeProcfs::new();
+    let mut make = FakeProcess::new(1, 0, "make");
+    make.cpu_ticks = [ticks, 0, 37 * ticks + ticks / 5, 0];
+    procfs.add_process(&make);
+    let mut cc = FakeProcess::new(2, 1, "cc");
+    cc.cpu_ticks = [4 * 3600 * ticks, 12 * 60 * ticks, 0, 0];
+    procfs.add_process(&cc);
+
+    let (stdout, _, code) = run(&procfs, &["--cpu-time"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "make time=1.0s\n└─ cc time=4h12m\n");
+    let (stdout, _, _) = run(&procfs, &["--cpu-time=children"]);
+    assert_eq!(stdout, "make time=38.2s+\n└─ cc time=4h12m\n");
+}
+
+#[test]
+fn metrics_output_counts_states_and_subtrees()
+{
+    let (stdout, _, code) = run(&fixture(), &["--output", "metrics", "-r", "5"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("pstree_processes_total 5\n"), "{}", stdout);
+    assert!(stdout.contains("pstree_processes{state=\"Z\"} 0\n"), "{}", stdout);
+    assert!(stdout.contains("pstree_subtree_processes{root=\"bash\",pid=\"30\"} 4\n"), "{}", stdout);
+}
+
+#[test]
+fn xml_output_names_the_host()
+{
+    let procfs = fixture();
+    fs::create_dir_all(procfs.path().join("sys/kernel")).unwrap();
+    fs::write(procfs.path().join("sys/kernel/hostname"), "web1\n").unwrap();
+    let (stdout, _, code) = run(&procfs, &["--output", "xml", "-r", "2"]);
+    assert_eq!(code, 0);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[1].starts_with(r#"<processes hostname="web1" timestamp=""#), "{}", stdout);
+    assert_eq!(lines[2 ..], [
+        r#"  <process pid="2" ppid="0" name="kthreadd" uid="0" state="S" tracer_pid="0">"#,
+        r#"    <process pid="10" ppid="2" name="kworker/0:1" uid="0" state="I" tracer_pid="0"/>"#,
+        "  </process>",
+        "</processes>",
+    ]);
+
+    let path = procfs.path().join("ps.txt");
+    fs::write(&path, "PID PPID CMD\n1 0 init\n").unwrap();
+    let (stdout, _, code) = run(&procfs, &["--output", "xml", "--from-ps", path.to_str().unwrap()]);
+    assert_eq!(code, 0);
+    assert!(stdout.lines().nth(1).unwrap().starts_with(r#"<processes timestamp=""#), "{}", stdout);
+}
+
+#[test]
+fn anonymize_leaks_no_name_into_any_output()
+{
+    let procfs = fixture();
+    fs::create_dir_all(procfs.path().join("sys/kernel")).unwrap();
+    fs::write(procfs.path().join("sys/kernel/hostname"), "web1\n").unwrap();
+    let map = procfs.path().join("map.json");
+
+    let (stdout, _, code) = run(&procfs, &["--anonymize", "--anonymize-map", map.to_str().unwrap()]);
+    assert_eq!((stdout.as_str(), code), ("\
+(all)
+├─ proc-01
+│  └─ proc-03
+│     └─ proc-06
+│        ├─ proc-05
+│        ├─ proc-05
+│        └─ proc-05
+└─ proc-02
+   └─ proc-04
+", 0));
+    let map = fs::read_to_string(&map).unwrap();
+    assert!(map.contains("\"proc-01\": \"init\",\n"), "{}", map);
+    assert!(map.contains("\"proc-06\": \"bash\"\n"), "{}", map);
+    // The user names come from the passwd file of the host, only the uids of the fixture are known.
+    assert!(map.contains("\"user-1\": ") && map.contains("\"user-2\": ") && !map.contains("\"user-3\""), "{}", map);
+
+    // --args only reads the command lines along with a pattern.
+    let mut outputs: Vec<&[&str]> = vec![
+        &["--output", "xml", "--args", "--root-name", "^/usr/bin/init$"],
+        &["--output", "forest", "--columns=pid,user"],
+        &["--output", "org", "--org-properties"],
+        &["--group-by", "user", "-p"],
+    ];
+    if cfg!(feature = "serde") {
+        outputs.push(&["--output", "json", "--args", "--root-name", "^/usr/bin/init$"]);
+    }
+    for args in outputs {
+        let (stdout, _, code) = run(&procfs, &[args, &["--anonymize"]].concat());
+        assert_eq!(code, 0, "{:?}", args);
+        assert!(stdout.contains("proc-05"), "{:?}: {}", args, stdout);
+        for original in ["init", "sshd", "bash", "sleep", "kthreadd", "kworker", "/usr/bin", "web1", "root"] {
+            assert!(!stdout.contains(original), "{:?} leaks {}: {}", args, original, stdout);
+        }
+    }
+
+    // Selections still see the real names and command lines.
+    let (stdout, _, _) = run(&procfs, &["--root-name", "^/usr/bin/bash$", "--args", "--anonymize", "-d", "0"]);
+    assert_eq!(stdout, "proc-06\n");
+    // The ancestor header names the processes with the placeholders of the whole tree.
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--anonymize", "-d", "0"]);
+    assert_eq!(stdout, "(proc-01(1) › proc-03(5) › proc-06(30))\nproc-06\n");
+    let (stdout, _, _) = run(&procfs, &["--find", "kworker", "--anonymize"]);
+    assert_eq!(stdout, "10\tproc-04\tproc-02(2) → proc-04(10)\n");
+}
+
+#[test]
+fn no_root_draws_the_children_as_a_forest()
+{
+    assert_output(&["-r", "30", "--no-root", "-p"], "[20] sleep\n[31] sleep\n[32] sleep\n");
+    assert_output(&["--no-root", "-p"], "[5] sshd\n└─ [30] bash\n   ├─ [20] sleep\n   ├─ [31] sleep\n   └─ [32] sleep\n[10] kworker/0:1\n");
+    assert_output(&["-r", "5", "--no-root", "-d", "1"], "(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep\n├─ sleep\n└─ sleep\n");
+
+    let (stdout, _, code) = run(&fixture(), &["-r", "30", "--no-root", "--pids"]);
+    assert_eq!((stdout.as_str(), code), ("20\n31\n32\n", 0));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_output_and_watch_interval()
+{
+    let (stdout, _, code) = run(&fixture(), &["--output", "json", "-r", "2"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, concat!(
+        r#"[{"pid":2,"ppid":0,"name":"kthreadd","uid":0,"state":"S","tracer_pid":0,"hidden_descendants":0,"children":["#,
+        r#"{"pid":10,"ppid":2,"name":"kworker/0:1","uid":0,"state":"I","tracer_pid":0,"hidden_descendants":0,"children":[]}]}]"#,
+        "\n",
+    ));
+
+    let (stdout, _, code) = run(&fixture(), &["--output", "tree-json", "-r", "2"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, concat!(
+        r#"[{"type":"process","name":"kthreadd","pid":2,"contents":[{"type":"process","name":"kworker/0:1","pid":10}]},"#,
+        r#"{"type":"report","directories":1,"files":1}]"#,
+        "\n",
+    ));
+
+    let (_, stderr, code) = run(&fixture(), &["--output", "json", "--diff", "snapshot.json"]);
+    assert!(stderr.contains("--diff can only be used with --output tree"), "{}", stderr);
+    assert_eq!(code, 2);
+
+    let (_, stderr, code) = run(&fixture(), &["--watch=-1"]);
+    assert!(stderr.contains("invalid interval '-1' (expected a positive number of seconds)"), "{}", stderr);
+    assert_eq!(code, 2);
+
+    for args in [&["--changes-only"][..], &["--watch", "1", "--rss-change", "10"]] {
+        let (_, stderr, code) = run(&fixture(), args);
+        assert!(stderr.contains("required arguments were not provided"), "{}", stderr);
+        assert_eq!(code, 2);
+    }
+}
+
+#[test]
+fn count_includes_roots_and_ignores_unique()
+{
+    assert_output(&["--count"], "8\n");
+    assert_output(&["--count", "-r", "30"], "4\n");
+    assert_output(&["--count", "-r", "30", "--unique"], "4\n");
+    assert_output(&["--count", "--user", "1000", "-d", "0"], "4\n");
+    assert_output(&["--count", "--root-name", "^sleep$"], "3\n");
+
+    let procfs = fixture();
+    let mut traced = FakeProcess::new(31, 30, "sleep");
+    traced.uid = 1000;
+    traced.tracer_pid = 20;
+    procfs.add_process(&traced);
+    let (stdout, _, code) = run(&procfs, &["--count", "--traced-only"]);
+    assert_eq!((stdout.as_str(), code), ("1\n", 0));
+}
+
+#[test]
+fn count_of_nothing_is_zero()
+{
+    assert_output(&["--count", "--root-name", "nginx"], "0\n");
+    assert_output(&["--count", "-r", "999"], "0\n");
+
+    let (stdout, stderr, code) = run(&fixture(), &["--count", "--fail-if-none", "--root-name", "nginx"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("0\n", "", 1));
+
+    let (stdout, _, code) = run(&fixture(), &["--count", "--fail-if-none", "-r", "5"]);
+    assert_eq!((stdout.as_str(), code), ("5\n", 0));
+
+    let (_, _, code) = run(&fixture(), &["--fail-if-none"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn signal_dry_run_lists_children_before_parents()
+{
+    assert_output(&["-r", "5", "--signal", "term", "--dry-run", "-d", "0"], "\
+(init(1) › sshd(5))
+sshd
+would send TERM to 20 (sleep)
+would send TERM to 31 (sleep)
+would send TERM to 32 (sleep)
+would send TERM to 30 (bash)
+would send TERM to 5 (sshd)
+");
+
+    for args in [&["--dry-run"][..], &["--yes"], &["--signal", "TERMINATE"], &["--signal", "0", "--count"]] {
+        let (_, _, code) = run(&fixture(), args);
+        assert_eq!(code, 2, "{:?}", args);
+    }
+}
+
+#[test]
+fn signal_leaves_out_the_ancestors_a_filter_draws()
+{
+    let procfs = fixture();
+    let mut traced = FakeProcess::new(31, 30, "sleep");
+    traced.uid = 1000;
+    traced.tracer_pid = 20;
+    procfs.add_process(&traced);
+
+    let (stdout, stderr, code) = run(&procfs, &["--traced-only", "--signal", "TERM", "--dry-run"]);
+    assert_eq!(stdout, "init\n└─ sshd\n   └─ bash\n      └─ sleep (traced by 20)\nwould send TERM to 31 (sleep)\n");
+    assert_eq!((stderr.as_str(), code), ("", 0));
+
+    let (stdout, stderr, code) = run(&procfs, &["--signal", "TERM", "--dry-run"]);
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "rusty-pstree: refusing to signal process 1, which is init or runs rusty-pstree (use --force to signal it anyway)\n"
+    );
+    assert_eq!(code, 2);
+    let (stdout, _, code) = run(&procfs, &["--signal", "TERM", "--dry-run", "--force", "-r", "1", "-d", "0"]);
+    assert!(stdout.ends_with("would send TERM to 5 (sshd)\nwould send TERM to 1 (init)\n"), "{}", stdout);
+    assert_eq!(code, 0);
+}
+
+/// Pids above the kernel limit of 2^22 never exist, so these can be signaled
+/// for real without touching the processes of the machine running the tests.
+#[test]
+fn signal_failures_are_reported_per_process()
+{
+    let procfs = FakeProcfs::new();
+    procfs.add_process(&FakeProcess::new(4_200_000, 0, "gone"));
+    procfs.add_process(&FakeProcess::new(4_200_001, 4_200_000, "also-gone"));
+
+    let (stdout, stderr, code) = run(&procfs, &["--signal", "0", "--yes"]);
+    assert_eq!(stdout, "gone\n└─ also-gone\n");
+    assert_eq!(stderr, "\
+rusty-pstree: cannot send 0 to 4200001 (also-gone): process already exited
+rusty-pstree: cannot send 0 to 4200000 (gone): process already exited
+rusty-pstree: could not signal 2 of 2 processes
+");
+    assert_eq!(code, 4);
+
+    let (_, stderr, code) = run(&procfs, &["--signal", "0"]);
+    assert_eq!(stderr, "send 0 to 2 processes? [y/N] rusty-pstree: no signal sent\n");
+    assert_eq!(code, 5);
+}
+
+#[test]
+fn pause_and_resume_refuse_init_without_force()
+{
+    assert_output(&["-r", "30", "--pause", "--dry-run", "-d", "0"], "\
+(init(1) › sshd(5) › bash(30))
+bash
+would send STOP to 20 (sleep)
+would send STOP to 31 (sleep)
+would send STOP to 32 (sleep)
+would send STOP to 30 (bash)
+");
+    assert_output(&["-r", "30", "--resume", "--dry-run", "-d", "0"], "\
+(init(1) › sshd(5) › bash(30))
+bash
+would send CONT to 30 (bash)
+would send CONT to 20 (sleep)
+would send CONT to 31 (sleep)
+would send CONT to 32 (sleep)
+");
+
+    let (stdout, stderr, code) = run(&fixture(), &["--pause", "--dry-run"]);
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "rusty-pstree: refusing to pause process 1, which is init or runs rusty-pstree (use --force to pause it anyway)\n"
+    );
+    assert_eq!(code, 2);
+    let (stdout, _, code) = run(&fixture(), &["--resume", "--dry-run", "--force", "-r", "1", "-d", "0"]);
+    assert!(stdout.starts_with("init\nwould send CONT to 1 (init)\nwould send CONT to 5 (sshd)\n"), "{}", stdout);
+    assert_eq!(code, 0);
+
+    for args in [&["--force"][..], &["--pause", "--resume"], &["--pause", "--signal", "TERM"], &["--pause", "--count"]] {
+        let (_, _, code) = run(&fixture(), args);
+        assert_eq!(code, 2, "{:?}", args);
+    }
+}
+
+#[test]
+fn pause_leaves_out_the_ancestors_a_filter_draws()
+{
+    let procfs = fixture();
+    let mut traced = FakeProcess::new(31, 30, "sleep");
+    traced.uid = 1000;
+    traced.tracer_pid = 20;
+    procfs.add_process(&traced);
+
+    let (stdout, stderr, code) = run(&procfs, &["-r", "5", "--traced-only", "--pause", "--dry-run"]);
+    assert_eq!(stdout, "(init(1) › sshd(5))\nsshd\n└─ bash\n   └─ sleep (traced by 20)\nwould send STOP to 31 (sleep)\n");
+    assert_eq!((stderr.as_str(), code), ("", 0));
+}
+
+#[test]
+fn renice_dry_run_shows_current_and_new_values()
+{
+    let procfs = FakeProcfs::new();
+    procfs.add_process(&FakeProcess::new(1, 0, "init"));
+    procfs.add_process(&FakeProcess { nice: 17, ..FakeProcess::new(5, 1, "batch") });
+    procfs.add_process(&FakeProcess { nice: -5, ..FakeProcess::new(6, 5, "worker") });
+
+    let (stdout, _, code) = run(&procfs, &["-r", "5", "--renice", "+5", "--dry-run"]);
+    assert_eq!(stdout, "(init(1) › batch(5))\nbatch\n└─ worker\nwould renice 6 (worker) from -5 to 0\nwould renice 5 (batch) from 17 to 19\n");
+    assert_eq!(code, 0);
+    let (stdout, _, _) = run(&procfs, &["-r", "6", "--renice", "-3", "--dry-run"]);
+    assert_eq!(stdout, "(init(1) › batch(5) › worker(6))\nworker\nwould renice 6 (worker) from -5 to -8\n");
+    let (stdout, _, _) = run(&procfs, &["-r", "6", "--renice", "=10", "--dry-run"]);
+    assert_eq!(stdout, "(init(1) › batch(5) › worker(6))\nworker\nwould renice 6 (worker) from -5 to 10\n");
+
+    for args in [&["--renice", "5"][..], &["--renice", "=20"], &["--renice", "+1", "--signal", "TERM"]] {
+        let (_, _, code) = run(&procfs, args);
+        assert_eq!(code, 2, "{:?}", args);
+    }
+}
+
+#[test]
+fn renice_leaves_out_the_ancestors_a_filter_draws()
+{
+    let procfs = fixture();
+    let mut traced = FakeProcess::new(31, 30, "sleep");
+    traced.uid = 1000;
+    traced.tracer_pid = 20;
+    procfs.add_process(&traced);
+
+    let (stdout, stderr, code) = run(&procfs, &["--traced-only", "--renice", "+5", "--dry-run"]);
+    assert_eq!(stdout, "init\n└─ sshd\n   └─ bash\n      └─ sleep (traced by 20)\nwould renice 31 (sleep) from 0 to 5\n");
+    assert_eq!((stderr.as_str(), code), ("", 0));
+
+    let (_, stderr, code) = run(&procfs, &["--renice", "+5", "--dry-run"]);
+    assert_eq!(
+        stderr,
+        "rusty-pstree: refusing to renice process 1, which is init or runs rusty-pstree (use --force to renice it anyway)\n"
+    );
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn renice_failures_are_reported_per_process()
+{
+    let procfs = FakeProcfs::new();
+    procfs.add_process(&FakeProcess::new(4_200_000, 0, "gone"));
+    procfs.add_process(&FakeProcess::new(4_200_001, 4_200_000, "also-gone"));
+
+    let (_, stderr, code) = run(&procfs, &["--renice", "+1", "--yes"]);
+    assert_eq!(stderr, "\
+rusty-pstree: cannot renice 4200001 (also-gone): process already exited
+rusty-pstree: cannot renice 4200000 (gone): process already exited
+rusty-pstree: could not renice 2 of 2 processes
+");
+    assert_eq!(code, 4);
+
+    let (_, stderr, code) = run(&procfs, &["--renice", "+1"]);
+    assert_eq!(stderr, "renice 2 processes by +1? [y/N] rusty-pstree: no process reniced\n");
+    assert_eq!(code, 5);
+}
+
+#[test]
+fn path_prints_the_ancestry_chain()
+{
+    assert_output(&["--path", "20"], "init(1) → sshd(5) → bash(30) → sleep(20)\n");
+    assert_output(&["--path", "10", "--ascii"], "kthreadd(2) -> kworker/0:1(10)\n");
+    assert_output(&["--path", "1"], "init(1)\n");
+
+    let (stdout, stderr, code) = run(&fixture(), &["--path", "999"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: process 999 does not exist\n", 1));
+
+    for args in [&["--path", "nope"][..], &["--path", "20", "-r", "5"], &["--path", "20", "--count"]] {
+        let (_, _, code) = run(&fixture(), args);
+        assert_eq!(code, 2, "{:?}", args);
+    }
+}
+
+#[test]
+fn find_lists_matches_with_their_ancestry()
+{
+    assert_output(&["--find", "^sl"], "\
+20\tsleep\tinit(1) → sshd(5) → bash(30) → sleep(20)
+31\tsleep\tinit(1) → sshd(5) → bash(30) → sleep(31)
+32\tsleep\tinit(1) → sshd(5) → bash(30) → sleep(32)
+");
+    assert_output(&["--find", "^/usr/bin/ss", "--args", "--ascii"], "5\tsshd\tinit(1) -> sshd(5)\n");
+
+    let (stdout, stderr, code) = run(&fixture(), &["--find", "nginx"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: no process matches 'nginx'\n", 1));
+
+    let (_, _, code) = run(&fixture(), &["--find", "sleep", "--first"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn pids_lists_parents_before_children()
+{
+    assert_output(&["--pids", "-r", "5"], "5\n30\n20\n31\n32\n");
+    assert_output(&["--pids=parents-first", "--user", "1000", "-d", "0", "-u"], "30\n20\n31\n32\n");
+    assert_output(&["--pids=leaves-first", "-r", "5"], "20\n31\n32\n30\n5\n");
+    assert_output(&["--pids=leaves-first", "--root-name", "^k"], "10\n2\n");
+    assert_output(&["--kill-order", "-r", "5"], "20\n31\n32\n30\n5\n");
+    assert_output(&["--kill-order", "--root-name", "^k", "-0"], "10\x002\0");
+    assert_output(&["--pids", "-r", "2", "--null"], "2\x0010\0");
+
+    let (stdout, _, code) = run(&fixture(), &["--pids", "--root-name", "nginx"]);
+    assert_eq!((stdout.as_str(), code), ("", 1));
+
+    let procfs = fixture();
+    let mut traced = FakeProcess::new(31, 30, "sleep");
+    traced.uid = 1000;
+    traced.tracer_pid = 20;
+    procfs.add_process(&traced);
+    let (stdout, _, code) = run(&procfs, &["--pids", "--traced-only"]);
+    assert_eq!((stdout.as_str(), code), ("31\n", 0));
+    let mut traced = FakeProcess::new(30, 5, "bash");
+    traced.uid = 1000;
+    traced.tracer_pid = 1;
+    procfs.add_process(&traced);
+    let (stdout, _, code) = run(&procfs, &["--kill-order", "--traced-only"]);
+    assert_eq!((stdout.as_str(), code), ("31\n30\n", 0));
+
+    for args in [&["--pids=children"][..], &["--pids", "--count"]] {
+        let (_, _, code) = run(&fixture(), args);
+        assert_eq!(code, 2, "{:?}", args);
+    }
+}
+
+#[test]
+fn traced_processes_are_marked()
+{
+    let procfs = fixture();
+    let mut traced = FakeProcess::new(31, 30, "sleep");
+    traced.uid = 1000;
+    traced.tracer_pid = 20;
+    procfs.add_process(&traced);
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "-p"]);
+    assert_eq!(stdout, "\
+(init(1) › sshd(5) › bash(30))
+[30] bash
+├─ [20] sleep
+├─ [31] sleep (traced by 20)
+└─ [32] sleep
+");
+
+    let (stdout, _, code) = run(&procfs, &["--traced-only"]);
+    assert_eq!((stdout.as_str(), code), ("init\n└─ sshd\n   └─ bash\n      └─ sleep (traced by 20)\n", 0));
+
+    let (stdout, stderr, code) = run(&fixture(), &["--traced-only"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: no traced process found\n", 1));
+}
+
+#[test]
+fn newer_than_keeps_processes_started_after_the_reference()
+{
+    let procfs = fixture();
+    assert_output(&["--newer-than", "20", "-p"], "\
+[1] init
+└─ [5] sshd
+   └─ [30] bash
+      ├─ [31] sleep
+      └─ [32] sleep
+");
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--newer-than", "5", "-p"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\n[30] bash\n├─ [20] sleep\n├─ [31] sleep\n└─ [32] sleep\n");
+    assert_output(&["--older-than", "5", "-p"], "(all)\n├─ [1] init\n└─ [2] kthreadd\n");
+
+    // Started within the same tick is not started later.
+    let mut sleep = FakeProcess::new(32, 30, "sleep");
+    sleep.start_time = 1031;
+    procfs.add_process(&sleep);
+    let (stdout, stderr, code) = run(&procfs, &["--newer-than", "31"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: no newer process found\n", 1));
+
+    let (stdout, stderr, code) = run(&procfs, &["--newer-than", "99"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: process 99 does not exist\n", 1));
+}
+
+#[test]
+fn listening_ports_are_shown()
+{
+    let procfs = fixture();
+    procfs.write_net("tcp", "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 2001 1
+   1: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 2002 1
+");
+    procfs.add_fd(20, 3, "socket:[2001]");
+    procfs.add_fd(20, 4, "/dev/null");
+    procfs.add_fd(32, 5, "socket:[2002]");
+    procfs.add_fd(32, 6, "socket:[9999]");
+
+    let (stdout, _, code) = run(&procfs, &["-r", "20", "--listen"]);
+    assert_eq!((stdout.as_str(), code), ("(init(1) › sshd(5) › bash(30) › sleep(20))\nsleep (:22)\n", 0));
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--listen"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep (:22)\n├─ sleep\n└─ sleep (127.0.0.1:8080)\n");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "20"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30) › sleep(20))\nsleep\n");
+}
+
+#[test]
+fn open_file_keeps_holders_and_ancestors()
+{
+    let procfs = fixture();
+    let log = procfs.path().join("app.log");
+    fs::write(&log, "").unwrap();
+    procfs.add_fd(31, 3, log.to_str().unwrap());
+    procfs.add_fd(20, 3, "/dev/null");
+
+    fs::create_dir(procfs.path().join("logs")).unwrap();
+    let dotted = procfs.path().join("logs").join("..").join("app.log");
+    let (stdout, _, code) = run(&procfs, &["-p", "--open-file", dotted.to_str().unwrap()]);
+    assert_eq!((stdout.as_str(), code), ("[1] init\n└─ [5] sshd\n   └─ [30] bash\n      └─ [31] sleep\n", 0));
+
+    let (stdout, stderr, code) = run(&procfs, &["--open-file", "/nonexistent/file"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: no process has \"/nonexistent/file\" open\n", 1));
+}
+
+#[test]
+fn wchan_is_shown_for_sleeping_processes()
+{
+    let procfs = fixture();
+    procfs.write_file(30, "wchan", b"do_wait");
+    procfs.write_file(20, "wchan", b"hrtimer_nanosleep");
+    procfs.write_file(31, "wchan", b"0");
+
+    let (stdout, _, code) = run(&procfs, &["-r", "30", "--wchan"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash [do_wait]\n├─ sleep [hrtimer_nanosleep]\n├─ sleep\n└─ sleep\n");
+}
+
+#[test]
+fn cpu_id_is_shown()
+{
+    let (stdout, _, code) = run(&fixture(), &["-r", "30", "-p", "--show-cpu-id"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\n[30] bash cpu=6\n├─ [20] sleep cpu=4\n├─ [31] sleep cpu=7\n└─ [32] sleep cpu=0\n");
+}
+
+#[test]
+fn affinity_is_shown_for_pinned_processes()
+{
+    let procfs = fixture();
+    procfs.write_cpuinfo(8);
+    let mut pinned = FakeProcess::new(31, 30, "sleep");
+    pinned.uid = 1000;
+    pinned.cpus_allowed = "0-3".to_string();
+    procfs.add_process(&pinned);
+
+    let (stdout, _, code) = run(&procfs, &["-r", "30", "--affinity"]);
+    assert_eq!((stdout.as_str(), code), ("(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep\n├─ sleep [cpus:0-3]\n└─ sleep\n", 0));
+}
+
+#[test]
+fn fd_pressure_shows_processes_near_their_limit()
+{
+    let procfs = fixture();
+    let mut haproxy = FakeProcess::new(31, 30, "haproxy");
+    haproxy.fd_size = 31 * 1024;
+    haproxy.open_files_limit = "32768".to_string();
+    procfs.add_process(&haproxy);
+
+    let (stdout, _, code) = run(&procfs, &["-r", "30", "--fd-pressure"]);
+    assert_eq!((stdout.as_str(), code), ("(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep\n├─ haproxy [fds 31k/32k]\n└─ sleep\n", 0));
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--fd-pressure=all"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash [fds 64/1024]\n├─ sleep [fds 64/1024]\n├─ haproxy [fds 31k/32k]\n└─ sleep [fds 64/1024]\n");
+}
+
+#[test]
+fn peak_memory_is_shown()
+{
+    let procfs = fixture();
+    procfs.write_file(32, "status", b"Name:\tsleep\nPid:\t32\nPPid:\t30\nUid:\t0\t0\t0\t0\n");
+
+    let (stdout, _, code) = run(&procfs, &["-r", "30", "--peak-memory"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+(init(1) › sshd(5) › bash(30))
+bash rss=4.0M peak=4.0M
+├─ sleep rss=4.0M peak=4.0M
+├─ sleep rss=4.0M peak=4.0M
+└─ sleep
+");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "20", "--peak-memory=virtual"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30) › sleep(20))\nsleep rss=4.0M peak=4.0M vpeak=16M\n");
+
+    // Options reading the resident memory for themselves read the peak along with it.
+    let (stdout, _, _) = run(&procfs, &["-r", "20", "--output", "forest", "--columns", "pid,rss"]);
+    assert_eq!(stdout, "PID  RSS COMMAND\n 20 4.0M sleep rss=4.0M\n");
+}
+
+#[test]
+fn units_change_how_sizes_are_written()
+{
+    let header = "(init(1) › sshd(5) › bash(30) › sleep(20))\n";
+    assert_output(
+        &["-r", "20", "--peak-memory=virtual", "--units", "raw"],
+        &format!("{}sleep rss=4096 peak=4096 vpeak=16388\n", header),
+    );
+    assert_output(&["-r", "20", "--peak-memory", "--units", "kib"], &format!("{}sleep rss=4096K peak=4096K\n", header));
+    assert_output(&["-r", "20", "--peak-memory", "--units", "mib"], &format!("{}sleep rss=4.0M peak=4.0M\n", header));
+}
+
+#[test]
+fn pss_is_shown_and_marks_the_processes_showing_rss()
+{
+    let procfs = fixture();
+    procfs.set_smaps_rollup(30, 3072, 1024, 2560);
+    procfs.set_smaps_rollup(20, 3072, 1024, 1536);
+    procfs.set_smaps_rollup(31, 3072, 1024, 1536);
+
+    let (stdout, stderr, code) = run(&procfs, &["-r", "30", "--pss"]);
+    assert_eq!((stderr.as_str(), code), ("", 0));
+    assert_eq!(stdout, "\
+(init(1) › sshd(5) › bash(30))
+bash pss=2.5M
+├─ sleep pss=1.5M
+├─ sleep pss=1.5M
+└─ sleep pss=4.0M*
+");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--pss", "--output", "forest", "--columns", "pid,rss"]);
+    assert_eq!(stdout, "\
+PID   PSS COMMAND
+ 30  2.5M bash pss=2.5M
+ 20  1.5M  \\_ sleep pss=1.5M
+ 31  1.5M  \\_ sleep pss=1.5M
+ 32 4.0M*  \\_ sleep pss=4.0M*
+");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--pss", "--output", "metrics"]);
+    assert!(stdout.contains("pstree_subtree_rss_bytes{root=\"sleep\",pid=\"20\"} 4194304\n"), "{}", stdout);
+    assert!(stdout.contains("pstree_subtree_pss_bytes{root=\"sleep\",pid=\"20\"} 1572864\n"), "{}", stdout);
+    assert!(stdout.contains("pstree_subtree_pss_bytes{root=\"sleep\",pid=\"32\"} 4194304\n"), "{}", stdout);
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--output", "metrics"]);
+    assert!(!stdout.contains("pstree_subtree_pss_bytes"), "{}", stdout);
+}
+
+#[test]
+fn memory_detail_splits_private_and_shared_memory()
+{
+    let procfs = fixture();
+    procfs.write_file(32, "status", b"Name:\tsleep\nPid:\t32\nPPid:\t30\nUid:\t0\t0\t0\t0\nVmHWM:\t    4096 kB\nVmRSS:\t    4096 kB\n");
+    procfs.set_smaps_rollup(30, 2048, 2048, 3072);
+
+    let (stdout, stderr, code) = run(&procfs, &["-r", "30", "--memory-detail"]);
+    assert_eq!((stderr.as_str(), code), ("", 0));
+    assert_eq!(stdout, "\
+(init(1) › sshd(5) › bash(30))
+bash rss=4.0M (priv 1.0M, shr 3.0M)
+├─ sleep rss=4.0M (priv 1.0M, shr 3.0M)
+├─ sleep rss=4.0M (priv 1.0M, shr 3.0M)
+└─ sleep rss=4.0M
+");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--memory-detail", "--pss"]);
+    assert_eq!(stdout, "\
+(init(1) › sshd(5) › bash(30))
+bash pss=3.0M (priv 2.0M, shr 2.0M)
+├─ sleep pss=4.0M* (priv 1.0M, shr 3.0M)
+├─ sleep pss=4.0M* (priv 1.0M, shr 3.0M)
+└─ sleep pss=4.0M*
+");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "5", "--memory-detail", "--output", "metrics"]);
+    assert!(stdout.contains("pstree_subtree_rss_bytes{root=\"bash\",pid=\"30\"} 16777216\n"), "{}", stdout);
+    assert!(stdout.contains("pstree_subtree_private_bytes{root=\"bash\",pid=\"30\"} 3145728\n"), "{}", stdout);
+}
+
+#[test]
+fn umask_is_shown_unless_default()
+{
+    let procfs = fixture();
+    let mut strict = FakeProcess::new(31, 30, "sleep");
+    strict.uid = 1000;
+    strict.umask = 0o077;
+    procfs.add_process(&strict);
+
+    let (stdout, _, code) = run(&procfs, &["-r", "30", "--umask"]);
+    assert_eq!((stdout.as_str(), code), ("(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep\n├─ sleep (umask 0077)\n└─ sleep\n", 0));
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--umask", "--umask"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash (umask 0022)\n├─ sleep (umask 0022)\n├─ sleep (umask 0077)\n└─ sleep (umask 0022)\n");
+}
+
+#[test]
+fn seccomp_confinement_is_marked_and_filtered()
+{
+    let procfs = fixture();
+    for (pid, seccomp, filters) in [(20, 2, 1), (31, 2, 4), (32, 1, 0)] {
+        let mut confined = FakeProcess::new(pid, 30, "sleep");
+        confined.seccomp = seccomp;
+        confined.seccomp_filters = filters;
+        procfs.add_process(&confined);
+    }
+
+    let (stdout, _, code) = run(&procfs, &["-r", "30", "--seccomp"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep [seccomp:filter]\n├─ sleep [seccomp:filter x4]\n└─ sleep [seccomp:strict]\n");
+
+    let (stdout, _, _) = run(&procfs, &["--seccomp-only", "-d", "2"]);
+    assert_eq!(stdout, "init\n└─ sshd\n   └─ bash\n");
+
+    let (stdout, _, _) = run(&procfs, &["--seccomp-only", "-r", "30"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep\n├─ sleep\n└─ sleep\n");
+
+    let (stdout, _, _) = run(&procfs, &["--seccomp-only", "--seccomp", "-r", "30", "-d", "1"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep [seccomp:filter]\n├─ sleep [seccomp:filter x4]\n└─ sleep [seccomp:strict]\n");
+
+    let (stdout, _, _) = run(&procfs, &["--no-seccomp-only", "-r", "5"]);
+    assert_eq!(stdout, "(init(1) › sshd(5))\nsshd\n└─ bash\n");
+
+    let (stdout, stderr, code) = run(&fixture(), &["--seccomp-only"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: no seccomp confined process found\n", 1));
+}
+
+#[test]
+fn no_new_privs_is_tagged_either_way()
+{
+    let procfs = fixture();
+    let mut hardened = FakeProcess::new(31, 30, "sleep");
+    hardened.uid = 1000;
+    hardened.no_new_privs = true;
+    procfs.add_process(&hardened);
+    procfs.write_file(32, "status", b"Name:\tsleep\nPid:\t32\nPPid:\t30\nUid:\t0\t0\t0\t0\n");
+
+    let (stdout, _, code) = run(&procfs, &["-r", "30", "--nnp"]);
+    assert_eq!((stdout.as_str(), code), ("(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep\n├─ sleep [nnp]\n└─ sleep\n", 0));
+
+    let (stdout, _, _) = run(&procfs, &["-r", "30", "--nnp=missing"]);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash [no nnp]\n├─ sleep [no nnp]\n├─ sleep\n└─ sleep\n");
+
+    let (stdout, _, _) = run(&procfs, &["-r", "5", "--nnp", "--nnp-only"]);
+    assert_eq!(stdout, "(init(1) › sshd(5))\nsshd\n└─ bash\n   └─ sleep [nnp]\n");
+
+    let (stdout, stderr, code) = run(&fixture(), &["--nnp", "--nnp-only"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: no nnp process found\n", 1));
+}
+
+#[test]
+fn supplementary_groups_are_shown_and_filtered()
+{
+    let procfs = fixture();
+    let mut many = FakeProcess::new(31, 30, "sleep");
+    many.uid = 1000;
+    many.groups = vec![54321, 54322, 54323, 54324, 54325, 54326];
+    procfs.add_process(&many);
+    let mut one = FakeProcess::new(32, 30, "sleep");
+    one.groups = vec![54322];
+    procfs.add_process(&one);
+
+    let (stdout, _, code) = run(&procfs, &["-r", "30", "--groups"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "(init(1) › sshd(5) › bash(30))\nbash\n├─ sleep\n├─ sleep (54321,54322,54323,54324,54325,…)\n└─ sleep (54322)\n");
+
+    let (stdout, _, _) = run(&procfs, &["--in-group", "54322", "-r", "5"]);
+    assert_eq!(stdout, "(init(1) › sshd(5))\nsshd\n└─ bash\n   ├─ sleep\n   └─ sleep\n");
+
+    let (stdout, _, _) = run(&procfs, &["--in-group", "54322", "--groups", "-r", "5"]);
+    assert_eq!(stdout, "(init(1) › sshd(5))\nsshd\n└─ bash\n   ├─ sleep (54321,54322,54323,54324,54325,…)\n   └─ sleep (54322)\n");
+
+    let (stdout, stderr, code) = run(&procfs, &["--in-group", "54329"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "rusty-pstree: no process in group 54329 found\n", 1));
+
+    let (_, stderr, code) = run(&procfs, &["--in-group", "no-such-group"]);
+    assert!(stderr.contains("unknown group 'no-such-group'"), "{}", stderr);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn state_color_is_checked_while_parsing()
+{
+    let (stdout, _, code) = run(&fixture(), &["--state-color", "Z=none", "--state-color", "R=green", "-d", "1"]);
+    assert_eq!((stdout.as_str(), code), ("(all)\n├─ init\n│  └─ sshd\n└─ kthreadd\n   └─ kworker/0:1\n", 0));
+
+    let (_, stderr, code) = run(&fixture(), &["--state-color", "Q=red"]);
+    assert!(stderr.contains("unknown process state 'Q'"), "{}", stderr);
+    assert_eq!(code, 2);
+
+    let (_, stderr, code) = run(&fixture(), &["--state-color", "Z=reddish"]);
+    assert!(stderr.contains("invalid color"), "{}", stderr);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn invalid_style_attribute()
+{
+    let (_, stderr, code) = run(&fixture(), &["--node-style", "bold,wavy"]);
+    assert!(stderr.contains("invalid style attribute 'wavy'"), "{}", stderr);
+    assert!(stderr.contains("bold, dimmed, italic, underline, blink, reverse"), "{}", stderr);
+    assert_eq!(code, 2);
+
+    let (stdout, _, code) = run(&fixture(), &["--branch-style", "dimmed", "--node-style", "bold", "-d", "0"]);
+    assert_eq!((stdout.as_str(), code), ("(all)\n├─ init\n└─ kthreadd\n", 0));
+}
+
+#[test]
+fn palette_needs_color_by_and_valid_colors()
+{
+    let (stdout, _, code) = run(&fixture(), &["--color-by", "depth", "--palette", "red,208,#00ff00", "-d", "0"]);
+    assert_eq!((stdout.as_str(), code), ("(all)\n├─ init\n└─ kthreadd\n", 0));
+
+    let (_, stderr, code) = run(&fixture(), &["--color-by", "depth", "--palette", "red,nope"]);
+    assert!(stderr.contains("invalid color 'nope'"), "{}", stderr);
+    assert_eq!(code, 2);
+
+    let (_, _, code) = run(&fixture(), &["--palette", "red"]);
+    assert_eq!(code, 2);
+
+    let (stdout, _, code) = run(&fixture(), &["--palette", "red", "--highlight", "sshd", "-d", "0"]);
+    assert_eq!((stdout.as_str(), code), ("(all)\n├─ init\n└─ kthreadd\n", 0));
+}
+
+#[test]
+fn upside_down_puts_children_above_their_parent()
+{
+    assert_output(&["--upside-down", "-r", "1", "-p"], "      ┌─ [32] sleep
+      ├─ [31] sleep
+      ├─ [20] sleep
+   ┌─ [30] bash
+┌─ [5] sshd
+[1] init
+");
+    assert_output(&["--upside-down", "--align-pids", "--ascii", "-d", "1"], "10    ,- kworker/0:1
+ 2 ,- kthreadd
+ 5 |  ,- sshd
+ 1 |- init
+   (all)
+");
+}
+
+#[test]
+fn plain_indents_with_spaces_only()
+{
+    assert_output(&["--plain", "-r", "1", "-p", "--sort", "name"], "\
+[1] init
+   [5] sshd
+      [30] bash
+         [20] sleep
+         [31] sleep
+         [32] sleep
+");
+    assert_output(&["--plain=levels", "--ascii", "--unique", "-I", "2"], "\
+level 1: (all)
+level 2:   init
+level 3:     sshd
+level 4:       bash
+level 5:         sleep
+level 2:   kthreadd
+level 3:     kworker/0:1
+");
+}
+
+#[test]
+fn align_pids()
+{
+    assert_output(&["--align-pids", "-p"], "   (all)
+ 1 ├─ init
+ 5 │  └─ sshd
+30 │     └─ bash
+20 │        ├─ sleep
+31 │        ├─ sleep
+32 │        └─ sleep
+ 2 └─ kthreadd
+10    └─ kworker/0:1
+");
+    assert_output(&["--align-pids", "-r", "5", "-d", "1"], "(init(1) › sshd(5))\n 5 sshd\n30 └─ bash\n");
+}
+
+#[test]
+fn group_by_user_cuts_at_user_boundaries()
+{
+    let procfs = FakeProcfs::new();
+    for (pid, ppid, name, uid) in [
+        (1, 0, "init", 0), (5, 1, "sshd", 0), (30, 5, "bash", 54321), (20, 30, "sleep", 54321),
+        (31, 30, "sudo", 0), (32, 31, "vim", 0), (40, 5, "bash", 54322), (41, 40, "top", 54322),
+    ] {
+        let mut process = FakeProcess::new(pid, ppid, name);
+        process.uid = uid;
+        procfs.add_process(&process);
+    }
+
+    let (stdout, _, code) = run(&procfs, &["--group-by", "user", "-p"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+54321
+└─ ([5] sshd)
+   └─ [30] bash
+      └─ [20] sleep
+54322
+└─ ([5] sshd)
+   └─ [40] bash
+      └─ [41] top
+root
+├─ [1] init
+│  └─ [5] sshd
+└─ ([30] bash)
+   └─ [31] sudo
+      └─ [32] vim
+");
+
+    let (stdout, _, _) = run(&procfs, &["--group-by", "user", "--group-sort", "count", "-d", "0", "--count"]);
+    assert_eq!(stdout, "8\n");
+    let (stdout, _, _) = run(&procfs, &["--group-by", "user", "--group-sort", "count", "-d", "0"]);
+    assert_eq!(stdout, "root\n├─ init\n└─ (bash)\n   └─ sudo\n54321\n└─ (sshd)\n   └─ bash\n54322\n└─ (sshd)\n   └─ bash\n");
+
+    let (_, _, code) = run(&procfs, &["--group-sort", "count"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn group_by_unit_nests_slices()
+{
+    let procfs = FakeProcfs::new();
+    for (pid, ppid, name, cgroup) in [
+        (1, 0, "systemd", "/init.scope"),
+        (2, 0, "kthreadd", "/"),
+        (10, 1, "nginx", "/system.slice/nginx.service"),
+        (11, 10, "nginx", "/system.slice/nginx.service"),
+        (20, 1, "sshd", "/system.slice/ssh.service"),
+        (30, 20, "sshd", "/user.slice/user-1000.slice/session-2.scope"),
+        (31, 30, "bash", "/user.slice/user-1000.slice/session-2.scope"),
+    ] {
+        let mut process = FakeProcess::new(pid, ppid, name);
+        process.cgroup = cgroup.to_string();
+        procfs.add_process(&process);
+    }
+    procfs.write_file(12, "status", b"Name:\tnginx\nPid:\t12\nPPid:\t10\n");
+
+    let (stdout, _, code) = run(&procfs, &["--group-by", "unit"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+(unmanaged)
+├─ (nginx)
+│  └─ nginx
+└─ kthreadd
+init.scope
+└─ systemd
+system.slice
+├─ nginx.service
+│  └─ (systemd)
+│     └─ nginx
+│        └─ nginx
+└─ ssh.service
+   └─ (systemd)
+      └─ sshd
+user.slice
+└─ user-1000.slice
+   └─ session-2.scope
+      └─ (sshd)
+         └─ sshd
+            └─ bash
+");
+
+    let (stdout, _, _) = run(&procfs, &["--group-by", "unit", "--group-sort", "count", "-d", "0"]);
+    assert!(stdout.starts_with("system.slice\n├─ nginx.service\n"), "{}", stdout);
+}
+
+#[test]
+fn group_by_session_puts_leaders_on_top()
+{
+    let procfs = FakeProcfs::new();
+    for (pid, ppid, name, session) in [
+        (1, 0, "init", 1), (2, 0, "kthreadd", 0), (10, 2, "kworker/0:1", 0), (5, 1, "sshd", 5),
+        (30, 5, "bash", 30), (31, 30, "vim", 30), (40, 5, "bash", 40), (41, 40, "sleep", 40),
+        (50, 1, "daemon", 77), (51, 50, "worker", 77),
+    ] {
+        let mut process = FakeProcess::new(pid, ppid, name);
+        process.session = session;
+        procfs.add_process(&process);
+    }
+
+    let (stdout, _, code) = run(&procfs, &["--group-by", "session", "-p"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+session 1
+└─ [1] init
+session 5
+└─ ([1] init)
+   └─ [5] sshd
+session 30
+└─ ([5] sshd)
+   └─ [30] bash
+      └─ [31] vim
+session 40
+└─ ([5] sshd)
+   └─ [40] bash
+      └─ [41] sleep
+(leader gone)
+└─ ([1] init)
+   └─ [50] daemon
+      └─ [51] worker
+(no session)
+└─ [2] kthreadd
+   └─ [10] kworker/0:1
+");
+
+    let (stdout, _, _) = run(&procfs, &["--group-by", "session", "--group-sort", "count", "-d", "0"]);
+    assert!(stdout.starts_with("(leader gone)\n"), "{}", stdout);
+}
+
+#[test]
+fn group_by_exe_counts_the_copies_of_every_binary()
+{
+    let procfs = fixture();
+    for (pid, exe) in [
+        (1, "/sbin/init"), (5, "/usr/sbin/sshd"), (30, "/bin/bash"), (20, "/bin/sleep"), (31, "/bin/sleep"), (32, "/bin/sleep"),
+    ] {
+        procfs.set_exe(pid, Path::new(exe));
+    }
+
+    let (stdout, _, code) = run(&procfs, &["--group-by", "exe"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "\
+/bin/sleep (3 processes)
+└─ (bash)
+   ├─ sleep
+   ├─ sleep
+   └─ sleep
+/bin/bash (1 process)
+└─ (sshd)
+   └─ bash
+/sbin/init (1 process)
+└─ init
+/usr/sbin/sshd (1 process)
+└─ (init)
+   └─ sshd
+[kthreadd] (1 process)
+└─ kthreadd
+[kworker/0:1] (1 process)
+└─ (kthreadd)
+   └─ kworker/0:1
+");
+
+    let (stdout, _, _) = run(&procfs, &["--group-by", "exe", "--group-sort", "name", "-d", "0"]);
+    assert!(stdout.starts_with("/bin/bash (1 process)\n"), "{}", stdout);
+}
@@ -0,0 +1,56 @@
+extern crate rusty_pstree;
+
+
+use std::cmp::Ordering;
+
+use rusty_pstree::{natural_cmp, Collation};
+
+fn sorted(names: &[&str], collation: Collation) -> Vec<String>
+{
+    let mut names: Vec<&str> = names.to_vec();
+    names.sort_by(|a, b| collation.compare(a.as_bytes(), b.as_bytes()));
+    names.into_iter().map(str::to_string).collect()
+}
+
+#[test]
+fn numbered_workers_sort_by_value()
+{
+    let names = ["worker-10", "worker-2", "worker-1", "worker"];
+    assert_eq!(sorted(&names, Collation::Natural), ["worker", "worker-1", "worker-2", "worker-10"]);
+    assert_eq!(sorted(&names, Collation::Bytewise), ["worker", "worker-1", "worker-10", "worker-2"]);
+}
+
+#[test]
+fn leading_zeros_compare_by_value_then_bytewise()
+{
+    assert_eq!(natural_cmp(b"a007", b"a10"), Ordering::Less);
+    assert_eq!(natural_cmp(b"a010", b"a9"), Ordering::Greater);
+    assert_eq!(natural_cmp(b"a01", b"a1"), Ordering::Less);
+    assert_eq!(natural_cmp(b"a1", b"a01"), Ordering::Greater);
+    assert_eq!(natural_cmp(b"a0", b"a00"), Ordering::Less);
+    assert_eq!(natural_cmp(b"a01", b"a01"), Ordering::Equal);
+}
+
+#[test]
+fn mixed_runs_compare_run_by_run()
+{
+    let names = ["kworker/1:10", "kworker/1:2", "kworker/10:0", "kworker/2:0H", "kworker/2:0", "kworker/u16:3"];
+    assert_eq!(
+        sorted(&names, Collation::Natural),
+        ["kworker/1:2", "kworker/1:10", "kworker/2:0", "kworker/2:0H", "kworker/10:0", "kworker/u16:3"]
+    );
+    // A digit sorts before a letter, as in bytewise order.
+    assert_eq!(natural_cmp(b"x1", b"xa"), Ordering::Less);
+    assert_eq!(natural_cmp(b"v2.10.1", b"v2.9.12"), Ordering::Greater);
+}
+
+#[test]
+fn pure_numeric_names_sort_by_value()
+{
+    let names = ["100", "20", "3", "0", "99999999999999999999999999", "100000000000000000000000000"];
+    assert_eq!(
+        sorted(&names, Collation::Natural),
+        ["0", "3", "20", "100", "99999999999999999999999999", "100000000000000000000000000"]
+    );
+    assert_eq!(natural_cmp(b"", b"0"), Ordering::Less);
+}
@@ -0,0 +1,133 @@
+extern crate ptree;
+extern crate rusty_pstree;
+
+
+use ptree::Color;
+use rusty_pstree::{parse_color, parse_state_color};
+
+fn rgb(color: &str) -> Option<(u8, u8, u8)>
+{
+    match parse_color(color) {
+        Ok(Color::RGB(r, g, b)) => Some((r, g, b)),
+        _ => None,
+    }
+}
+
+#[test]
+fn names_in_any_case()
+{
+    assert!(matches!(parse_color("black"), Ok(Color::Black)));
+    assert!(matches!(parse_color("Red"), Ok(Color::Red)));
+    assert!(matches!(parse_color("GREEN"), Ok(Color::Green)));
+    assert!(matches!(parse_color("yellow"), Ok(Color::Yellow)));
+    assert!(matches!(parse_color("blue"), Ok(Color::Blue)));
+    assert!(matches!(parse_color("Purple"), Ok(Color::Purple)));
+    assert!(matches!(parse_color("cyan"), Ok(Color::Cyan)));
+    assert!(matches!(parse_color(" white "), Ok(Color::White)));
+}
+
+#[test]
+fn valid_triples()
+{
+    assert_eq!(rgb("255,255,0"), Some((255, 255, 0)));
+    assert_eq!(rgb("255, 128,0"), Some((255, 128, 0)));
+    assert_eq!(rgb(" 0 , 0 , 0 "), Some((0, 0, 0)));
+    assert_eq!(rgb("007,10,200"), Some((7, 10, 200)));
+}
+
+#[test]
+fn out_of_range_components_are_rejected()
+{
+    for color in ["256,0,0", "0,256,0", "0,0,256", "1000,0,0", "99999999999,0,0"] {
+        let why = parse_color(color).unwrap_err();
+        assert!(why.starts_with(&format!("invalid color '{}'", color)), "{}", why);
+    }
+}
+
+#[test]
+fn garbage_is_a_clean_error()
+{
+    assert_eq!(
+        parse_color("banana"),
+        Err("invalid color 'banana' (expected a name like red/bright-red/..., a palette index from 0 to 255, \
+             R,G,B with 0-255 components, #RRGGBB or #RGB)".to_string())
+    );
+    for color in ["", "255,255", "1,2,3,4", "-1,0,0", "1,,2", "r,g,b", "1.5,0,0", "red,green,blue"] {
+        let why = parse_color(color).unwrap_err();
+        assert!(why.starts_with(&format!("invalid color '{}'", color)), "{}", why);
+    }
+}
+
+#[test]
+fn long_hex_with_or_without_hash()
+{
+    assert_eq!(rgb("#112233"), Some((0x11, 0x22, 0x33)));
+    assert_eq!(rgb("112233"), Some((0x11, 0x22, 0x33)));
+    assert_eq!(rgb("#AbCdEf"), Some((0xab, 0xcd, 0xef)));
+    assert_eq!(rgb("#000000"), Some((0, 0, 0)));
+    assert_eq!(rgb("FFFFFF"), Some((255, 255, 255)));
+}
+
+#[test]
+fn short_hex_needs_the_hash()
+{
+    assert_eq!(rgb("#fff"), Some((255, 255, 255)));
+    assert_eq!(rgb("#000"), Some((0, 0, 0)));
+    assert_eq!(rgb("#1aF"), Some((0x11, 0xaa, 0xff)));
+    assert!(parse_color("fff").is_err());
+}
+
+#[test]
+fn malformed_hex_is_a_clean_error()
+{
+    for color in ["#", "#12345", "#1234567", "#ggg", "#12 345", "12345g", "#ff00ff00", "#é12"] {
+        let why = parse_color(color).unwrap_err();
+        assert!(why.starts_with(&format!("invalid color '{}'", color)), "{}", why);
+    }
+}
+
+#[test]
+fn bright_names_map_to_the_upper_16_colors()
+{
+    assert!(matches!(parse_color("bright-red"), Ok(Color::Fixed(9))));
+    assert!(matches!(parse_color("Bright-Cyan"), Ok(Color::Fixed(14))));
+    assert!(matches!(parse_color("bright-black"), Ok(Color::Fixed(8))));
+    assert!(matches!(parse_color("bright-white"), Ok(Color::Fixed(15))));
+    assert!(parse_color("bright-orange").is_err());
+}
+
+#[test]
+fn palette_indices()
+{
+    assert!(matches!(parse_color("0"), Ok(Color::Fixed(0))));
+    assert!(matches!(parse_color("255"), Ok(Color::Fixed(255))));
+    assert!(matches!(parse_color("color123"), Ok(Color::Fixed(123))));
+    assert!(matches!(parse_color("COLOR7"), Ok(Color::Fixed(7))));
+    assert_eq!(parse_color("256"), Err("invalid color '256' (palette indices go from 0 to 255)".to_string()));
+    assert!(parse_color("color256").unwrap_err().contains("palette indices go from 0 to 255"));
+    assert!(parse_color("color").is_err());
+    assert!(parse_color("color-1").is_err());
+}
+
+#[test]
+fn precedence_between_numbers_triples_and_hex()
+{
+    assert!(matches!(parse_color("12"), Ok(Color::Fixed(12))));
+    assert_eq!(rgb("12,0,0"), Some((12, 0, 0)));
+    assert_eq!(rgb("112233"), Some((0x11, 0x22, 0x33)));
+    assert!(parse_color("11223").unwrap_err().contains("palette"));
+}
+
+#[test]
+fn state_color_mappings()
+{
+    assert!(matches!(parse_state_color("Z=red"), Ok(('Z', Some(Color::Red)))));
+    assert!(matches!(parse_state_color("D=color208"), Ok(('D', Some(Color::Fixed(208))))));
+    assert!(matches!(parse_state_color("t=#ff0000"), Ok(('t', Some(Color::RGB(255, 0, 0))))));
+    assert!(matches!(parse_state_color("Z=none"), Ok(('Z', None))));
+    assert!(parse_state_color("Q=red").unwrap_err().contains("unknown process state 'Q'"));
+    assert!(parse_state_color("ZZ=red").is_err());
+    assert!(parse_state_color("=red").is_err());
+    assert!(parse_state_color("Z=reddish").is_err());
+    assert!(parse_state_color("red").unwrap_err().contains("STATE=COLOR"));
+}
@@ -0,0 +1,118 @@
+extern crate ptree;
+extern crate rusty_pstree;
+
+
+use ptree::Color;
+use rusty_pstree::{ColorBy, ConfigError, PidFormat, PsTreePrintConfig, SortKey, TextStyle};
+
+#[test]
+fn builder_defaults_match_new()
+{
+    let config = PsTreePrintConfig::builder().build().unwrap();
+    let default = PsTreePrintConfig::new();
+
+    assert_eq!(config.show_pid, default.show_pid);
+    assert_eq!(config.forest, default.forest);
+    assert_eq!(config.depth, None);
+    assert!(!config.unique);
+    assert_eq!(config.sort, SortKey::Pid);
+    assert_eq!(config.print_config.indent, default.print_config.indent);
+    assert_eq!(config.print_config.padding, default.print_config.padding);
+}
+
+#[test]
+fn builder_sets_options()
+{
+    let config = PsTreePrintConfig::builder()
+        .show_pid(true)
+        .node_color(Color::Green)
+        .depth(3)
+        .sort(SortKey::Name)
+        .build()
+        .unwrap();
+
+    assert!(config.show_pid);
+    assert_eq!(config.depth, Some(3));
+    assert_eq!(config.sort, SortKey::Name);
+    assert!(matches!(config.print_config.leaf.foreground, Some(Color::Green)));
+}
+
+#[test]
+fn styles_combine_with_colors()
+{
+    let config = PsTreePrintConfig::builder()
+        .node_color(Color::Cyan)
+        .node_style(TextStyle { bold: true, ..TextStyle::default() })
+        .branch_style(TextStyle { dimmed: true, ..TextStyle::default() })
+        .branch_color(Color::Blue)
+        .build()
+        .unwrap();
+
+    let leaf = &config.print_config.leaf;
+    assert!(matches!(leaf.foreground, Some(Color::Cyan)));
+    assert!(leaf.bold && !leaf.dimmed);
+    let branch = &config.print_config.branch;
+    assert!(matches!(branch.foreground, Some(Color::Blue)));
+    assert!(branch.dimmed && !branch.bold);
+}
+
+#[test]
+fn state_colors_merge_overrides_into_defaults()
+{
+    let config = PsTreePrintConfig::builder()
+        .state_color('D', Some(Color::Cyan))
+        .state_color('R', Some(Color::Green))
+        .state_color('Z', None)
+        .build()
+        .unwrap();
+
+    assert!(matches!(config.state_colors.get(&'D'), Some(Color::Cyan)));
+    assert!(matches!(config.state_colors.get(&'R'), Some(Color::Green)));
+    assert!(matches!(config.state_colors.get(&'T'), Some(Color::Purple)));
+    assert!(!config.state_colors.contains_key(&'Z'));
+    assert!(matches!(PsTreePrintConfig::new().state_colors.get(&'Z'), Some(Color::Red)));
+}
+
+#[test]
+fn depth_colors_cycle_through_the_palette()
+{
+    let config = PsTreePrintConfig::builder()
+        .color_by(ColorBy::Depth)
+        .palette(vec![Color::Red, Color::Fixed(208), Color::RGB(0, 128, 255)])
+        .build()
+        .unwrap();
+
+    assert!(matches!(config.depth_color(0), Some(Color::Red)));
+    assert!(matches!(config.depth_color(1), Some(Color::Fixed(208))));
+    assert!(matches!(config.depth_color(2), Some(Color::RGB(0, 128, 255))));
+    assert!(matches!(config.depth_color(3), Some(Color::Red)));
+    assert!(matches!(config.depth_color(7), Some(Color::Fixed(208))));
+
+    let default = PsTreePrintConfig::builder().color_by(ColorBy::Depth).build().unwrap();
+    assert!(matches!(default.depth_color(0), Some(Color::Cyan)));
+    assert!(matches!(default.depth_color(5), Some(Color::Cyan)));
+    assert!(PsTreePrintConfig::new().depth_color(0).is_none());
+    assert!(PsTreePrintConfig::builder().color_by(ColorBy::Depth).palette(Vec::new()).build().unwrap().depth_color(0).is_none());
+}
+
+#[test]
+fn builder_rejects_indent_not_larger_than_padding()
+{
+    let result = PsTreePrintConfig::builder().indent(2).padding(2).build();
+    assert_eq!(result.err(), Some(ConfigError::IndentTooSmall { indent: 2, padding: 2 }));
+}
+
+#[test]
+fn sort_key_from_str()
+{
+    assert_eq!("name".parse::<SortKey>(), Ok(SortKey::Name));
+    assert!("size".parse::<SortKey>().is_err());
+}
+
+#[test]
+fn pid_format_from_str()
+{
+    assert_eq!("parens".parse::<PidFormat>(), Ok(PidFormat::Parens));
+    assert_eq!("brackets".parse::<PidFormat>(), Ok(PidFormat::Brackets));
+    assert!("braces".parse::<PidFormat>().is_err());
+}
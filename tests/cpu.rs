@@ -0,0 +1,29 @@
+extern crate rusty_pstree;
+
+
+use std::collections::BTreeSet;
+
+use rusty_pstree::parse_cpu_list;
+
+fn cpus(cpus: &[u32]) -> Option<BTreeSet<u32>>
+{
+    Some(cpus.iter().copied().collect())
+}
+
+#[test]
+fn parses_ranges_and_single_cpus()
+{
+    assert_eq!(parse_cpu_list("0-3,8,10-11"), cpus(&[0, 1, 2, 3, 8, 10, 11]));
+    assert_eq!(parse_cpu_list("5"), cpus(&[5]));
+    assert_eq!(parse_cpu_list("0-0"), cpus(&[0]));
+    assert_eq!(parse_cpu_list("0-7\n"), cpus(&[0, 1, 2, 3, 4, 5, 6, 7]));
+    assert_eq!(parse_cpu_list(""), cpus(&[]));
+}
+
+#[test]
+fn rejects_malformed_lists()
+{
+    for list in ["3-1", "a", "0-", "-2", "1,,x", "0-3-5"] {
+        assert_eq!(parse_cpu_list(list), None, "{}", list);
+    }
+}
@@ -0,0 +1,148 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::{Change, ChangeThresholds, ProcessInfo, ProcessTree, PsTreePrintConfig};
+
+fn started(pid: u32, ppid: u32, name: &str, start_time: u64) -> ProcessInfo
+{
+    ProcessInfo { start_time: Some(start_time), ..ProcessInfo::new(pid, ppid, name) }
+}
+
+fn earlier() -> ProcessTree
+{
+    ProcessTree::from_process_list(vec![
+        started(1, 0, "init", 1),
+        started(5, 1, "sshd", 10),
+        started(30, 5, "bash", 20),
+        started(31, 30, "sleep", 21),
+        started(40, 1, "cron", 12),
+        started(41, 40, "sh", 300),
+        started(42, 41, "backup", 301),
+    ])
+}
+
+fn render(tree: &ProcessTree) -> String
+{
+    let mut out = Vec::new();
+    tree.write(&mut out, &PsTreePrintConfig::new()).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn reused_pid_is_new_and_gone()
+{
+    // 31 exited and its pid went to another sleep, the cron job finished
+    // and a second shell started.
+    let later = ProcessTree::from_process_list(vec![
+        started(1, 0, "init", 1),
+        started(5, 1, "sshd", 10),
+        started(30, 5, "bash", 20),
+        started(31, 30, "sleep", 500),
+        started(40, 1, "cron", 12),
+        started(50, 5, "bash", 510),
+    ]);
+    let diff = later.diff(&earlier());
+
+    let mut new: Vec<(u32, Option<u64>)> = diff.new.iter().copied().collect();
+    new.sort();
+    assert_eq!(new, vec![(31, Some(500)), (50, Some(510))]);
+    let gone: Vec<u32> = diff.gone.iter().map(|proc_info| proc_info.pid).collect();
+    assert_eq!(gone, vec![31, 41, 42]);
+    assert!(!diff.is_empty());
+}
+
+#[test]
+fn changes_are_marked_and_drawn()
+{
+    let mut later = ProcessTree::from_process_list(vec![
+        started(1, 0, "init", 1),
+        started(5, 1, "sshd", 10),
+        started(30, 5, "bash", 20),
+        started(31, 30, "sleep", 500),
+        started(40, 1, "cron", 12),
+    ]);
+    let diff = later.diff(&earlier());
+    later.mark_changes(&diff);
+
+    assert_eq!(later.find(31).unwrap().change, Some(Change::New));
+    assert_eq!(later.find(30).unwrap().change, None);
+    assert_eq!(render(&later), "init\n├─ sshd\n│  └─ bash\n│     └─ sleep [new]\n└─ cron\n");
+
+    // sh and backup keep their nesting, the old sleep hangs from bash which
+    // is still running.
+    assert_eq!(
+        render(&diff.gone_tree()),
+        "(gone)\n├─ sleep [gone]\n└─ sh [gone]\n   └─ backup [gone]\n"
+    );
+}
+
+#[test]
+fn same_processes_are_no_change()
+{
+    let tree = earlier();
+    let diff = tree.diff(&earlier());
+    assert!(diff.is_empty());
+    assert_eq!(render(&diff.gone_tree()), "");
+}
+
+#[test]
+fn state_and_memory_changes_follow_the_thresholds()
+{
+    let with = |proc_info: ProcessInfo, state: char, rss: u64| ProcessInfo {
+        state: Some(state),
+        rss: Some(rss),
+        ..proc_info
+    };
+    let earlier = ProcessTree::from_process_list(vec![
+        with(started(1, 0, "init", 1), 'S', 1000),
+        with(started(5, 1, "sshd", 10), 'S', 1000),
+        with(started(30, 5, "bash", 20), 'S', 1000),
+        with(started(40, 1, "cron", 12), 'S', 1000),
+    ]);
+    let later = ProcessTree::from_process_list(vec![
+        with(started(1, 0, "init", 1), 'S', 1000),
+        with(started(5, 1, "sshd", 10), 'S', 1050),
+        with(started(30, 5, "bash", 20), 'D', 1000),
+        with(started(40, 1, "cron", 12), 'S', 2000),
+    ]);
+
+    assert!(later.diff(&earlier).is_empty());
+    let states = later.diff_with(&earlier, &ChangeThresholds { state: true, rss_percent: None });
+    assert_eq!(states.changed.len(), 1);
+    assert_eq!(states.changed[&(30, Some(20))], Change::Changed { state: Some('S'), rss: None });
+
+    let memory = later.diff_with(&earlier, &ChangeThresholds { state: false, rss_percent: Some(10) });
+    assert_eq!(memory.changed.len(), 1);
+    assert_eq!(memory.changed[&(40, Some(12))], Change::Changed { state: None, rss: Some(1000) });
+    let memory = later.diff_with(&earlier, &ChangeThresholds { state: false, rss_percent: Some(1) });
+    assert_eq!(memory.changed.len(), 2);
+}
+
+#[test]
+fn only_changes_and_their_ancestors_are_kept()
+{
+    let running = |pid: u32, ppid: u32, name: &str, start_time: u64, state: char| ProcessInfo {
+        state: Some(state),
+        ..started(pid, ppid, name, start_time)
+    };
+    let earlier = ProcessTree::from_process_list(vec![
+        running(1, 0, "init", 1, 'S'),
+        running(5, 1, "sshd", 10, 'S'),
+        running(30, 5, "bash", 20, 'S'),
+        running(40, 1, "cron", 12, 'S'),
+    ]);
+    let mut later = ProcessTree::from_process_list(vec![
+        running(1, 0, "init", 1, 'S'),
+        running(5, 1, "sshd", 10, 'S'),
+        running(30, 5, "bash", 20, 'R'),
+        running(40, 1, "cron", 12, 'S'),
+        running(41, 40, "sh", 300, 'S'),
+    ]);
+    let diff = later.diff_with(&earlier, &ChangeThresholds { state: true, rss_percent: None });
+    later.mark_changes(&diff);
+
+    assert_eq!(later.retain_changes(&diff), 2);
+    assert_eq!(later.find(5).unwrap().change, Some(Change::Context));
+    assert_eq!(later.find(41).unwrap().change, Some(Change::New));
+    assert_eq!(render(&later), "init\n├─ sshd\n│  └─ bash [S→R]\n└─ cron\n   └─ sh [new]\n");
+}
@@ -0,0 +1,27 @@
+extern crate rusty_pstree;
+
+
+use std::time::Duration;
+
+use rusty_pstree::format_duration;
+
+#[test]
+fn durations_use_the_two_largest_units()
+{
+    assert_eq!(format_duration(Duration::ZERO), "0.0s");
+    assert_eq!(format_duration(Duration::from_millis(380)), "0.4s");
+    assert_eq!(format_duration(Duration::from_millis(38_240)), "38.2s");
+    assert_eq!(format_duration(Duration::from_secs(725)), "12m5s");
+    assert_eq!(format_duration(Duration::from_secs(4 * 3600 + 12 * 60 + 59)), "4h12m");
+    assert_eq!(format_duration(Duration::from_secs(3 * 86400 + 7 * 3600 + 1)), "3d7h");
+}
+
+#[test]
+fn rounding_carries_into_the_next_unit()
+{
+    assert_eq!(format_duration(Duration::from_millis(59_949)), "59.9s");
+    assert_eq!(format_duration(Duration::from_millis(59_950)), "1m0s");
+    assert_eq!(format_duration(Duration::from_millis(3_599_500)), "1h0m");
+    assert_eq!(format_duration(Duration::from_secs(86_399)), "23h59m");
+    assert_eq!(format_duration(Duration::from_secs(u64::MAX)), "213503982334601d7h");
+}
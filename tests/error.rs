@@ -0,0 +1,46 @@
+extern crate rusty_pstree;
+
+
+use std::io;
+use std::path::PathBuf;
+
+use rusty_pstree::{PidKeyword, PsTreeError};
+
+#[test]
+fn exit_codes()
+{
+    let table = [
+        (PsTreeError::ProcessNotFound(vec![42]), 1),
+        (PsTreeError::KeywordNotFound(PidKeyword::Parent, 42), 1),
+        (PsTreeError::NoMatch("sshd".to_string()), 1),
+        (PsTreeError::AmbiguousMatch("sleep".to_string(), vec![(20, "sleep".to_string())]), 2),
+        (PsTreeError::NoUserProcess(1000), 1),
+        (PsTreeError::NoGroupProcess(998), 1),
+        (PsTreeError::NoFilterMatch("traced".to_string()), 1),
+        (PsTreeError::FileNotOpen(PathBuf::from("/var/log/syslog")), 1),
+        (PsTreeError::StartTimeUnknown(42), 3),
+        (PsTreeError::NoProcesses(PathBuf::from("/tmp")), 3),
+        (PsTreeError::ProcUnreadable(PathBuf::from("/proc"), io::Error::from(io::ErrorKind::NotFound)), 3),
+        (PsTreeError::LoadFailed(PathBuf::from("/snapshot.json"), io::Error::from(io::ErrorKind::InvalidData)), 3),
+        (PsTreeError::PsOutputUnreadable(PathBuf::from("ps.txt"), io::Error::from(io::ErrorKind::InvalidData)), 3),
+        (PsTreeError::SaveFailed(PathBuf::from("/snapshot.json"), io::Error::from(io::ErrorKind::PermissionDenied)), 3),
+        (PsTreeError::MapWriteFailed(PathBuf::from("/map.json"), io::Error::from(io::ErrorKind::PermissionDenied)), 3),
+        (PsTreeError::SignalFailed(1, 3), 4),
+        (PsTreeError::ReniceFailed(2, 3), 4),
+        (PsTreeError::ProtectedProcess(1, "pause".to_string()), 2),
+        (PsTreeError::Declined("no signal sent".to_string()), 5),
+    ];
+
+    for (error, code) in table {
+        assert_eq!(error.exit_code(), code, "{}", error);
+    }
+}
+
+#[test]
+fn only_selections_are_empty()
+{
+    assert!(PsTreeError::ProcessNotFound(vec![42]).is_empty_selection());
+    assert!(PsTreeError::NoFilterMatch("traced".to_string()).is_empty_selection());
+    assert!(!PsTreeError::NoProcesses(PathBuf::from("/tmp")).is_empty_selection());
+    assert!(!PsTreeError::SignalFailed(1, 3).is_empty_selection());
+}
@@ -0,0 +1,39 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::{fuzzy_score, EXACT_MATCH_SCORE};
+
+#[test]
+fn equal_names_score_highest_whatever_their_case()
+{
+    assert_eq!(fuzzy_score("sshd", "sshd"), Some(EXACT_MATCH_SCORE));
+    assert_eq!(fuzzy_score("NGINX", "nginx"), Some(EXACT_MATCH_SCORE));
+    assert!(fuzzy_score("sshd", "sshd-session").unwrap() < EXACT_MATCH_SCORE);
+}
+
+#[test]
+fn subsequences_score_higher_at_the_start_and_close_together()
+{
+    assert!(fuzzy_score("ssh", "sshd") > fuzzy_score("ssh", "openssh"));
+    assert!(fuzzy_score("cron", "crond") > fuzzy_score("cron", "cryptoroutine"));
+    assert!(fuzzy_score("pgsql", "postgresql").is_some());
+    assert!(fuzzy_score("sleep", "sleep") > fuzzy_score("sleep", "sleeper"));
+}
+
+#[test]
+fn typos_match_below_every_subsequence()
+{
+    let typo = fuzzy_score("postgress", "postgresql@14-main").unwrap();
+    assert!(typo < fuzzy_score("pgsql", "postgresql").unwrap());
+    assert!(fuzzy_score("sshd", "ssh-agent").is_some());
+    assert!(fuzzy_score("bazh", "bash") > fuzzy_score("bazh", "bashful"));
+}
+
+#[test]
+fn unrelated_names_do_not_match()
+{
+    assert_eq!(fuzzy_score("ssd", "bash"), None);
+    assert_eq!(fuzzy_score("xyz", "init"), None);
+    assert_eq!(fuzzy_score("postgres", "systemd"), None);
+    assert_eq!(fuzzy_score("", "init"), None);
+}
@@ -0,0 +1,57 @@
+extern crate ptree;
+extern crate rusty_pstree;
+
+
+use ptree::Color;
+use rusty_pstree::{assign_highlight_colors, HighlightSpec};
+
+fn spec(spec: &str) -> HighlightSpec
+{
+    spec.parse().unwrap()
+}
+
+#[test]
+fn specs_take_the_color_after_the_last_equals_sign()
+{
+    let postgres = spec("postgres=green");
+    assert_eq!((postgres.pattern.as_str(), postgres.color), ("postgres", Some(Color::Green)));
+    let pgbouncer = spec("^pgbouncer$");
+    assert_eq!((pgbouncer.pattern.as_str(), pgbouncer.color), ("^pgbouncer$", None));
+    let equals = spec("a=b=");
+    assert_eq!((equals.pattern.as_str(), equals.color), ("a=b", None));
+    let indexed = spec("a=b=208");
+    assert_eq!((indexed.pattern.as_str(), indexed.color), ("a=b", Some(Color::Fixed(208))));
+}
+
+#[test]
+fn invalid_specs_are_errors()
+{
+    assert!("postgres=banana".parse::<HighlightSpec>().unwrap_err().contains("invalid color 'banana'"));
+    assert_eq!(
+        "=green".parse::<HighlightSpec>().unwrap_err(),
+        "invalid highlight '=green' (expected PATTERN or PATTERN=COLOR)"
+    );
+    assert!("(=red".parse::<HighlightSpec>().unwrap_err().starts_with("invalid highlight pattern '('"));
+}
+
+#[test]
+fn specs_without_a_color_take_the_free_palette_colors()
+{
+    let specs = [spec("a=green"), spec("b"), spec("c"), spec("d")];
+    let colors: Vec<Color> = assign_highlight_colors(&specs, &[Color::Cyan, Color::Green, Color::Yellow])
+        .into_iter()
+        .map(|highlight| highlight.color)
+        .collect();
+    assert_eq!(colors, [Color::Green, Color::Cyan, Color::Yellow, Color::Cyan]);
+}
+
+#[test]
+fn a_palette_taken_by_the_specs_is_shared()
+{
+    let specs = [spec("a=cyan"), spec("b")];
+    let highlights = assign_highlight_colors(&specs, &[Color::Cyan]);
+    assert_eq!(highlights[1].color, Color::Cyan);
+
+    let highlights = assign_highlight_colors(&[spec("a")], &[]);
+    assert_eq!((highlights[0].pattern.as_str(), &highlights[0].color), ("a", &Color::Cyan));
+}
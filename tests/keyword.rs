@@ -0,0 +1,56 @@
+extern crate rusty_pstree;
+
+
+use std::io;
+
+use rusty_pstree::{MockSource, PidKeyword, ProcessInfo};
+
+fn source() -> MockSource
+{
+    let mut source = MockSource::new();
+    source.add_process(&ProcessInfo::new(812, 30, "make"));
+    source.insert(812, "stat", "812 (make) S 30 812 25 34816 812 4194304 0 0 0 0 0 0 0 0 20 0 1 0 100 0 0");
+    source
+}
+
+#[test]
+fn keywords_are_parsed_by_name()
+{
+    for keyword in PidKeyword::ALL {
+        assert_eq!(keyword.name().parse::<PidKeyword>(), Ok(keyword));
+    }
+    assert_eq!(
+        "parents".parse::<PidKeyword>(),
+        Err("invalid pid 'parents' (expected a number, self, parent or session)".to_string())
+    );
+}
+
+#[test]
+fn keywords_resolve_relative_to_the_running_process()
+{
+    let source = source();
+    assert_eq!(PidKeyword::Current.resolve(&source, 812).unwrap(), 812);
+    assert_eq!(PidKeyword::Parent.resolve(&source, 812).unwrap(), 30);
+    assert_eq!(PidKeyword::Session.resolve(&source, 812).unwrap(), 25);
+}
+
+#[test]
+fn keywords_outside_the_pid_namespace_are_errors()
+{
+    let mut source = MockSource::new();
+    source.add_process(&ProcessInfo::new(1, 0, "init"));
+    source.insert(1, "stat", "1 (init) S 0 1 0 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 1 0 0");
+
+    let why = PidKeyword::Parent.resolve(&source, 1).unwrap_err();
+    assert_eq!(why.to_string(), "the parent process is outside this pid namespace");
+    let why = PidKeyword::Session.resolve(&source, 1).unwrap_err();
+    assert_eq!(why.to_string(), "the session leader is outside this pid namespace");
+}
+
+#[test]
+fn unreadable_processes_are_errors()
+{
+    let source = MockSource::new();
+    assert_eq!(PidKeyword::Parent.resolve(&source, 812).unwrap_err().kind(), io::ErrorKind::NotFound);
+    assert_eq!(PidKeyword::Session.resolve(&source, 812).unwrap_err().kind(), io::ErrorKind::NotFound);
+}
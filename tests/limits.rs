@@ -0,0 +1,32 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::{parse_soft_limit, ResourceLimit};
+
+const LIMITS: &str = "\
+Limit                     Soft Limit           Hard Limit           Units     
+Max cpu time              unlimited            unlimited            seconds   
+Max file size             unlimited            unlimited            bytes     
+Max open files            1024                 1048576              files     
+Max locked memory         8388608              8388608              bytes     
+Max msgqueue size         819200               819200               bytes     
+Max realtime timeout      unlimited            unlimited            us        
+";
+
+#[test]
+fn soft_limit_is_read_from_its_column()
+{
+    assert_eq!(parse_soft_limit(LIMITS, "Max open files"), Some(ResourceLimit::Limited(1024)));
+    assert_eq!(parse_soft_limit(LIMITS, "Max locked memory"), Some(ResourceLimit::Limited(8388608)));
+    assert_eq!(parse_soft_limit(LIMITS, "Max cpu time"), Some(ResourceLimit::Unlimited));
+    assert_eq!(parse_soft_limit(LIMITS, "Max realtime timeout"), Some(ResourceLimit::Unlimited));
+}
+
+#[test]
+fn names_must_match_whole()
+{
+    assert_eq!(parse_soft_limit(LIMITS, "Max open"), None);
+    assert_eq!(parse_soft_limit(LIMITS, "Max processes"), None);
+    assert_eq!(parse_soft_limit("", "Max open files"), None);
+    assert_eq!(parse_soft_limit("Max open files            1024\n", "Max open files"), None);
+}
@@ -0,0 +1,73 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::{ProcessInfo, ProcessTree};
+
+fn process(pid: u32, ppid: u32, name: &str, state: char, rss: u64) -> ProcessInfo
+{
+    let mut proc_info = ProcessInfo::new(pid, ppid, name);
+    proc_info.state = Some(state);
+    proc_info.rss = Some(rss);
+    proc_info
+}
+
+fn metrics(tree: &ProcessTree) -> String
+{
+    let mut out = Vec::new();
+    tree.write_metrics(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn subtrees_below_the_root_are_counted()
+{
+    let tree = ProcessTree::from_process_list(vec![
+        process(1, 0, "init", 'S', 1024),
+        process(5, 1, "sshd", 'S', 2048),
+        process(30, 5, "bash", 'R', 4096),
+        process(31, 30, "defunct", 'Z', 0),
+        process(40, 1, "a \"b\" c\\d\ne", 'D', 100),
+    ]);
+    assert_eq!(metrics(&tree), concat!(
+        "# HELP pstree_processes_total Processes in the selected tree.\n",
+        "# TYPE pstree_processes_total gauge\n",
+        "pstree_processes_total 5\n",
+        "# HELP pstree_processes Processes in the selected tree by state.\n",
+        "# TYPE pstree_processes gauge\n",
+        "pstree_processes{state=\"D\"} 1\n",
+        "pstree_processes{state=\"I\"} 0\n",
+        "pstree_processes{state=\"R\"} 1\n",
+        "pstree_processes{state=\"S\"} 2\n",
+        "pstree_processes{state=\"T\"} 0\n",
+        "pstree_processes{state=\"X\"} 0\n",
+        "pstree_processes{state=\"Z\"} 1\n",
+        "pstree_processes{state=\"t\"} 0\n",
+        "# HELP pstree_subtree_processes Processes in each subtree below the root.\n",
+        "# TYPE pstree_subtree_processes gauge\n",
+        "pstree_subtree_processes{root=\"sshd\",pid=\"5\"} 3\n",
+        "pstree_subtree_processes{root=\"a \\\"b\\\" c\\\\d\\ne\",pid=\"40\"} 1\n",
+        "# HELP pstree_subtree_rss_bytes Resident memory of each subtree below the root.\n",
+        "# TYPE pstree_subtree_rss_bytes gauge\n",
+        "pstree_subtree_rss_bytes{root=\"sshd\",pid=\"5\"} 6144\n",
+        "pstree_subtree_rss_bytes{root=\"a \\\"b\\\" c\\\\d\\ne\",pid=\"40\"} 100\n",
+    ));
+}
+
+#[test]
+fn unread_fields_leave_out_their_series()
+{
+    let tree = ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "init"),
+        ProcessInfo::new(2, 0, "kthreadd"),
+        ProcessInfo::new(10, 2, "kworker"),
+    ]);
+    assert_eq!(metrics(&tree), concat!(
+        "# HELP pstree_processes_total Processes in the selected tree.\n",
+        "# TYPE pstree_processes_total gauge\n",
+        "pstree_processes_total 3\n",
+        "# HELP pstree_subtree_processes Processes in each subtree below the root.\n",
+        "# TYPE pstree_subtree_processes gauge\n",
+        "pstree_subtree_processes{root=\"init\",pid=\"1\"} 1\n",
+        "pstree_subtree_processes{root=\"kthreadd\",pid=\"2\"} 2\n",
+    ));
+}
@@ -0,0 +1,44 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::NiceChange;
+
+#[test]
+fn parses_relative_and_absolute_changes()
+{
+    assert_eq!("+5".parse(), Ok(NiceChange::Relative(5)));
+    assert_eq!("-3".parse(), Ok(NiceChange::Relative(-3)));
+    assert_eq!("=10".parse(), Ok(NiceChange::Absolute(10)));
+    assert_eq!("=-20".parse(), Ok(NiceChange::Absolute(-20)));
+    assert_eq!("+39".parse(), Ok(NiceChange::Relative(39)));
+}
+
+#[test]
+fn rejects_malformed_changes()
+{
+    for change in ["5", "", "=", "+", "=x", "++5", "+5x"] {
+        assert_eq!(
+            change.parse::<NiceChange>(),
+            Err(format!("invalid nice change '{}' (expected +N, -N or =N)", change))
+        );
+    }
+    assert_eq!("=20".parse::<NiceChange>(), Err("invalid nice value 20 (expected -20 to 19)".to_string()));
+    assert_eq!("-40".parse::<NiceChange>(), Err("invalid nice change -40 (expected -39 to +39)".to_string()));
+}
+
+#[test]
+fn changes_stay_within_the_nice_range()
+{
+    assert_eq!(NiceChange::Relative(5).apply(0), 5);
+    assert_eq!(NiceChange::Relative(5).apply(17), 19);
+    assert_eq!(NiceChange::Relative(-3).apply(-19), -20);
+    assert_eq!(NiceChange::Absolute(10).apply(-5), 10);
+}
+
+#[test]
+fn displays_like_it_parses()
+{
+    for change in ["+5", "-3", "=10", "+0"] {
+        assert_eq!(change.parse::<NiceChange>().unwrap().to_string(), change);
+    }
+}
@@ -0,0 +1,85 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::{OrgOptions, ProcessInfo, ProcessTree, PsTreePrintConfig, UserTable};
+
+fn tree() -> ProcessTree
+{
+    ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "init"),
+        ProcessInfo::new(5, 1, "sshd"),
+        ProcessInfo::new(30, 5, "bash"),
+        ProcessInfo::new(31, 30, "sleep"),
+        ProcessInfo::new(32, 31, "sleep"),
+        ProcessInfo::new(40, 1, "cron"),
+    ])
+}
+
+fn org(tree: &ProcessTree, config: &PsTreePrintConfig, options: &OrgOptions) -> String
+{
+    let mut out = Vec::new();
+    tree.write_org(&mut out, config, options).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn headings_follow_the_depth()
+{
+    let config = PsTreePrintConfig::builder().show_pid(true).build().unwrap();
+    assert_eq!(
+        org(&tree(), &config, &OrgOptions::default()),
+        "* [1] init\n** [5] sshd\n*** [30] bash\n**** [31] sleep\n***** [32] sleep\n** [40] cron\n"
+    );
+}
+
+#[test]
+fn deep_processes_become_list_items()
+{
+    let options = OrgOptions { max_level: 2, ..OrgOptions::default() };
+    assert_eq!(
+        org(&tree(), &PsTreePrintConfig::new(), &options),
+        "* init\n** sshd\n- bash\n  - sleep\n    - sleep\n** cron\n"
+    );
+}
+
+#[test]
+fn leading_asterisks_are_escaped()
+{
+    // Without the escape "** ** x" would read as a heading one level deeper,
+    // and "*bold*" as bold markup.
+    let tree = ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "init"),
+        ProcessInfo::new(2, 1, "** x"),
+        ProcessInfo::new(3, 1, "*bold*"),
+        ProcessInfo::new(4, 3, "*\nline"),
+        ProcessInfo::new(5, 1, "a*b"),
+    ]);
+    let options = OrgOptions { max_level: 2, ..OrgOptions::default() };
+    assert_eq!(
+        org(&tree, &PsTreePrintConfig::new(), &options),
+        "* init\n** \u{200b}** x\n** \u{200b}*bold*\n- \u{200b}*\\nline\n** a*b\n"
+    );
+}
+
+#[test]
+fn property_drawers_describe_the_process()
+{
+    let mut sshd = ProcessInfo::new(5, 1, "sshd");
+    sshd.uid = Some(0);
+    sshd.rss = Some(4 * 1024 * 1024);
+    let mut bash = ProcessInfo::new(30, 5, "bash");
+    bash.uid = Some(1000);
+    let tree = ProcessTree::from_process_list(vec![sshd, bash]);
+    let options = OrgOptions {
+        properties: true,
+        user_names: UserTable::parse("root:x:0:0::/root:/bin/sh\n"),
+        ..OrgOptions::default()
+    };
+    assert_eq!(
+        org(&tree, &PsTreePrintConfig::new(), &options),
+        concat!(
+            "* sshd rss=4.0M\n:PROPERTIES:\n:PID: 5\n:PPID: 1\n:USER: root\n:RSS: 4.0M\n:END:\n",
+            "** bash\n:PROPERTIES:\n:PID: 30\n:PPID: 5\n:USER: 1000\n:END:\n",
+        )
+    );
+}
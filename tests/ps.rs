@@ -0,0 +1,74 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::{parse_ps_output, UserTable};
+
+const PS_EF: &str = "\
+UID          PID    PPID  C STIME TTY          TIME CMD
+root           1       0  0 Oct16 ?        00:00:03 /sbin/init splash
+root           2       0  0 Oct16 ?        00:00:00 [kthreadd]
+root         512       1  0 Oct16 ?        00:00:00 sshd: /usr/sbin/sshd -D [listener] 0 of 10-100 startups
+alice       1234     512  0 09:41 pts/0    00:00:00 -bash
+1000        1240    1234  0 09:42 pts/0    00:00:00 /usr/bin/sleep 60
+";
+
+fn users() -> UserTable
+{
+    UserTable::parse("root:x:0:0::/root:/bin/sh\nalice:x:1000:1000::/home/alice:/bin/sh\n")
+}
+
+#[test]
+fn ps_ef_output_is_parsed()
+{
+    let processes = parse_ps_output(PS_EF, &users()).unwrap();
+    let summary: Vec<(u32, u32, String, Option<u32>)> = processes
+        .iter()
+        .map(|proc_info| (proc_info.pid, proc_info.ppid, proc_info.display_name().into_owned(), proc_info.uid))
+        .collect();
+    assert_eq!(summary, vec![
+        (1, 0, "init".to_string(), Some(0)),
+        (2, 0, "kthreadd".to_string(), Some(0)),
+        (512, 1, "sshd".to_string(), Some(0)),
+        (1234, 512, "bash".to_string(), Some(1000)),
+        (1240, 1234, "sleep".to_string(), Some(1000)),
+    ]);
+
+    assert_eq!(processes[0].cmdline, Some(vec![b"/sbin/init".to_vec(), b"splash".to_vec()]));
+    assert_eq!(processes[1].cmdline, Some(Vec::new()));
+    assert_eq!(processes[4].display_cmdline().as_deref(), Some("/usr/bin/sleep 60"));
+    assert!(processes.iter().all(|proc_info| proc_info.state.is_none() && proc_info.rss.is_none()));
+}
+
+#[test]
+fn state_and_rss_columns_are_read()
+{
+    let output = "  PID  PPID S   RSS COMMAND\n    1     0 S  9012 init\n   42     1 Zs    0 Web Content\n";
+    let processes = parse_ps_output(output, &UserTable::default()).unwrap();
+
+    assert_eq!(processes[0].state, Some('S'));
+    assert_eq!(processes[0].rss, Some(9012 * 1024));
+    assert_eq!(processes[1].state, Some('Z'));
+    assert_eq!(processes[1].display_name(), "Web");
+    assert_eq!(processes[1].uid, None);
+
+    let output = "PID PPID COMM\n42 1 Web Content\n";
+    let processes = parse_ps_output(output, &UserTable::default()).unwrap();
+    assert_eq!(processes[0].display_name(), "Web Content");
+    assert_eq!(processes[0].cmdline, None);
+}
+
+#[test]
+fn malformed_output_is_refused()
+{
+    let users = UserTable::default();
+
+    assert_eq!(parse_ps_output("", &users).unwrap_err(), "no header line");
+    assert_eq!(parse_ps_output("PID CMD\n1 init\n", &users).unwrap_err(), "no PPID column");
+    assert_eq!(
+        parse_ps_output("PID PPID CMD TTY\n1 0 init ?\n", &users).unwrap_err(),
+        "the last column is not COMMAND, CMD, ARGS or COMM"
+    );
+    assert_eq!(parse_ps_output("PID PPID CMD\n\n1 0\n", &users).unwrap_err(), "line 3: expected 3 columns");
+    assert_eq!(parse_ps_output("PID PPID CMD\nx 0 init\n", &users).unwrap_err(), "line 2: invalid PID \"x\"");
+    assert_eq!(parse_ps_output("PID PPID CMD\n", &users).unwrap(), Vec::new());
+}
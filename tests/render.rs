@@ -0,0 +1,325 @@
+extern crate ptree;
+extern crate rusty_pstree;
+
+
+use ptree::print_config::StyleWhen;
+use ptree::Color;
+use rusty_pstree::{assign_highlight_colors, ColorBy, ProcessInfo, ProcessTree, PsTreePrintConfig};
+
+fn render(config: &mut PsTreePrintConfig) -> String
+{
+    config.print_config.styled = StyleWhen::Always;
+    let mut zombie = ProcessInfo::new(5, 4, "defunct");
+    zombie.state = Some('Z');
+    let tree = ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "init"),
+        ProcessInfo::new(2, 1, "sshd"),
+        ProcessInfo::new(3, 1, "cron"),
+        ProcessInfo::new(4, 2, "bash"),
+        zombie,
+    ]);
+    let mut out = Vec::new();
+    tree.write(&mut out, config).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn node_and_branch_colors_render_together()
+{
+    let mut config = PsTreePrintConfig::builder()
+        .node_color(Color::Yellow)
+        .background(Color::Blue)
+        .branch_color(Color::Cyan)
+        .branch_background(Color::Black)
+        .build()
+        .unwrap();
+
+    // ptree dims the branches by default, the node has no attributes.
+    let branch = |text: &str| format!("\x1b[2;40;36m{}\x1b[0m", text);
+    let node = |text: &str| format!("\x1b[44;33m{}\x1b[0m", text);
+    let expected = format!(
+        "{}{}\n{}{}\n{}{}\n{}{}\n{}{}\n",
+        branch(""), node("init"),
+        branch("├─ "), node("sshd"),
+        branch("│  └─ "), node("bash"),
+        branch("│     └─ "), "\x1b[44;31mdefunct\x1b[0m",
+        branch("└─ "), node("cron"),
+    );
+    assert_eq!(render(&mut config), expected);
+}
+
+#[test]
+fn default_config_only_colors_states()
+{
+    let mut config = PsTreePrintConfig::new();
+    config.print_config.branch.dimmed = false;
+
+    assert_eq!(render(&mut config), "init\n├─ sshd\n│  └─ bash\n│     └─ \x1b[31mdefunct\x1b[0m\n└─ cron\n");
+}
+
+#[test]
+fn depth_picks_the_palette_slot_below_state_colors()
+{
+    let mut config = PsTreePrintConfig::builder()
+        .color_by(ColorBy::Depth)
+        .palette(vec![Color::Green, Color::Blue])
+        .build()
+        .unwrap();
+    config.print_config.branch.dimmed = false;
+
+    let green = |text: &str| format!("\x1b[32m{}\x1b[0m", text);
+    let blue = |text: &str| format!("\x1b[34m{}\x1b[0m", text);
+    let expected = format!(
+        "{}\n├─ {}\n│  └─ {}\n│     └─ {}\n└─ {}\n",
+        green("init"), blue("sshd"), green("bash"), "\x1b[31mdefunct\x1b[0m", blue("cron"),
+    );
+    assert_eq!(render(&mut config), expected);
+}
+
+#[test]
+fn the_first_matching_highlight_wins_over_state_colors()
+{
+    let highlights = assign_highlight_colors(&["sh=green".parse().unwrap(), "sshd|defunct".parse().unwrap()], &[Color::Blue]);
+    let mut config = PsTreePrintConfig::builder().highlights(highlights).build().unwrap();
+    config.print_config.branch.dimmed = false;
+
+    let green = |text: &str| format!("\x1b[32m{}\x1b[0m", text);
+    let blue = |text: &str| format!("\x1b[34m{}\x1b[0m", text);
+    let expected = format!(
+        "init\n├─ {}\n│  └─ {}\n│     └─ {}\n└─ cron\nhighlights: {} {}\n",
+        green("sshd"), green("bash"), blue("defunct"), green("sh"), blue("sshd|defunct"),
+    );
+    assert_eq!(render(&mut config), expected);
+}
+
+#[test]
+fn aligned_pids_keep_colors_after_the_column()
+{
+    let mut config = PsTreePrintConfig::builder().align_pids(true).show_pid(true).build().unwrap();
+    config.print_config.branch.dimmed = false;
+
+    let expected = "1 init\n2 ├─ sshd\n4 │  └─ bash\n5 │     └─ \x1b[31mdefunct\x1b[0m\n3 └─ cron\n";
+    assert_eq!(render(&mut config), expected);
+}
+
+/// A tree with a kernel thread root, siblings with repeated names and a
+/// chain deep enough to need every kind of indentation.
+fn snapshot_tree() -> ProcessTree
+{
+    ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "systemd"),
+        ProcessInfo::new(2, 0, "kthreadd"),
+        ProcessInfo::new(3, 2, "kworker/0:0"),
+        ProcessInfo::new(310, 1, "sshd"),
+        ProcessInfo::new(311, 310, "sshd"),
+        ProcessInfo::new(312, 311, "bash"),
+        ProcessInfo::new(313, 312, "sleep"),
+        ProcessInfo::new(314, 312, "sleep"),
+        ProcessInfo::new(315, 312, "vim"),
+        ProcessInfo::new(400, 1, "cron"),
+    ])
+}
+
+fn render_plain(tree: &ProcessTree, config: &PsTreePrintConfig) -> Vec<u8>
+{
+    let mut out = Vec::new();
+    tree.write(&mut out, config).unwrap();
+    out
+}
+
+#[test]
+fn default_snapshot()
+{
+    let expected: &[u8] = "\
+(all)
+├─ systemd
+│  ├─ sshd
+│  │  └─ sshd
+│  │     └─ bash
+│  │        ├─ sleep
+│  │        ├─ sleep
+│  │        └─ vim
+│  └─ cron
+└─ kthreadd
+   └─ kworker/0:0
+".as_bytes();
+    assert_eq!(render_plain(&snapshot_tree(), &PsTreePrintConfig::new()), expected);
+}
+
+#[test]
+fn pid_ascii_and_indent_snapshot()
+{
+    let config = PsTreePrintConfig::builder().show_pid(true).ascii(true).indent(4).padding(2).build().unwrap();
+    let expected: &[u8] = b"\
+(all)
+|-  [1] systemd
+|   |-  [310] sshd
+|   |   `-  [311] sshd
+|   |       `-  [312] bash
+|   |           |-  [313] sleep
+|   |           |-  [314] sleep
+|   |           `-  [315] vim
+|   `-  [400] cron
+`-  [2] kthreadd
+    `-  [3] kworker/0:0
+";
+    assert_eq!(render_plain(&snapshot_tree(), &config), expected);
+}
+
+#[test]
+fn unique_forest_snapshot()
+{
+    let config = PsTreePrintConfig::builder().unique(true).forest(true).build().unwrap();
+    let mut tree = snapshot_tree();
+    tree.apply_config(&config);
+
+    let expected: &[u8] = "\
+systemd
+├─ sshd
+│  └─ sshd
+│     └─ bash
+│        ├─ sleep
+│        └─ vim
+└─ cron
+kthreadd
+└─ kworker/0:0
+".as_bytes();
+    assert_eq!(render_plain(&tree, &config), expected);
+}
+
+#[test]
+fn wide_and_deep_tree_snapshot()
+{
+    // 300 siblings with long names below a 40 level chain.
+    let name = |pid: u32| format!("worker-{:0>60}", pid);
+    let mut processes: Vec<ProcessInfo> = (1 ..= 40).map(|pid| ProcessInfo::new(pid, pid - 1, "chain")).collect();
+    processes.extend((1000 .. 1300).map(|pid| ProcessInfo::new(pid, 40, name(pid))));
+    let config = PsTreePrintConfig::builder().show_pid(true).build().unwrap();
+
+    let mut expected = String::from("[1] chain\n");
+    for pid in 2 ..= 40 {
+        expected.push_str(&format!("{}└─ [{}] chain\n", "   ".repeat(pid as usize - 2), pid));
+    }
+    let indent = "   ".repeat(39);
+    for pid in 1000 .. 1300 {
+        let branch = if pid == 1299 { "└─" } else { "├─" };
+        expected.push_str(&format!("{}{} [{}] {}\n", indent, branch, pid, name(pid)));
+    }
+    assert_eq!(render_plain(&ProcessTree::from_process_list(processes), &config), expected.as_bytes());
+}
+
+#[test]
+fn collapsed_chains_snapshot()
+{
+    // sshd ▸ sshd ▸ bash is a chain in the middle of the tree, kthreadd ▸
+    // kworker/0:0 one that ends at a leaf.
+    let config = PsTreePrintConfig::builder().show_pid(true).collapse_chains(true).build().unwrap();
+    let expected: &[u8] = "\
+(all)
+├─ [1] systemd
+│  ├─ [310] sshd ▸ [311] sshd ▸ [312] bash
+│  │  ├─ [313] sleep
+│  │  ├─ [314] sleep
+│  │  └─ [315] vim
+│  └─ [400] cron
+└─ [2] kthreadd ▸ [3] kworker/0:0
+".as_bytes();
+    assert_eq!(render_plain(&snapshot_tree(), &config), expected);
+
+    let config = PsTreePrintConfig::builder().ascii(true).collapse_chains(true).forest(true).build().unwrap();
+    let expected: &[u8] = b"\
+systemd
+|- sshd > sshd > bash
+|  |- sleep
+|  |- sleep
+|  `- vim
+`- cron
+kthreadd > kworker/0:0
+";
+    assert_eq!(render_plain(&snapshot_tree(), &config), expected);
+}
+
+#[test]
+fn top_subtrees_snapshot()
+{
+    let config = PsTreePrintConfig::builder().top(1).build().unwrap();
+    let mut tree = snapshot_tree();
+    tree.apply_config(&config);
+    let expected: &[u8] = "\
+(all)
+├─ systemd
+│  ├─ sshd
+│  │  └─ sshd
+│  │     └─ bash
+│  │        ├─ sleep
+│  │        ├─ sleep
+│  │        └─ vim
+│  └─ cron
+└─ … 1 smaller subtree (2 processes)
+".as_bytes();
+    assert_eq!(render_plain(&tree, &config), expected);
+
+    let config = PsTreePrintConfig::builder().top(1).collapse_chains(true).build().unwrap();
+    let mut tree = snapshot_tree().subtree(1).unwrap();
+    tree.apply_config(&config);
+    let expected: &[u8] = "\
+systemd
+├─ sshd ▸ sshd ▸ bash
+│  ├─ sleep
+│  ├─ sleep
+│  └─ vim
+└─ … 1 smaller subtree (1 process)
+".as_bytes();
+    assert_eq!(render_plain(&tree, &config), expected);
+}
+
+#[test]
+fn deepest_branch_drawn_last_closes_before_the_next_root()
+{
+    // The last child of init is four levels deep, and kthreadd follows it.
+    let tree = ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "init"),
+        ProcessInfo::new(2, 0, "kthreadd"),
+        ProcessInfo::new(3, 1, "cron"),
+        ProcessInfo::new(4, 1, "sshd"),
+        ProcessInfo::new(5, 4, "sshd"),
+        ProcessInfo::new(6, 5, "bash"),
+        ProcessInfo::new(7, 6, "vim"),
+        ProcessInfo::new(8, 2, "kworker"),
+    ]);
+    let draw = |config: &PsTreePrintConfig| {
+        let mut out = Vec::new();
+        tree.write(&mut out, config).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+
+    assert_eq!(draw(&PsTreePrintConfig::new()), "\
+(all)
+├─ init
+│  ├─ cron
+│  └─ sshd
+│     └─ sshd
+│        └─ bash
+│           └─ vim
+└─ kthreadd
+   └─ kworker
+");
+    assert_eq!(draw(&PsTreePrintConfig::builder().show_pid(true).align_pids(true).build().unwrap()),
+"  (all)
+1 ├─ init
+3 │  ├─ cron
+4 │  └─ sshd
+5 │     └─ sshd
+6 │        └─ bash
+7 │           └─ vim
+2 └─ kthreadd
+8    └─ kworker
+");
+
+    // With a single root, nothing follows the deepest branch.
+    let mut tree = tree;
+    tree.set_roots(&[1]).unwrap();
+    let mut out = Vec::new();
+    tree.write(&mut out, &PsTreePrintConfig::new()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "init\n├─ cron\n└─ sshd\n   └─ sshd\n      └─ bash\n         └─ vim\n");
+}
@@ -0,0 +1,172 @@
+#![cfg(feature = "serde")]
+
+extern crate rusty_pstree;
+extern crate serde_json;
+
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use rusty_pstree::{
+    scan_source, Collation, GroupOrder, MockSource, ProcessInfo, ProcessTree, PsTreePrintConfig, ScanOptions, SerializableNode,
+};
+
+fn sample_tree() -> ProcessTree
+{
+    ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "init"),
+        ProcessInfo::new(2, 1, "sshd"),
+        ProcessInfo::new(3, 2, b"bad\xffname".to_vec()),
+        ProcessInfo::new(4, 1, "cron"),
+        ProcessInfo::new(5, 0, "kthreadd"),
+    ])
+}
+
+#[test]
+fn process_info_round_trip()
+{
+    let proc_info = ProcessInfo::new(42, 1, "bash");
+    let json = serde_json::to_string(&proc_info).unwrap();
+    assert_eq!(json, r#"{"pid":42,"ppid":1,"name":"bash"}"#);
+    assert_eq!(serde_json::from_str::<ProcessInfo>(&json).unwrap(), proc_info);
+}
+
+#[test]
+fn non_utf8_name_round_trip()
+{
+    let proc_info = ProcessInfo::new(3, 2, b"bad\xffname".to_vec());
+    let json = serde_json::to_string(&proc_info).unwrap();
+    assert_eq!(serde_json::from_str::<ProcessInfo>(&json).unwrap(), proc_info);
+}
+
+#[test]
+fn cmdline_round_trip()
+{
+    let proc_info = ProcessInfo {
+        cmdline: Some(vec![b"cat".to_vec(), b"caf\xe9.txt".to_vec()]),
+        ..ProcessInfo::new(42, 1, "cat")
+    };
+    let json = serde_json::to_string(&proc_info).unwrap();
+    assert_eq!(json, r#"{"pid":42,"ppid":1,"name":"cat","cmdline":["cat",[99,97,102,233,46,116,120,116]]}"#);
+    assert_eq!(serde_json::from_str::<ProcessInfo>(&json).unwrap(), proc_info);
+}
+
+#[test]
+fn tree_round_trip()
+{
+    let mut tree = sample_tree();
+    tree.prune_depth(1);
+    let nodes = tree.to_serializable();
+
+    let json = serde_json::to_string(&nodes).unwrap();
+    let decoded: Vec<SerializableNode> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, nodes);
+
+    let rebuilt = ProcessTree::from_serializable(decoded);
+    assert_eq!(rebuilt.to_serializable(), nodes);
+    assert_eq!(rebuilt.descendant_count(rebuilt.pid_index[&1]), 3);
+}
+
+#[test]
+fn depth_limit_applies_to_json_like_text()
+{
+    let config = PsTreePrintConfig::builder().depth(1).build().unwrap();
+    let mut tree = sample_tree();
+    tree.apply_config(&config);
+
+    let json = serde_json::to_string(&tree.to_serializable()).unwrap();
+    assert_eq!(json, concat!(
+        r#"[{"pid":1,"ppid":0,"name":"init","hidden_descendants":0,"children":["#,
+        r#"{"pid":2,"ppid":1,"name":"sshd","hidden_descendants":1,"children":[]},"#,
+        r#"{"pid":4,"ppid":1,"name":"cron","hidden_descendants":0,"children":[]}]},"#,
+        r#"{"pid":5,"ppid":0,"name":"kthreadd","hidden_descendants":0,"children":[]}]"#,
+    ));
+
+    let mut text = Vec::new();
+    tree.write(&mut text, &config).unwrap();
+    assert_eq!(String::from_utf8(text).unwrap(), "(all)\n├─ init\n│  ├─ sshd\n│  └─ cron\n└─ kthreadd\n");
+}
+
+#[test]
+fn groups_are_not_processes()
+{
+    let mut tree = sample_tree();
+    tree.group_by(|proc_info| proc_info.pid % 2, |odd| format!("odd {}", odd), GroupOrder::Name, Collation::Natural);
+
+    let json = serde_json::to_string(&tree.to_serializable_groups()).unwrap();
+    assert_eq!(json, concat!(
+        r#"[{"group":"odd 0","processes":[{"pid":2,"ppid":1,"name":"sshd","hidden_descendants":0,"children":[]},"#,
+        r#"{"pid":4,"ppid":1,"name":"cron","hidden_descendants":0,"children":[]}]},"#,
+        r#"{"group":"odd 1","processes":[{"pid":1,"ppid":0,"name":"init","hidden_descendants":0,"children":[]},"#,
+        r#"{"pid":3,"ppid":2,"name":[98,97,100,255,110,97,109,101],"hidden_descendants":0,"children":[]},"#,
+        r#"{"pid":5,"ppid":0,"name":"kthreadd","hidden_descendants":0,"children":[]}]}]"#,
+    ));
+
+    let pids: Vec<u32> = tree.to_serializable().iter().map(|node| node.process.pid).collect();
+    assert_eq!(pids, vec![2, 4, 1, 3, 5]);
+}
+
+#[test]
+fn snapshot_keeps_processes_cut_from_the_drawing()
+{
+    let mut tree = sample_tree();
+    tree.set_roots(&[2]).unwrap();
+    tree.prune_depth(0);
+
+    let mut snapshot = Vec::new();
+    tree.write_snapshot(&mut snapshot).unwrap();
+    let read = ProcessTree::read_snapshot(snapshot.as_slice()).unwrap();
+
+    let mut pids: Vec<u32> = read.nodes.iter().map(|node| node.proc_info.pid).collect();
+    pids.sort_unstable();
+    assert_eq!(pids, vec![1, 2, 3, 4, 5]);
+    assert!(ProcessTree::read_snapshot(&b"{not json"[..]).is_err());
+}
+
+#[test]
+fn watch_frames_are_one_json_document_per_line()
+{
+    let mut source = MockSource::new();
+    source.add_process(&ProcessInfo::new(1, 0, "init"));
+    source.add_process(&ProcessInfo::new(2, 1, "sshd"));
+
+    let mut stream = Vec::new();
+    let tree = ProcessTree::from_process_list(scan_source(&source, &ScanOptions::default()).unwrap().processes);
+    tree.write_json_frame(&mut stream, UNIX_EPOCH + Duration::from_millis(1_700_000_000_250)).unwrap();
+
+    source.add_process(&ProcessInfo::new(3, 2, "bash"));
+    let tree = ProcessTree::from_process_list(scan_source(&source, &ScanOptions::default()).unwrap().processes);
+    tree.write_json_frame(&mut stream, UNIX_EPOCH + Duration::from_secs(1_700_000_002)).unwrap();
+
+    let stream = String::from_utf8(stream).unwrap();
+    let frames: Vec<serde_json::Value> = stream.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0]["timestamp"], 1_700_000_000.25);
+    assert_eq!(frames[1]["timestamp"], 1_700_000_002.0);
+
+    // Each tree is what a single --output json prints.
+    let mut single = Vec::new();
+    tree.write_json(&mut single).unwrap();
+    let single: serde_json::Value = serde_json::from_slice(&single).unwrap();
+    assert_eq!(frames[1]["tree"], single);
+    assert_eq!(frames[0]["tree"][0]["children"][0]["children"], serde_json::json!([]));
+    assert_eq!(frames[1]["tree"][0]["children"][0]["children"][0]["name"], "bash");
+}
+
+#[test]
+fn tree_json_golden()
+{
+    let mut tree = sample_tree();
+    let config = PsTreePrintConfig::builder().show_pid(true).top(1).build().unwrap();
+    tree.apply_config(&config);
+    let mut out = Vec::new();
+    tree.write_tree_json(&mut out, &config).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        r#"[{"type":"process","name":"[1] init","pid":1,"contents":["#,
+        r#"{"type":"process","name":"[2] sshd","pid":2,"contents":["#,
+        r#"{"type":"process","name":"[3] bad�name","pid":3}]},"#,
+        r#"{"type":"process","name":"[4] cron","pid":4}]},"#,
+        r#"{"type":"label","name":"… 1 smaller subtree (1 process)"},"#,
+        r#"{"type":"report","directories":2,"files":2}]"#,
+        "\n",
+    ));
+}
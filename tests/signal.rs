@@ -0,0 +1,111 @@
+extern crate libc;
+extern crate rusty_pstree;
+
+
+use std::io;
+
+use rusty_pstree::{send_all, Freeze, ProcessInfo, ProcessTree, Signal, SignalSender};
+
+/// Records the signals instead of sending them, failing for the pids in
+/// `gone`.
+#[derive(Default)]
+struct Recorder
+{
+    sent: Vec<(u32, i32)>,
+    gone: Vec<u32>,
+}
+
+impl SignalSender for Recorder
+{
+    fn send(&mut self, pid: u32, signal: Signal) -> io::Result<()>
+    {
+        if self.gone.contains(&pid) {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH));
+        }
+        self.sent.push((pid, signal.number()));
+        Ok(())
+    }
+}
+
+//  1
+//  ├─ 2
+//  │  ├─ 4
+//  │  └─ 5
+//  └─ 3
+//     └─ 6
+fn build_tree() -> ProcessTree
+{
+    ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "make"),
+        ProcessInfo::new(2, 1, "cc"),
+        ProcessInfo::new(3, 1, "ld"),
+        ProcessInfo::new(4, 2, "cc1"),
+        ProcessInfo::new(5, 2, "as"),
+        ProcessInfo::new(6, 3, "collect2"),
+    ])
+}
+
+fn freeze(tree: &ProcessTree, freeze: Freeze, sender: &mut Recorder) -> Vec<(u32, io::Error)>
+{
+    let pids: Vec<u32> = freeze.order(tree).into_iter().map(|index| tree.nodes[index].proc_info.pid).collect();
+    send_all(sender, freeze.signal(), &pids)
+}
+
+#[test]
+fn parses_names_in_any_case_with_or_without_prefix()
+{
+    for name in ["TERM", "term", "SIGTERM", "SigTerm", "15"] {
+        assert_eq!(name.parse::<Signal>().unwrap().number(), 15, "{}", name);
+    }
+    assert_eq!("KILL".parse::<Signal>().unwrap().number(), 9);
+    assert_eq!("sigusr1".parse::<Signal>().unwrap().number(), 10);
+    assert_eq!("0".parse::<Signal>().unwrap().number(), 0);
+}
+
+#[test]
+fn rejects_unknown_signals()
+{
+    assert_eq!("TERMINATE".parse::<Signal>(), Err("unknown signal 'TERMINATE'".to_string()));
+    assert_eq!("SIG".parse::<Signal>(), Err("unknown signal 'SIG'".to_string()));
+    assert_eq!("65".parse::<Signal>(), Err("invalid signal number 65 (expected 0 to 64)".to_string()));
+    assert!("-1".parse::<Signal>().is_err());
+    assert!("".parse::<Signal>().is_err());
+}
+
+#[test]
+fn displays_the_short_name()
+{
+    assert_eq!("sigterm".parse::<Signal>().unwrap().to_string(), "TERM");
+    assert_eq!("34".parse::<Signal>().unwrap().to_string(), "34");
+    assert_eq!("9".parse::<Signal>().unwrap().name(), Some("KILL"));
+}
+
+#[test]
+fn pause_stops_children_before_parents()
+{
+    let mut sender = Recorder::default();
+    assert!(freeze(&build_tree(), Freeze::Pause, &mut sender).is_empty());
+    let stop = libc::SIGSTOP;
+    assert_eq!(sender.sent, vec![(4, stop), (5, stop), (2, stop), (6, stop), (3, stop), (1, stop)]);
+}
+
+#[test]
+fn resume_continues_parents_before_children()
+{
+    let mut sender = Recorder::default();
+    assert!(freeze(&build_tree(), Freeze::Resume, &mut sender).is_empty());
+    let cont = libc::SIGCONT;
+    assert_eq!(sender.sent, vec![(1, cont), (2, cont), (4, cont), (5, cont), (3, cont), (6, cont)]);
+}
+
+#[test]
+fn failures_do_not_stop_the_others()
+{
+    let mut tree = build_tree();
+    tree.set_roots(&[2]).unwrap();
+    let mut sender = Recorder { gone: vec![4], ..Recorder::default() };
+    let failures = freeze(&tree, Freeze::Pause, &mut sender);
+    let failed: Vec<(u32, Option<i32>)> = failures.iter().map(|(pid, why)| (*pid, why.raw_os_error())).collect();
+    assert_eq!(failed, vec![(4, Some(libc::ESRCH))]);
+    assert_eq!(sender.sent, vec![(5, libc::SIGSTOP), (2, libc::SIGSTOP)]);
+}
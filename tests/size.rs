@@ -0,0 +1,37 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::{format_size, SizeUnit};
+
+#[test]
+fn sizes_use_the_largest_unit()
+{
+    assert_eq!(format_size(0), "0B");
+    assert_eq!(format_size(1023), "1023B");
+    assert_eq!(format_size(1024), "1.0K");
+    assert_eq!(format_size(4096 * 1024), "4.0M");
+    assert_eq!(format_size(245 * 1024 * 1024), "245M");
+    assert_eq!(format_size(1181116006), "1.1G");
+    assert_eq!(format_size(6764573491), "6.3G");
+}
+
+#[test]
+fn rounding_never_shows_a_full_unit()
+{
+    assert_eq!(format_size(10 * 1024 - 1), "10K");
+    assert_eq!(format_size(1024 * 1024 - 1), "1.0M");
+    assert_eq!(format_size(u64::MAX), "16384P");
+}
+
+#[test]
+fn units_force_the_unit()
+{
+    let bytes = 1153433 * 1024;
+    assert_eq!(SizeUnit::Human.format(bytes), "1.1G");
+    assert_eq!(SizeUnit::Raw.format(bytes), "1153433");
+    assert_eq!(SizeUnit::Kib.format(bytes), "1153433K");
+    assert_eq!(SizeUnit::Mib.format(bytes), "1126.4M");
+    assert_eq!(SizeUnit::Kib.format(1535), "1K");
+    assert_eq!(SizeUnit::Mib.format(0), "0.0M");
+    assert_eq!("gib".parse::<SizeUnit>(), Err("invalid unit 'gib' (expected human, raw, kib or mib)".to_string()));
+}
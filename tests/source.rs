@@ -0,0 +1,762 @@
+extern crate regex;
+extern crate rusty_pstree;
+
+#[path = "support/fake_procfs.rs"]
+mod fake_procfs;
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use regex::Regex;
+use rusty_pstree::{
+    scan_source, scan_source_cached, LinuxProcfs, MockSource, ProcessInfo, ProcessTree, PsTreeError, ResourceLimit, ScanCache,
+    ScanOptions,
+};
+
+use fake_procfs::{FakeProcess, FakeProcfs};
+
+fn mock(processes: &[(u32, u32, &str)]) -> MockSource
+{
+    let mut source = MockSource::new();
+    for &(pid, ppid, name) in processes {
+        source.add_process(&ProcessInfo::new(pid, ppid, name));
+    }
+    source
+}
+
+fn options(root_pid: u32, jobs: usize) -> ScanOptions
+{
+    ScanOptions { root_pids: vec![root_pid], jobs, ..ScanOptions::default() }
+}
+
+#[test]
+fn scan_sorts_by_pid()
+{
+    let source = mock(&[(12, 1, "child"), (1, 0, "init"), (2, 0, "kthreadd")]);
+    let output = scan_source(&source, &options(0, 2)).unwrap();
+    let pids: Vec<u32> = output.processes.iter().map(|proc_info| proc_info.pid).collect();
+    assert_eq!(pids, vec![1, 2, 12]);
+    assert!(output.errors.is_empty());
+}
+
+#[test]
+fn equal_names_share_one_allocation()
+{
+    let source = mock(&[(1, 0, "init"), (10, 1, "apache2"), (11, 1, "apache2"), (12, 1, "apache2x")]);
+    let processes = scan_source(&source, &options(0, 2)).unwrap().processes;
+    assert!(Arc::ptr_eq(&processes[1].name, &processes[2].name));
+    assert!(!Arc::ptr_eq(&processes[1].name, &processes[3].name));
+    assert_eq!(&*processes[3].name, b"apache2x");
+}
+
+/// The status parser that the line scanner replaced, one regex run over the
+/// whole file, kept to check that both read the same processes.
+fn regex_parse_status(status: &str) -> ProcessInfo
+{
+    let regex = Regex::new(r"(Name:\s*(?P<name>.+)\n)([\s\S]*)(Pid:\s*(?P<pid>\d+))([\s\S]*)(PPid:\s*(?P<ppid>\d+))").unwrap();
+    let capture = regex.captures(status).unwrap();
+    ProcessInfo::new(capture["pid"].parse().unwrap(), capture["ppid"].parse().unwrap(), &capture["name"])
+}
+
+#[test]
+fn line_scanner_builds_the_same_tree_as_the_regex()
+{
+    let procfs = FakeProcfs::generate(300, 4);
+    procfs.add_process(&FakeProcess::new(301, 1, "Web Content"));
+    procfs.add_process(&FakeProcess::new(302, 301, "(sd-pam)"));
+    procfs.add_process(&FakeProcess::new(303, 302, "a: b"));
+
+    let mut old_list: Vec<ProcessInfo> = fs::read_dir(procfs.path()).unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path().join("status")).unwrap())
+        .map(|status| regex_parse_status(&status))
+        .collect();
+    old_list.sort_by_key(|proc_info| proc_info.pid);
+    let new_list = scan_source(&LinuxProcfs::new(procfs.path()), &options(0, 4)).unwrap().processes;
+
+    let shape = |list: Vec<ProcessInfo>| -> Vec<(u32, u32, u32, Vec<u8>)> {
+        let tree = ProcessTree::from_process_list(list);
+        tree.iter_dfs()
+            .map(|(depth, index)| {
+                let proc_info = &tree.nodes[index].proc_info;
+                (depth, proc_info.pid, proc_info.ppid, proc_info.name.to_vec())
+            })
+            .collect()
+    };
+    let old_shape = shape(old_list);
+    assert_eq!(old_shape.len(), 303);
+    assert_eq!(shape(new_list), old_shape);
+}
+
+#[test]
+fn vanished_process_is_skipped()
+{
+    let mut source = mock(&[(1, 0, "init")]);
+    source.insert(5, "cmdline", "gone\0");
+    let output = scan_source(&source, &options(0, 1)).unwrap();
+    assert_eq!(output.processes.len(), 1);
+    assert!(output.errors.is_empty());
+}
+
+#[test]
+fn malformed_status_is_reported()
+{
+    let mut source = mock(&[(1, 0, "init")]);
+    source.insert(7, "status", "Name:\tbroken\nPid:\tseven\nPPid:\t1\n");
+    source.insert(8, "status", "Name:\tno-ppid\nPid:\t8\n");
+    let output = scan_source(&source, &options(0, 1)).unwrap();
+    assert_eq!(output.processes.len(), 1);
+    let pids: Vec<u32> = output.errors.iter().map(|error| error.pid).collect();
+    assert_eq!(pids, vec![7, 8]);
+}
+
+#[test]
+fn odd_names_are_kept_verbatim()
+{
+    let mut source = mock(&[(1, 0, "init"), (3, 1, "kworker/0:1-events")]);
+    source.insert(4, "status", b"Name:\tname with\xff spaces\nPid:\t4\nPPid:\t1\n".to_vec());
+    let output = scan_source(&source, &options(0, 1)).unwrap();
+    assert_eq!(&*output.processes[1].name, b"kworker/0:1-events");
+    assert_eq!(&*output.processes[2].name, b"name with\xff spaces");
+}
+
+#[test]
+fn orphan_becomes_root()
+{
+    let source = mock(&[(1, 0, "init"), (40, 30, "orphan")]);
+    let tree = ProcessTree::from_process_list(scan_source(&source, &options(0, 1)).unwrap().processes);
+    let roots: Vec<u32> = tree.roots.iter().map(|&root| tree.nodes[root].proc_info.pid).collect();
+    assert_eq!(roots, vec![1, 40]);
+}
+
+#[test]
+fn child_scanned_before_its_parent_is_linked()
+{
+    // After pid wraparound the parent can have the higher pid, so it is read
+    // after its child.
+    let source = mock(&[(1, 0, "init"), (5, 900, "worker"), (6, 5, "helper"), (900, 1, "daemon"), (7, 4242, "stray")]);
+    let tree = ProcessTree::from_process_list(scan_source(&source, &options(0, 1)).unwrap().processes);
+
+    let daemon = tree.find(900).unwrap();
+    let children: Vec<u32> = daemon.children.iter().map(|&child| tree.nodes[child].proc_info.pid).collect();
+    assert_eq!(children, vec![5]);
+    assert_eq!(tree.nodes[tree.find(5).unwrap().children[0]].proc_info.pid, 6);
+
+    let roots: Vec<u32> = tree.roots.iter().map(|&root| tree.nodes[root].proc_info.pid).collect();
+    assert_eq!(roots, vec![1, 7]);
+    let drawn: Vec<u32> = tree.iter_dfs().map(|(_, index)| tree.nodes[index].proc_info.pid).collect();
+    assert_eq!(drawn, vec![1, 900, 5, 6, 7]);
+}
+
+/// Pids in drawing order, stopping after twice the number of nodes so that
+/// a cycle left in the tree fails instead of hanging.
+fn drawn_pids(tree: &ProcessTree) -> Vec<u32>
+{
+    tree.iter_dfs().take(2 * tree.nodes.len()).map(|(_, index)| tree.nodes[index].proc_info.pid).collect()
+}
+
+#[test]
+fn self_parent_becomes_a_root()
+{
+    let source = mock(&[(1, 0, "init"), (7, 7, "selfish"), (8, 7, "child")]);
+    let tree = ProcessTree::from_process_list(scan_source(&source, &options(0, 1)).unwrap().processes);
+
+    assert_eq!(drawn_pids(&tree), vec![1, 7, 8]);
+    assert_eq!(tree.broken_cycles, vec![vec![7]]);
+}
+
+#[test]
+fn two_process_cycle_is_cut_once()
+{
+    let source = mock(&[(1, 0, "init"), (10, 11, "a"), (11, 10, "b"), (12, 11, "c"), (13, 1, "d")]);
+    let tree = ProcessTree::from_process_list(scan_source(&source, &options(0, 1)).unwrap().processes);
+
+    let mut drawn = drawn_pids(&tree);
+    assert_eq!(drawn.len(), 5);
+    drawn.sort_unstable();
+    assert_eq!(drawn, vec![1, 10, 11, 12, 13]);
+    assert_eq!(tree.broken_cycles.len(), 1);
+    let mut cycle = tree.broken_cycles[0].clone();
+    cycle.sort_unstable();
+    assert_eq!(cycle, vec![10, 11]);
+    // The process whose parent link was cut is drawn at the top of the cycle.
+    let cut = *tree.broken_cycles[0].last().unwrap();
+    assert!(tree.roots.contains(&tree.pid_index[&cut]));
+    assert_eq!(tree.iter_post_order().count(), 5);
+}
+
+#[test]
+fn root_pid_follows_children()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "a"), (3, 2, "b"), (4, 1, "c")]);
+    source.insert(1, "children", "2 4");
+    source.insert(2, "children", "3 ");
+    source.insert(3, "children", "");
+    let mut pids: Vec<u32> = scan_source(&source, &options(2, 1)).unwrap().processes.iter()
+        .map(|proc_info| proc_info.pid)
+        .collect();
+    pids.sort_unstable();
+    assert_eq!(pids, vec![2, 3]);
+}
+
+#[test]
+fn root_pid_reads_the_ancestors_on_request()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "a"), (3, 2, "b"), (4, 1, "c"), (6, 9, "orphan"), (7, 6, "d")]);
+    for (pid, children) in [(1, "2 4"), (2, "3"), (3, ""), (4, ""), (6, "7"), (7, "")] {
+        source.insert(pid, "children", children);
+    }
+    let scan_options = ScanOptions { root_pids: vec![3, 7], read_ancestors: true, ..options(0, 1) };
+    let pids: Vec<u32> = scan_source(&source, &scan_options).unwrap().processes.iter()
+        .map(|proc_info| proc_info.pid)
+        .collect();
+    assert_eq!(pids, vec![1, 2, 3, 6, 7]);
+}
+
+#[test]
+fn empty_source_is_an_error()
+{
+    let source = MockSource::new();
+    assert!(matches!(scan_source(&source, &options(0, 1)), Err(PsTreeError::NoProcesses(_))));
+}
+
+#[test]
+fn cmdline_and_start_time_on_request()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 0, "kthreadd")]);
+    source.insert(1, "cmdline", "/sbin/init\0splash\0");
+    source.insert(1, "stat", "1 (init (weird) name) S 0 1 1 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 9 0 0");
+    source.insert(2, "cmdline", "");
+
+    let output = scan_source(&source, &options(0, 1)).unwrap();
+    assert_eq!(output.processes[0].cmdline, None);
+
+    let with_details = ScanOptions { read_cmdline: true, read_start_time: true, read_session: true, ..options(0, 1) };
+    let output = scan_source(&source, &with_details).unwrap();
+    assert_eq!(output.processes[0].display_cmdline().as_deref(), Some("/sbin/init splash"));
+    assert_eq!(output.processes[0].start_time, Some(9));
+    assert_eq!(output.processes[0].session, Some(1));
+    assert!(output.processes[0].is_session_leader());
+    assert_eq!(output.processes[1].cmdline, Some(Vec::new()));
+    assert_eq!(output.processes[1].start_time, None);
+}
+
+#[test]
+fn cmdline_keeps_raw_bytes()
+{
+    let mut source = mock(&[(1, 0, "init")]);
+    source.insert(1, "cmdline", b"/usr/bin/cat\0caf\xe9.txt\0\0".to_vec());
+
+    let output = scan_source(&source, &ScanOptions { read_cmdline: true, ..options(0, 1) }).unwrap();
+    let expected: Vec<Vec<u8>> = vec![b"/usr/bin/cat".to_vec(), b"caf\xe9.txt".to_vec(), Vec::new()];
+    assert_eq!(output.processes[0].cmdline, Some(expected));
+    assert_eq!(output.processes[0].display_cmdline().as_deref(), Some("/usr/bin/cat caf\u{fffd}.txt "));
+}
+
+#[test]
+fn multiple_root_pids_follow_children()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "a"), (3, 2, "b"), (4, 1, "c"), (5, 1, "d")]);
+    for (pid, children) in [(1, "2 4 5"), (2, "3"), (3, ""), (4, ""), (5, "")] {
+        source.insert(pid, "children", children);
+    }
+    let scan_options = ScanOptions { root_pids: vec![4, 2, 3, 4], ..options(0, 1) };
+    let pids: Vec<u32> = scan_source(&source, &scan_options).unwrap().processes.iter()
+        .map(|proc_info| proc_info.pid)
+        .collect();
+    assert_eq!(pids, vec![2, 3, 4]);
+}
+
+#[test]
+fn tracer_pid_is_not_taken_for_pid()
+{
+    let mut source = MockSource::new();
+    source.insert(6, "status", "Name:\ttraced\nTracerPid:\t99\nPid:\t6\nPPid:\t1\n");
+    source.insert(7, "status", "Name:\tfree\nPid:\t7\nPPid:\t1\nTracerPid:\t0\n");
+    let output = scan_source(&source, &options(0, 1)).unwrap();
+    let traced: Vec<(u32, Option<u32>)> = output.processes.iter()
+        .map(|proc_info| (proc_info.pid, proc_info.tracer()))
+        .collect();
+    assert_eq!(traced, vec![(6, Some(99)), (7, None)]);
+}
+
+#[test]
+fn cgroup_prefers_the_unified_hierarchy()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "v1"), (3, 1, "none")]);
+    source.insert(1, "cgroup", "12:cpu,cpuacct:/\n1:name=systemd:/init.scope\n0::/init.scope\n");
+    source.insert(2, "cgroup", "4:memory:/system.slice\n1:name=systemd:/system.slice/cron.service\n");
+    source.insert(3, "cgroup", "garbage\n");
+    let output = scan_source(&source, &ScanOptions { read_cgroup: true, ..options(0, 1) }).unwrap();
+    let cgroups: Vec<Option<&str>> = output.processes.iter().map(|proc_info| proc_info.cgroup.as_deref()).collect();
+    assert_eq!(cgroups, vec![Some("/init.scope"), Some("/system.slice/cron.service"), None]);
+}
+
+#[test]
+fn listening_sockets_come_from_fd_inodes()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "nginx"), (3, 1, "resolved"), (4, 1, "locked")]);
+    source.insert_net("tcp", "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0050 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 101 1
+   1: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 102 1
+   2: 0100007F:8AE2 0100007F:0050 01 00000000:00000000 00:00000000 00000000  1000        0 103 1
+");
+    source.insert_net("tcp6", "\
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:01BB 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 104 1
+   1: 00000000000000000000000001000000:0277 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 105 1
+");
+    source.insert_net("udp", "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+   0: 3500007F:0035 00000000:0000 07 00000000:00000000 00:00000000 00000000   101        0 106 2 0000000000000000 0
+");
+    source.insert(2, "fd", "/dev/null\nsocket:[101]\nsocket:[104]\nsocket:[103]\nsocket:[101]\n");
+    source.insert(3, "fd", "socket:[106]\nsocket:[102]\nsocket:[105]\n");
+    let output = scan_source(&source, &ScanOptions { read_listen: true, ..options(0, 2) }).unwrap();
+    let listen: Vec<Option<Vec<String>>> = output.processes.iter().map(|proc_info| proc_info.listen.clone()).collect();
+    let labels = |labels: &[&str]| Some(labels.iter().map(|label| label.to_string()).collect::<Vec<String>>());
+    assert_eq!(listen, vec![
+        None,
+        labels(&[":80", ":443"]),
+        labels(&["127.0.0.1:631", "[::1]:631", "127.0.0.53:53/udp"]),
+        None,
+    ]);
+}
+
+#[test]
+fn open_file_is_found_in_fds_and_maps()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "rsyslogd"), (3, 1, "python3"), (4, 1, "logrotate"), (5, 1, "locked")]);
+    source.insert(1, "fd", "/dev/null\nsocket:[12]\n");
+    source.insert(2, "fd", "/dev/null\n/var/log/syslog (deleted)\n");
+    source.insert(3, "maps", "\
+55d0c000-55d0d000 r--p 00000000 08:01 1234                       /usr/bin/python3.11
+7f00a000-7f00b000 r--s 00000000 08:01 5678                       /var/log/my logs/trace.db
+7ffc1000-7ffc2000 rw-p 00000000 00:00 0                          [stack]
+");
+    source.insert(4, "fd", "/var/log/syslog.1\n/var/log\n");
+    let options = ScanOptions { open_file: Some("/var/log/syslog".into()), ..options(0, 2) };
+    let holds: Vec<Option<bool>> = scan_source(&source, &options).unwrap().processes.iter()
+        .map(|proc_info| proc_info.holds_file)
+        .collect();
+    assert_eq!(holds, vec![Some(false), Some(true), Some(false), Some(false), None]);
+
+    let options = ScanOptions { open_file: Some("/var/log".into()), ..options };
+    let holds: Vec<Option<bool>> = scan_source(&source, &options).unwrap().processes.iter()
+        .map(|proc_info| proc_info.holds_file)
+        .collect();
+    assert_eq!(holds, vec![Some(false), Some(true), Some(true), Some(true), None]);
+}
+
+#[test]
+fn exe_links_are_read_on_request()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "nginx"), (3, 1, "kworker")]);
+    source.insert(1, "exe", "/usr/lib/systemd/systemd");
+    source.insert(2, "exe", "/opt/nginx/sbin/nginx (deleted)");
+    let processes = scan_source(&source, &ScanOptions { read_exe: true, ..options(0, 2) }).unwrap().processes;
+    let exes: Vec<Option<&Path>> = processes.iter().map(|proc_info| proc_info.exe.as_deref()).collect();
+    assert_eq!(exes, vec![Some(Path::new("/usr/lib/systemd/systemd")), Some(Path::new("/opt/nginx/sbin/nginx (deleted)")), None]);
+
+    let under = |dir: &str| -> Vec<bool> { processes.iter().map(|proc_info| proc_info.exe_is_under(Path::new(dir))).collect() };
+    assert_eq!(under("/opt/nginx"), vec![false, true, false]);
+    assert_eq!(under("/opt/nginx/sbin/nginx"), vec![false, true, false]);
+    assert_eq!(under("/opt/ngin"), vec![false, false, false]);
+    assert_eq!(under("/"), vec![true, true, false]);
+
+    let processes = scan_source(&source, &options(0, 2)).unwrap().processes;
+    assert!(processes.iter().all(|proc_info| proc_info.exe.is_none()));
+}
+
+#[test]
+fn namespace_inodes_are_read_on_request()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "snapd"), (3, 1, "hidden")]);
+    source.insert(1, "ns/mnt", "mnt:[4026531841]");
+    source.insert(1, "ns/pid", "pid:[4026531836]");
+    source.insert(2, "ns/mnt", "mnt:[4026532301]");
+    source.insert(2, "ns/pid", "net:[4026531840]");
+    let read_ns = ScanOptions { namespaces: vec!["mnt".to_string(), "pid".to_string()], ..options(0, 2) };
+    let processes = scan_source(&source, &read_ns).unwrap().processes;
+    let namespaces: Vec<Vec<(&str, u64)>> = processes.iter()
+        .map(|proc_info| proc_info.namespaces.iter().flatten().map(|(ns, &inode)| (ns.as_str(), inode)).collect())
+        .collect();
+    assert_eq!(namespaces, vec![
+        vec![("mnt", 4026531841), ("pid", 4026531836)],
+        vec![("mnt", 4026532301)],
+        vec![],
+    ]);
+
+    let processes = scan_source(&source, &options(0, 2)).unwrap().processes;
+    assert!(processes.iter().all(|proc_info| proc_info.namespaces.is_none()));
+}
+
+#[test]
+fn wchan_is_read_without_newline()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "nfsd"), (3, 1, "busy"), (4, 1, "gone")]);
+    source.insert(1, "wchan", "do_epoll_wait");
+    source.insert(2, "wchan", "svc_recv\n");
+    source.insert(3, "wchan", "0");
+    let wchans = |options: &ScanOptions| -> Vec<Option<String>> {
+        scan_source(&source, options).unwrap().processes.into_iter().map(|proc_info| proc_info.wchan).collect()
+    };
+    assert_eq!(wchans(&ScanOptions { read_wchan: true, ..options(0, 2) }), vec![
+        Some("do_epoll_wait".to_string()),
+        Some("svc_recv".to_string()),
+        None,
+        None,
+    ]);
+    assert_eq!(wchans(&options(0, 2)), vec![None; 4]);
+}
+
+#[test]
+fn processor_is_counted_past_the_name()
+{
+    let mut source = mock(&[(1, 0, "init"), (2, 1, "a) b (c"), (3, 1, "short")]);
+    source.insert(1, "stat", "1 (init) S 0 1 1 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 9 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 17 5 0 0");
+    source.insert(2, "stat", "2 (a) b (c) S 1 2 2 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 9 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 17 12 0 0");
+    source.insert(3, "stat", "3 (short) S 1 3 3 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 9");
+    let output = scan_source(&source, &ScanOptions { read_processor: true, ..options(0, 1) }).unwrap();
+    let processors: Vec<Option<u32>> = output.processes.iter().map(|proc_info| proc_info.processor).collect();
+    assert_eq!(processors, vec![Some(5), Some(12), None]);
+}
+
+#[test]
+fn cpu_time_is_converted_with_the_clock_ticks()
+{
+    let mut source = mock(&[(1, 0, "make"), (2, 1, "cc")]);
+    source.insert(1, "stat", "1 (make) S 0 1 1 0 -1 0 0 0 0 0 500 250 9000 1000 20 0 1 0 9");
+    source.insert(2, "stat", "2 (cc) R 1 1 1 0 -1 0 0 0 0 0 3 1 0 0 20 0 1 0 9");
+    let options = ScanOptions { read_cpu_time: true, read_children_cpu_time: true, clock_ticks: 250, ..options(0, 1) };
+    let output = scan_source(&source, &options).unwrap();
+    let times: Vec<(Option<u64>, Option<u64>)> = output.processes.iter()
+        .map(|proc_info| (proc_info.cpu_time_ms, proc_info.children_cpu_time_ms))
+        .collect();
+    assert_eq!(times, vec![(Some(3000), Some(40000)), (Some(16), Some(0))]);
+}
+
+#[test]
+fn affinity_is_kept_only_when_narrower_than_online_cpus()
+{
+    let mut source = MockSource::new();
+    for (pid, cpus_allowed) in [(1, "0-3"), (2, "0-1"), (3, "0-63"), (4, "2,3")] {
+        source.add_process(&ProcessInfo { cpus_allowed: Some(cpus_allowed.to_string()), ..ProcessInfo::new(pid, 0, "p") });
+    }
+    source.set_cpuinfo("processor\t: 0\n\nprocessor\t: 1\n\nprocessor\t: 2\n\nprocessor\t: 3\n");
+    let mut options = options(0, 2);
+    options.status_fields.cpus_allowed = true;
+    let allowed = |source: &MockSource| -> Vec<Option<String>> {
+        scan_source(source, &options).unwrap().processes.into_iter().map(|proc_info| proc_info.cpus_allowed).collect()
+    };
+    assert_eq!(allowed(&source), vec![None, Some("0-1".to_string()), None, Some("2,3".to_string())]);
+
+    source.set_cpuinfo("");
+    assert_eq!(allowed(&source).iter().filter(|cpus| cpus.is_some()).count(), 4);
+}
+
+#[test]
+fn fd_pressure_keeps_processes_near_their_limit()
+{
+    let limits = |soft: &str| format!(
+        "Limit                     Soft Limit           Hard Limit           Units     \n\
+         Max open files            {:<20} 4096                 files     \n",
+        soft,
+    );
+    let mut source = MockSource::new();
+    for (pid, fd_size, soft) in [(1, 64, "1024"), (2, 1024, "1024"), (3, 832, "1024"), (4, 256, "unlimited"), (5, 64, "")] {
+        source.add_process(&ProcessInfo { fd_size: Some(fd_size), ..ProcessInfo::new(pid, 0, "p") });
+        if !soft.is_empty() {
+            source.insert(pid, "limits", limits(soft));
+        }
+    }
+    let pressure = |percent: u32| -> Vec<(Option<u32>, Option<ResourceLimit>)> {
+        let options = ScanOptions { fd_pressure: Some(percent), ..options(0, 2) };
+        scan_source(&source, &options).unwrap().processes.into_iter()
+            .map(|proc_info| (proc_info.fd_size, proc_info.fd_limit))
+            .collect()
+    };
+    assert_eq!(pressure(80), vec![
+        (None, None),
+        (Some(1024), Some(ResourceLimit::Limited(1024))),
+        (Some(832), Some(ResourceLimit::Limited(1024))),
+        (None, None),
+        (None, None),
+    ]);
+    assert_eq!(pressure(0), vec![
+        (Some(64), Some(ResourceLimit::Limited(1024))),
+        (Some(1024), Some(ResourceLimit::Limited(1024))),
+        (Some(832), Some(ResourceLimit::Limited(1024))),
+        (Some(256), Some(ResourceLimit::Unlimited)),
+        (None, None),
+    ]);
+}
+
+#[test]
+fn memory_sizes_are_read_in_bytes()
+{
+    let mut source = MockSource::new();
+    source.insert(1, "status", "Name:\tjava\nPid:\t1\nPPid:\t0\nVmPeak:\t 6600000 kB\nVmHWM:\t 6500000 kB\nVmRSS:\t 1100000 kB\n");
+    source.insert(2, "status", "Name:\tkthreadd\nPid:\t2\nPPid:\t0\n");
+    let mut options = options(0, 1);
+    options.status_fields.rss = true;
+    let output = scan_source(&source, &options).unwrap();
+    let memory: Vec<(Option<u64>, Option<u64>, Option<u64>)> = output.processes.iter()
+        .map(|proc_info| (proc_info.rss, proc_info.peak_rss, proc_info.peak_virtual))
+        .collect();
+    assert_eq!(memory, vec![(Some(1100000 * 1024), Some(6500000 * 1024), None), (None, None, None)]);
+}
+
+#[test]
+fn pss_is_read_from_smaps_rollup_and_falls_back_to_rss()
+{
+    let procfs = FakeProcfs::new();
+    procfs.add_process(&FakeProcess::new(1, 0, "postgres"));
+    procfs.add_process(&FakeProcess::new(2, 1, "postgres"));
+    procfs.add_process(&FakeProcess::new(3, 1, "hidden"));
+    procfs.set_smaps_rollup(1, 3072, 1024, 2560);
+    procfs.set_smaps_rollup(2, 3072, 1024, 1536);
+    procfs.write_file(3, "smaps_rollup", b"");
+    let mut options = options(0, 2);
+    options.status_fields.rss = true;
+
+    let output = scan_source(&LinuxProcfs::new(procfs.path()), &options).unwrap();
+    assert!(output.processes.iter().all(|proc_info| proc_info.pss.is_none()), "read without being asked");
+
+    options.read_pss = true;
+    let output = scan_source(&LinuxProcfs::new(procfs.path()), &options).unwrap();
+    let pss: Vec<Option<u64>> = output.processes.iter().map(|proc_info| proc_info.pss).collect();
+    assert_eq!(pss, vec![Some(2560 * 1024), Some(1536 * 1024), None]);
+    let memory: Vec<Option<(u64, bool)>> = output.processes.iter().map(|proc_info| proc_info.memory(true)).collect();
+    assert_eq!(memory, vec![Some((2560 * 1024, false)), Some((1536 * 1024, false)), Some((4096 * 1024, true))]);
+}
+
+#[test]
+fn memory_split_is_read_from_status_or_smaps_rollup()
+{
+    let procfs = FakeProcfs::new();
+    procfs.add_process(&FakeProcess::new(1, 0, "php-fpm"));
+    procfs.add_process(&FakeProcess::new(2, 1, "old kernel"));
+    procfs.add_process(&FakeProcess::new(3, 1, "php-fpm"));
+    procfs.write_file(2, "status", b"Name:\told kernel\nPid:\t2\nPPid:\t1\nUid:\t0\t0\t0\t0\nVmHWM:\t    4096 kB\nVmRSS:\t    4096 kB\n");
+    procfs.set_smaps_rollup(3, 2048, 2048, 3072);
+    let mut options = options(0, 2);
+    options.status_fields.rss = true;
+    options.status_fields.memory_split = true;
+    let split = |options: &ScanOptions| -> Vec<Option<(u64, u64)>> {
+        let output = scan_source(&LinuxProcfs::new(procfs.path()), options).unwrap();
+        output.processes.iter().map(ProcessInfo::memory_split).collect()
+    };
+
+    assert_eq!(split(&options), vec![Some((1024 * 1024, 3072 * 1024)), None, Some((1024 * 1024, 3072 * 1024))]);
+    options.read_pss = true;
+    assert_eq!(split(&options), vec![Some((1024 * 1024, 3072 * 1024)), None, Some((2048 * 1024, 2048 * 1024))]);
+}
+
+#[test]
+fn umask_is_read_as_octal()
+{
+    let mut source = mock(&[(3, 1, "old kernel")]);
+    source.add_process(&ProcessInfo { umask: Some(0o077), ..ProcessInfo::new(1, 0, "sshd") });
+    source.add_process(&ProcessInfo { umask: Some(0o002), ..ProcessInfo::new(2, 1, "bash") });
+    let mut options = options(0, 1);
+    options.status_fields.umask = true;
+    let output = scan_source(&source, &options).unwrap();
+    let umasks: Vec<(Option<u32>, Option<String>)> = output.processes.iter()
+        .map(|proc_info| (proc_info.umask, proc_info.display_umask()))
+        .collect();
+    assert_eq!(umasks, vec![
+        (Some(0o077), Some("0077".to_string())),
+        (Some(0o002), Some("0002".to_string())),
+        (None, None),
+    ]);
+}
+
+#[test]
+fn seccomp_mode_and_filters_are_read()
+{
+    let mut source = MockSource::new();
+    source.insert(1, "status", "Name:\tinit\nPid:\t1\nPPid:\t0\nSeccomp:\t0\nSeccomp_filters:\t0\n");
+    source.insert(2, "status", "Name:\tresolved\nPid:\t2\nPPid:\t1\nSeccomp:\t2\nSeccomp_filters:\t3\n");
+    source.insert(3, "status", "Name:\told\nPid:\t3\nPPid:\t1\nSeccomp:\t1\n");
+    let mut options = options(0, 1);
+    options.status_fields.seccomp = true;
+    let output = scan_source(&source, &options).unwrap();
+    let seccomp: Vec<(Option<u8>, Option<u32>, bool)> = output.processes.iter()
+        .map(|proc_info| (proc_info.seccomp, proc_info.seccomp_filters, proc_info.is_seccomp_confined()))
+        .collect();
+    assert_eq!(seccomp, vec![(Some(0), Some(0), false), (Some(2), Some(3), true), (Some(1), None, true)]);
+}
+
+#[test]
+fn no_new_privs_is_read_when_present()
+{
+    let mut source = MockSource::new();
+    source.insert(1, "status", "Name:\tinit\nPid:\t1\nPPid:\t0\nNoNewPrivs:\t0\n");
+    source.insert(2, "status", "Name:\tsandboxed\nPid:\t2\nPPid:\t1\nNoNewPrivs:\t1\n");
+    source.insert(3, "status", "Name:\told kernel\nPid:\t3\nPPid:\t1\n");
+    let mut options = options(0, 1);
+    options.status_fields.no_new_privs = true;
+    let output = scan_source(&source, &options).unwrap();
+    let flags: Vec<Option<bool>> = output.processes.iter().map(|proc_info| proc_info.no_new_privs).collect();
+    assert_eq!(flags, vec![Some(false), Some(true), None]);
+}
+
+#[test]
+fn supplementary_groups_are_read()
+{
+    let mut source = MockSource::new();
+    source.insert(1, "status", "Name:\tinit\nPid:\t1\nPPid:\t0\nGroups:\t\n");
+    source.insert(2, "status", "Name:\tdockerd\nPid:\t2\nPPid:\t1\nGroups:\t4 27 998 \n");
+    source.insert(3, "status", "Name:\tbad\nPid:\t3\nPPid:\t1\nGroups:\t4 x\n");
+    let mut options = options(0, 1);
+    options.status_fields.groups = true;
+    let output = scan_source(&source, &options).unwrap();
+    let groups: Vec<Option<Vec<u32>>> = output.processes.iter().map(|proc_info| proc_info.groups.clone()).collect();
+    assert_eq!(groups, vec![Some(vec![]), Some(vec![4, 27, 998])]);
+    assert_eq!(output.errors.len(), 1);
+}
+
+/// Stores the status, stat and command line of `proc_info`, whose stat line
+/// carries its state, parent, thread count and resident set size.
+fn add_with_stat(source: &mut MockSource, proc_info: &ProcessInfo, start_time: u64)
+{
+    source.add_process(proc_info);
+    let stat = format!(
+        "{0} ({1}) {2} {3} {0} {0} 0 -1 0 0 0 0 0 0 0 0 0 20 0 {4} 0 {5} 0 {6}",
+        proc_info.pid,
+        proc_info.display_name(),
+        proc_info.state.unwrap_or('S'),
+        proc_info.ppid,
+        proc_info.threads.unwrap_or(1),
+        start_time,
+        proc_info.rss.unwrap_or(0) / 4096,
+    );
+    source.insert(proc_info.pid, "stat", stat);
+    source.insert(proc_info.pid, "cmdline", [&proc_info.name[..], b"\0--flag\0"].concat());
+}
+
+fn cached_options() -> ScanOptions
+{
+    let mut options = options(0, 3);
+    options.read_cmdline = true;
+    options.read_start_time = true;
+    options.status_fields.state = true;
+    options.status_fields.threads = true;
+    options.status_fields.rss = true;
+    options
+}
+
+struct Random(u64);
+
+impl Random
+{
+    fn below(&mut self, bound: u32) -> u32
+    {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % u64::from(bound)) as u32
+    }
+}
+
+#[test]
+fn incremental_scan_matches_a_full_scan()
+{
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+    let mut source = MockSource::new();
+    let mut live: Vec<(ProcessInfo, u64)> = Vec::new();
+    let mut clock = 100;
+    let options = cached_options();
+    let mut cache = ScanCache::default();
+
+    for round in 0 .. 40 {
+        // Every round some processes exit, start, possibly under the pid of
+        // one that exited, or change state, parent, size or name.
+        for _ in 0 .. random.below(8) {
+            if live.len() > 1 {
+                let (gone, _) = live.swap_remove(random.below(live.len() as u32) as usize);
+                source.remove(gone.pid);
+            }
+        }
+        for _ in 0 .. 1 + random.below(8) {
+            let pid = 1 + random.below(120);
+            if let Some(at) = live.iter().position(|(proc_info, _)| proc_info.pid == pid) {
+                live.swap_remove(at);
+            }
+            clock += 1;
+            let ppid = if pid == 1 { 0 } else { 1 + random.below(120) };
+            let proc_info = ProcessInfo {
+                state: Some('S'),
+                threads: Some(1),
+                rss: Some(4096),
+                ..ProcessInfo::new(pid, ppid, format!("p{}-{}", pid, round))
+            };
+            live.push((proc_info, clock));
+        }
+        for (proc_info, _) in &mut live {
+            match random.below(10) {
+                0 => proc_info.state = Some(['R', 'S', 'D', 'Z'][random.below(4) as usize]),
+                1 => proc_info.ppid = random.below(120),
+                2 => proc_info.threads = Some(1 + random.below(4)),
+                3 => proc_info.rss = Some(4096 * u64::from(random.below(50))),
+                4 => proc_info.name = format!("exec-{}", round).into_bytes().into(),
+                _ => {}
+            }
+        }
+        for (proc_info, start_time) in &live {
+            add_with_stat(&mut source, proc_info, *start_time);
+        }
+
+        let incremental = scan_source_cached(&source, &options, &mut cache).unwrap();
+        let full = scan_source(&source, &options).unwrap();
+        assert_eq!(incremental.processes, full.processes, "round {}", round);
+        assert_eq!(cache.len(), live.len());
+    }
+}
+
+#[test]
+fn unchanged_stat_reuses_the_cached_process()
+{
+    let mut source = MockSource::new();
+    let init = ProcessInfo { uid: Some(0), ..ProcessInfo::new(1, 0, "init") };
+    let mut daemon = ProcessInfo { uid: Some(0), rss: Some(8192), ..ProcessInfo::new(2, 1, "daemon") };
+    add_with_stat(&mut source, &init, 1);
+    add_with_stat(&mut source, &daemon, 5);
+    let options = cached_options();
+    let mut cache = ScanCache::default();
+    let uids = |source: &MockSource, cache: &mut ScanCache| -> Vec<Option<u32>> {
+        scan_source_cached(source, &options, cache).unwrap().processes.iter().map(|proc_info| proc_info.uid).collect()
+    };
+    assert_eq!(uids(&source, &mut cache), vec![Some(0), Some(0)]);
+
+    // Dropping privileges shows in the status only, so the cached uid stays
+    // until the stat line changes too.
+    daemon.uid = Some(1000);
+    source.add_process(&daemon);
+    assert_eq!(uids(&source, &mut cache), vec![Some(0), Some(0)]);
+    daemon.rss = Some(12288);
+    add_with_stat(&mut source, &daemon, 5);
+    assert_eq!(uids(&source, &mut cache), vec![Some(0), Some(1000)]);
+}
+
+#[test]
+fn scan_cache_is_bounded_and_drops_exited_processes()
+{
+    let mut source = MockSource::new();
+    for pid in 1 ..= 5 {
+        add_with_stat(&mut source, &ProcessInfo::new(pid, pid - 1, format!("p{}", pid)), u64::from(pid));
+    }
+    let mut cache = ScanCache::new(3);
+    let output = scan_source_cached(&source, &cached_options(), &mut cache).unwrap();
+    assert_eq!(output.processes.len(), 5);
+    assert_eq!(cache.len(), 3);
+
+    let mut cache = ScanCache::default();
+    scan_source_cached(&source, &cached_options(), &mut cache).unwrap();
+    source.remove(4);
+    source.remove(5);
+    scan_source_cached(&source, &cached_options(), &mut cache).unwrap();
+    assert_eq!(cache.len(), 3);
+}
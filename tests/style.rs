@@ -0,0 +1,44 @@
+extern crate ptree;
+extern crate rusty_pstree;
+
+
+use ptree::{Color, Style};
+use rusty_pstree::TextStyle;
+
+#[test]
+fn parse_attribute_lists()
+{
+    assert_eq!("bold".parse(), Ok(TextStyle { bold: true, ..TextStyle::default() }));
+    assert_eq!(
+        "Underline, italic,bold".parse(),
+        Ok(TextStyle { bold: true, italic: true, underline: true, ..TextStyle::default() })
+    );
+    assert_eq!(
+        "dimmed,blink,reverse,reverse".parse(),
+        Ok(TextStyle { dimmed: true, blink: true, reverse: true, ..TextStyle::default() })
+    );
+}
+
+#[test]
+fn unknown_attributes_list_the_valid_set()
+{
+    assert_eq!(
+        "bold,strike".parse::<TextStyle>(),
+        Err("invalid style attribute 'strike' (expected a comma separated list of \
+             bold, dimmed, italic, underline, blink, reverse)".to_string())
+    );
+    assert!("".parse::<TextStyle>().unwrap_err().contains("invalid style attribute ''"));
+    assert!("bold,".parse::<TextStyle>().is_err());
+}
+
+#[test]
+fn apply_keeps_colors_and_attributes()
+{
+    let mut style = Style { foreground: Some(Color::Green), background: Some(Color::Black), dimmed: true, ..Style::default() };
+    TextStyle { bold: true, underline: true, ..TextStyle::default() }.apply(&mut style);
+
+    assert!(matches!(style.foreground, Some(Color::Green)));
+    assert!(matches!(style.background, Some(Color::Black)));
+    assert!(style.bold && style.underline && style.dimmed);
+    assert!(!style.italic && !style.blink && !style.reverse);
+}
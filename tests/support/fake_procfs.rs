@@ -0,0 +1,282 @@
+//! Generates fake procfs directory trees, so integration tests and benchmarks
+//! can point `--proc-root` at a known set of processes.
+
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+const WORKER_NAMES: [&str; 8] = [
+    "systemd", "sshd", "bash", "nginx", "postgres", "python3", "kworker/0:1", "sleep",
+];
+
+pub struct FakeProcess
+{
+    pub pid: u32,
+    pub ppid: u32,
+    pub name: String,
+    pub state: char,
+    pub uid: u32,
+    pub threads: u32,
+    pub nice: i32,
+    /// User, system, waited-for children's user and children's system
+    /// CPU time in clock ticks.
+    pub cpu_ticks: [u64; 4],
+    pub tracer_pid: u32,
+    pub start_time: u64,
+    pub session: u32,
+    pub cgroup: String,
+    pub cpus_allowed: String,
+    pub fd_size: u32,
+    pub umask: u32,
+    pub groups: Vec<u32>,
+    pub no_new_privs: bool,
+    pub seccomp: u8,
+    pub seccomp_filters: u32,
+    pub open_files_limit: String,
+    pub cmdline: Vec<String>,
+}
+
+impl FakeProcess
+{
+    pub fn new(pid: u32, ppid: u32, name: &str) -> FakeProcess
+    {
+        FakeProcess
+        {
+            pid,
+            ppid,
+            name: name.to_string(),
+            state: 'S',
+            uid: 0,
+            threads: 1,
+            nice: 0,
+            cpu_ticks: [12, 3, 0, 0],
+            tracer_pid: 0,
+            start_time: 1000 + u64::from(pid),
+            session: pid,
+            cgroup: String::from("/"),
+            cpus_allowed: String::from("0-7"),
+            fd_size: 64,
+            umask: 0o022,
+            groups: Vec::new(),
+            no_new_privs: false,
+            seccomp: 0,
+            seccomp_filters: 0,
+            open_files_limit: String::from("1024"),
+            cmdline: vec![format!("/usr/bin/{}", name)],
+        }
+    }
+
+    pub fn status(&self) -> String
+    {
+        format!(
+            "Name:\t{name}\nUmask:\t{umask:04o}\nState:\t{state} (sleeping)\nTgid:\t{pid}\nNgid:\t0\n\
+             Pid:\t{pid}\nPPid:\t{ppid}\nTracerPid:\t{tracer_pid}\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\n\
+             Gid:\t{uid}\t{uid}\t{uid}\t{uid}\nFDSize:\t{fd_size}\nGroups:\t{groups}\nNStgid:\t{pid}\nNSpid:\t{pid}\n\
+             NSpgid:\t{pid}\nNSsid:\t{pid}\nVmPeak:\t   16388 kB\nVmSize:\t   16384 kB\nVmLck:\t       0 kB\n\
+             VmPin:\t       0 kB\nVmHWM:\t    4096 kB\nVmRSS:\t    4096 kB\nRssAnon:\t    1024 kB\n\
+             RssFile:\t    3072 kB\nRssShmem:\t       0 kB\nVmData:\t    1024 kB\nVmStk:\t     132 kB\n\
+             VmExe:\t     200 kB\nVmLib:\t    2048 kB\nVmPTE:\t      48 kB\nVmSwap:\t       0 kB\n\
+             HugetlbPages:\t       0 kB\nCoreDumping:\t0\nTHP_enabled:\t1\nThreads:\t{threads}\n\
+             SigQ:\t0/63382\nSigPnd:\t0000000000000000\nShdPnd:\t0000000000000000\n\
+             SigBlk:\t0000000000000000\nSigIgn:\t0000000000001000\nSigCgt:\t0000000180000000\n\
+             CapInh:\t0000000000000000\nCapPrm:\t0000000000000000\nCapEff:\t0000000000000000\n\
+             CapBnd:\t000001ffffffffff\nCapAmb:\t0000000000000000\nNoNewPrivs:\t{no_new_privs}\nSeccomp:\t{seccomp}\n\
+             Seccomp_filters:\t{seccomp_filters}\nSpeculation_Store_Bypass:\tthread vulnerable\n\
+             Cpus_allowed:\tff\nCpus_allowed_list:\t{cpus_allowed}\nMems_allowed:\t1\nMems_allowed_list:\t0\n\
+             voluntary_ctxt_switches:\t42\nnonvoluntary_ctxt_switches:\t7\n",
+            name = self.name,
+            state = self.state,
+            pid = self.pid,
+            ppid = self.ppid,
+            uid = self.uid,
+            threads = self.threads,
+            tracer_pid = self.tracer_pid,
+            cpus_allowed = self.cpus_allowed,
+            fd_size = self.fd_size,
+            umask = self.umask,
+            groups = self.groups.iter().map(u32::to_string).collect::<Vec<_>>().join(" "),
+            no_new_privs = u8::from(self.no_new_privs),
+            seccomp = self.seccomp,
+            seccomp_filters = self.seccomp_filters,
+        )
+    }
+
+    pub fn stat(&self) -> String
+    {
+        format!(
+            "{pid} ({name}) {state} {ppid} {pid} {session} 0 -1 4194304 86 0 0 0 {utime} {stime} {cutime} {cstime} 20 \
+             {nice} {threads} 0 {starttime} 16777216 1024 18446744073709551615 1 1 0 0 0 0 0 4096 0 0 0 0 17 {cpu} \
+             0 0 0 0 0 0 0 0 0 0 0 0\n",
+            pid = self.pid,
+            name = self.name,
+            state = self.state,
+            ppid = self.ppid,
+            session = self.session,
+            nice = self.nice,
+            utime = self.cpu_ticks[0],
+            stime = self.cpu_ticks[1],
+            cutime = self.cpu_ticks[2],
+            cstime = self.cpu_ticks[3],
+            threads = self.threads,
+            starttime = self.start_time,
+            cpu = self.pid % 8,
+        )
+    }
+
+    pub fn limits(&self) -> String
+    {
+        format!(
+            "Limit                     Soft Limit           Hard Limit           Units     \n\
+             Max cpu time              unlimited            unlimited            seconds   \n\
+             Max processes             63382                63382                processes \n\
+             Max open files            {:<20} 524288               files     \n\
+             Max locked memory         8388608              8388608              bytes     \n",
+            self.open_files_limit,
+        )
+    }
+
+    pub fn cmdline(&self) -> Vec<u8>
+    {
+        let mut cmdline = Vec::new();
+        for arg in &self.cmdline {
+            cmdline.extend_from_slice(arg.as_bytes());
+            cmdline.push(0);
+        }
+        cmdline
+    }
+}
+
+/// A fake procfs under the system temp directory, removed again on drop.
+pub struct FakeProcfs
+{
+    root: PathBuf,
+}
+
+impl FakeProcfs
+{
+    pub fn new() -> FakeProcfs
+    {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let root = env::temp_dir().join(format!("rusty-pstree-procfs-{}-{}", process::id(), id));
+        fs::create_dir_all(&root).unwrap();
+        FakeProcfs { root }
+    }
+
+    /// Generates `process_count` processes rooted at pid 1 where every
+    /// process has up to `fan_out` children.
+    pub fn generate(process_count: u32, fan_out: u32) -> FakeProcfs
+    {
+        let procfs = FakeProcfs::new();
+        for pid in 1 ..= process_count {
+            let ppid = if pid == 1 { 0 } else { (pid - 2) / fan_out.max(1) + 1 };
+            let name = WORKER_NAMES[pid as usize % WORKER_NAMES.len()];
+            procfs.add_process(&FakeProcess::new(pid, ppid, name));
+        }
+        procfs
+    }
+
+    pub fn path(&self) -> &Path
+    {
+        &self.root
+    }
+
+    pub fn add_process(&self, process: &FakeProcess)
+    {
+        let dir = self.root.join(process.pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("status"), process.status()).unwrap();
+        fs::write(dir.join("stat"), process.stat()).unwrap();
+        fs::write(dir.join("cmdline"), process.cmdline()).unwrap();
+        fs::write(dir.join("limits"), process.limits()).unwrap();
+        fs::write(dir.join("cgroup"), format!("0::{}\n", process.cgroup)).unwrap();
+    }
+
+    /// Writes a file of a process directory as is, e.g. a malformed status.
+    /// `file_name` may contain subdirectories like "task/1/children".
+    pub fn write_file(&self, pid: u32, file_name: &str, contents: &[u8])
+    {
+        let path = self.root.join(pid.to_string()).join(file_name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// Adds /proc/[pid]/fd/`fd` as a symlink to `target`, e.g. "socket:[123]".
+    pub fn add_fd(&self, pid: u32, fd: u32, target: &str)
+    {
+        let dir = self.root.join(pid.to_string()).join("fd");
+        fs::create_dir_all(&dir).unwrap();
+        symlink(target, dir.join(fd.to_string())).unwrap();
+    }
+
+    /// Adds /proc/[pid]/exe as a symlink to `target`.
+    pub fn set_exe(&self, pid: u32, target: &Path)
+    {
+        symlink(target, self.root.join(pid.to_string()).join("exe")).unwrap();
+    }
+
+    /// Adds /proc/[pid]/ns/`ns` as a symlink to a namespace with `inode`,
+    /// replacing the link of an earlier call.
+    pub fn set_ns(&self, pid: u32, ns: &str, inode: u64)
+    {
+        let dir = self.root.join(pid.to_string()).join("ns");
+        fs::create_dir_all(&dir).unwrap();
+        let _ = fs::remove_file(dir.join(ns));
+        symlink(format!("{}:[{}]", ns, inode), dir.join(ns)).unwrap();
+    }
+
+    /// Writes /proc/[pid]/smaps_rollup like a 6.1 kernel does, with `shared_kib`
+    /// of clean file pages shared with other processes, `private_kib` of dirty
+    /// anonymous pages of its own and a proportional set size of `pss_kib`.
+    pub fn set_smaps_rollup(&self, pid: u32, shared_kib: u64, private_kib: u64, pss_kib: u64)
+    {
+        let smaps = format!(
+            "55d6e1c4e000-7ffd5a3f1000 ---p 00000000 00:00 0                          [rollup]\n\
+             Rss:              {rss:>6} kB\nPss:              {pss:>6} kB\nPss_Dirty:        {private:>6} kB\n\
+             Pss_Anon:         {private:>6} kB\nPss_File:         {pss_file:>6} kB\nPss_Shmem:             0 kB\n\
+             Shared_Clean:     {shared:>6} kB\nShared_Dirty:          0 kB\nPrivate_Clean:         0 kB\n\
+             Private_Dirty:    {private:>6} kB\nReferenced:       {rss:>6} kB\nAnonymous:        {private:>6} kB\n\
+             LazyFree:              0 kB\nAnonHugePages:         0 kB\nShmemPmdMapped:        0 kB\n\
+             FilePmdMapped:         0 kB\nShared_Hugetlb:        0 kB\nPrivate_Hugetlb:       0 kB\n\
+             Swap:                  0 kB\nSwapPss:               0 kB\nLocked:                0 kB\n",
+            rss = shared_kib + private_kib,
+            pss = pss_kib,
+            pss_file = pss_kib.saturating_sub(private_kib),
+            shared = shared_kib,
+            private = private_kib,
+        );
+        self.write_file(pid, "smaps_rollup", smaps.as_bytes());
+    }
+
+    /// Writes a /proc/cpuinfo listing `cpu_count` online CPUs.
+    pub fn write_cpuinfo(&self, cpu_count: u32)
+    {
+        let cpuinfo: String = (0 .. cpu_count)
+            .map(|cpu| format!("processor\t: {}\nvendor_id\t: GenuineIntel\ncpu MHz\t\t: 2400.000\n\n", cpu))
+            .collect();
+        fs::write(self.root.join("cpuinfo"), cpuinfo).unwrap();
+    }
+
+    /// Writes /proc/net/`table`, e.g. "tcp".
+    pub fn write_net(&self, table: &str, contents: &str)
+    {
+        let dir = self.root.join("net");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(table), contents).unwrap();
+    }
+}
+
+impl Drop for FakeProcfs
+{
+    fn drop(&mut self)
+    {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,502 @@
+extern crate proptest;
+extern crate rusty_pstree;
+
+
+use proptest::prelude::*;
+use rusty_pstree::{Collation, GroupOrder, OmittedSubtrees, ProcessInfo, ProcessTree, PsTreePrintConfig, TreeStats};
+
+//  1
+//  ├─ 2
+//  │  └─ 4
+//  └─ 3
+//     └─ 5
+//  6
+fn sample_tree() -> ProcessTree
+{
+    ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "init"),
+        ProcessInfo::new(2, 1, "a"),
+        ProcessInfo::new(3, 1, "b"),
+        ProcessInfo::new(4, 2, "c"),
+        ProcessInfo::new(5, 3, "d"),
+        ProcessInfo::new(6, 0, "kthreadd"),
+    ])
+}
+
+fn pids(tree: &ProcessTree, walk: impl Iterator<Item = (u32, usize)>) -> Vec<(u32, u32)>
+{
+    walk.map(|(depth, index)| (depth, tree.nodes[index].proc_info.pid)).collect()
+}
+
+#[test]
+fn dfs_visits_in_drawing_order()
+{
+    let tree = sample_tree();
+    assert_eq!(
+        pids(&tree, tree.iter_dfs()),
+        vec![(0, 1), (1, 2), (2, 4), (1, 3), (2, 5), (0, 6)]
+    );
+}
+
+#[test]
+fn bfs_visits_level_by_level()
+{
+    let tree = sample_tree();
+    assert_eq!(
+        pids(&tree, tree.iter_bfs()),
+        vec![(0, 1), (0, 6), (1, 2), (1, 3), (2, 4), (2, 5)]
+    );
+}
+
+#[test]
+fn post_order_visits_children_before_parents()
+{
+    let tree = sample_tree();
+    assert_eq!(
+        pids(&tree, tree.iter_post_order()),
+        vec![(2, 4), (1, 2), (2, 5), (1, 3), (0, 1), (0, 6)]
+    );
+}
+
+/// A small xorshift generator, so the random trees are the same on every run.
+struct Random(u64);
+
+impl Random
+{
+    fn below(&mut self, bound: u32) -> u32
+    {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % u64::from(bound)) as u32
+    }
+}
+
+#[test]
+fn post_order_never_lists_a_process_before_its_descendants()
+{
+    let mut random = Random(0x9e37_79b9_7f4a_7c15);
+    for _ in 0 .. 200 {
+        // Parents are drawn from all pids, so some trees have cycles and
+        // processes whose parent is missing.
+        let count = 1 + random.below(60);
+        let processes: Vec<ProcessInfo> = (1 ..= count)
+            .map(|pid| ProcessInfo::new(pid, random.below(count + 5), format!("p{}", random.below(4))))
+            .collect();
+        let mut tree = ProcessTree::from_process_list(processes);
+        if random.below(2) == 0 {
+            let name = format!("p{}", random.below(4));
+            tree.retain_matches(|proc_info| *proc_info.name == *name.as_bytes());
+        }
+        if random.below(2) == 0 {
+            let _ = tree.set_roots(&[1 + random.below(count)]);
+        }
+
+        let order: Vec<usize> = tree.iter_post_order().map(|(_, index)| index).collect();
+        let mut position = vec![None; tree.nodes.len()];
+        for (at, &index) in order.iter().enumerate() {
+            assert!(position[index].replace(at).is_none(), "listed twice: {}", tree.nodes[index].proc_info.pid);
+        }
+        assert_eq!(order.len(), tree.iter_dfs().count());
+        for &index in &order {
+            for &child in &tree.nodes[index].children {
+                assert!(position[child] < position[index], "{} before its child", tree.nodes[index].proc_info.pid);
+            }
+        }
+    }
+}
+
+#[test]
+fn single_node_tree()
+{
+    let tree = ProcessTree::from_process_list(vec![ProcessInfo::new(1, 0, "init")]);
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 1)]);
+    assert_eq!(pids(&tree, tree.iter_bfs()), vec![(0, 1)]);
+    assert_eq!(tree.descendant_count(0), 0);
+}
+
+#[test]
+fn init_and_kthreadd_are_both_roots()
+{
+    let mut tree = ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "systemd"),
+        ProcessInfo::new(2, 0, "kthreadd"),
+        ProcessInfo::new(3, 2, "rcu_gp"),
+        ProcessInfo::new(4, 2, "kworker/0:0"),
+        ProcessInfo::new(5, 4, "kworker/0:0H"),
+        ProcessInfo::new(300, 1, "sshd"),
+    ]);
+    let draw = |tree: &ProcessTree| {
+        let mut out = Vec::new();
+        tree.write(&mut out, &PsTreePrintConfig::new()).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+
+    assert_eq!(
+        draw(&tree),
+        "(all)\n├─ systemd\n│  └─ sshd\n└─ kthreadd\n   ├─ rcu_gp\n   └─ kworker/0:0\n      └─ kworker/0:0H\n"
+    );
+
+    tree.set_roots(&[2]).unwrap();
+    assert_eq!(draw(&tree), "kthreadd\n├─ rcu_gp\n└─ kworker/0:0\n   └─ kworker/0:0H\n");
+}
+
+#[test]
+fn find_and_subtree()
+{
+    let tree = sample_tree();
+    assert_eq!(tree.find(4).map(|node| node.proc_info.ppid), Some(2));
+    assert!(tree.find(7).is_none());
+
+    let subtree = tree.subtree(3).unwrap();
+    assert_eq!(pids(&subtree, subtree.iter_dfs()), vec![(0, 3), (1, 5)]);
+    assert!(tree.subtree(7).is_none());
+}
+
+#[test]
+fn hide_roots_promotes_their_children()
+{
+    let mut tree = sample_tree();
+    tree.hide_roots();
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 2), (1, 4), (0, 3), (1, 5)]);
+    tree.hide_roots();
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 4), (0, 5)]);
+    tree.hide_roots();
+    assert!(tree.roots.is_empty());
+}
+
+#[test]
+fn prune_depth_keeps_hidden_counts()
+{
+    let mut tree = sample_tree();
+    tree.prune_depth(1);
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 1), (1, 2), (1, 3), (0, 6)]);
+    assert_eq!(tree.descendant_count(tree.pid_index[&1]), 4);
+}
+
+#[test]
+fn descendant_counts_ignore_the_depth_limit()
+{
+    let counts = |tree: &ProcessTree| -> Vec<(u32, usize)> {
+        tree.iter_dfs()
+            .map(|(_, index)| (tree.nodes[index].proc_info.pid, tree.descendant_count(index)))
+            .collect()
+    };
+    let full = counts(&sample_tree());
+    assert_eq!(full, vec![(1, 4), (2, 1), (4, 0), (3, 1), (5, 0), (6, 0)]);
+
+    let mut tree = sample_tree();
+    tree.prune_depth(2);
+    assert_eq!(counts(&tree), full);
+
+    let mut tree = sample_tree();
+    tree.prune_depth(0);
+    assert_eq!(counts(&tree), vec![(1, 4), (6, 0)]);
+    assert_eq!(tree.nodes[tree.pid_index[&1]].hidden_descendants, 4);
+}
+
+#[test]
+fn ancestry_follows_parent_pids()
+{
+    let tree = sample_tree();
+    let chain = |pid| tree.ancestry(pid).map(|chain| -> Vec<u32> {
+        chain.iter().map(|&index| tree.nodes[index].proc_info.pid).collect()
+    });
+    assert_eq!(chain(5), Some(vec![1, 3, 5]));
+    assert_eq!(chain(6), Some(vec![6]));
+    assert_eq!(chain(7), None);
+}
+
+#[test]
+fn retain_matches_keeps_ancestors()
+{
+    let mut tree = sample_tree();
+    assert_eq!(tree.retain_matches(|proc_info| proc_info.pid == 4 || proc_info.pid == 6), 2);
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 1), (1, 2), (2, 4), (0, 6)]);
+
+    assert_eq!(tree.retain_matches(|_| false), 0);
+    assert!(tree.roots.is_empty());
+}
+
+#[test]
+fn group_by_cuts_links_between_keys()
+{
+    let mut tree = sample_tree();
+    tree.group_by(|proc_info| proc_info.name.len() > 1, |long| long.to_string(), GroupOrder::Count, Collation::Natural);
+
+    let groups: Vec<(&str, Vec<u32>)> = tree.groups.iter()
+        .map(|group| (group.label.as_str(), group.roots.iter().map(|&root| tree.nodes[root].proc_info.pid).collect()))
+        .collect();
+    assert_eq!(groups, vec![("false", vec![2, 3]), ("true", vec![1, 6])]);
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 2), (1, 4), (0, 3), (1, 5), (0, 1), (0, 6)]);
+}
+
+#[test]
+fn group_labels_sort_in_the_collation()
+{
+    let processes: Vec<ProcessInfo> = [10, 9, 100, 2].iter()
+        .map(|&uid| ProcessInfo { uid: Some(uid), ..ProcessInfo::new(uid, 0, "sh") })
+        .collect();
+    let labels = |order: GroupOrder, collation: Collation| -> Vec<String> {
+        let mut tree = ProcessTree::from_process_list(processes.clone());
+        tree.group_by(|proc_info| proc_info.uid, |uid| format!("user{}", uid.unwrap()), order, collation);
+        tree.groups.iter().map(|group| group.label.clone()).collect()
+    };
+    assert_eq!(labels(GroupOrder::Name, Collation::Natural), ["user2", "user9", "user10", "user100"]);
+    assert_eq!(labels(GroupOrder::Count, Collation::Natural), ["user2", "user9", "user10", "user100"]);
+    assert_eq!(labels(GroupOrder::Name, Collation::Bytewise), ["user10", "user100", "user2", "user9"]);
+}
+
+#[test]
+fn top_subtrees_rank_by_size_then_pid()
+{
+    // Below 1: 2 with two processes, 3 and 7 with one each, 8 with three.
+    let mut tree = ProcessTree::from_process_list(vec![
+        ProcessInfo::new(1, 0, "init"),
+        ProcessInfo::new(2, 1, "a"),
+        ProcessInfo::new(3, 1, "b"),
+        ProcessInfo::new(4, 2, "c"),
+        ProcessInfo::new(7, 1, "e"),
+        ProcessInfo::new(8, 1, "f"),
+        ProcessInfo::new(9, 8, "g"),
+        ProcessInfo::new(10, 9, "h"),
+    ]);
+    tree.keep_top_subtrees(3);
+
+    // 3 and 7 tie, the lower pid is kept. The kept ones stay in pid order.
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 1), (1, 2), (2, 4), (1, 3), (1, 8), (2, 9), (3, 10)]);
+    assert_eq!(tree.omitted_subtrees, Some(OmittedSubtrees { parent: Some(0), subtrees: 1, processes: 1 }));
+}
+
+#[test]
+fn truncate_cuts_the_deepest_processes_first()
+{
+    let mut tree = sample_tree();
+    tree.prune_depth(1);
+    tree.truncate(4);
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 1), (1, 2), (1, 3), (0, 6)]);
+    assert_eq!(tree.truncated_processes, 0);
+
+    let mut tree = sample_tree();
+    tree.truncate(5);
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 1), (1, 2), (2, 4), (1, 3), (0, 6)]);
+    assert_eq!(tree.truncated_processes, 1);
+
+    let mut tree = sample_tree();
+    tree.truncate(1);
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 1)]);
+    assert_eq!(tree.truncated_processes, 5);
+}
+
+#[test]
+fn top_subtrees_of_several_roots()
+{
+    let mut tree = sample_tree();
+    tree.keep_top_subtrees(1);
+    assert_eq!(pids(&tree, tree.iter_dfs()), vec![(0, 1), (1, 2), (2, 4), (1, 3), (2, 5)]);
+    assert_eq!(tree.omitted_subtrees, Some(OmittedSubtrees { parent: None, subtrees: 1, processes: 1 }));
+
+    let mut tree = sample_tree();
+    tree.keep_top_subtrees(2);
+    assert_eq!(tree.roots.len(), 2);
+    assert_eq!(tree.omitted_subtrees, None);
+}
+
+#[test]
+fn stats_pin_the_shape_of_the_tree()
+{
+    //  1 (root, 2 threads)
+    //  ├─ 2
+    //  │  ├─ 4 (3 threads)
+    //  │  ├─ 5
+    //  │  └─ 6
+    //  └─ 3 (uid 1000)
+    //     └─ 7 (uid 1000)
+    //        └─ 8 (uid 33)
+    //  9
+    let mut processes: Vec<ProcessInfo> = [(1, 0), (2, 1), (3, 1), (4, 2), (5, 2), (6, 2), (7, 3), (8, 7), (9, 0)]
+        .into_iter()
+        .map(|(pid, ppid)| ProcessInfo::new(pid, ppid, "p"))
+        .collect();
+    processes[0].uid = Some(0);
+    processes[0].threads = Some(2);
+    processes[3].threads = Some(3);
+    processes[2].uid = Some(1000);
+    processes[6].uid = Some(1000);
+    processes[7].uid = Some(33);
+    let mut tree = ProcessTree::from_process_list(processes);
+
+    assert_eq!(tree.stats(), TreeStats {
+        processes: 9,
+        threads: Some(5),
+        max_depth: 3,
+        widest: Some((2, 3)),
+        leaves: 5,
+        users: 3,
+        largest_subtree: Some((2, 4)),
+    });
+
+    // 3 and 2 now tie on children and on subtree size, the lower pid wins.
+    tree.prune_depth(1);
+    assert_eq!(tree.stats(), TreeStats {
+        processes: 4,
+        threads: Some(2),
+        max_depth: 1,
+        widest: Some((1, 2)),
+        leaves: 3,
+        users: 2,
+        largest_subtree: Some((2, 1)),
+    });
+
+    let single = ProcessTree::from_process_list(vec![ProcessInfo::new(1, 0, "init")]);
+    assert_eq!(single.stats(), TreeStats { processes: 1, leaves: 1, ..TreeStats::default() });
+}
+
+/// Random process lists with repeated pids, processes whose parent is
+/// missing and processes that are their own parent.
+fn process_lists() -> impl Strategy<Value = Vec<ProcessInfo>>
+{
+    (1u32 .. 60)
+        .prop_flat_map(|count| {
+            let process = (1 ..= count + 5, 0 .. count + 10, prop::bool::weighted(0.1), 0u32 .. 4);
+            prop::collection::vec(process, count as usize)
+        })
+        .prop_map(|processes| {
+            processes
+                .into_iter()
+                .map(|(pid, ppid, own_parent, name)| {
+                    ProcessInfo::new(pid, if own_parent { pid } else { ppid }, format!("p{}", name))
+                })
+                .collect()
+        })
+}
+
+/// One of the ways a tree is pruned.
+#[derive(Clone, Debug)]
+enum Pruning
+{
+    Depth(u32),
+    Truncate(usize),
+    KeepTop(usize),
+    Unique,
+}
+
+fn prunings() -> impl Strategy<Value = Pruning>
+{
+    prop_oneof![
+        (0u32 .. 4).prop_map(Pruning::Depth),
+        (0usize .. 40).prop_map(Pruning::Truncate),
+        (0usize .. 5).prop_map(Pruning::KeepTop),
+        Just(Pruning::Unique),
+    ]
+}
+
+/// The drawn nodes, checking that none is drawn twice, which a cycle
+/// among them would also cause.
+fn drawn_nodes(tree: &ProcessTree) -> Vec<usize>
+{
+    let drawn: Vec<usize> = tree.iter_dfs().take(tree.nodes.len() + 1).map(|(_, index)| index).collect();
+    let mut seen = vec![false; tree.nodes.len()];
+    for &index in &drawn {
+        assert!(!std::mem::replace(&mut seen[index], true), "{} drawn twice", tree.nodes[index].proc_info.pid);
+    }
+    drawn
+}
+
+/// Checks that every drawn pid is drawn once and hangs below the process
+/// its ppid names, or is a root.
+fn check_links(tree: &ProcessTree)
+{
+    let drawn = drawn_nodes(tree);
+    let mut pids: Vec<u32> = drawn.iter().map(|&index| tree.nodes[index].proc_info.pid).collect();
+    pids.sort_unstable();
+    let count = pids.len();
+    pids.dedup();
+    assert_eq!(pids.len(), count, "a pid is drawn twice");
+
+    let mut parents = vec![0; tree.nodes.len()];
+    for &index in &drawn {
+        for &child in &tree.nodes[index].children {
+            parents[child] += 1;
+            assert_eq!(tree.nodes[child].proc_info.ppid, tree.nodes[index].proc_info.pid);
+        }
+    }
+    for &index in &drawn {
+        let expected = usize::from(!tree.roots.contains(&index));
+        assert_eq!(parents[index], expected, "{} has the wrong number of parents", tree.nodes[index].proc_info.pid);
+    }
+}
+
+proptest! {
+    #[test]
+    fn random_trees_link_every_pid_once(processes in process_lists())
+    {
+        let mut distinct: Vec<u32> = processes.iter().map(|proc_info| proc_info.pid).collect();
+        distinct.sort_unstable();
+        distinct.dedup();
+        let tree = ProcessTree::from_process_list(processes);
+
+        check_links(&tree);
+        // Every process is drawn when nothing is selected, each child of
+        // its parent unless that link was cut to break a cycle.
+        prop_assert_eq!(drawn_nodes(&tree).len(), distinct.len());
+        for (_, index) in tree.iter_dfs() {
+            let proc_info = &tree.nodes[index].proc_info;
+            let parent = tree.find(proc_info.ppid).filter(|_| !proc_info.is_top_level());
+            let in_cycle = tree.broken_cycles.iter().any(|cycle| cycle.last() == Some(&proc_info.pid));
+            prop_assert_eq!(tree.roots.contains(&index), parent.is_none() || in_cycle, "pid {}", proc_info.pid);
+            prop_assert_eq!(tree.descendant_count(index), tree.subtree(proc_info.pid).unwrap().nodes.len() - 1);
+        }
+    }
+
+    #[test]
+    fn random_filters_keep_the_matches_and_their_ancestors(
+        processes in process_lists(),
+        root in prop::option::of(any::<prop::sample::Index>()),
+        name in 0u32 .. 4,
+    )
+    {
+        let mut tree = ProcessTree::from_process_list(processes);
+        if let Some(root) = root {
+            let pid = tree.nodes[root.index(tree.nodes.len())].proc_info.pid;
+            tree.set_roots(&[pid]).unwrap();
+            check_links(&tree);
+        }
+        let before: Vec<usize> = drawn_nodes(&tree);
+        let name = format!("p{}", name);
+        let matches = tree.retain_matches(|proc_info| *proc_info.name == *name.as_bytes());
+        check_links(&tree);
+
+        let drawn = drawn_nodes(&tree);
+        let matching = |index: &usize| *tree.nodes[*index].proc_info.name == *name.as_bytes();
+        prop_assert_eq!(drawn.iter().filter(|index| matching(index)).count(), matches);
+        prop_assert_eq!(before.iter().filter(|index| matching(index)).count(), matches);
+        // A process that does not match is only kept above one that does.
+        for &index in &drawn {
+            prop_assert!(before.contains(&index));
+            prop_assert!(matching(&index) || !tree.nodes[index].children.is_empty());
+        }
+    }
+
+    #[test]
+    fn random_pruning_never_adds_processes(
+        processes in process_lists(),
+        prunings in prop::collection::vec(prunings(), 1 .. 4),
+    )
+    {
+        let mut tree = ProcessTree::from_process_list(processes);
+        for pruning in prunings {
+            let before = drawn_nodes(&tree);
+            match pruning {
+                Pruning::Depth(depth) => tree.prune_depth(depth),
+                Pruning::Truncate(count) => tree.truncate(count),
+                Pruning::KeepTop(count) => tree.keep_top_subtrees(count),
+                Pruning::Unique => tree.filter_unique(),
+            }
+            check_links(&tree);
+            let after = drawn_nodes(&tree);
+            prop_assert!(after.len() <= before.len());
+            prop_assert!(after.iter().all(|index| before.contains(index)));
+        }
+    }
+}
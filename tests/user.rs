@@ -0,0 +1,42 @@
+extern crate rusty_pstree;
+
+
+use rusty_pstree::{GroupTable, UserTable};
+
+#[test]
+fn parse_passwd()
+{
+    let users = UserTable::parse("\
+root:x:0:0:root:/root:/bin/bash
+# comment
+broken:x:not-a-uid:0::/:/bin/false
+alice:x:1000:1000:Alice:/home/alice:/bin/sh
+alias:x:1000:1000::/home/alice:/bin/sh
+1234:x:1001:1001::/home/digits:/bin/sh
+");
+
+    assert_eq!(users.uid("alice"), Some(1000));
+    assert_eq!(users.name(1000), Some("alice"));
+    assert_eq!(users.uid("broken"), None);
+    assert_eq!(users.resolve("root"), Some(0));
+    assert_eq!(users.resolve("1234"), Some(1001));
+    assert_eq!(users.resolve("4242"), Some(4242));
+    assert_eq!(users.resolve("nobody-here"), None);
+}
+
+#[test]
+fn parse_group()
+{
+    let groups = GroupTable::parse("\
+root:x:0:
+adm:x:4:syslog,alice
+docker:x:998:alice
+broken:x::
+");
+
+    assert_eq!(groups.gid("docker"), Some(998));
+    assert_eq!(groups.name(4), Some("adm"));
+    assert_eq!(groups.gid("broken"), None);
+    assert_eq!(groups.resolve("998"), Some(998));
+    assert_eq!(groups.resolve("wheel"), None);
+}
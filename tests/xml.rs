@@ -0,0 +1,116 @@
+extern crate regex;
+extern crate rusty_pstree;
+
+
+use regex::Regex;
+use rusty_pstree::{ProcessInfo, ProcessTree, XmlMetadata};
+
+fn fixture() -> ProcessTree
+{
+    let mut init = ProcessInfo::new(1, 0, "init");
+    init.uid = Some(0);
+    init.state = Some('S');
+    let mut shell = ProcessInfo::new(30, 1, "a&b <\"sh\">");
+    shell.uid = Some(1000);
+    shell.umask = Some(0o077);
+    shell.cmdline = Some(vec![b"sh".to_vec(), b"-c".to_vec(), b"echo 'x' > y".to_vec()]);
+    shell.groups = Some(vec![10, 100]);
+    let mut odd = ProcessInfo::new(31, 30, "tab\there\x01");
+    odd.listen = Some(vec![":80".to_string()]);
+    ProcessTree::from_process_list(vec![init, shell, odd, ProcessInfo::new(40, 1, "cron")])
+}
+
+fn xml(tree: &ProcessTree, metadata: &XmlMetadata) -> String
+{
+    let mut out = Vec::new();
+    tree.write_xml(&mut out, metadata).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn unescape(text: &str) -> String
+{
+    Regex::new("&(amp|lt|gt|quot|apos|#[0-9]+);").unwrap()
+        .replace_all(text, |caps: &regex::Captures| match &caps[1] {
+            "amp" => "&".to_string(),
+            "lt" => "<".to_string(),
+            "gt" => ">".to_string(),
+            "quot" => "\"".to_string(),
+            "apos" => "'".to_string(),
+            code => char::from_u32(code[1 ..].parse().unwrap()).unwrap().to_string(),
+        })
+        .into_owned()
+}
+
+/// Reads the pid, ppid and name of every process element back, in document
+/// order, with the pid of the enclosing process element.
+fn read_back(document: &str) -> Vec<(u32, u32, String, Option<u32>)>
+{
+    let tag = Regex::new(r#"<(/?)process((?: [a-z_]+="[^"<]*")*)(/?)>"#).unwrap();
+    let attribute = Regex::new(r#" ([a-z_]+)="([^"]*)""#).unwrap();
+    let mut open: Vec<u32> = Vec::new();
+    let mut processes = Vec::new();
+    for caps in tag.captures_iter(document) {
+        if &caps[1] == "/" {
+            open.pop();
+            continue;
+        }
+        let get = |name: &str| attribute.captures_iter(&caps[2])
+            .find(|attr| &attr[1] == name)
+            .map(|attr| unescape(&attr[2]))
+            .unwrap();
+        let pid: u32 = get("pid").parse().unwrap();
+        processes.push((pid, get("ppid").parse().unwrap(), get("name"), open.last().copied()));
+        if caps[3].is_empty() {
+            open.push(pid);
+        }
+    }
+    assert!(open.is_empty(), "unclosed process elements in {}", document);
+    processes
+}
+
+#[test]
+fn document_format()
+{
+    let metadata = XmlMetadata { hostname: Some("web&1".to_string()), timestamp: Some(1700000000) };
+    assert_eq!(xml(&fixture(), &metadata), concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<processes hostname=\"web&amp;1\" timestamp=\"1700000000\">\n",
+        "  <process pid=\"1\" ppid=\"0\" name=\"init\" uid=\"0\" state=\"S\">\n",
+        "    <process pid=\"30\" ppid=\"1\" name=\"a&amp;b &lt;&quot;sh&quot;&gt;\" uid=\"1000\" umask=\"0077\">\n",
+        "      <arg>sh</arg>\n",
+        "      <arg>-c</arg>\n",
+        "      <arg>echo &apos;x&apos; &gt; y</arg>\n",
+        "      <group gid=\"10\"/>\n",
+        "      <group gid=\"100\"/>\n",
+        "      <process pid=\"31\" ppid=\"30\" name=\"tab&#9;here\u{fffd}\">\n",
+        "        <listen>:80</listen>\n",
+        "      </process>\n",
+        "    </process>\n",
+        "    <process pid=\"40\" ppid=\"1\" name=\"cron\"/>\n",
+        "  </process>\n",
+        "</processes>\n",
+    ));
+}
+
+#[test]
+fn names_and_nesting_read_back()
+{
+    let tree = fixture();
+    let read: Vec<(u32, u32, String, Option<u32>)> = read_back(&xml(&tree, &XmlMetadata::default()));
+    let expected: Vec<(u32, u32, String, Option<u32>)> = tree.iter_dfs()
+        .map(|(_, index)| {
+            let proc_info = &tree.nodes[index].proc_info;
+            let parent = tree.find(proc_info.ppid).map(|parent| parent.proc_info.pid);
+            (proc_info.pid, proc_info.ppid, String::from_utf8_lossy(&proc_info.name).replace('\x01', "\u{fffd}"), parent)
+        })
+        .collect();
+    assert_eq!(read, expected);
+    assert_eq!(read[1].2, "a&b <\"sh\">");
+}
+
+#[test]
+fn empty_tree_is_an_empty_element()
+{
+    let tree = ProcessTree::from_process_list(Vec::new());
+    assert_eq!(xml(&tree, &XmlMetadata::default()), "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<processes/>\n");
+}